@@ -7,14 +7,20 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("cargo:rerun-if-changed=proto/common.proto");
     println!("cargo:rerun-if-changed=proto/trace.proto");
     println!("cargo:rerun-if-changed=proto/resource.proto");
+    println!("cargo:rerun-if-changed=proto/bulk.proto");
     
+    // Upstream example JSON in this comment is picked up as a failing doctest
+    let mut prost_config = prost_build::Config::new();
+    prost_config.disable_comments([".opentelemetry.proto.trace.v1.Span.attributes"]);
+
     tonic_build::configure()
         .build_server(true)
         .build_client(true)
         .type_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]")
         .out_dir("src/proto")
-        .compile(
-            &["proto/service.proto"],
+        .compile_with_config(
+            prost_config,
+            &["proto/service.proto", "proto/bulk.proto"],
             &["proto"],
         )?;
 
@@ -1,5 +1,8 @@
 //! Example demonstrating how to implement a custom storage backend
+use async_trait::async_trait;
+use opentelemetry::sdk::export::trace::SpanData;
 use storage_engine::storage::StorageWriter;
+use storage_engine::StorageError;
 
 #[derive(Debug)]
 struct CustomStorage {
@@ -10,5 +13,31 @@ struct CustomStorage {
 impl StorageWriter for CustomStorage {
     async fn write(&self, key: &str, data: &[u8]) -> Result<(), StorageError> {
         // Custom implementation
+        println!("write {} ({} bytes)", key, data.len());
+        Ok(())
     }
-} 
\ No newline at end of file
+
+    async fn write_batch(&self, entries: Vec<(&str, &[u8])>) -> Result<(), StorageError> {
+        for (key, data) in entries {
+            self.write(key, data).await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    async fn write_spans(&self, spans: Vec<SpanData>) -> Result<(), StorageError> {
+        println!("write_spans ({} spans)", spans.len());
+        Ok(())
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let storage = CustomStorage {};
+    storage.write("example", b"{}").await?;
+    storage.flush().await?;
+    Ok(())
+}
@@ -13,11 +13,28 @@ use storage_engine::proto::{
     ExportTraceServiceRequest,
     ResourceSpans, ScopeSpans, Span,
 };
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::service::Interceptor;
 use tonic::transport::Channel;
+use tonic::{Request, Status};
 use tracing::{info, error};
 use uuid::Uuid;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// Adds an `authorization` header to every request when a token is set
+#[cfg(feature = "client")]
+struct BearerToken(Option<MetadataValue<Ascii>>);
+
+#[cfg(feature = "client")]
+impl Interceptor for BearerToken {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(token) = &self.0 {
+            request.metadata_mut().insert("authorization", token.clone());
+        }
+        Ok(request)
+    }
+}
+
 #[cfg(feature = "client")]
 fn generate_sample_trace() -> ExportTraceServiceRequest {
     // Generate unique IDs for the trace
@@ -71,9 +88,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt::init();
 
     // Connect to the collector
-    info!("Connecting to trace collector at http://[::1]:50051...");
-    let channel = Channel::from_static("http://[::1]:50051").connect().await?;
-    let mut client = TraceServiceClient::new(channel);
+    let endpoint = std::env::var("COLLECTOR_ENDPOINT")
+        .unwrap_or_else(|_| "http://[::1]:50051".to_string());
+    info!("Connecting to trace collector at {}...", endpoint);
+    let channel = Channel::from_shared(endpoint)?.connect().await?;
+
+    // Attach a bearer token when the server has authentication enabled
+    let token = match std::env::var("AUTH_BEARER_TOKEN") {
+        Ok(token) => Some(MetadataValue::try_from(format!("Bearer {}", token))?),
+        Err(_) => None,
+    };
+    let mut client = TraceServiceClient::with_interceptor(channel, BearerToken(token));
     info!("Connected successfully!");
 
     // Generate and send multiple traces
@@ -0,0 +1,105 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+use tracing::warn;
+
+use crate::config::AuthConfig;
+
+/// Bearer-token authenticator shared by the gRPC and HTTP servers.
+/// With no tokens configured every export is accepted; admin endpoints
+/// refuse every request instead (see `SpanReader`).
+#[derive(Clone, Debug, Default)]
+pub struct BearerAuth {
+    /// Set of accepted bearer tokens
+    tokens: Arc<HashSet<String>>,
+}
+
+impl BearerAuth {
+    /// Creates a new BearerAuth from the configured tokens
+    pub fn new(config: &AuthConfig) -> Self {
+        Self {
+            tokens: Arc::new(config.bearer_tokens.iter().cloned().collect()),
+        }
+    }
+
+    /// Returns whether authentication is enforced
+    pub fn is_enabled(&self) -> bool {
+        !self.tokens.is_empty()
+    }
+
+    /// Checks an `authorization` header value against the allowed tokens
+    pub fn is_authorized(&self, header: Option<&str>) -> bool {
+        if !self.is_enabled() {
+            return true;
+        }
+
+        header
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|token| self.tokens.contains(token.trim()))
+            .unwrap_or(false)
+    }
+}
+
+impl Interceptor for BearerAuth {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        let header = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok());
+
+        if self.is_authorized(header) {
+            Ok(request)
+        } else {
+            warn!("Rejected unauthenticated export request");
+            Err(Status::unauthenticated("Missing or invalid bearer token"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth() -> BearerAuth {
+        BearerAuth::new(&AuthConfig {
+            bearer_tokens: vec!["secret".into()],
+        })
+    }
+
+    fn request_with_header(value: Option<&str>) -> Request<()> {
+        let mut request = Request::new(());
+        if let Some(value) = value {
+            request
+                .metadata_mut()
+                .insert("authorization", value.parse().unwrap());
+        }
+        request
+    }
+
+    #[test]
+    fn test_accepted_token() {
+        let result = auth().call(request_with_header(Some("Bearer secret")));
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_wrong_token() {
+        let status = auth()
+            .call(request_with_header(Some("Bearer wrong")))
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    }
+
+    #[test]
+    fn test_missing_header() {
+        let status = auth().call(request_with_header(None)).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    }
+
+    #[test]
+    fn test_no_tokens_is_noop() {
+        let mut auth = BearerAuth::new(&AuthConfig::default());
+        assert!(auth.call(request_with_header(None)).is_ok());
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use crate::config::{RetryConfig, RetryJitter};
+
+/// Exponential backoff: the n-th delay (from 0) is capped at
+/// `min(max_backoff, initial_backoff * 2^n)` and, with full jitter, drawn
+/// uniformly below it, so clients failing together spread their retries
+/// instead of retrying in lockstep. Yields at most `max_retries` delays.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    /// Delay ceiling of the first retry
+    initial: Duration,
+    /// Upper bound on any delay
+    max: Duration,
+    /// Number of delays yielded before giving up
+    max_retries: u32,
+    /// Randomization of delays below their ceiling
+    jitter: RetryJitter,
+    /// Delays yielded so far
+    attempt: u32,
+    /// SplitMix64 state
+    state: u64,
+}
+
+impl Backoff {
+    /// Creates a backoff for the retry policy with a randomly seeded generator
+    pub fn new(config: &RetryConfig) -> Self {
+        Self::with_seed(config, RandomState::new().build_hasher().finish())
+    }
+
+    /// Creates a backoff whose delays are fully determined by `seed`
+    pub fn with_seed(config: &RetryConfig, seed: u64) -> Self {
+        Self {
+            initial: Duration::from_millis(config.initial_backoff_ms),
+            max: Duration::from_millis(config.max_backoff_ms),
+            max_retries: config.max_retries,
+            jitter: config.jitter,
+            attempt: 0,
+            state: seed,
+        }
+    }
+
+    /// Returns the next delay, or `hint` capped at the maximum backoff when
+    /// the server said how long to wait. A hinted retry counts towards `max_retries`.
+    pub fn next_after(&mut self, hint: Option<Duration>) -> Option<Duration> {
+        let delay = self.next()?;
+        Some(hint.map_or(delay, |hint| hint.min(self.max)))
+    }
+
+    /// Upper bound of the delay before retry `attempt`
+    fn ceiling(&self, attempt: u32) -> Duration {
+        self.initial
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max)
+    }
+
+    /// Next SplitMix64 output
+    fn next_random(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl Iterator for Backoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        if self.attempt >= self.max_retries {
+            return None;
+        }
+        let ceiling = u64::try_from(self.ceiling(self.attempt).as_nanos()).unwrap_or(u64::MAX);
+        self.attempt += 1;
+        let delay = match (self.jitter, ceiling.checked_add(1)) {
+            (RetryJitter::None, _) => ceiling,
+            (RetryJitter::Full, Some(range)) => self.next_random() % range,
+            (RetryJitter::Full, None) => self.next_random(),
+        };
+        Some(Duration::from_nanos(delay))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_retries: u32, initial_backoff_ms: u64, max_backoff_ms: u64) -> RetryConfig {
+        RetryConfig {
+            max_retries,
+            initial_backoff_ms,
+            max_backoff_ms,
+            jitter: RetryJitter::Full,
+        }
+    }
+
+    #[test]
+    fn test_delays_within_jitter_bounds() {
+        let config = config(10, 100, 1_000);
+        for seed in 0..100 {
+            let delays: Vec<Duration> = Backoff::with_seed(&config, seed).collect();
+            assert_eq!(delays.len(), 10);
+            for (n, delay) in delays.iter().enumerate() {
+                let ceiling = Duration::from_millis(1_000.min(100 * 2u64.pow(n as u32)));
+                assert!(*delay <= ceiling, "delay {} of {:?} above {:?}", n, delay, ceiling);
+            }
+        }
+    }
+
+    #[test]
+    fn test_delays_without_jitter_at_ceiling() {
+        let config = RetryConfig { jitter: RetryJitter::None, ..config(5, 100, 1_000) };
+        let delays: Vec<u64> = Backoff::new(&config).map(|delay| delay.as_millis() as u64).collect();
+        assert_eq!(delays, [100, 200, 400, 800, 1_000]);
+    }
+
+    #[test]
+    fn test_hint_capped_at_max_backoff() {
+        let mut backoff = Backoff::with_seed(&config(3, 100, 1_000), 7);
+        assert_eq!(backoff.next_after(Some(Duration::from_millis(300))), Some(Duration::from_millis(300)));
+        assert_eq!(backoff.next_after(Some(Duration::from_secs(30))), Some(Duration::from_secs(1)));
+        assert!(backoff.next_after(None).unwrap() <= Duration::from_millis(400));
+        assert_eq!(backoff.next_after(Some(Duration::from_millis(300))), None);
+    }
+
+    #[test]
+    fn test_steps_limited_by_max_retries() {
+        assert_eq!(Backoff::with_seed(&config(3, 100, 1_000), 7).count(), 3);
+        assert_eq!(Backoff::with_seed(&config(0, 100, 1_000), 7).count(), 0);
+        assert_eq!(Backoff::new(&config(64, 1, u64::MAX)).count(), 64);
+    }
+
+    #[test]
+    fn test_seeded_sequence_is_deterministic() {
+        let config = config(5, 100, 1_000);
+        let first: Vec<Duration> = Backoff::with_seed(&config, 42).collect();
+        assert_eq!(first, Backoff::with_seed(&config, 42).collect::<Vec<_>>());
+        assert_ne!(first, Backoff::with_seed(&config, 43).collect::<Vec<_>>());
+    }
+}
@@ -1,12 +1,23 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::path::Path;
+use std::time::Duration;
 use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
 use crate::error::ConfigError;
+use crate::rate_limit::TokenBucket;
+use crate::storage::key_template::{KeyTemplate, DEFAULT_KEY_TEMPLATE};
+use crate::storage::tagging::ObjectTags;
+use crate::storage::DEFAULT_WRITE_TIMEOUT;
+
+/// Smallest batch timeout the engine will use; lower values are clamped
+/// to avoid the batch timer spinning a CPU core
+pub const MIN_BATCH_TIMEOUT_MS: u64 = 10;
 
 /// Main configuration structure for the storage engine
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Config {
     /// Server-related configuration
     pub server: ServerConfig,
@@ -15,25 +26,93 @@ pub struct Config {
     /// Message processing configuration
     pub processing: ProcessingConfig,
     /// Retry policy configuration
+    #[serde(default)]
     pub retry: RetryConfig,
     /// Metrics collection configuration
+    #[serde(default)]
     pub metrics: MetricsConfig,
+    /// Authentication configuration
+    #[serde(default)]
+    pub auth: AuthConfig,
+    /// Query API configuration
+    #[serde(default)]
+    pub reader: ReaderConfig,
+    /// Self-instrumentation configuration
+    #[serde(default)]
+    pub self_telemetry: SelfTelemetryConfig,
+    /// Console log filtering
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    /// Ingest sampling configuration
+    #[serde(default)]
+    pub sampling: SamplingConfig,
+    /// Duplicate span suppression
+    #[serde(default)]
+    pub dedup: DedupConfig,
+    /// Span name filtering at ingestion
+    #[serde(default)]
+    pub ingest_filter: IngestFilterConfig,
+    /// Export request rate limiting
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    /// Local spill of spans that could not be written to storage
+    #[serde(default)]
+    pub spill: SpillConfig,
+    /// Write-ahead log of received requests
+    #[serde(default)]
+    pub wal: WalConfig,
+    /// Health reporting configuration
+    #[serde(default)]
+    pub health: HealthConfig,
+    /// Separate listener for liveness and readiness probes
+    #[serde(default)]
+    pub ops: OpsConfig,
+    /// Consumption of export requests from a Kafka topic
+    #[serde(default)]
+    pub kafka: Option<KafkaIngestConfig>,
 }
 
 /// Server configuration options
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ServerConfig {
     /// Server host address
     pub host: String,
     /// Server port number
     pub port: u16,
-    /// Maximum concurrent connections
+    /// Maximum concurrent export requests; further requests are queued
     #[serde(default = "default_max_connections")]
     pub max_connections: usize,
+    /// Maximum size in bytes of a decoded gRPC request
+    #[serde(default = "default_max_decoding_message_size")]
+    pub max_decoding_message_size: usize,
+    /// Whether gzip-compressed gRPC requests are accepted
+    #[serde(default = "default_accept_gzip")]
+    pub accept_gzip: bool,
+    /// How long shutdown waits for the engine to write queued spans
+    #[serde(default = "default_shutdown_timeout_ms")]
+    pub shutdown_timeout_ms: u64,
+    /// Most spans accepted in one export request
+    #[serde(default = "default_max_spans_per_request")]
+    pub max_spans_per_request: usize,
+    /// Most `resource_spans` entries accepted in one export request
+    #[serde(default = "default_max_resource_spans")]
+    pub max_resource_spans: usize,
+    /// Whether exports with spans that conversion would drop (malformed ids,
+    /// start times outside `max_span_age_secs`/`max_span_skew_secs`) are
+    /// rejected with `INVALID_ARGUMENT` instead of being queued
+    #[serde(default)]
+    pub validate_on_ingest: bool,
+}
+
+impl ServerConfig {
+    /// Returns how long shutdown waits for queued messages
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_millis(self.shutdown_timeout_ms)
+    }
 }
 
 /// Storage backend configuration
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct StorageConfig {
     /// Storage bucket name
     pub bucket: String,
@@ -42,35 +121,693 @@ pub struct StorageConfig {
     /// Storage region (for cloud storage)
     #[serde(default = "default_region")]
     pub region: String,
+    /// Where spans are written
+    #[serde(default)]
+    pub backend: StorageBackend,
+    /// Object layout used when writing spans
+    #[serde(default)]
+    pub write_mode: WriteMode,
+    /// Skip writes whose object already exists, so retried exports are not stored twice
+    #[serde(default)]
+    pub idempotent_writes: bool,
+    /// Metadata attached to every stored object, e.g. `environment: production`
+    #[serde(default)]
+    pub object_metadata: HashMap<String, String>,
+    /// S3 tags attached to every span object; values may use `{service}` and `{date}`
+    #[serde(default)]
+    pub object_tags: HashMap<String, String>,
+    /// Encoding of stored objects
+    #[serde(default)]
+    pub format: StorageFormat,
+    /// Per-tenant buckets selected by a resource attribute
+    #[serde(default)]
+    pub tenant_routing: TenantRoutingConfig,
+    /// Key layout of per-span objects, e.g. `{prefix}/{service}/{date}/{trace_id}/{span_id}.json`
+    #[serde(default = "default_key_template")]
+    pub key_template: String,
+    /// Insert a `{hash}/` segment after the prefix of object keys, derived
+    /// from the trace id of per-span keys and from the key of batch objects,
+    /// spreading writes over S3 partitions
+    #[serde(default)]
+    pub key_prefix_hash: bool,
+    /// With `write_mode: per_trace`, how long a trace receives no new spans
+    /// before its buffered spans are written
+    #[serde(default = "default_trace_idle_timeout_ms")]
+    pub trace_idle_timeout_ms: u64,
+    /// With `write_mode: per_trace`, encoded bytes of buffered spans beyond
+    /// which the longest-idle traces are written before going idle
+    #[serde(default = "default_trace_buffer_max_bytes")]
+    pub trace_buffer_max_bytes: u64,
+    /// Custom S3-compatible endpoint, e.g. LocalStack or MinIO; `null` uses
+    /// the AWS endpoint of `region`
+    #[serde(default = "default_endpoint")]
+    pub endpoint: Option<String>,
+    /// Address buckets as `endpoint/bucket` instead of `bucket.endpoint`;
+    /// unset means path-style only with a custom `endpoint`
+    #[serde(default)]
+    pub force_path_style: Option<bool>,
+    /// Endpoints writes fail over to, in order, once writes to the active
+    /// endpoint fail after retries; reads stay on the primary endpoint
+    #[serde(default)]
+    pub fallback_endpoints: Vec<FallbackEndpoint>,
+    /// Maintain a span index under `_index/spans/` that `GET /search` reads
+    /// instead of scanning objects
+    #[serde(default)]
+    pub search_index: bool,
+    /// Span attribute keys recorded in span index entries, e.g. `http.method`,
+    /// so `/search` attribute filters on them need not read span objects
+    #[serde(default)]
+    pub indexed_attributes: Vec<String>,
+    /// Write JSON span objects indented instead of compact, for debugging
+    #[serde(default)]
+    pub pretty_json: bool,
+    /// How span start and end times are written in JSON objects
+    #[serde(default)]
+    pub timestamp_format: TimestampFormat,
+    /// How long one object PUT may take before it is abandoned and retried
+    #[serde(default = "default_write_timeout_ms")]
+    pub write_timeout_ms: u64,
+    /// Compression of JSON objects; Parquet files are always snappy-compressed
+    #[serde(default)]
+    pub compression: CompressionConfig,
+}
+
+impl StorageConfig {
+    /// Returns how long a trace must be idle before it is written
+    pub fn trace_idle_timeout(&self) -> Duration {
+        Duration::from_millis(self.trace_idle_timeout_ms)
+    }
+
+    /// Returns how long one object PUT may take
+    pub fn write_timeout(&self) -> Duration {
+        Duration::from_millis(self.write_timeout_ms)
+    }
+
+    /// Parses the per-span key layout, including the hash segment when enabled
+    pub fn key_template(&self) -> Result<KeyTemplate, ConfigError> {
+        Ok(KeyTemplate::parse(&self.key_template)?.with_prefix_hash(self.key_prefix_hash))
+    }
+
+    /// Parses the object tags, enforcing S3's tag limits
+    pub fn object_tags(&self) -> Result<ObjectTags, ConfigError> {
+        ObjectTags::parse(&self.object_tags)
+    }
+
+    /// Returns whether buckets are addressed path-style
+    pub fn path_style(&self) -> bool {
+        self.force_path_style.unwrap_or(self.endpoint.is_some())
+    }
+}
+
+/// Routing of spans to per-tenant storage locations
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TenantRoutingConfig {
+    /// Resource attribute holding the tenant id
+    #[serde(default = "default_tenant_attribute")]
+    pub attribute: String,
+    /// Storage location per tenant id; spans of other tenants use the default bucket
+    #[serde(default)]
+    pub tenants: HashMap<String, TenantLocation>,
+}
+
+/// S3-compatible endpoint holding the same bucket, used when the ones
+/// before it fail
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FallbackEndpoint {
+    /// Endpoint URL, addressed path-style unless `force_path_style` is false
+    pub endpoint: String,
+    /// Region requests are signed for; `null` uses the storage region
+    #[serde(default)]
+    pub region: Option<String>,
+}
+
+/// Bucket and key prefix of one tenant's spans
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TenantLocation {
+    /// Storage bucket name
+    pub bucket: String,
+    /// Key prefix for stored objects
+    #[serde(default)]
+    pub prefix: String,
+}
+
+/// Encoding of stored objects
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageFormat {
+    /// JSON objects laid out according to `write_mode`
+    #[default]
+    Json,
+    /// One Parquet file per batch, regardless of `write_mode`
+    Parquet,
+}
+
+impl std::str::FromStr for StorageFormat {
+    type Err = ConfigError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "json" => Ok(Self::Json),
+            "parquet" => Ok(Self::Parquet),
+            _ => Err(ConfigError::InvalidValue(format!(
+                "format must be json or parquet, got {}", value
+            ))),
+        }
+    }
+}
+
+/// How span start and end times are written in JSON objects
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampFormat {
+    /// Integer nanoseconds since epoch
+    #[default]
+    UnixNanos,
+    /// RFC3339 strings in UTC with nanosecond precision
+    Rfc3339,
+}
+
+impl std::str::FromStr for TimestampFormat {
+    type Err = ConfigError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "unix_nanos" => Ok(Self::UnixNanos),
+            "rfc3339" => Ok(Self::Rfc3339),
+            _ => Err(ConfigError::InvalidValue(format!(
+                "timestamp_format must be unix_nanos or rfc3339, got {}", value
+            ))),
+        }
+    }
+}
+
+/// Compression of stored JSON objects
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionConfig {
+    /// Algorithm objects are compressed with
+    #[serde(default)]
+    pub algorithm: CompressionAlgorithm,
+    /// Compression level, within the algorithm's range; higher trades speed for ratio
+    #[serde(default = "default_compression_level")]
+    pub level: i32,
+}
+
+impl CompressionConfig {
+    /// Checks the level against the algorithm's range
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let levels = self.algorithm.levels();
+        if !levels.contains(&self.level) {
+            return Err(ConfigError::InvalidValue(format!(
+                "storage.compression.level must be between {} and {} for {}, got {}",
+                levels.start(), levels.end(), self.algorithm, self.level
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            algorithm: CompressionAlgorithm::default(),
+            level: default_compression_level(),
+        }
+    }
+}
+
+/// Algorithm stored JSON objects are compressed with
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CompressionAlgorithm {
+    /// Objects are stored as written
+    #[default]
+    None,
+    /// gzip, readable by any HTTP client
+    Gzip,
+    /// zstd, faster and smaller than gzip at comparable levels
+    Zstd,
+}
+
+impl CompressionAlgorithm {
+    /// Returns the accepted compression levels
+    pub fn levels(self) -> std::ops::RangeInclusive<i32> {
+        match self {
+            Self::None => i32::MIN..=i32::MAX,
+            Self::Gzip => 0..=9,
+            Self::Zstd => 1..=22,
+        }
+    }
+}
+
+impl std::fmt::Display for CompressionAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::None => "none",
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        })
+    }
+}
+
+impl std::str::FromStr for CompressionAlgorithm {
+    type Err = ConfigError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "none" => Ok(Self::None),
+            "gzip" => Ok(Self::Gzip),
+            "zstd" => Ok(Self::Zstd),
+            _ => Err(ConfigError::InvalidValue(format!(
+                "compression must be none, gzip or zstd, got {}", value
+            ))),
+        }
+    }
+}
+
+/// Where spans are written
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    /// The S3 (or S3-compatible) bucket configured above
+    #[default]
+    S3,
+    /// Nowhere: spans are counted and discarded, for load testing ingestion
+    Null,
+}
+
+impl std::fmt::Display for StorageBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::S3 => "s3",
+            Self::Null => "null",
+        })
+    }
+}
+
+impl std::str::FromStr for StorageBackend {
+    type Err = ConfigError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "s3" => Ok(Self::S3),
+            "null" => Ok(Self::Null),
+            _ => Err(ConfigError::InvalidValue(format!(
+                "backend must be s3 or null, got {}", value
+            ))),
+        }
+    }
+}
+
+/// Object layout used when writing spans
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WriteMode {
+    /// One object per span, keyed by trace and span id
+    #[default]
+    PerSpan,
+    /// One object per batch holding an array of spans, keyed by hour
+    PerBatch,
+    /// One array object per trace, keyed by hour; the engine buffers each
+    /// trace's spans until the trace is idle
+    PerTrace,
+}
+
+impl std::str::FromStr for WriteMode {
+    type Err = ConfigError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "per_span" => Ok(Self::PerSpan),
+            "per_batch" => Ok(Self::PerBatch),
+            "per_trace" => Ok(Self::PerTrace),
+            _ => Err(ConfigError::InvalidValue(format!(
+                "write_mode must be per_span, per_batch or per_trace, got {}", value
+            ))),
+        }
+    }
+}
+
+/// What `batch_size` counts
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchUnit {
+    /// Export requests, however many spans each carries
+    #[default]
+    Requests,
+    /// Spans across all queued requests, for predictable write sizes
+    Spans,
 }
 
 /// Message processing configuration
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ProcessingConfig {
-    /// Number of messages to process in a batch
+    /// Number of messages (or spans, see `batch_by`) to process in a batch
     pub batch_size: usize,
-    /// Maximum time to wait before processing a partial batch
+    /// Whether `batch_size` counts requests or spans
+    #[serde(default)]
+    pub batch_by: BatchUnit,
+    /// Maximum time to wait before processing a partial batch.
+    /// Must be > 0; values below `MIN_BATCH_TIMEOUT_MS` (10ms) are clamped.
     pub batch_timeout_ms: u64,
+    /// Number of workers writing batches concurrently. Fixed once the engine
+    /// starts; with more than one worker, spans of a trace may be written out of order.
+    #[serde(default = "default_worker_count")]
+    pub worker_count: usize,
+    /// Requests buffered between the gRPC server and the engine before
+    /// exports wait; larger buffers absorb bursts at the cost of memory.
+    /// Fixed once the engine starts.
+    #[serde(default = "default_channel_capacity")]
+    pub channel_capacity: usize,
+    /// Encoded size of queued messages that triggers an immediate flush,
+    /// however far the queue is from `batch_size`
+    #[serde(default = "default_max_queue_bytes")]
+    pub max_queue_bytes: usize,
+    /// Attributes kept per span; further attributes are dropped
+    #[serde(default = "default_span_item_limit")]
+    pub max_attributes: usize,
+    /// Events kept per span; the oldest are dropped first
+    #[serde(default = "default_span_item_limit")]
+    pub max_events: usize,
+    /// Links kept per span; the oldest are dropped first
+    #[serde(default = "default_span_item_limit")]
+    pub max_links: usize,
+    /// Spans that started longer ago than this many seconds are rejected
+    #[serde(default)]
+    pub max_span_age_secs: Option<u64>,
+    /// Spans starting more than this many seconds in the future are rejected
+    #[serde(default)]
+    pub max_span_skew_secs: Option<u64>,
+    /// Flush storage once every write of a batch has finished, so a
+    /// buffering backend persists each batch before the next one starts
+    #[serde(default)]
+    pub flush_after_batch: bool,
+}
+
+impl ProcessingConfig {
+    /// Validates the batching parameters
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.batch_size == 0 {
+            return Err(ConfigError::InvalidValue("batch_size must be > 0".into()));
+        }
+        if self.batch_timeout_ms == 0 {
+            return Err(ConfigError::InvalidValue("batch_timeout_ms must be > 0".into()));
+        }
+        if self.worker_count == 0 {
+            return Err(ConfigError::InvalidValue("worker_count must be > 0".into()));
+        }
+        if self.channel_capacity == 0 {
+            return Err(ConfigError::InvalidValue("channel_capacity must be > 0".into()));
+        }
+        if self.max_queue_bytes == 0 {
+            return Err(ConfigError::InvalidValue("max_queue_bytes must be > 0".into()));
+        }
+        for (field, limit) in [
+            ("max_attributes", self.max_attributes),
+            ("max_events", self.max_events),
+            ("max_links", self.max_links),
+        ] {
+            if limit == 0 {
+                return Err(ConfigError::InvalidValue(format!("{} must be > 0", field)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the batch timeout, clamped to `MIN_BATCH_TIMEOUT_MS`
+    pub fn batch_timeout(&self) -> Duration {
+        if self.batch_timeout_ms < MIN_BATCH_TIMEOUT_MS {
+            warn!(
+                "batch_timeout_ms of {} is below the minimum, using {}ms",
+                self.batch_timeout_ms, MIN_BATCH_TIMEOUT_MS
+            );
+            return Duration::from_millis(MIN_BATCH_TIMEOUT_MS);
+        }
+        Duration::from_millis(self.batch_timeout_ms)
+    }
 }
 
 /// Retry policy configuration
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct RetryConfig {
     /// Maximum number of retry attempts
     pub max_retries: u32,
     /// Initial backoff duration in milliseconds
     pub initial_backoff_ms: u64,
-    /// Maximum backoff duration in milliseconds
+    /// Maximum backoff duration in milliseconds; also caps `Retry-After` hints
     pub max_backoff_ms: u64,
+    /// How delays are randomized below their exponential ceiling
+    #[serde(default)]
+    pub jitter: RetryJitter,
+}
+
+/// Randomization of retry delays
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryJitter {
+    /// Uniformly from zero up to the ceiling, so workers failing together
+    /// do not retry in lockstep
+    #[default]
+    Full,
+    /// Exactly the ceiling
+    None,
+}
+
+/// Trace-consistent sampling of ingested spans
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SamplingConfig {
+    /// Fraction of traces stored, from 0.0 to 1.0; whole traces are kept or dropped
+    #[serde(default = "default_sampling_ratio")]
+    pub ratio: f64,
+}
+
+/// In-memory suppression of spans resent within a short window
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DedupConfig {
+    /// Recently received span ids remembered; 0 disables deduplication
+    #[serde(default)]
+    pub max_entries: usize,
+    /// How long a stored span id suppresses repeats, in milliseconds
+    #[serde(default = "default_dedup_window_ms")]
+    pub window_ms: u64,
+}
+
+impl DedupConfig {
+    /// Returns the deduplication window
+    pub fn window(&self) -> Duration {
+        Duration::from_millis(self.window_ms)
+    }
+}
+
+/// Span name allow/deny lists and attribute redaction applied at ingestion.
+/// Patterns are globs (`*` and `?`), so `GET /health*` matches by prefix.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct IngestFilterConfig {
+    /// When non-empty, only spans whose name matches one of these are stored
+    #[serde(default)]
+    pub name_allow: Vec<String>,
+    /// Spans whose name matches any of these are dropped; takes precedence over `name_allow`
+    #[serde(default)]
+    pub name_deny: Vec<String>,
+    /// Attributes whose key matches any of these are removed from resources,
+    /// scopes, spans, events and links as requests are received, before
+    /// they are logged, spilled or stored
+    #[serde(default)]
+    pub redact_attributes: Vec<String>,
+}
+
+/// Token-bucket limit on export requests across all connections
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RateLimitConfig {
+    /// Average export requests admitted per second; 0 disables the limit
+    #[serde(default)]
+    pub max_requests_per_sec: f64,
+    /// Requests admitted at once after a quiet period
+    #[serde(default = "default_rate_limit_burst")]
+    pub burst: u32,
+}
+
+impl RateLimitConfig {
+    /// Returns the configured token bucket, or `None` when unlimited
+    pub fn limiter(&self) -> Option<TokenBucket> {
+        (self.max_requests_per_sec > 0.0).then(|| TokenBucket::new(self.max_requests_per_sec, self.burst))
+    }
+}
+
+/// Local buffering of requests whose storage writes failed
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SpillConfig {
+    /// Whether failed writes are spilled to `dir` and retried
+    #[serde(default)]
+    pub enabled: bool,
+    /// Directory spilled requests are written to
+    #[serde(default = "default_spill_dir")]
+    pub dir: String,
+    /// Total size of spilled requests kept; the oldest are dropped beyond this
+    #[serde(default = "default_spill_max_bytes")]
+    pub max_bytes: u64,
+    /// How often uploading spilled requests is retried, in milliseconds
+    #[serde(default = "default_spill_retry_interval_ms")]
+    pub retry_interval_ms: u64,
+}
+
+impl SpillConfig {
+    /// Returns the interval between upload attempts
+    pub fn retry_interval(&self) -> Duration {
+        Duration::from_millis(self.retry_interval_ms)
+    }
+}
+
+/// Local log of received requests replayed after a crash
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct WalConfig {
+    /// Whether requests are logged to `dir` until stored
+    #[serde(default)]
+    pub enabled: bool,
+    /// Directory the log segments are written to
+    #[serde(default = "default_wal_dir")]
+    pub dir: String,
+    /// Size at which a new log segment is started
+    #[serde(default = "default_wal_max_segment_bytes")]
+    pub max_segment_bytes: u64,
+}
+
+/// When repeated write failures mark the engine unhealthy
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct HealthConfig {
+    /// Write failures tolerated; one more marks the engine unhealthy
+    #[serde(default = "default_unhealthy_after_failures")]
+    pub unhealthy_after_failures: u64,
+    /// Count failures within this many seconds instead of consecutive
+    /// failures; 0 keeps the consecutive count
+    #[serde(default)]
+    pub failure_window_secs: u64,
+    /// Requests waiting in the channel to the engine above which health is
+    /// marked degraded once sustained for `degraded_after_ms`; 0 disables
+    /// backlog detection
+    #[serde(default)]
+    pub degraded_queue_size: u64,
+    /// How long the backlog must stay above `degraded_queue_size`, in milliseconds
+    #[serde(default = "default_degraded_after_ms")]
+    pub degraded_after_ms: u64,
+}
+
+impl HealthConfig {
+    /// Returns how long a backlog lasts before health is degraded
+    pub fn degraded_after(&self) -> Duration {
+        Duration::from_millis(self.degraded_after_ms)
+    }
+
+    /// Returns the failure window, or `None` when consecutive failures are counted
+    pub fn failure_window(&self) -> Option<Duration> {
+        (self.failure_window_secs > 0).then(|| Duration::from_secs(self.failure_window_secs))
+    }
 }
 
 /// Metrics collection configuration
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct MetricsConfig {
     /// Whether metrics collection is enabled
     pub enabled: bool,
     /// Interval for pushing metrics in milliseconds
     pub push_interval_ms: u64,
+    /// StatsD server (`host:port`) health counters are pushed to; nothing is pushed when unset
+    #[serde(default)]
+    pub statsd_addr: Option<String>,
+    /// Prefix of pushed metric names
+    #[serde(default = "default_metrics_prefix")]
+    pub prefix: String,
+}
+
+impl MetricsConfig {
+    /// Returns the interval between pushes
+    pub fn push_interval(&self) -> Duration {
+        Duration::from_millis(self.push_interval_ms)
+    }
+}
+
+/// Authentication configuration
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct AuthConfig {
+    /// Accepted bearer tokens; authentication is disabled when empty.
+    /// Serialized redacted, so only their number is ever exposed.
+    #[serde(default, serialize_with = "redact_secrets")]
+    pub bearer_tokens: Vec<String>,
+}
+
+/// Query API configuration
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReaderConfig {
+    /// Whether the HTTP query API is served at all
+    #[serde(default = "default_reader_enabled")]
+    pub enabled: bool,
+    /// Number of spans returned by `/spans` when no limit is given
+    #[serde(default = "default_reader_default_limit")]
+    pub default_limit: usize,
+    /// Largest `limit` accepted by `/spans`; larger requests are clamped
+    #[serde(default = "default_reader_max_limit")]
+    pub max_limit: usize,
+    /// Most recent objects read when `/spans` filters by service or attribute
+    #[serde(default = "default_reader_scan_limit")]
+    pub scan_limit: usize,
+    /// Address the HTTP query API binds to
+    #[serde(default = "default_reader_host")]
+    pub host: String,
+    /// Port of the HTTP query API; 0 picks an ephemeral port
+    #[serde(default = "default_reader_port")]
+    pub port: u16,
+}
+
+/// Listener serving only `/livez` and `/readyz`, so probes can be exposed
+/// without the query API
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OpsConfig {
+    /// Whether the ops listener is started
+    #[serde(default)]
+    pub enabled: bool,
+    /// Address the ops listener binds to
+    #[serde(default = "default_reader_host")]
+    pub host: String,
+    /// Port of the ops listener; 0 picks an ephemeral port
+    #[serde(default = "default_ops_port")]
+    pub port: u16,
+}
+
+/// Kafka topic carrying OTLP export requests, consumed alongside the gRPC
+/// server when the `kafka` feature is enabled
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct KafkaIngestConfig {
+    /// Comma-separated bootstrap brokers, e.g. `kafka-1:9092,kafka-2:9092`
+    pub brokers: String,
+    /// Topic whose messages are protobuf-encoded `ExportTraceServiceRequest`s
+    pub topic: String,
+    /// Consumer group committing offsets of messages queued for processing
+    #[serde(default = "default_kafka_group_id")]
+    pub group_id: String,
+}
+
+/// Self-instrumentation configuration.
+/// Leave disabled when `otlp_endpoint` points at this engine, since every
+/// export it receives would produce further spans to export.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SelfTelemetryConfig {
+    /// Whether the engine exports spans about its own work
+    #[serde(default)]
+    pub enabled: bool,
+    /// OTLP gRPC endpoint receiving the engine's own spans
+    #[serde(default = "default_self_telemetry_endpoint")]
+    pub otlp_endpoint: String,
+    /// `service.name` reported on the engine's own spans
+    #[serde(default = "default_self_telemetry_service_name")]
+    pub service_name: String,
+}
+
+/// Console log filtering; `level` can be changed by a reload
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct LoggingConfig {
+    /// `RUST_LOG`-style filter such as `info` or `info,storage_engine=debug`;
+    /// unset uses `RUST_LOG`, defaulting to `info`
+    #[serde(default)]
+    pub level: Option<String>,
 }
 
 impl Config {
@@ -87,10 +824,23 @@ impl Config {
         Self::from_env_vars()
     }
 
-    /// Loads configuration from a YAML file
+    /// Loads configuration from a file, choosing the parser from its extension
+    /// (`.json`, `.yaml` or `.yml`)
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
-        let contents = fs::read_to_string(path)
-            .map_err(|e| ConfigError::InvalidFormat(format!("Failed to read config file: {}", e)))?;
+        let path = path.as_ref();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json_file(path),
+            Some("yaml") | Some("yml") => Self::from_yaml_file(path),
+            _ => Err(ConfigError::InvalidFormat(format!(
+                "Unsupported config file {}; supported formats: .json, .yaml, .yml",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Loads configuration from a YAML file
+    pub fn from_yaml_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let contents = Self::read_file(path)?;
 
         let config: Config = serde_yaml::from_str(&contents)
             .map_err(|e| ConfigError::InvalidFormat(format!("Invalid YAML format: {}", e)))?;
@@ -99,6 +849,23 @@ impl Config {
         Ok(config)
     }
 
+    /// Loads configuration from a JSON file
+    pub fn from_json_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let contents = Self::read_file(path)?;
+
+        let config: Config = serde_json::from_str(&contents)
+            .map_err(|e| ConfigError::InvalidFormat(format!("Invalid JSON format: {}", e)))?;
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reads a config file into a string
+    fn read_file<P: AsRef<Path>>(path: P) -> Result<String, ConfigError> {
+        fs::read_to_string(path)
+            .map_err(|e| ConfigError::InvalidFormat(format!("Failed to read config file: {}", e)))
+    }
+
     /// Loads configuration from environment variables
     fn from_env_vars() -> Result<Self, ConfigError> {
         let config = Config {
@@ -109,16 +876,228 @@ impl Config {
                     .and_then(|p| p.parse().ok())
                     .unwrap_or(50051),
                 max_connections: default_max_connections(),
+                max_decoding_message_size: env::var("SERVER_MAX_MESSAGE_BYTES")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_max_decoding_message_size),
+                accept_gzip: default_accept_gzip(),
+                shutdown_timeout_ms: env::var("SERVER_SHUTDOWN_TIMEOUT_MS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_shutdown_timeout_ms),
+                max_spans_per_request: env::var("SERVER_MAX_SPANS_PER_REQUEST")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_max_spans_per_request),
+                max_resource_spans: env::var("SERVER_MAX_RESOURCE_SPANS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_max_resource_spans),
+                validate_on_ingest: env::var("SERVER_VALIDATE_ON_INGEST")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(false),
             },
             storage: StorageConfig {
                 bucket: env::var("STORAGE_BUCKET")
                     .map_err(|_| ConfigError::MissingField("STORAGE_BUCKET".into()))?,
                 prefix: env::var("STORAGE_PREFIX").unwrap_or_else(|_| "messages".to_string()),
-                region: default_region(),
+                region: env::var("STORAGE_REGION").unwrap_or_else(|_| default_region()),
+                backend: match env::var("STORAGE_BACKEND") {
+                    Ok(backend) => backend.parse()?,
+                    Err(_) => StorageBackend::default(),
+                },
+                write_mode: match env::var("STORAGE_WRITE_MODE") {
+                    Ok(mode) => mode.parse()?,
+                    Err(_) => WriteMode::default(),
+                },
+                idempotent_writes: env::var("STORAGE_IDEMPOTENT_WRITES")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(false),
+                object_metadata: HashMap::new(),
+                object_tags: HashMap::new(),
+                format: match env::var("STORAGE_FORMAT") {
+                    Ok(format) => format.parse()?,
+                    Err(_) => StorageFormat::default(),
+                },
+                tenant_routing: TenantRoutingConfig::default(),
+                key_template: env::var("STORAGE_KEY_TEMPLATE")
+                    .unwrap_or_else(|_| default_key_template()),
+                key_prefix_hash: env::var("STORAGE_KEY_PREFIX_HASH")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(false),
+                trace_idle_timeout_ms: env::var("STORAGE_TRACE_IDLE_TIMEOUT_MS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_trace_idle_timeout_ms),
+                trace_buffer_max_bytes: env::var("STORAGE_TRACE_BUFFER_MAX_BYTES")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_trace_buffer_max_bytes),
+                endpoint: match env::var("STORAGE_ENDPOINT") {
+                    Ok(endpoint) => Some(endpoint).filter(|e| !e.is_empty()),
+                    Err(_) => default_endpoint(),
+                },
+                force_path_style: env::var("STORAGE_FORCE_PATH_STYLE")
+                    .ok()
+                    .map(|v| v == "true" || v == "1"),
+                fallback_endpoints: env_list("STORAGE_FALLBACK_ENDPOINTS")
+                    .into_iter()
+                    .map(|endpoint| FallbackEndpoint { endpoint, region: None })
+                    .collect(),
+                search_index: env::var("STORAGE_SEARCH_INDEX")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(false),
+                indexed_attributes: env_list("STORAGE_INDEXED_ATTRIBUTES"),
+                pretty_json: env::var("STORAGE_PRETTY_JSON")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(false),
+                timestamp_format: match env::var("STORAGE_TIMESTAMP_FORMAT") {
+                    Ok(format) => format.parse()?,
+                    Err(_) => TimestampFormat::default(),
+                },
+                write_timeout_ms: env::var("STORAGE_WRITE_TIMEOUT_MS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_write_timeout_ms),
+                compression: CompressionConfig {
+                    algorithm: match env::var("STORAGE_COMPRESSION") {
+                        Ok(algorithm) => algorithm.parse()?,
+                        Err(_) => CompressionAlgorithm::default(),
+                    },
+                    level: env::var("STORAGE_COMPRESSION_LEVEL")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or_else(default_compression_level),
+                },
             },
             processing: ProcessingConfig::default(),
             retry: RetryConfig::default(),
-            metrics: MetricsConfig::default(),
+            metrics: MetricsConfig {
+                statsd_addr: env::var("METRICS_STATSD_ADDR").ok().filter(|addr| !addr.is_empty()),
+                ..MetricsConfig::default()
+            },
+            auth: AuthConfig {
+                bearer_tokens: env_list("AUTH_BEARER_TOKENS"),
+            },
+            reader: ReaderConfig {
+                enabled: env::var("READER_ENABLED")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or_else(|_| default_reader_enabled()),
+                default_limit: env::var("READER_DEFAULT_LIMIT")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_reader_default_limit),
+                max_limit: env::var("READER_MAX_LIMIT")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_reader_max_limit),
+                scan_limit: env::var("READER_SCAN_LIMIT")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_reader_scan_limit),
+                host: env::var("READER_HOST").unwrap_or_else(|_| default_reader_host()),
+                port: env::var("READER_PORT")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_reader_port),
+            },
+            self_telemetry: SelfTelemetryConfig {
+                enabled: env::var("SELF_TELEMETRY_ENABLED")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(false),
+                otlp_endpoint: env::var("SELF_TELEMETRY_ENDPOINT")
+                    .unwrap_or_else(|_| default_self_telemetry_endpoint()),
+                service_name: default_self_telemetry_service_name(),
+            },
+            logging: LoggingConfig::default(),
+            sampling: SamplingConfig {
+                ratio: env::var("SAMPLING_RATIO")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_sampling_ratio),
+            },
+            dedup: DedupConfig {
+                max_entries: env::var("DEDUP_MAX_ENTRIES")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(0),
+                window_ms: env::var("DEDUP_WINDOW_MS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_dedup_window_ms),
+            },
+            ingest_filter: IngestFilterConfig {
+                name_allow: env_list("INGEST_NAME_ALLOW"),
+                name_deny: env_list("INGEST_NAME_DENY"),
+                redact_attributes: env_list("INGEST_REDACT_ATTRIBUTES"),
+            },
+            rate_limit: RateLimitConfig {
+                max_requests_per_sec: env::var("RATE_LIMIT_MAX_REQUESTS_PER_SEC")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(0.0),
+                burst: env::var("RATE_LIMIT_BURST")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_rate_limit_burst),
+            },
+            spill: SpillConfig {
+                enabled: env::var("SPILL_ENABLED")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(false),
+                dir: env::var("SPILL_DIR").unwrap_or_else(|_| default_spill_dir()),
+                max_bytes: env::var("SPILL_MAX_BYTES")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_spill_max_bytes),
+                retry_interval_ms: env::var("SPILL_RETRY_INTERVAL_MS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_spill_retry_interval_ms),
+            },
+            wal: WalConfig {
+                enabled: env::var("WAL_ENABLED")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(false),
+                dir: env::var("WAL_DIR").unwrap_or_else(|_| default_wal_dir()),
+                max_segment_bytes: env::var("WAL_MAX_SEGMENT_BYTES")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_wal_max_segment_bytes),
+            },
+            health: HealthConfig {
+                unhealthy_after_failures: env::var("HEALTH_UNHEALTHY_AFTER_FAILURES")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_unhealthy_after_failures),
+                failure_window_secs: env::var("HEALTH_FAILURE_WINDOW_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(0),
+                degraded_queue_size: env::var("HEALTH_DEGRADED_QUEUE_SIZE")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(0),
+                degraded_after_ms: env::var("HEALTH_DEGRADED_AFTER_MS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_degraded_after_ms),
+            },
+            ops: OpsConfig {
+                enabled: env::var("OPS_ENABLED")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(false),
+                host: env::var("OPS_HOST").unwrap_or_else(|_| default_reader_host()),
+                port: env::var("OPS_PORT")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(default_ops_port),
+            },
+            kafka: env::var("KAFKA_BROKERS").ok().map(|brokers| KafkaIngestConfig {
+                brokers,
+                topic: env::var("KAFKA_TOPIC").unwrap_or_default(),
+                group_id: env::var("KAFKA_GROUP_ID").unwrap_or_else(|_| default_kafka_group_id()),
+            }),
         };
 
         config.validate()?;
@@ -127,26 +1106,209 @@ impl Config {
 
     /// Validates the configuration values
     pub fn validate(&self) -> Result<(), ConfigError> {
-        if self.processing.batch_size == 0 {
-            return Err(ConfigError::InvalidValue("batch_size must be > 0".into()));
+        if self.server.port == 0 {
+            return Err(ConfigError::InvalidValue("server.port must be > 0".into()));
+        }
+        if self.server.max_connections == 0 {
+            return Err(ConfigError::InvalidValue("server.max_connections must be > 0".into()));
+        }
+        if self.server.shutdown_timeout_ms == 0 {
+            return Err(ConfigError::InvalidValue("server.shutdown_timeout_ms must be > 0".into()));
+        }
+        if self.server.max_spans_per_request == 0 {
+            return Err(ConfigError::InvalidValue("server.max_spans_per_request must be > 0".into()));
         }
+        if self.server.max_resource_spans == 0 {
+            return Err(ConfigError::InvalidValue("server.max_resource_spans must be > 0".into()));
+        }
+        if self.storage.bucket.trim().is_empty() {
+            return Err(ConfigError::InvalidValue("storage.bucket must not be empty".into()));
+        }
+        if self.storage.region.trim().is_empty() {
+            return Err(ConfigError::InvalidValue("storage.region must not be empty".into()));
+        }
+        if self.storage.endpoint.as_ref().is_some_and(|e| e.trim().is_empty()) {
+            return Err(ConfigError::InvalidValue("storage.endpoint must not be empty when set".into()));
+        }
+        for (i, fallback) in self.storage.fallback_endpoints.iter().enumerate() {
+            if fallback.endpoint.trim().is_empty() {
+                return Err(ConfigError::InvalidValue(format!(
+                    "storage.fallback_endpoints[{}].endpoint must not be empty", i
+                )));
+            }
+            if fallback.region.as_ref().is_some_and(|region| region.trim().is_empty()) {
+                return Err(ConfigError::InvalidValue(format!(
+                    "storage.fallback_endpoints[{}].region must not be empty when set", i
+                )));
+            }
+        }
+        if self.storage.indexed_attributes.iter().any(|key| key.trim().is_empty()) {
+            return Err(ConfigError::InvalidValue("storage.indexed_attributes must not contain empty keys".into()));
+        }
+        if self.metrics.enabled && self.metrics.push_interval_ms == 0 {
+            return Err(ConfigError::InvalidValue(
+                "metrics.push_interval_ms must be > 0 when metrics are enabled".into()
+            ));
+        }
+        if self.metrics.statsd_addr.as_ref().is_some_and(|addr| addr.trim().is_empty()) {
+            return Err(ConfigError::InvalidValue("metrics.statsd_addr must not be empty when set".into()));
+        }
+        if self.server.max_decoding_message_size == 0 {
+            return Err(ConfigError::InvalidValue("max_decoding_message_size must be > 0".into()));
+        }
+        self.processing.validate()?;
         if self.retry.max_retries == 0 {
             return Err(ConfigError::InvalidValue("max_retries must be > 0".into()));
         }
+        if self.auth.bearer_tokens.iter().any(|t| t.trim().is_empty()) {
+            return Err(ConfigError::InvalidValue("bearer_tokens must not be empty strings".into()));
+        }
         if self.retry.max_backoff_ms < self.retry.initial_backoff_ms {
             return Err(ConfigError::InvalidValue(
                 "max_backoff_ms must be >= initial_backoff_ms".into()
             ));
         }
+        if self.reader.default_limit == 0 {
+            return Err(ConfigError::InvalidValue("reader.default_limit must be > 0".into()));
+        }
+        if self.reader.max_limit < self.reader.default_limit {
+            return Err(ConfigError::InvalidValue(
+                "reader.max_limit must be >= reader.default_limit".into()
+            ));
+        }
+        if self.reader.scan_limit == 0 {
+            return Err(ConfigError::InvalidValue("reader.scan_limit must be > 0".into()));
+        }
+        if self.reader.host.trim().is_empty() {
+            return Err(ConfigError::InvalidValue("reader.host must not be empty".into()));
+        }
+        if self.ops.enabled {
+            if self.ops.host.trim().is_empty() {
+                return Err(ConfigError::InvalidValue("ops.host must not be empty when ops is enabled".into()));
+            }
+            if self.reader.enabled && self.ops.port != 0 && self.ops.port == self.reader.port {
+                return Err(ConfigError::InvalidValue("ops.port must differ from reader.port".into()));
+            }
+        }
+        if self.storage.tenant_routing.attribute.trim().is_empty() {
+            return Err(ConfigError::InvalidValue(
+                "storage.tenant_routing.attribute must not be empty".into()
+            ));
+        }
+        if let Some(tenant) = self.storage.tenant_routing.tenants.iter()
+            .find_map(|(tenant, location)| location.bucket.trim().is_empty().then_some(tenant))
+        {
+            return Err(ConfigError::InvalidValue(format!(
+                "storage.tenant_routing.tenants.{}.bucket must not be empty", tenant
+            )));
+        }
+        self.storage.key_template()?;
+        self.storage.object_tags()?;
+        if !(0.0..=1.0).contains(&self.sampling.ratio) {
+            return Err(ConfigError::InvalidValue(
+                "sampling.ratio must be between 0.0 and 1.0".into()
+            ));
+        }
+        if !(self.rate_limit.max_requests_per_sec >= 0.0 && self.rate_limit.max_requests_per_sec.is_finite()) {
+            return Err(ConfigError::InvalidValue(
+                "rate_limit.max_requests_per_sec must be a non-negative number".into()
+            ));
+        }
+        if self.rate_limit.max_requests_per_sec > 0.0 && self.rate_limit.burst == 0 {
+            return Err(ConfigError::InvalidValue(
+                "rate_limit.burst must be > 0 when rate_limit.max_requests_per_sec is set".into()
+            ));
+        }
+        if self.dedup.max_entries > 0 && self.dedup.window_ms == 0 {
+            return Err(ConfigError::InvalidValue(
+                "dedup.window_ms must be > 0 when dedup.max_entries is set".into()
+            ));
+        }
+        let pattern_lists = [
+            ("name_allow", &self.ingest_filter.name_allow),
+            ("name_deny", &self.ingest_filter.name_deny),
+            ("redact_attributes", &self.ingest_filter.redact_attributes),
+        ];
+        if let Some(field) = pattern_lists
+            .into_iter()
+            .find_map(|(field, patterns)| patterns.iter().any(|p| p.is_empty()).then_some(field))
+        {
+            return Err(ConfigError::InvalidValue(format!(
+                "ingest_filter.{} must not contain empty patterns", field
+            )));
+        }
+        if let Some(level) = &self.logging.level {
+            if let Err(e) = EnvFilter::try_new(level) {
+                return Err(ConfigError::InvalidValue(format!("logging.level is not a valid filter: {}", e)));
+            }
+        }
+        if self.storage.write_timeout_ms == 0 {
+            return Err(ConfigError::InvalidValue("storage.write_timeout_ms must be > 0".into()));
+        }
+        self.storage.compression.validate()?;
+        if self.storage.write_mode == WriteMode::PerTrace && self.storage.trace_idle_timeout_ms == 0 {
+            return Err(ConfigError::InvalidValue(
+                "storage.trace_idle_timeout_ms must be > 0 with write_mode per_trace".into()
+            ));
+        }
+        if self.storage.write_mode == WriteMode::PerTrace && self.storage.trace_buffer_max_bytes == 0 {
+            return Err(ConfigError::InvalidValue(
+                "storage.trace_buffer_max_bytes must be > 0 with write_mode per_trace".into()
+            ));
+        }
+        if self.spill.enabled {
+            if self.spill.dir.trim().is_empty() {
+                return Err(ConfigError::InvalidValue(
+                    "spill.dir must not be empty when spill is enabled".into()
+                ));
+            }
+            if self.spill.max_bytes == 0 {
+                return Err(ConfigError::InvalidValue(
+                    "spill.max_bytes must be > 0 when spill is enabled".into()
+                ));
+            }
+            if self.spill.retry_interval_ms == 0 {
+                return Err(ConfigError::InvalidValue(
+                    "spill.retry_interval_ms must be > 0 when spill is enabled".into()
+                ));
+            }
+        }
+        if self.wal.enabled {
+            if self.wal.dir.trim().is_empty() {
+                return Err(ConfigError::InvalidValue(
+                    "wal.dir must not be empty when the WAL is enabled".into()
+                ));
+            }
+            if self.wal.max_segment_bytes == 0 {
+                return Err(ConfigError::InvalidValue(
+                    "wal.max_segment_bytes must be > 0 when the WAL is enabled".into()
+                ));
+            }
+        }
+        if let Some(kafka) = &self.kafka {
+            if let Some(field) = [("brokers", &kafka.brokers), ("topic", &kafka.topic), ("group_id", &kafka.group_id)]
+                .into_iter()
+                .find_map(|(field, value)| value.trim().is_empty().then_some(field))
+            {
+                return Err(ConfigError::InvalidValue(format!("kafka.{} must not be empty", field)));
+            }
+        }
+        if self.self_telemetry.enabled && self.self_telemetry.otlp_endpoint.trim().is_empty() {
+            return Err(ConfigError::InvalidValue(
+                "self_telemetry.otlp_endpoint must not be empty when self telemetry is enabled".into()
+            ));
+        }
         Ok(())
     }
 
+    /// Creates a validated configuration; sections not passed use their defaults
     pub fn new(
         server: ServerConfig,
         storage: StorageConfig,
         processing: ProcessingConfig,
         retry: RetryConfig,
         metrics: MetricsConfig,
+        auth: AuthConfig,
     ) -> Result<Self, ConfigError> {
         let config = Self {
             server,
@@ -154,6 +1316,19 @@ impl Config {
             processing,
             retry,
             metrics,
+            auth,
+            reader: ReaderConfig::default(),
+            self_telemetry: SelfTelemetryConfig::default(),
+            logging: LoggingConfig::default(),
+            sampling: SamplingConfig::default(),
+            dedup: DedupConfig::default(),
+            ingest_filter: IngestFilterConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            spill: SpillConfig::default(),
+            wal: WalConfig::default(),
+            health: HealthConfig::default(),
+            ops: OpsConfig::default(),
+            kafka: None,
         };
         config.validate()?;
         Ok(config)
@@ -166,6 +1341,16 @@ impl Default for ProcessingConfig {
         Self {
             batch_size: 100,
             batch_timeout_ms: 5000,
+            batch_by: BatchUnit::default(),
+            worker_count: default_worker_count(),
+            channel_capacity: default_channel_capacity(),
+            max_queue_bytes: default_max_queue_bytes(),
+            max_attributes: default_span_item_limit(),
+            max_events: default_span_item_limit(),
+            max_links: default_span_item_limit(),
+            max_span_age_secs: None,
+            max_span_skew_secs: None,
+            flush_after_batch: false,
         }
     }
 }
@@ -176,6 +1361,107 @@ impl Default for RetryConfig {
             max_retries: 3,
             initial_backoff_ms: 100,
             max_backoff_ms: 1000,
+            jitter: RetryJitter::default(),
+        }
+    }
+}
+
+impl Default for ReaderConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_reader_enabled(),
+            default_limit: default_reader_default_limit(),
+            max_limit: default_reader_max_limit(),
+            scan_limit: default_reader_scan_limit(),
+            host: default_reader_host(),
+            port: default_reader_port(),
+        }
+    }
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            unhealthy_after_failures: default_unhealthy_after_failures(),
+            failure_window_secs: 0,
+            degraded_queue_size: 0,
+            degraded_after_ms: default_degraded_after_ms(),
+        }
+    }
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            ratio: default_sampling_ratio(),
+        }
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_requests_per_sec: 0.0,
+            burst: default_rate_limit_burst(),
+        }
+    }
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: 0,
+            window_ms: default_dedup_window_ms(),
+        }
+    }
+}
+
+impl Default for OpsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: default_reader_host(),
+            port: default_ops_port(),
+        }
+    }
+}
+
+impl Default for SpillConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: default_spill_dir(),
+            max_bytes: default_spill_max_bytes(),
+            retry_interval_ms: default_spill_retry_interval_ms(),
+        }
+    }
+}
+
+impl Default for WalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: default_wal_dir(),
+            max_segment_bytes: default_wal_max_segment_bytes(),
+        }
+    }
+}
+
+impl Default for TenantRoutingConfig {
+    fn default() -> Self {
+        Self {
+            attribute: default_tenant_attribute(),
+            tenants: HashMap::new(),
+        }
+    }
+}
+
+impl Default for SelfTelemetryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            otlp_endpoint: default_self_telemetry_endpoint(),
+            service_name: default_self_telemetry_service_name(),
         }
     }
 }
@@ -185,18 +1471,188 @@ impl Default for MetricsConfig {
         Self {
             enabled: true,
             push_interval_ms: 10000,
+            statsd_addr: None,
+            prefix: default_metrics_prefix(),
         }
     }
 }
 
+/// Placeholder serialized in place of each secret value
+pub const REDACTED: &str = "[redacted]";
+
+/// Serializes secrets as one `REDACTED` placeholder each
+fn redact_secrets<S: Serializer>(secrets: &[String], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(secrets.iter().map(|_| REDACTED))
+}
+
+fn default_degraded_after_ms() -> u64 {
+    30_000
+}
+
+fn default_metrics_prefix() -> String {
+    "storage_engine".to_string()
+}
+
+/// Reads a comma-separated list from an environment variable, skipping empty items
+fn env_list(name: &str) -> Vec<String> {
+    env::var(name)
+        .map(|list| {
+            list.split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn default_reader_default_limit() -> usize {
+    5
+}
+
+fn default_reader_max_limit() -> usize {
+    1000
+}
+
+fn default_reader_scan_limit() -> usize {
+    10_000
+}
+
+fn default_worker_count() -> usize {
+    1
+}
+
+fn default_channel_capacity() -> usize {
+    1000
+}
+
+fn default_unhealthy_after_failures() -> u64 {
+    5
+}
+
+fn default_sampling_ratio() -> f64 {
+    1.0
+}
+
+fn default_dedup_window_ms() -> u64 {
+    10_000
+}
+
+fn default_rate_limit_burst() -> u32 {
+    100
+}
+
+fn default_spill_dir() -> String {
+    "spill".to_string()
+}
+
+fn default_spill_max_bytes() -> u64 {
+    1024 * 1024 * 1024
+}
+
+fn default_spill_retry_interval_ms() -> u64 {
+    5000
+}
+
+fn default_wal_dir() -> String {
+    "wal".to_string()
+}
+
+fn default_wal_max_segment_bytes() -> u64 {
+    64 * 1024 * 1024
+}
+
+fn default_reader_host() -> String {
+    "0.0.0.0".to_string()
+}
+
+fn default_reader_port() -> u16 {
+    3000
+}
+
+fn default_reader_enabled() -> bool {
+    true
+}
+
+fn default_ops_port() -> u16 {
+    8081
+}
+
+fn default_kafka_group_id() -> String {
+    "storage-engine".to_string()
+}
+
+fn default_tenant_attribute() -> String {
+    "tenant.id".to_string()
+}
+
+fn default_span_item_limit() -> usize {
+    128
+}
+
+fn default_max_queue_bytes() -> usize {
+    64 * 1024 * 1024
+}
+
+fn default_self_telemetry_endpoint() -> String {
+    "http://localhost:4317".to_string()
+}
+
+fn default_self_telemetry_service_name() -> String {
+    "storage-engine".to_string()
+}
+
 fn default_max_connections() -> usize {
     1000
 }
 
+fn default_max_decoding_message_size() -> usize {
+    4 * 1024 * 1024
+}
+
+fn default_accept_gzip() -> bool {
+    true
+}
+
+fn default_shutdown_timeout_ms() -> u64 {
+    30_000
+}
+
+fn default_max_spans_per_request() -> usize {
+    100_000
+}
+
+fn default_max_resource_spans() -> usize {
+    10_000
+}
+
 fn default_region() -> String {
     "us-west-2".to_string()
 }
 
+fn default_endpoint() -> Option<String> {
+    Some("http://localhost:4566".to_string())
+}
+
+fn default_key_template() -> String {
+    DEFAULT_KEY_TEMPLATE.to_string()
+}
+
+fn default_trace_idle_timeout_ms() -> u64 {
+    5_000
+}
+
+fn default_trace_buffer_max_bytes() -> u64 {
+    256 * 1024 * 1024
+}
+
+fn default_compression_level() -> i32 {
+    6
+}
+
+fn default_write_timeout_ms() -> u64 {
+    DEFAULT_WRITE_TIMEOUT.as_millis() as u64
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -210,23 +1666,267 @@ mod tests {
                 host: "localhost".into(),
                 port: 8080,
                 max_connections: 1000,
+                max_decoding_message_size: 4 * 1024 * 1024,
+                accept_gzip: true,
+                shutdown_timeout_ms: 30_000,
+                max_spans_per_request: 100_000,
+                max_resource_spans: 10_000,
+                validate_on_ingest: false,
             },
             storage: StorageConfig {
                 bucket: "test-bucket".into(),
                 prefix: "test".into(),
                 region: "us-west-2".into(),
+                backend: StorageBackend::S3,
+                write_mode: WriteMode::PerSpan,
+                idempotent_writes: false,
+                object_metadata: HashMap::new(),
+                object_tags: HashMap::new(),
+                format: StorageFormat::Json,
+                tenant_routing: TenantRoutingConfig::default(),
+                key_template: default_key_template(),
+                key_prefix_hash: false,
+                trace_idle_timeout_ms: 5_000,
+                trace_buffer_max_bytes: 268_435_456,
+                endpoint: None,
+                force_path_style: None,
+                fallback_endpoints: Vec::new(),
+                indexed_attributes: Vec::new(),
+                search_index: false,
+                pretty_json: false,
+                timestamp_format: TimestampFormat::UnixNanos,
+                write_timeout_ms: 30_000,
+                compression: CompressionConfig::default(),
             },
             processing: ProcessingConfig {
                 batch_size: 0,  // Invalid
                 batch_timeout_ms: 1000,
+                ..ProcessingConfig::default()
             },
             retry: RetryConfig::default(),
             metrics: MetricsConfig::default(),
+            auth: AuthConfig::default(),
+            reader: ReaderConfig::default(),
+            self_telemetry: SelfTelemetryConfig::default(),
+            logging: LoggingConfig::default(),
+            sampling: SamplingConfig::default(),
+            dedup: DedupConfig::default(),
+            ingest_filter: IngestFilterConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            spill: SpillConfig::default(),
+            wal: WalConfig::default(),
+            health: HealthConfig::default(),
+            ops: OpsConfig::default(),
+            kafka: None,
         };
 
         assert!(config.validate().is_err());
     }
 
+    type ConfigMutation = fn(&mut Config);
+
+    fn valid_config() -> Config {
+        Config {
+            server: ServerConfig {
+                host: "localhost".into(),
+                port: 8080,
+                max_connections: 1000,
+                max_decoding_message_size: 4 * 1024 * 1024,
+                accept_gzip: true,
+                shutdown_timeout_ms: 30_000,
+                max_spans_per_request: 100_000,
+                max_resource_spans: 10_000,
+                validate_on_ingest: false,
+            },
+            storage: StorageConfig {
+                bucket: "test-bucket".into(),
+                prefix: "test".into(),
+                region: "us-west-2".into(),
+                backend: StorageBackend::S3,
+                write_mode: WriteMode::PerSpan,
+                idempotent_writes: false,
+                object_metadata: HashMap::new(),
+                object_tags: HashMap::new(),
+                format: StorageFormat::Json,
+                tenant_routing: TenantRoutingConfig::default(),
+                key_template: default_key_template(),
+                key_prefix_hash: false,
+                trace_idle_timeout_ms: 5_000,
+                trace_buffer_max_bytes: 268_435_456,
+                endpoint: None,
+                force_path_style: None,
+                fallback_endpoints: Vec::new(),
+                indexed_attributes: Vec::new(),
+                search_index: false,
+                pretty_json: false,
+                timestamp_format: TimestampFormat::UnixNanos,
+                write_timeout_ms: 30_000,
+                compression: CompressionConfig::default(),
+            },
+            processing: ProcessingConfig::default(),
+            retry: RetryConfig::default(),
+            metrics: MetricsConfig::default(),
+            auth: AuthConfig::default(),
+            reader: ReaderConfig::default(),
+            self_telemetry: SelfTelemetryConfig::default(),
+            logging: LoggingConfig::default(),
+            sampling: SamplingConfig::default(),
+            dedup: DedupConfig::default(),
+            ingest_filter: IngestFilterConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            spill: SpillConfig::default(),
+            wal: WalConfig::default(),
+            health: HealthConfig::default(),
+            ops: OpsConfig::default(),
+            kafka: None,
+        }
+    }
+
+    #[test]
+    fn test_valid_config_passes() {
+        assert!(valid_config().validate().is_ok());
+    }
+
+    #[test]
+    fn test_invalid_fields_rejected() {
+        let cases: Vec<(&str, ConfigMutation)> = vec![
+            ("storage.bucket", |c| c.storage.bucket = "".into()),
+            ("storage.region", |c| c.storage.region = " ".into()),
+            ("storage.endpoint", |c| c.storage.endpoint = Some(String::new())),
+            ("storage.indexed_attributes", |c| c.storage.indexed_attributes = vec!["".into()]),
+            ("storage.fallback_endpoints[0].endpoint", |c| {
+                c.storage.fallback_endpoints = vec![FallbackEndpoint { endpoint: " ".into(), region: None }];
+            }),
+            ("storage.write_timeout_ms", |c| c.storage.write_timeout_ms = 0),
+            ("storage.compression.level", |c| {
+                c.storage.compression = CompressionConfig { algorithm: CompressionAlgorithm::Gzip, level: 10 };
+            }),
+            ("storage.compression.level", |c| {
+                c.storage.compression = CompressionConfig { algorithm: CompressionAlgorithm::Zstd, level: 0 };
+            }),
+            ("server.port", |c| c.server.port = 0),
+            ("server.max_connections", |c| c.server.max_connections = 0),
+            ("server.shutdown_timeout_ms", |c| c.server.shutdown_timeout_ms = 0),
+            ("server.max_spans_per_request", |c| c.server.max_spans_per_request = 0),
+            ("server.max_resource_spans", |c| c.server.max_resource_spans = 0),
+            ("metrics.push_interval_ms", |c| c.metrics.push_interval_ms = 0),
+            ("metrics.statsd_addr", |c| c.metrics.statsd_addr = Some(" ".into())),
+            ("batch_timeout_ms", |c| c.processing.batch_timeout_ms = 0),
+            ("worker_count", |c| c.processing.worker_count = 0),
+            ("channel_capacity", |c| c.processing.channel_capacity = 0),
+            ("max_queue_bytes", |c| c.processing.max_queue_bytes = 0),
+            ("max_attributes", |c| c.processing.max_attributes = 0),
+            ("max_events", |c| c.processing.max_events = 0),
+            ("max_links", |c| c.processing.max_links = 0),
+            ("reader.default_limit", |c| c.reader.default_limit = 0),
+            ("reader.max_limit", |c| c.reader.max_limit = c.reader.default_limit - 1),
+            ("reader.scan_limit", |c| c.reader.scan_limit = 0),
+            ("reader.host", |c| c.reader.host = String::new()),
+            ("ops.host", |c| {
+                c.ops.enabled = true;
+                c.ops.host = String::new();
+            }),
+            ("ops.port", |c| {
+                c.ops.enabled = true;
+                c.ops.port = c.reader.port;
+            }),
+            ("kafka.topic", |c| {
+                c.kafka = Some(KafkaIngestConfig {
+                    brokers: "localhost:9092".into(),
+                    topic: " ".into(),
+                    group_id: default_kafka_group_id(),
+                });
+            }),
+            ("sampling.ratio", |c| c.sampling.ratio = 1.5),
+            ("ingest_filter.name_deny", |c| c.ingest_filter.name_deny = vec![String::new()]),
+            ("logging.level", |c| c.logging.level = Some("info,=[".into())),
+            ("ingest_filter.redact_attributes", |c| c.ingest_filter.redact_attributes = vec![String::new()]),
+            ("rate_limit.max_requests_per_sec", |c| c.rate_limit.max_requests_per_sec = -1.0),
+            ("rate_limit.burst", |c| {
+                c.rate_limit.max_requests_per_sec = 50.0;
+                c.rate_limit.burst = 0;
+            }),
+            ("dedup.window_ms", |c| {
+                c.dedup.max_entries = 1000;
+                c.dedup.window_ms = 0;
+            }),
+            ("spill.dir", |c| {
+                c.spill.enabled = true;
+                c.spill.dir = String::new();
+            }),
+            ("spill.max_bytes", |c| {
+                c.spill.enabled = true;
+                c.spill.max_bytes = 0;
+            }),
+            ("wal.dir", |c| {
+                c.wal.enabled = true;
+                c.wal.dir = String::new();
+            }),
+            ("wal.max_segment_bytes", |c| {
+                c.wal.enabled = true;
+                c.wal.max_segment_bytes = 0;
+            }),
+            ("key_template", |c| c.storage.key_template = "{prefix}/{trace_id}.json".into()),
+            ("storage.object_tags.team", |c| {
+                c.storage.object_tags.insert("team".into(), "x".repeat(257));
+            }),
+            ("storage.trace_idle_timeout_ms", |c| {
+                c.storage.write_mode = WriteMode::PerTrace;
+                c.storage.trace_idle_timeout_ms = 0;
+            }),
+            ("storage.trace_buffer_max_bytes", |c| {
+                c.storage.write_mode = WriteMode::PerTrace;
+                c.storage.trace_buffer_max_bytes = 0;
+            }),
+            ("storage.tenant_routing.attribute", |c| c.storage.tenant_routing.attribute = " ".into()),
+            ("storage.tenant_routing.tenants.acme.bucket", |c| {
+                c.storage.tenant_routing.tenants.insert(
+                    "acme".into(),
+                    TenantLocation { bucket: String::new(), prefix: "traces".into() },
+                );
+            }),
+            ("self_telemetry.otlp_endpoint", |c| {
+                c.self_telemetry.enabled = true;
+                c.self_telemetry.otlp_endpoint = String::new();
+            }),
+        ];
+
+        for (field, mutate) in cases {
+            let mut config = valid_config();
+            mutate(&mut config);
+
+            match config.validate() {
+                Err(ConfigError::InvalidValue(msg)) => {
+                    assert!(msg.contains(field), "message {:?} should name {}", msg, field)
+                }
+                other => panic!("{}: expected InvalidValue, got {:?}", field, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_small_batch_timeout_clamped() {
+        let mut config = valid_config();
+        config.processing.batch_timeout_ms = 1;
+
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.processing.batch_timeout(),
+            Duration::from_millis(MIN_BATCH_TIMEOUT_MS)
+        );
+
+        config.processing.batch_timeout_ms = 250;
+        assert_eq!(config.processing.batch_timeout(), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_push_interval_ignored_when_metrics_disabled() {
+        let mut config = valid_config();
+        config.metrics.enabled = false;
+        config.metrics.push_interval_ms = 0;
+        assert!(config.validate().is_ok());
+    }
+
     #[test]
     fn test_config_from_file() -> Result<(), Box<dyn std::error::Error>> {
         let config_content = r#"
@@ -241,12 +1941,77 @@ mod tests {
               batch_timeout_ms: 5000
         "#;
 
-        let mut file = NamedTempFile::new()?;
+        let mut file = NamedTempFile::with_suffix(".yaml")?;
         write!(file, "{}", config_content)?;
 
         let config = Config::from_file(file.path())?;
         assert_eq!(config.server.port, 50051);
         assert_eq!(config.storage.bucket, "test-bucket");
+        assert_eq!(config.server.max_decoding_message_size, 4 * 1024 * 1024);
+        assert!(config.server.accept_gzip);
+        assert_eq!(config.storage.backend, StorageBackend::S3);
+        assert_eq!(config.storage.write_mode, WriteMode::PerSpan);
+        assert_eq!(config.storage.endpoint.as_deref(), Some("http://localhost:4566"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_path_style_follows_endpoint() {
+        let mut storage = valid_config().storage;
+        assert!(!storage.path_style(), "AWS endpoints default to virtual-hosted style");
+
+        storage.endpoint = Some("http://minio:9000".into());
+        assert!(storage.path_style(), "custom endpoints default to path-style");
+
+        storage.endpoint = None;
+
+        storage.force_path_style = Some(true);
+        assert!(storage.path_style());
+    }
+
+    #[test]
+    fn test_json_and_yaml_configs_match() -> Result<(), Box<dyn std::error::Error>> {
+        let yaml_content = r#"
+            server:
+              host: "0.0.0.0"
+              port: 50051
+            storage:
+              bucket: "test-bucket"
+              prefix: "test"
+              write_mode: per_batch
+            processing:
+              batch_size: 50
+              batch_timeout_ms: 2000
+        "#;
+        let json_content = r#"{
+            "server": { "host": "0.0.0.0", "port": 50051 },
+            "storage": { "bucket": "test-bucket", "prefix": "test", "write_mode": "per_batch" },
+            "processing": { "batch_size": 50, "batch_timeout_ms": 2000 }
+        }"#;
+
+        let mut yaml_file = NamedTempFile::with_suffix(".yml")?;
+        write!(yaml_file, "{}", yaml_content)?;
+        let mut json_file = NamedTempFile::with_suffix(".json")?;
+        write!(json_file, "{}", json_content)?;
+
+        let from_yaml = Config::from_file(yaml_file.path())?;
+        let from_json = Config::from_file(json_file.path())?;
+        assert_eq!(from_yaml, from_json);
+        assert_eq!(from_json.processing.batch_size, 50);
+        assert_eq!(from_json.storage.write_mode, WriteMode::PerBatch);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_config_extension() -> Result<(), Box<dyn std::error::Error>> {
+        let file = NamedTempFile::with_suffix(".toml")?;
+
+        match Config::from_file(file.path()) {
+            Err(ConfigError::InvalidFormat(msg)) => assert!(msg.contains(".json, .yaml, .yml")),
+            other => panic!("Expected InvalidFormat, got {:?}", other),
+        }
 
         Ok(())
     }
@@ -0,0 +1,455 @@
+use std::borrow::Cow;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::ProcessingConfig;
+use crate::error::ProcessingError;
+use crate::proto::Span;
+use crate::proto::opentelemetry::proto::common::v1::{
+    any_value, AnyValue, InstrumentationScope, KeyValue as ProtoKeyValue,
+};
+use crate::proto::opentelemetry::proto::trace::v1::span::{
+    Event as ProtoEvent, Link as ProtoLink, SpanKind as ProtoSpanKind,
+};
+use crate::proto::opentelemetry::proto::trace::v1::status::StatusCode;
+use crate::proto::opentelemetry::proto::trace::v1::Status as ProtoStatus;
+
+use opentelemetry::{
+    sdk::{
+        export::trace::SpanData,
+        trace::{EvictedHashMap, EvictedQueue},
+        Resource,
+    },
+    trace::{Event, Link, SpanContext, SpanId, SpanKind, Status, TraceFlags, TraceId, TraceState},
+    Array, InstrumentationLibrary, KeyValue, StringValue, Value,
+};
+
+/// Length in bytes of an OTLP trace id
+pub(crate) const TRACE_ID_LEN: usize = 16;
+
+/// Length in bytes of an OTLP span id
+pub(crate) const SPAN_ID_LEN: usize = 8;
+
+/// Per-span ceilings on converted attributes, events and links, and the
+/// accepted range of start times
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SpanLimits {
+    pub(crate) attributes: usize,
+    pub(crate) events: usize,
+    pub(crate) links: usize,
+    max_age: Option<Duration>,
+    max_skew: Option<Duration>,
+}
+
+impl SpanLimits {
+    /// Rejects start times older than `max_age` or more than `max_skew` ahead of `now`
+    fn check_start_time(&self, start_time: SystemTime, now: SystemTime) -> Result<(), ProcessingError> {
+        match now.duration_since(start_time) {
+            Ok(age) => match self.max_age {
+                Some(max_age) if age > max_age => Err(ProcessingError::ValidationError(format!(
+                    "start_time is {}s in the past, beyond max_span_age_secs of {}",
+                    age.as_secs(), max_age.as_secs()
+                ))),
+                _ => Ok(()),
+            },
+            Err(e) => match self.max_skew {
+                Some(max_skew) if e.duration() > max_skew => Err(ProcessingError::ValidationError(format!(
+                    "start_time is {}s in the future, beyond max_span_skew_secs of {}",
+                    e.duration().as_secs(), max_skew.as_secs()
+                ))),
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+impl From<&ProcessingConfig> for SpanLimits {
+    fn from(config: &ProcessingConfig) -> Self {
+        Self {
+            attributes: config.max_attributes,
+            events: config.max_events,
+            links: config.max_links,
+            max_age: config.max_span_age_secs.map(Duration::from_secs),
+            max_skew: config.max_span_skew_secs.map(Duration::from_secs),
+        }
+    }
+}
+
+/// Converts proto spans into OpenTelemetry spans field by field, applying
+/// the configured span limits
+#[derive(Debug, Clone, Copy)]
+pub struct SpanConverter {
+    limits: SpanLimits,
+}
+
+impl From<&ProcessingConfig> for SpanConverter {
+    fn from(config: &ProcessingConfig) -> Self {
+        Self { limits: SpanLimits::from(config) }
+    }
+}
+
+impl Default for SpanConverter {
+    fn default() -> Self {
+        Self::from(&ProcessingConfig::default())
+    }
+}
+
+impl SpanConverter {
+    /// Returns the limits applied during conversion
+    pub(crate) fn limits(&self) -> &SpanLimits {
+        &self.limits
+    }
+
+    /// Converts a proto span into an OpenTelemetry span
+    pub fn convert_span(
+        &self,
+        span: Span,
+        resource: &Resource,
+        scope: &InstrumentationLibrary,
+    ) -> Result<SpanData, ProcessingError> {
+        let (span_context, parent_span_id) = self.convert_ids(&span)?;
+        let (start_time, end_time) = self.convert_times(&span, SystemTime::now())?;
+
+        Ok(SpanData {
+            span_context,
+            parent_span_id,
+            span_kind: self.convert_kind(span.kind),
+            name: Cow::from(span.name),
+            start_time,
+            end_time,
+            attributes: self.convert_attributes(span.attributes),
+            events: self.convert_events(span.events),
+            links: self.convert_links(span.links),
+            status: self.convert_status(span.status),
+            resource: Cow::Owned(resource.clone()),
+            instrumentation_lib: scope.clone(),
+        })
+    }
+
+    /// Parses the trace, span and parent span ids of a span into its context
+    /// and parent id; an empty or all-zero parent marks a root span
+    pub fn convert_ids(&self, span: &Span) -> Result<(SpanContext, SpanId), ProcessingError> {
+        let parent_span_id = if span.parent_span_id.iter().any(|&b| b != 0) {
+            parse_span_id(&span.parent_span_id, "parent_span_id")?
+        } else if span.parent_span_id.is_empty() || span.parent_span_id.len() == SPAN_ID_LEN {
+            SpanId::INVALID
+        } else {
+            return Err(invalid_length("parent_span_id", SPAN_ID_LEN, span.parent_span_id.len()));
+        };
+
+        let span_context = SpanContext::new(
+            parse_trace_id(&span.trace_id)?,
+            parse_span_id(&span.span_id, "span_id")?,
+            TraceFlags::default(),
+            false,
+            TraceState::default(),
+        );
+        Ok((span_context, parent_span_id))
+    }
+
+    /// Runs the id and time checks of `convert_span` without converting the
+    /// rest, so a span it would drop can be rejected before being queued
+    pub fn validate(&self, span: &Span, now: SystemTime) -> Result<(), ProcessingError> {
+        self.convert_ids(span)?;
+        self.convert_times(span, now)?;
+        Ok(())
+    }
+
+    /// Converts the start and end of a span, rejecting start times outside
+    /// the configured age and skew window around `now`
+    pub fn convert_times(&self, span: &Span, now: SystemTime) -> Result<(SystemTime, SystemTime), ProcessingError> {
+        let start_time = UNIX_EPOCH + Duration::from_nanos(span.start_time_unix_nano);
+        self.limits.check_start_time(start_time, now)?;
+        Ok((start_time, UNIX_EPOCH + Duration::from_nanos(span.end_time_unix_nano)))
+    }
+
+    /// Maps a proto span kind; unspecified and unknown kinds are internal
+    pub fn convert_kind(&self, kind: i32) -> SpanKind {
+        match ProtoSpanKind::try_from(kind) {
+            Ok(ProtoSpanKind::Server) => SpanKind::Server,
+            Ok(ProtoSpanKind::Client) => SpanKind::Client,
+            Ok(ProtoSpanKind::Producer) => SpanKind::Producer,
+            Ok(ProtoSpanKind::Consumer) => SpanKind::Consumer,
+            Ok(ProtoSpanKind::Internal | ProtoSpanKind::Unspecified) | Err(_) => SpanKind::Internal,
+        }
+    }
+
+    /// Maps a proto span status; a missing status or unknown code is unset
+    pub fn convert_status(&self, status: Option<ProtoStatus>) -> Status {
+        let Some(status) = status else {
+            return Status::Unset;
+        };
+        match StatusCode::try_from(status.code) {
+            Ok(StatusCode::Ok) => Status::Ok,
+            Ok(StatusCode::Error) => Status::error(status.message),
+            Ok(StatusCode::Unset) | Err(_) => Status::Unset,
+        }
+    }
+
+    /// Converts span attributes, evicting those beyond the attribute limit
+    pub fn convert_attributes(&self, attributes: Vec<ProtoKeyValue>) -> EvictedHashMap {
+        let mut converted = EvictedHashMap::new(self.limits.attributes as u32, attributes.len());
+        for attribute in convert_key_values(attributes) {
+            converted.insert(attribute);
+        }
+        converted
+    }
+
+    /// Converts proto span events, evicting those beyond the event limit
+    pub fn convert_events(&self, events: Vec<ProtoEvent>) -> EvictedQueue<Event> {
+        let mut converted: Vec<Event> = events
+            .into_iter()
+            .map(|event| Event::new(
+                event.name,
+                UNIX_EPOCH + Duration::from_nanos(event.time_unix_nano),
+                convert_key_values(event.attributes),
+                event.dropped_attributes_count,
+            ))
+            .collect();
+
+        let mut queue = EvictedQueue::new(self.limits.events as u32);
+        queue.append_vec(&mut converted);
+        queue
+    }
+
+    /// Converts proto span links, evicting those beyond the link limit.
+    /// Links with malformed trace or span ids are skipped.
+    pub fn convert_links(&self, links: Vec<ProtoLink>) -> EvictedQueue<Link> {
+        let mut converted: Vec<Link> = links
+            .into_iter()
+            .filter_map(|link| {
+                let span_context = SpanContext::new(
+                    parse_trace_id(&link.trace_id).ok()?,
+                    parse_span_id(&link.span_id, "link span_id").ok()?,
+                    TraceFlags::default(),
+                    true,
+                    TraceState::default(),
+                );
+                let mut converted = Link::new(span_context, convert_key_values(link.attributes));
+                converted.dropped_attributes_count = link.dropped_attributes_count;
+                Some(converted)
+            })
+            .collect();
+
+        let mut queue = EvictedQueue::new(self.limits.links as u32);
+        queue.append_vec(&mut converted);
+        queue
+    }
+}
+
+/// Builds the validation error for an id of the wrong length
+pub(crate) fn invalid_length(field: &str, expected: usize, actual: usize) -> ProcessingError {
+    ProcessingError::ValidationError(format!(
+        "{} must be {} bytes, got {}", field, expected, actual
+    ))
+}
+
+/// Parses a 16-byte trace id, rejecting other lengths and the all-zero id
+pub(crate) fn parse_trace_id(bytes: &[u8]) -> Result<TraceId, ProcessingError> {
+    let bytes: [u8; TRACE_ID_LEN] = bytes
+        .try_into()
+        .map_err(|_| invalid_length("trace_id", TRACE_ID_LEN, bytes.len()))?;
+    let trace_id = TraceId::from_bytes(bytes);
+    if trace_id == TraceId::INVALID {
+        return Err(ProcessingError::ValidationError("trace_id must not be all zeros".into()));
+    }
+    Ok(trace_id)
+}
+
+/// Parses an 8-byte span id, rejecting other lengths and the all-zero id
+pub(crate) fn parse_span_id(bytes: &[u8], field: &str) -> Result<SpanId, ProcessingError> {
+    let bytes: [u8; SPAN_ID_LEN] = bytes
+        .try_into()
+        .map_err(|_| invalid_length(field, SPAN_ID_LEN, bytes.len()))?;
+    let span_id = SpanId::from_bytes(bytes);
+    if span_id == SpanId::INVALID {
+        return Err(ProcessingError::ValidationError(format!("{} must not be all zeros", field)));
+    }
+    Ok(span_id)
+}
+
+/// Converts a proto instrumentation scope; empty strings mean unset
+pub(crate) fn convert_scope(scope: Option<InstrumentationScope>, schema_url: &str) -> InstrumentationLibrary {
+    let scope = scope.unwrap_or_default();
+    let non_empty = |value: String| (!value.is_empty()).then_some(value);
+    InstrumentationLibrary::new(
+        scope.name,
+        non_empty(scope.version),
+        non_empty(schema_url.to_string()),
+        Some(convert_key_values(scope.attributes)),
+    )
+}
+
+/// Converts proto key/value pairs into OpenTelemetry attributes
+pub(crate) fn convert_key_values(attributes: Vec<ProtoKeyValue>) -> Vec<KeyValue> {
+    attributes
+        .into_iter()
+        .filter_map(|kv| {
+            let value = convert_any_value(kv.value?)?;
+            Some(KeyValue::new(kv.key, value))
+        })
+        .collect()
+}
+
+/// Converts a proto `AnyValue` into an OpenTelemetry `Value`.
+/// Heterogeneous arrays, key/value lists and bytes are kept as JSON/hex strings.
+fn convert_any_value(value: AnyValue) -> Option<Value> {
+    let value = match value.value? {
+        any_value::Value::StringValue(v) => Value::String(v.into()),
+        any_value::Value::BoolValue(v) => Value::Bool(v),
+        any_value::Value::IntValue(v) => Value::I64(v),
+        any_value::Value::DoubleValue(v) => Value::F64(v),
+        any_value::Value::BytesValue(v) => Value::String(hex::encode(v).into()),
+        any_value::Value::ArrayValue(array) => convert_array(&array.values)
+            .unwrap_or_else(|| Value::String(serde_json::to_string(&array).unwrap_or_default().into())),
+        any_value::Value::KvlistValue(list) => {
+            Value::String(serde_json::to_string(&list).unwrap_or_default().into())
+        }
+    };
+    Some(value)
+}
+
+/// Converts a homogeneous proto array into an OpenTelemetry array
+fn convert_array(values: &[AnyValue]) -> Option<Value> {
+    let items: Vec<_> = values.iter().filter_map(|v| v.value.as_ref()).collect();
+
+    if let Some(strings) = items.iter().map(|v| match v {
+        any_value::Value::StringValue(s) => Some(StringValue::from(s.clone())),
+        _ => None,
+    }).collect::<Option<Vec<_>>>() {
+        return Some(Value::Array(Array::String(strings)));
+    }
+    if let Some(bools) = items.iter().map(|v| match v {
+        any_value::Value::BoolValue(b) => Some(*b),
+        _ => None,
+    }).collect::<Option<Vec<_>>>() {
+        return Some(Value::Array(Array::Bool(bools)));
+    }
+    if let Some(ints) = items.iter().map(|v| match v {
+        any_value::Value::IntValue(i) => Some(*i),
+        _ => None,
+    }).collect::<Option<Vec<_>>>() {
+        return Some(Value::Array(Array::I64(ints)));
+    }
+    if let Some(doubles) = items.iter().map(|v| match v {
+        any_value::Value::DoubleValue(d) => Some(*d),
+        _ => None,
+    }).collect::<Option<Vec<_>>>() {
+        return Some(Value::Array(Array::F64(doubles)));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::opentelemetry::proto::common::v1::ArrayValue;
+
+    fn attribute(key: &str, value: any_value::Value) -> ProtoKeyValue {
+        ProtoKeyValue {
+            key: key.to_string(),
+            value: Some(AnyValue { value: Some(value) }),
+        }
+    }
+
+    fn span_at(start_time_unix_nano: u64) -> Span {
+        Span {
+            trace_id: vec![1; 16],
+            span_id: vec![2; 8],
+            start_time_unix_nano,
+            end_time_unix_nano: start_time_unix_nano + 1_000,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_convert_ids() {
+        let converter = SpanConverter::default();
+        let mut span = span_at(1);
+
+        let (context, parent) = converter.convert_ids(&span).unwrap();
+        assert_eq!(context.trace_id(), TraceId::from_bytes([1; 16]));
+        assert_eq!(context.span_id(), SpanId::from_bytes([2; 8]));
+        assert_eq!(parent, SpanId::INVALID);
+
+        span.parent_span_id = vec![0; 8];
+        assert_eq!(converter.convert_ids(&span).unwrap().1, SpanId::INVALID);
+        span.parent_span_id = vec![3; 8];
+        assert_eq!(converter.convert_ids(&span).unwrap().1, SpanId::from_bytes([3; 8]));
+        span.parent_span_id = vec![3; 4];
+        assert!(converter.convert_ids(&span).is_err());
+    }
+
+    #[test]
+    fn test_convert_times() {
+        let converter = SpanConverter::from(&ProcessingConfig {
+            max_span_age_secs: Some(60),
+            max_span_skew_secs: Some(10),
+            ..ProcessingConfig::default()
+        });
+        let now = UNIX_EPOCH + Duration::from_secs(1_000);
+        let span = |secs: u64| span_at(secs * 1_000_000_000);
+
+        let (start, end) = converter.convert_times(&span(990), now).unwrap();
+        assert_eq!(start, UNIX_EPOCH + Duration::from_secs(990));
+        assert_eq!(end, start + Duration::from_micros(1));
+        assert!(converter.convert_times(&span(1_005), now).is_ok());
+        assert!(converter.convert_times(&span(900), now).is_err());
+        assert!(converter.convert_times(&span(1_020), now).is_err());
+        assert!(SpanConverter::default().convert_times(&span(1), now).is_ok());
+    }
+
+    #[test]
+    fn test_convert_kind() {
+        let converter = SpanConverter::default();
+        let cases = [
+            (ProtoSpanKind::Unspecified as i32, SpanKind::Internal),
+            (ProtoSpanKind::Internal as i32, SpanKind::Internal),
+            (ProtoSpanKind::Server as i32, SpanKind::Server),
+            (ProtoSpanKind::Client as i32, SpanKind::Client),
+            (ProtoSpanKind::Producer as i32, SpanKind::Producer),
+            (ProtoSpanKind::Consumer as i32, SpanKind::Consumer),
+            (42, SpanKind::Internal),
+        ];
+        for (kind, expected) in cases {
+            assert_eq!(converter.convert_kind(kind), expected, "kind {}", kind);
+        }
+    }
+
+    #[test]
+    fn test_convert_status() {
+        let converter = SpanConverter::default();
+        let status = |code: i32, message: &str| Some(ProtoStatus { code, message: message.to_string() });
+
+        assert_eq!(converter.convert_status(None), Status::Unset);
+        assert_eq!(converter.convert_status(status(StatusCode::Unset as i32, "")), Status::Unset);
+        assert_eq!(converter.convert_status(status(StatusCode::Ok as i32, "")), Status::Ok);
+        assert_eq!(
+            converter.convert_status(status(StatusCode::Error as i32, "card declined")),
+            Status::error("card declined")
+        );
+        assert_eq!(converter.convert_status(status(7, "")), Status::Unset);
+    }
+
+    #[test]
+    fn test_convert_attributes() {
+        let converter = SpanConverter::from(&ProcessingConfig { max_attributes: 2, ..ProcessingConfig::default() });
+        let array = ArrayValue {
+            values: vec![
+                AnyValue { value: Some(any_value::Value::IntValue(1)) },
+                AnyValue { value: Some(any_value::Value::IntValue(2)) },
+            ],
+        };
+        let attributes = converter.convert_attributes(vec![
+            attribute("http.method", any_value::Value::StringValue("GET".into())),
+            attribute("retries", any_value::Value::ArrayValue(array)),
+            attribute("payload", any_value::Value::BytesValue(vec![0xab])),
+            ProtoKeyValue { key: "unset".into(), value: None },
+        ]);
+
+        assert_eq!(attributes.len(), 2);
+        assert_eq!(attributes.dropped_count(), 1);
+        let value = |key: &str| attributes.get(&opentelemetry::Key::new(key.to_string())).cloned();
+        assert_eq!(value("retries"), Some(Value::Array(Array::I64(vec![1, 2]))));
+        assert_eq!(value("payload"), Some(Value::String("ab".into())));
+    }
+}
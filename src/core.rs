@@ -1,60 +1,489 @@
-use std::borrow::Cow;
+use std::collections::HashMap;
 use std::time::Duration;
 use std::sync::Arc;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, watch, Mutex};
+use tokio::task::JoinHandle;
 use tokio::time::{self, Instant};
-use tracing::{error, info};
+use prost::Message;
+use tracing::{error, info, info_span, instrument, warn, Instrument};
 
-use crate::config::ProcessingConfig;
-use crate::error::{ProcessingError, StorageError};
-use crate::proto::{ExportTraceServiceRequest, ExportTraceServiceResponse, Span};
-use crate::storage::{S3StorageWriter, StorageWriter};
+use crate::config::{BatchUnit, ProcessingConfig, SpillConfig};
+use crate::convert::{convert_key_values, convert_scope, parse_span_id, parse_trace_id, SpanConverter};
+use crate::error::{ConfigError, ProcessingError, StorageError};
+use crate::proto::ExportTraceServiceRequest;
+use crate::storage::{S3ClientSettings, S3StorageWriter, StorageWriter};
 use crate::health::HealthCheck;
+use crate::dedup::{SpanDeduplicator, SpanKey};
+use crate::enrich::SpanEnricher;
+use crate::ingest_filter::{AttributeRedactor, SpanNameFilter};
+use crate::sampling::TraceSampler;
+use crate::spill::SpillBuffer;
+use crate::trace_buffer::{BufferedTrace, TraceBuffer};
+use crate::wal::WriteAheadLog;
 
-use opentelemetry::{
-    sdk::{
-        export::trace::SpanData,
-        trace::{EvictedHashMap, EvictedQueue},
-    },
-    trace::{SpanContext, SpanId, SpanKind, Status, TraceFlags, TraceId, TraceState},
-};
+use opentelemetry::sdk::{export::trace::SpanData, Resource};
+
+/// Handle for adjusting the engine's batching while `process_messages` runs.
+/// Cloned handles share the same engine.
+#[derive(Clone)]
+pub struct EngineControl {
+    /// Latest batching parameters, observed by the engine loop
+    processing: Arc<watch::Sender<ProcessingConfig>>,
+}
+
+impl EngineControl {
+    /// Returns the batching parameters currently in effect
+    pub fn processing_config(&self) -> ProcessingConfig {
+        self.processing.borrow().clone()
+    }
+
+    /// Validates and applies new batching parameters
+    pub fn update_processing(&self, config: ProcessingConfig) -> Result<(), ConfigError> {
+        config.validate()?;
+        info!(
+            "Updating batching: batch_size={}, batch_timeout_ms={}",
+            config.batch_size, config.batch_timeout_ms
+        );
+        self.processing.send_replace(config);
+        Ok(())
+    }
+}
+
+/// Spans converted from a batch of requests, or one buffered trace,
+/// waiting to be written by a worker
+struct WriteJob {
+    /// Converted spans
+    spans: Vec<SpanData>,
+    /// Encoded size of the originating requests
+    byte_size: u64,
+    /// Originating requests' kept spans as one encoded request, kept only
+    /// when spilling is enabled
+    spill_data: Option<Vec<u8>>,
+    /// Write-ahead log entries committed once the spans are stored or spilled
+    wal_entries: Vec<u64>,
+    /// Dropped once the job is written, letting its batch wait for its writes
+    batch_guard: Option<mpsc::Sender<()>>,
+    /// Batch span the write is recorded under
+    parent: tracing::Span,
+}
+
+/// Writes converted spans to storage and records the outcome
+#[derive(Clone)]
+struct BatchWriter {
+    /// Storage backend for persisting trace data
+    storage_writer: Arc<dyn StorageWriter>,
+    /// Health monitoring for the engine
+    health_check: Arc<HealthCheck>,
+    /// Where requests go when their write fails
+    spill: Option<Arc<SpillBuffer>>,
+    /// Log whose entries are committed once stored
+    wal: Option<Arc<WriteAheadLog>>,
+    /// Entries whose spans are buffered by trace, shared with the engine
+    buffered_entries: BufferedEntries,
+    /// Dedup window of the engine, which forgets spans whose write is lost
+    deduplicator: SharedDeduplicator,
+}
+
+/// Write-ahead log entries whose spans were split across buffered traces,
+/// with the number of those traces not yet written
+type BufferedEntries = Arc<std::sync::Mutex<HashMap<u64, usize>>>;
+
+/// Deduplicator checked by the engine and updated by the batch workers
+type SharedDeduplicator = Arc<std::sync::Mutex<SpanDeduplicator>>;
+
+impl BatchWriter {
+    /// Writes one job's spans, logging failures and spilling the job's
+    /// requests when a spill buffer is attached
+    async fn write(&self, job: WriteJob) {
+        let span_count = job.spans.len() as u64;
+        let keys: Vec<SpanKey> = job.spans
+            .iter()
+            .map(|span| (span.span_context.trace_id(), span.span_context.span_id()))
+            .collect();
+
+        let started = Instant::now();
+        let result = self.storage_writer
+            .write_spans(job.spans)
+            .instrument(info_span!(parent: &job.parent, "write_spans", spans = span_count))
+            .await;
+        self.health_check.record_write_latency(started.elapsed());
+
+        match result {
+            Ok(()) => {
+                self.health_check.record_successful_write();
+                self.health_check.record_spans_written(span_count, job.byte_size);
+                info!("Message processed successfully");
+                self.commit_wal_entries(job.wal_entries).await;
+            }
+            Err(e) => {
+                self.health_check.record_failed_write();
+                error!("Failed to process message: {}", ProcessingError::StorageError(e.to_string()));
+                let spilled = match (&self.spill, job.spill_data) {
+                    (Some(spill), Some(data)) => self.spill_request(spill, &data, span_count).await,
+                    _ => false,
+                };
+                if spilled {
+                    self.commit_wal_entries(job.wal_entries).await;
+                } else {
+                    // Lost, so a resend must not be dropped as a duplicate
+                    self.deduplicator.lock().unwrap().forget(keys);
+                }
+            }
+        }
+        // However the write ended, shutdown no longer waits for these spans,
+        // and only now may a batch waiting to flush proceed
+        self.health_check.record_spans_settled(span_count);
+        drop(job.batch_guard);
+    }
+
+    /// Marks a job's write-ahead log entries as no longer needed. An entry
+    /// whose spans were split across buffered traces is committed once the
+    /// last of those traces is written.
+    async fn commit_wal_entries(&self, entries: Vec<u64>) {
+        let Some(wal) = &self.wal else { return };
+        for entry in entries {
+            let written = {
+                let mut buffered = self.buffered_entries.lock().unwrap();
+                match buffered.get_mut(&entry) {
+                    Some(traces) if *traces > 1 => {
+                        *traces -= 1;
+                        false
+                    }
+                    _ => {
+                        buffered.remove(&entry);
+                        true
+                    }
+                }
+            };
+            if written {
+                if let Err(e) = wal.commit(entry).await {
+                    error!("Failed to commit write-ahead log entry {}: {}", entry, e);
+                }
+            }
+        }
+    }
+
+    /// Keeps a request whose write failed for a later upload, returning
+    /// whether it was spilled
+    async fn spill_request(&self, spill: &SpillBuffer, data: &[u8], span_count: u64) -> bool {
+        match spill.spill(data).await {
+            Ok(dropped) => {
+                self.health_check.record_spilled(span_count);
+                if dropped > 0 {
+                    warn!("Spill buffer full, dropped {} oldest spilled requests", dropped);
+                    self.health_check.record_spill_dropped(dropped);
+                }
+                info!("Spilled {} spans for a later upload", span_count);
+                true
+            }
+            Err(e) => {
+                error!("Failed to spill {} spans: {}", span_count, e);
+                false
+            }
+        }
+    }
+}
+
+/// Workers spawned by `process_messages`, draining a shared job queue
+struct WorkerPool {
+    /// Sends jobs to whichever worker is free
+    jobs: mpsc::Sender<WriteJob>,
+    /// Worker tasks, awaited on shutdown
+    handles: Vec<JoinHandle<()>>,
+}
+
+/// A received request on its way to the engine, with the write-ahead log
+/// entry it was logged under, if any
+#[derive(Debug)]
+pub struct QueuedMessage {
+    pub request: ExportTraceServiceRequest,
+    pub wal_entry: Option<u64>,
+}
+
+impl From<ExportTraceServiceRequest> for QueuedMessage {
+    fn from(request: ExportTraceServiceRequest) -> Self {
+        Self { request, wal_entry: None }
+    }
+}
+
+/// Sending half of the queue between receivers (gRPC, Kafka) and the
+/// engine. With a write-ahead log attached, each request is logged before
+/// it is queued, so a request is on disk by the time it is acknowledged.
+/// With a redactor, requests are redacted before anything else sees them.
+#[derive(Clone)]
+pub struct MessageSender {
+    sender: mpsc::Sender<QueuedMessage>,
+    wal: Option<Arc<WriteAheadLog>>,
+    redactor: Option<AttributeRedactor>,
+}
+
+impl From<mpsc::Sender<QueuedMessage>> for MessageSender {
+    fn from(sender: mpsc::Sender<QueuedMessage>) -> Self {
+        Self { sender, wal: None, redactor: None }
+    }
+}
+
+impl MessageSender {
+    /// Removes the attributes `redactor` matches from each request before
+    /// it is logged or queued, so they never reach the log, spill or storage
+    pub fn with_redactor(mut self, redactor: AttributeRedactor) -> Self {
+        self.redactor = Some(redactor);
+        self
+    }
+
+    /// Appends each request to `wal` before queueing it; the engine holding
+    /// the same log commits the entry once the request is stored
+    pub fn with_wal(mut self, wal: Arc<WriteAheadLog>) -> Self {
+        self.wal = Some(wal);
+        self
+    }
+
+    /// Logs `request` when a write-ahead log is attached, then queues it,
+    /// waiting for room. A request that fails to be logged is still queued.
+    /// Fails once the engine has closed its channel; the request's entry is
+    /// then committed, as its sender is told it was not taken.
+    pub async fn send(&self, mut request: ExportTraceServiceRequest) -> Result<(), ProcessingError> {
+        if let Some(redactor) = &self.redactor {
+            redactor.redact(&mut request);
+        }
+        let wal_entry = match &self.wal {
+            Some(wal) => match wal.append(&request.encode_to_vec()).await {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    error!("Failed to append request to write-ahead log: {}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+        if self.sender.send(QueuedMessage { request, wal_entry }).await.is_ok() {
+            return Ok(());
+        }
+        if let (Some(wal), Some(entry)) = (&self.wal, wal_entry) {
+            if let Err(e) = wal.commit(entry).await {
+                error!("Failed to commit write-ahead log entry {}: {}", entry, e);
+            }
+        }
+        Err(ProcessingError::ShutdownError("engine channel closed".into()))
+    }
+
+    /// Most requests the channel buffers
+    pub fn max_capacity(&self) -> usize {
+        self.sender.max_capacity()
+    }
+
+    /// Room left in the channel
+    pub fn capacity(&self) -> usize {
+        self.sender.capacity()
+    }
+
+    /// Records the channel's backlog in `health_check` every `interval` from
+    /// a task of its own, so a backlog is seen while the engine is blocked
+    /// writing a batch. The task ends once every sender is dropped.
+    pub fn spawn_backlog_monitor(&self, health_check: Arc<HealthCheck>, interval: Duration) -> JoinHandle<()> {
+        let sender = self.sender.downgrade();
+        tokio::spawn(async move {
+            let mut ticks = time::interval(interval);
+            loop {
+                ticks.tick().await;
+                let Some(sender) = sender.upgrade() else { break };
+                health_check.update_channel_backlog((sender.max_capacity() - sender.capacity()) as u64);
+            }
+        })
+    }
+}
+
+/// Creates the queue between the gRPC server and the engine. Up to
+/// `channel_capacity` requests are buffered before exports wait for room.
+pub fn message_channel(config: &ProcessingConfig) -> (MessageSender, mpsc::Receiver<QueuedMessage>) {
+    let (sender, receiver) = mpsc::channel(config.channel_capacity);
+    (MessageSender::from(sender), receiver)
+}
 
 /// Core engine responsible for processing and storing trace data.
 /// Handles message batching, span conversion, and storage operations.
+///
+/// Batches are converted on the engine task and written by
+/// `worker_count` workers, so slow writes overlap. With more than one
+/// worker, writes complete in no particular order: spans of one trace may
+/// be stored out of order or across separate writes.
 pub struct EngineCore {
     /// Channel for receiving trace messages
-    message_receiver: mpsc::Receiver<ExportTraceServiceRequest>,
-    /// Maximum number of messages to process in a batch
+    message_receiver: mpsc::Receiver<QueuedMessage>,
+    /// Maximum number of messages (or spans, see `batch_by`) to process in a batch
     batch_size: usize,
+    /// Whether `batch_size` counts requests or spans
+    batch_by: BatchUnit,
     /// Maximum time to wait before processing a partial batch
     batch_timeout: Duration,
     /// Queue for accumulating messages before batch processing
     message_queue: Vec<ExportTraceServiceRequest>,
+    /// Write-ahead log entry of each queued message, if it was logged
+    queued_wal_entries: Vec<Option<u64>>,
+    /// Encoded size in bytes of the queued messages
+    queue_bytes: usize,
+    /// Spans carried by the queued messages
+    queued_spans: usize,
+    /// Queue size in bytes that triggers a flush before `batch_size` is reached
+    max_queue_bytes: usize,
     /// Storage backend for persisting trace data
-    storage_writer: S3StorageWriter,
+    storage_writer: Arc<dyn StorageWriter>,
     /// Health monitoring for the engine
     health_check: Arc<HealthCheck>,
+    /// Signal that triggers a graceful shutdown when set to true
+    shutdown_signal: Option<watch::Receiver<bool>>,
+    /// Handle through which batching can be reconfigured at runtime
+    control: EngineControl,
+    /// Receives batching changes made through `control`
+    processing_updates: watch::Receiver<ProcessingConfig>,
+    /// Number of batch workers started by `process_messages`
+    worker_count: usize,
+    /// Running batch workers; writes happen inline when none are running
+    workers: Option<WorkerPool>,
+    /// Decides which traces are stored
+    sampler: TraceSampler,
+    /// Drops spans resent shortly after being stored, shared with the workers
+    deduplicator: SharedDeduplicator,
+    /// Drops spans by name before storage
+    name_filter: SpanNameFilter,
+    /// Converts proto spans, applying attribute, event and link limits
+    converter: SpanConverter,
+    /// Rewrites converted spans before storage, if set
+    enricher: Option<Box<dyn SpanEnricher>>,
+    /// Whether storage is flushed after each batch's writes finish
+    flush_after_batch: bool,
+    /// Holds requests whose writes failed until they can be uploaded
+    spill: Option<Arc<SpillBuffer>>,
+    /// How often uploading spilled requests is retried
+    spill_retry_interval: Duration,
+    /// Holds spans per trace until the trace is idle, when writing per trace
+    trace_buffer: Option<TraceBuffer>,
+    /// Log whose entries are committed once their requests are stored
+    wal: Option<Arc<WriteAheadLog>>,
+    /// Entries whose spans are buffered by trace, until their traces are written
+    buffered_entries: BufferedEntries,
 }
 
 impl EngineCore {
     /// Creates a new EngineCore with the specified configuration
     pub async fn new(
-        receiver: mpsc::Receiver<ExportTraceServiceRequest>,
+        receiver: mpsc::Receiver<QueuedMessage>,
         config: ProcessingConfig,
     ) -> Result<Self, StorageError> {
         let storage_writer = S3StorageWriter::new(
             "my-test-bucket".to_string(),
             "messages".to_string(),
+            &S3ClientSettings::default(),
         ).await?;
 
-        Ok(Self {
+        Ok(Self::with_storage(receiver, config, Arc::new(storage_writer)))
+    }
+
+    /// Creates a new EngineCore that persists spans to the given storage backend
+    pub fn with_storage(
+        receiver: mpsc::Receiver<QueuedMessage>,
+        config: ProcessingConfig,
+        storage_writer: Arc<dyn StorageWriter>,
+    ) -> Self {
+        let (processing_tx, processing_updates) = watch::channel(config.clone());
+        Self {
             message_receiver: receiver,
             batch_size: config.batch_size,
-            batch_timeout: Duration::from_millis(config.batch_timeout_ms),
+            batch_by: config.batch_by,
+            batch_timeout: config.batch_timeout(),
             message_queue: Vec::with_capacity(config.batch_size),
+            queued_wal_entries: Vec::with_capacity(config.batch_size),
+            queue_bytes: 0,
+            queued_spans: 0,
+            max_queue_bytes: config.max_queue_bytes,
             storage_writer,
             health_check: Arc::new(HealthCheck::new()),
-        })
+            shutdown_signal: None,
+            control: EngineControl {
+                processing: Arc::new(processing_tx),
+            },
+            processing_updates,
+            worker_count: config.worker_count,
+            workers: None,
+            sampler: TraceSampler::default(),
+            deduplicator: SharedDeduplicator::default(),
+            name_filter: SpanNameFilter::default(),
+            converter: SpanConverter::from(&config),
+            enricher: None,
+            flush_after_batch: config.flush_after_batch,
+            spill: None,
+            spill_retry_interval: SpillConfig::default().retry_interval(),
+            trace_buffer: None,
+            wal: None,
+            buffered_entries: BufferedEntries::default(),
+        }
+    }
+
+    /// Attaches a shutdown signal; once it flips to true `process_messages`
+    /// drains buffered messages, flushes storage and returns
+    pub fn with_shutdown_signal(mut self, signal: watch::Receiver<bool>) -> Self {
+        self.shutdown_signal = Some(signal);
+        self
+    }
+
+    /// Uses a shared health monitor, e.g. one also attached to the storage backend
+    pub fn with_health_check(mut self, health_check: Arc<HealthCheck>) -> Self {
+        self.health_check = health_check;
+        self
+    }
+
+    /// Stores only `ratio` of traces; spans of one trace are kept or dropped together
+    pub fn with_sampling_ratio(mut self, ratio: f64) -> Self {
+        self.sampler = TraceSampler::new(ratio);
+        self
+    }
+
+    /// Skips spans whose trace and span id were already received within
+    /// `window`, remembering up to `max_entries` ids; 0 disables this.
+    /// Ids are recorded on receipt and forgotten if the write fails unspilled.
+    pub fn with_dedup_window(mut self, max_entries: usize, window: Duration) -> Self {
+        self.deduplicator = Arc::new(std::sync::Mutex::new(SpanDeduplicator::new(max_entries, window)));
+        self
+    }
+
+    /// Drops spans whose names the filter does not keep, counting them in health
+    pub fn with_name_filter(mut self, filter: SpanNameFilter) -> Self {
+        self.name_filter = filter;
+        self
+    }
+
+    /// Passes every converted span through `enricher` before it is stored,
+    /// including spans of replayed requests
+    pub fn with_enricher(mut self, enricher: Box<dyn SpanEnricher>) -> Self {
+        self.enricher = Some(enricher);
+        self
+    }
+
+    /// Spills requests whose writes fail to `spill`, retrying their upload
+    /// every `retry_interval` while the engine runs
+    pub fn with_spill(mut self, spill: Arc<SpillBuffer>, retry_interval: Duration) -> Self {
+        self.spill = Some(spill);
+        self.spill_retry_interval = retry_interval;
+        self
+    }
+
+    /// Buffers spans by trace and writes each trace's spans together once
+    /// no new spans arrived for it within `idle_timeout`, or earlier for the
+    /// longest-idle traces once more than `max_bytes` are buffered. Buffered
+    /// traces are written on shutdown; their writes are not spilled.
+    pub fn with_trace_buffer(mut self, idle_timeout: Duration, max_bytes: u64) -> Self {
+        self.trace_buffer = Some(TraceBuffer::new(idle_timeout, max_bytes));
+        self
+    }
+
+    /// Commits the `wal` entries of queued requests once they are stored or
+    /// spilled; the entries are appended by a `MessageSender` holding the
+    /// same log. Entries a previous run left uncommitted are queued again
+    /// when `process_messages` starts. With a trace buffer, an entry is
+    /// committed once every trace holding its spans is written.
+    pub fn with_wal(mut self, wal: Arc<WriteAheadLog>) -> Self {
+        self.wal = Some(wal);
+        self
     }
 
     /// Returns a reference to the health check monitor
@@ -62,15 +491,38 @@ impl EngineCore {
         Arc::clone(&self.health_check)
     }
 
+    /// Returns a handle for reconfiguring batching while the engine runs
+    pub fn control(&self) -> EngineControl {
+        self.control.clone()
+    }
+
     /// Main message processing loop
     /// Handles batching of messages and triggers processing based on:
-    /// - Batch size threshold
+    /// - Batch size threshold, in requests or spans
+    /// - Queued bytes high-water mark
     /// - Timeout threshold
+    ///
+    /// With a spill buffer attached, spilled requests are uploaded every
+    /// spill retry interval. With a trace buffer attached, idle traces are
+    /// written as they are found.
+    /// All thresholds may be changed through `EngineControl` while running.
+    /// Returns after a graceful shutdown once the shutdown signal fires.
     pub async fn process_messages(&mut self) {
+        if self.workers.is_none() {
+            self.workers = Some(self.spawn_workers());
+        }
         let mut batch_timer = time::interval_at(
             Instant::now() + self.batch_timeout,
             self.batch_timeout,
         );
+        let mut spill_timer = time::interval(self.spill_retry_interval);
+        let trace_check_interval = self.trace_buffer
+            .as_ref()
+            .map_or(MAX_TRACE_CHECK_INTERVAL, |buffer| buffer.idle_timeout() / 2)
+            .clamp(MIN_TRACE_CHECK_INTERVAL, MAX_TRACE_CHECK_INTERVAL);
+        let mut trace_timer = time::interval(trace_check_interval);
+
+        self.replay_wal().await;
 
         loop {
             tokio::select! {
@@ -80,111 +532,460 @@ impl EngineCore {
                         self.process_batch().await;
                     }
                 }
-                // Process new messages as they arrive
-                Some(message) = self.message_receiver.recv() => {
-                    self.message_queue.push(message);
-                    if self.message_queue.len() >= self.batch_size {
+                // Process new messages as they arrive; stop once every sender is gone
+                message = self.message_receiver.recv() => match message {
+                    Some(message) => {
+                        self.push_message(message.request, message.wal_entry);
+                        if self.should_flush() {
+                            self.process_batch().await;
+                            batch_timer.reset();
+                        }
+                    }
+                    None => {
+                        info!("Message channel closed, flushing {} queued messages", self.message_queue.len());
+                        break;
+                    }
+                },
+                // Apply batching changes made through EngineControl
+                Ok(()) = self.processing_updates.changed() => {
+                    let config = self.processing_updates.borrow_and_update().clone();
+                    self.batch_size = config.batch_size;
+                    self.batch_by = config.batch_by;
+                    self.max_queue_bytes = config.max_queue_bytes;
+                    self.converter = SpanConverter::from(&config);
+                    self.flush_after_batch = config.flush_after_batch;
+                    let batch_timeout = config.batch_timeout();
+                    if batch_timeout != self.batch_timeout {
+                        self.batch_timeout = batch_timeout;
+                        batch_timer = time::interval_at(Instant::now() + batch_timeout, batch_timeout);
+                    }
+                    if !self.message_queue.is_empty() && self.should_flush() {
                         self.process_batch().await;
                         batch_timer.reset();
                     }
                 }
-                else => break,
+                // Retry uploading spilled requests
+                _ = spill_timer.tick(), if self.spill.is_some() => {
+                    self.upload_spilled().await;
+                }
+                // Write traces that stopped receiving spans
+                _ = trace_timer.tick(), if self.trace_buffer.is_some() => {
+                    self.write_idle_traces().await;
+                }
+                // Drain and stop on shutdown
+                _ = wait_for_shutdown(&mut self.shutdown_signal) => break,
             }
             self.health_check.update_queue_size(self.message_queue.len() as u64);
+            self.health_check.update_queue_bytes(self.queue_bytes as u64);
+        }
+
+        // Write and flush whatever is still queued, however the loop stopped
+        if let Err(e) = self.shutdown().await {
+            error!("Graceful shutdown failed: {}", e);
+        }
+        self.health_check.update_queue_size(0);
+        self.health_check.update_queue_bytes(0);
+    }
+
+    /// Adds a message to the queue, tracking its encoded size and span count
+    fn push_message(&mut self, message: ExportTraceServiceRequest, wal_entry: Option<u64>) {
+        let span_count = count_spans(&message);
+        self.queue_bytes += message.encoded_len();
+        self.queued_spans += span_count;
+        self.health_check.record_spans_taken(span_count as u64);
+        self.message_queue.push(message);
+        self.queued_wal_entries.push(wal_entry);
+    }
+
+    /// Queues the requests a previous run logged but never stored, keeping
+    /// their entries so they are committed once written
+    async fn replay_wal(&mut self) {
+        let Some(wal) = self.wal.clone() else { return };
+        let entries = wal.take_uncommitted().await;
+        if entries.is_empty() {
+            return;
+        }
+        info!("Replaying {} uncommitted write-ahead log entries", entries.len());
+        for (entry, data) in entries {
+            match ExportTraceServiceRequest::decode(data.as_slice()) {
+                Ok(message) => self.push_message(message, Some(entry)),
+                Err(e) => {
+                    error!("Discarding undecodable write-ahead log entry {}: {}", entry, e);
+                    if let Err(e) = wal.commit(entry).await {
+                        error!("Failed to commit write-ahead log entry {}: {}", entry, e);
+                    }
+                }
+            }
+        }
+        if !self.message_queue.is_empty() {
+            self.process_batch().await;
+        }
+    }
+
+    /// Returns the queue length in the unit `batch_size` counts
+    fn queued_units(&self) -> usize {
+        match self.batch_by {
+            BatchUnit::Requests => self.message_queue.len(),
+            BatchUnit::Spans => self.queued_spans,
         }
     }
 
+    /// Returns whether the queue has reached `batch_size` or `max_queue_bytes`
+    fn should_flush(&self) -> bool {
+        if self.queue_bytes >= self.max_queue_bytes && self.queued_units() < self.batch_size {
+            info!(
+                "Queue reached {} bytes (high-water mark {}), flushing early",
+                self.queue_bytes, self.max_queue_bytes
+            );
+            return true;
+        }
+        self.queued_units() >= self.batch_size
+    }
+
+    /// Returns the writer shared by the batch workers
+    fn batch_writer(&self) -> BatchWriter {
+        BatchWriter {
+            storage_writer: Arc::clone(&self.storage_writer),
+            health_check: Arc::clone(&self.health_check),
+            spill: self.spill.clone(),
+            wal: self.wal.clone(),
+            buffered_entries: Arc::clone(&self.buffered_entries),
+            deduplicator: Arc::clone(&self.deduplicator),
+        }
+    }
+
+    /// Spawns `worker_count` workers taking jobs from one shared queue.
+    /// The queue holds one job per worker, so a full pool applies
+    /// backpressure to the engine loop and in turn to exporters.
+    fn spawn_workers(&self) -> WorkerPool {
+        let (jobs, receiver) = mpsc::channel(self.worker_count);
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let handles = (0..self.worker_count)
+            .map(|_| {
+                let receiver = Arc::clone(&receiver);
+                let writer = self.batch_writer();
+                tokio::spawn(async move {
+                    loop {
+                        let job = receiver.lock().await.recv().await;
+                        match job {
+                            Some(job) => writer.write(job).await,
+                            None => break,
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        WorkerPool { jobs, handles }
+    }
+
     /// Processes a batch of accumulated messages
+    #[instrument(skip_all, fields(messages = self.message_queue.len()))]
     async fn process_batch(&mut self) {
-        info!("Processing batch of {} messages", self.message_queue.len());
-        
+        let span_count: usize = self.message_queue.iter().map(count_spans).sum();
+        let byte_size: usize = self.message_queue.iter().map(Message::encoded_len).sum();
+        info!(
+            "Processing batch of {} messages ({} spans, {} bytes)",
+            self.message_queue.len(), span_count, byte_size
+        );
+
         let messages = std::mem::take(&mut self.message_queue);
-        for message in messages {
-            match self.process_message(message).await {
-                Ok(_) => info!("Message processed successfully"),
-                Err(e) => error!("Failed to process message: {}", e),
+        let wal_entries = std::mem::take(&mut self.queued_wal_entries);
+        self.queue_bytes = 0;
+        self.queued_spans = 0;
+        let (batch_guard, mut batch_written) = mpsc::channel(1);
+        let batch_guard = self.flush_after_batch.then_some(batch_guard);
+        // Requests outside the trace buffer are written together as one job
+        let mut batch: Option<WriteJob> = None;
+        let mut kept = ExportTraceServiceRequest::default();
+        for (mut message, wal_entry) in messages.into_iter().zip(wal_entries) {
+            let byte_size = message.encoded_len() as u64;
+            let taken = count_spans(&message);
+            if self.trace_buffer.is_some() {
+                let spans = self.convert_request_to_spans(message);
+                self.health_check.record_spans_settled((taken - spans.len()) as u64);
+                let traces = match &mut self.trace_buffer {
+                    Some(buffer) => buffer.add(spans, byte_size, wal_entry),
+                    None => 0,
+                };
+                match wal_entry {
+                    // Committed once the last of its traces is written
+                    Some(entry) if traces > 0 => {
+                        self.buffered_entries.lock().unwrap().insert(entry, traces);
+                    }
+                    _ => self.batch_writer().commit_wal_entries(wal_entry.into_iter().collect()).await,
+                }
+                let evicted = match &mut self.trace_buffer {
+                    Some(buffer) => buffer.take_over_limit(),
+                    None => Vec::new(),
+                };
+                self.write_traces(evicted).await;
+                continue;
             }
+            // Spill only the kept spans, so an upload stores what this write would have
+            self.retain_kept_spans(&mut message);
+            if self.spill.is_some() {
+                kept.resource_spans.extend(message.resource_spans.iter().cloned());
+            }
+            let spans = self.convert_spans(message, false);
+            self.health_check.record_spans_settled((taken - spans.len()) as u64);
+            let job = batch.get_or_insert_with(|| WriteJob {
+                spans: Vec::new(),
+                byte_size: 0,
+                spill_data: None,
+                wal_entries: Vec::new(),
+                batch_guard: batch_guard.clone(),
+                parent: tracing::Span::current(),
+            });
+            job.spans.extend(spans);
+            job.byte_size += byte_size;
+            job.wal_entries.extend(wal_entry);
+        }
+        if let Some(mut job) = batch {
+            job.spill_data = self.spill.as_ref().map(|_| kept.encode_to_vec());
+            self.dispatch(job).await;
+        }
+
+        if batch_guard.is_some() {
+            drop(batch_guard);
+            // Resolves once every job of the batch has dropped its guard
+            let _ = batch_written.recv().await;
+            self.flush_storage().await;
         }
     }
 
-    /// Processes a single message, converting it to spans and storing them
-    async fn process_message(
-        &self,
-        request: ExportTraceServiceRequest
-    ) -> Result<ExportTraceServiceResponse, ProcessingError> {
-        let spans = self.convert_request_to_spans(request)?;
-        
-        self.storage_writer.write_spans(spans).await
-            .map_err(|e| ProcessingError::StorageError(e.to_string()))?;
+    /// Writes the buffered traces that have gone idle, one job per trace
+    async fn write_idle_traces(&mut self) {
+        let traces = match &mut self.trace_buffer {
+            Some(buffer) => buffer.take_idle(),
+            None => return,
+        };
+        self.write_traces(traces).await;
+    }
 
-        self.health_check.record_successful_write();
-        Ok(ExportTraceServiceResponse {})
+    /// Hands each trace's spans to the workers as one job
+    async fn write_traces(&self, traces: Vec<BufferedTrace>) {
+        if !traces.is_empty() {
+            info!("Writing {} buffered traces", traces.len());
+        }
+        for trace in traces {
+            let job = WriteJob {
+                spans: trace.spans,
+                byte_size: trace.byte_size,
+                spill_data: None,
+                wal_entries: trace.wal_entries,
+                batch_guard: None,
+                parent: tracing::Span::current(),
+            };
+            self.dispatch(job).await;
+        }
     }
 
-    /// Converts a trace request into OpenTelemetry spans
-    fn convert_request_to_spans(
-        &self,
-        request: ExportTraceServiceRequest
-    ) -> Result<Vec<SpanData>, ProcessingError> {
-        let mut spans = Vec::new();
-        
-        for resource_spans in request.resource_spans {
-            for scope_spans in resource_spans.scope_spans {
-                for span in scope_spans.spans {
-                    spans.push(self.convert_span(span)?);
+    /// Flushes storage after a batch, recording how long it took
+    async fn flush_storage(&self) {
+        let started = Instant::now();
+        let result = self.storage_writer.flush().await;
+        self.health_check.record_flush_latency(started.elapsed());
+        if let Err(e) = result {
+            error!("Failed to flush storage after batch: {}", e);
+        }
+    }
+
+    /// Uploads spilled requests oldest first, deleting each once written.
+    /// Stops at the first failed write, so while storage is down each
+    /// round costs a single attempt; files left over are retried next round.
+    async fn upload_spilled(&mut self) {
+        let Some(spill) = self.spill.clone() else { return };
+        loop {
+            let (path, data) = match spill.oldest().await {
+                Ok(Some(oldest)) => oldest,
+                Ok(None) => break,
+                Err(e) => {
+                    error!("Failed to read spilled request: {}", e);
+                    break;
+                }
+            };
+            let spans = match ExportTraceServiceRequest::decode(data.as_slice()) {
+                Ok(request) => self.convert_spans(request, true),
+                Err(e) => {
+                    error!("Discarding unreadable spill file {}: {}", path.display(), e);
+                    Vec::new()
                 }
+            };
+
+            let span_count = spans.len() as u64;
+            if span_count > 0 {
+                if let Err(e) = self.storage_writer.write_spans(spans).await {
+                    warn!("Storage still unavailable, keeping {} spilled requests: {}", spill.len().await, e);
+                    break;
+                }
+                self.health_check.record_successful_write();
+                self.health_check.record_spans_written(span_count, data.len() as u64);
+                info!("Uploaded {} spilled spans", span_count);
+            }
+            if let Err(e) = spill.remove(&path).await {
+                error!("Failed to remove uploaded spill file: {}", e);
+                break;
             }
         }
-
-        Ok(spans)
     }
 
-    /// Converts a proto span into an OpenTelemetry span
-    fn convert_span(&self, span: Span) -> Result<SpanData, ProcessingError> {
-        let parent_span_id = if !span.parent_span_id.is_empty() {
-            SpanId::from_hex(&hex::encode(&span.parent_span_id))
-                .map_err(|e| ProcessingError::ValidationError(e.to_string()))?
-        } else {
-            SpanId::INVALID
+    /// Hands a job to the workers, or writes it inline when none are running
+    async fn dispatch(&self, job: WriteJob) {
+        let job = match &self.workers {
+            Some(pool) => match pool.jobs.send(job).await {
+                Ok(()) => return,
+                // Every worker has exited; fall back to writing inline
+                Err(mpsc::error::SendError(job)) => job,
+            },
+            None => job,
         };
+        self.batch_writer().write(job).await;
+    }
 
-        Ok(SpanData {
-            span_context: self.create_span_context(&span)?,
-            parent_span_id,
-            span_kind: SpanKind::Client,
-            name: Cow::from(span.name),
-            start_time: std::time::SystemTime::UNIX_EPOCH + 
-                std::time::Duration::from_nanos(span.start_time_unix_nano),
-            end_time: std::time::SystemTime::UNIX_EPOCH + 
-                std::time::Duration::from_nanos(span.end_time_unix_nano),
-            attributes: EvictedHashMap::new(Default::default(), 128),
-            events: EvictedQueue::new(128),
-            links: EvictedQueue::new(128),
-            status: Status::Ok,
-            resource: Default::default(),
-            instrumentation_lib: Default::default(),
-        })
+    /// Converts a trace request into OpenTelemetry spans.
+    /// Malformed spans are dropped and counted; the rest of the request is kept.
+    fn convert_request_to_spans(&mut self, mut request: ExportTraceServiceRequest) -> Vec<SpanData> {
+        self.retain_kept_spans(&mut request);
+        self.convert_spans(request, false)
     }
 
-    /// Creates a span context from a proto span
-    fn create_span_context(&self, span: &Span) -> Result<SpanContext, ProcessingError> {
-        Ok(SpanContext::new(
-            TraceId::from_hex(&hex::encode(&span.trace_id))
-                .map_err(|e| ProcessingError::ValidationError(e.to_string()))?,
-            SpanId::from_hex(&hex::encode(&span.span_id))
-                .map_err(|e| ProcessingError::ValidationError(e.to_string()))?,
-            TraceFlags::default(),
-            false,
-            TraceState::default(),
-        ))
+    /// Removes the spans the name filter, sampler and deduplicator drop from
+    /// a request, counting them. Spans with malformed ids are left for
+    /// conversion to reject.
+    fn retain_kept_spans(&mut self, request: &mut ExportTraceServiceRequest) {
+        let mut filtered = 0;
+        let mut sampled_out = 0;
+        let mut deduplicated = 0;
+
+        let mut deduplicator = self.deduplicator.lock().unwrap();
+        let span_lists = request.resource_spans
+            .iter_mut()
+            .flat_map(|resource_spans| &mut resource_spans.scope_spans)
+            .map(|scope_spans| &mut scope_spans.spans);
+        for spans in span_lists {
+            spans.retain(|span| {
+                if !self.name_filter.keeps(&span.name) {
+                    filtered += 1;
+                    return false;
+                }
+                if !self.sampler.keeps(&span.trace_id) {
+                    sampled_out += 1;
+                    return false;
+                }
+                let ids = parse_trace_id(&span.trace_id)
+                    .and_then(|trace_id| Ok((trace_id, parse_span_id(&span.span_id, "span_id")?)));
+                if let Ok((trace_id, span_id)) = ids {
+                    if deduplicator.is_duplicate(trace_id, span_id) {
+                        deduplicated += 1;
+                        return false;
+                    }
+                }
+                true
+            });
+        }
+
+        if sampled_out > 0 {
+            self.health_check.record_sampled_out(sampled_out);
+        }
+        if filtered > 0 {
+            self.health_check.record_filtered(filtered);
+        }
+        if deduplicated > 0 {
+            info!("Skipped {} spans already received within the dedup window", deduplicated);
+            self.health_check.record_deduplicated(deduplicated);
+        }
     }
 
-    /// Performs graceful shutdown, processing remaining messages
+    /// Converts the kept spans of a trace request. A `replayed` request was
+    /// spilled after `retain_kept_spans` and its spans counted when first
+    /// received, so it is only converted.
+    fn convert_spans(&mut self, request: ExportTraceServiceRequest, replayed: bool) -> Vec<SpanData> {
+        let mut spans = Vec::new();
+        let mut invalid = Vec::new();
+        let mut truncated = 0;
+
+        for resource_spans in request.resource_spans {
+            let resource = resource_spans
+                .resource
+                .map(|resource| Resource::new(convert_key_values(resource.attributes)))
+                .unwrap_or_else(Resource::empty);
+
+            for scope_spans in resource_spans.scope_spans {
+                // A scope's own schema URL takes precedence over its resource's
+                let schema_url = if scope_spans.schema_url.is_empty() {
+                    &resource_spans.schema_url
+                } else {
+                    &scope_spans.schema_url
+                };
+                let scope = convert_scope(scope_spans.scope, schema_url);
+
+                for span in scope_spans.spans {
+                    match self.converter.convert_span(span, &resource, &scope) {
+                        Ok(mut span) => {
+                            if is_truncated(&span) {
+                                truncated += 1;
+                            }
+                            if let Some(enricher) = &self.enricher {
+                                enricher.enrich(&mut span);
+                            }
+                            spans.push(span);
+                        }
+                        Err(e) => invalid.push(e),
+                    }
+                }
+            }
+        }
+
+        if replayed {
+            return spans;
+        }
+        if let Some(first) = invalid.first() {
+            warn!(
+                "Dropped {} invalid spans of {} in request (first: {})",
+                invalid.len(), invalid.len() + spans.len(), first
+            );
+            self.health_check.record_invalid_spans(invalid.len() as u64);
+        }
+        if truncated > 0 {
+            let limits = self.converter.limits();
+            warn!(
+                "Truncated {} spans exceeding limits of {} attributes, {} events or {} links",
+                truncated, limits.attributes, limits.events, limits.links
+            );
+            self.health_check.record_truncated_spans(truncated);
+        }
+        spans
+    }
+
+    /// Performs graceful shutdown, processing remaining messages.
+    /// Closes the channel so no new messages are accepted, drains messages
+    /// already buffered in it, processes the final batch and flushes storage.
     pub async fn shutdown(&mut self) -> Result<(), ProcessingError> {
         info!("Initiating graceful shutdown...");
         
-        while let Some(message) = self.message_queue.pop() {
-            self.process_message(message).await?;
+        self.message_receiver.close();
+        while let Some(message) = self.message_receiver.recv().await {
+            self.push_message(message.request, message.wal_entry);
+        }
+
+        if !self.message_queue.is_empty() {
+            self.process_batch().await;
+        }
+        // Traces still receiving spans are written as they are
+        if let Some(traces) = self.trace_buffer.as_mut().map(TraceBuffer::drain) {
+            self.write_traces(traces).await;
+        }
+
+        // Let the workers finish queued and in-flight writes
+        if let Some(pool) = self.workers.take() {
+            drop(pool.jobs);
+            for handle in pool.handles {
+                if let Err(e) = handle.await {
+                    error!("Batch worker failed: {}", e);
+                }
+            }
         }
         
         self.storage_writer.flush().await?;
@@ -192,3 +993,1206 @@ impl EngineCore {
         Ok(())
     }
 }
+
+/// How often `MessageSender::spawn_backlog_monitor` records the channel backlog
+pub const BACKLOG_SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Bounds on how often the trace buffer is checked for idle traces
+const MIN_TRACE_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+const MAX_TRACE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Returns the number of spans carried by a trace request
+fn count_spans(request: &ExportTraceServiceRequest) -> usize {
+    request
+        .resource_spans
+        .iter()
+        .flat_map(|resource_spans| &resource_spans.scope_spans)
+        .map(|scope_spans| scope_spans.spans.len())
+        .sum()
+}
+
+/// Returns whether a span lost attributes, events or links to eviction
+fn is_truncated(span: &SpanData) -> bool {
+    span.attributes.dropped_count() > 0
+        || span.events.dropped_count() > 0
+        || span.links.dropped_count() > 0
+}
+
+/// Resolves once the shutdown signal is set; never resolves without a signal
+async fn wait_for_shutdown(signal: &mut Option<watch::Receiver<bool>>) {
+    match signal {
+        Some(signal) => {
+            // A dropped sender also counts as a request to shut down
+            let _ = signal.wait_for(|stop| *stop).await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::opentelemetry::proto::resource::v1::Resource as ProtoResource;
+    use crate::proto::{ResourceSpans, ScopeSpans, Span};
+    use crate::storage::{null::NullStorageWriter, service_name, StoredSpan};
+    use crate::test_support::MockStorage;
+    use crate::config::{HealthConfig, WalConfig};
+    use crate::proto::opentelemetry::proto::common::v1::{
+        any_value, AnyValue, InstrumentationScope, KeyValue as ProtoKeyValue,
+    };
+    use crate::proto::opentelemetry::proto::trace::v1::span::{Event as ProtoEvent, Link as ProtoLink};
+    use crate::proto::opentelemetry::proto::trace::v1::Status as ProtoStatus;
+    use opentelemetry::trace::{SpanId, TraceId};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn request_with_span(span_id: u8) -> ExportTraceServiceRequest {
+        ExportTraceServiceRequest {
+            resource_spans: vec![ResourceSpans {
+                resource: None,
+                scope_spans: vec![ScopeSpans {
+                    scope: None,
+                    spans: vec![Span {
+                        trace_id: vec![1; 16],
+                        span_id: vec![span_id; 8],
+                        name: format!("span-{}", span_id),
+                        ..Default::default()
+                    }],
+                    schema_url: String::new(),
+                }],
+                schema_url: String::new(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_sampling_keeps_whole_traces() {
+        let mut engine = engine().with_sampling_ratio(0.5);
+        let mut kept = std::collections::HashMap::<TraceId, usize>::new();
+        for trace in 0..1000u32 {
+            let mut trace_id = vec![0; 16];
+            trace_id[12..].copy_from_slice(&trace.to_be_bytes());
+            let spans = (1..=3)
+                .map(|span| Span {
+                    trace_id: trace_id.clone(),
+                    span_id: vec![span; 8],
+                    ..Default::default()
+                })
+                .collect();
+            let request = ExportTraceServiceRequest {
+                resource_spans: vec![ResourceSpans {
+                    resource: None,
+                    scope_spans: vec![ScopeSpans { scope: None, spans, schema_url: String::new() }],
+                    schema_url: String::new(),
+                }],
+            };
+            for span in engine.convert_request_to_spans(request) {
+                *kept.entry(span.span_context.trace_id()).or_default() += 1;
+            }
+        }
+
+        assert!((400..=600).contains(&kept.len()), "kept {} of 1000 traces", kept.len());
+        assert!(kept.values().all(|&spans| spans == 3), "a trace was partially sampled");
+        let sampled_out = engine.get_health_check().get_detailed_status().spans_sampled_out;
+        assert_eq!(sampled_out as usize, (1000 - kept.len()) * 3);
+    }
+
+    fn engine() -> EngineCore {
+        let (_tx, rx) = mpsc::channel(1);
+        EngineCore::with_storage(rx, ProcessingConfig::default(), Arc::new(MockStorage::new()))
+    }
+
+    fn string_attribute(key: &str, value: &str) -> ProtoKeyValue {
+        ProtoKeyValue {
+            key: key.to_string(),
+            value: Some(AnyValue {
+                value: Some(any_value::Value::StringValue(value.to_string())),
+            }),
+        }
+    }
+
+    fn span_with_attributes(count: usize) -> Span {
+        Span {
+            trace_id: vec![1; 16],
+            span_id: vec![2; 8],
+            attributes: (0..count)
+                .map(|i| string_attribute(&format!("key.{}", i), "value"))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_attribute_limit_configurable() {
+        let (_tx, rx) = mpsc::channel(1);
+        let config = ProcessingConfig { max_attributes: 256, ..ProcessingConfig::default() };
+        let engine = EngineCore::with_storage(rx, config, Arc::new(MockStorage::new()));
+
+        let converted = engine.converter.convert_span(span_with_attributes(200), &Resource::empty(), &Default::default()).unwrap();
+        assert_eq!(converted.attributes.len(), 200);
+        assert!(!is_truncated(&converted));
+    }
+
+    #[test]
+    fn test_truncated_spans_counted() {
+        let mut engine = engine();
+        let mut request = request_with_span(1);
+        request.resource_spans[0].scope_spans[0].spans = vec![span_with_attributes(200)];
+
+        let spans = engine.convert_request_to_spans(request);
+        assert_eq!(spans[0].attributes.len(), 128);
+        assert_eq!(engine.get_health_check().get_detailed_status().truncated_spans_total, 1);
+    }
+
+    #[test]
+    fn test_resource_attributes_preserved() {
+        let request = ExportTraceServiceRequest {
+            resource_spans: vec![ResourceSpans {
+                resource: Some(ProtoResource {
+                    attributes: vec![string_attribute("service.name", "checkout")],
+                    dropped_attributes_count: 0,
+                }),
+                scope_spans: vec![ScopeSpans {
+                    scope: None,
+                    spans: vec![Span {
+                        trace_id: vec![1; 16],
+                        span_id: vec![2; 8],
+                        name: "charge".to_string(),
+                        ..Default::default()
+                    }],
+                    schema_url: String::new(),
+                }],
+                schema_url: String::new(),
+            }],
+        };
+
+        let spans = engine().convert_request_to_spans(request);
+        assert_eq!(spans.len(), 1);
+        assert_eq!(service_name(&spans[0]).as_deref(), Some("checkout"));
+    }
+
+    #[test]
+    fn test_instrumentation_scope_preserved() {
+        let request = ExportTraceServiceRequest {
+            resource_spans: vec![ResourceSpans {
+                resource: None,
+                scope_spans: vec![ScopeSpans {
+                    scope: Some(InstrumentationScope {
+                        name: "my-tracer".into(),
+                        version: "1.2.3".into(),
+                        ..Default::default()
+                    }),
+                    spans: vec![Span {
+                        trace_id: vec![1; 16],
+                        span_id: vec![2; 8],
+                        ..Default::default()
+                    }],
+                    schema_url: String::new(),
+                }],
+                schema_url: "https://opentelemetry.io/schemas/1.21.0".into(),
+            }],
+        };
+
+        let spans = engine().convert_request_to_spans(request);
+        let scope = &spans[0].instrumentation_lib;
+        assert_eq!(scope.schema_url.as_deref(), Some("https://opentelemetry.io/schemas/1.21.0"));
+
+        let stored = StoredSpan::from(&spans[0]);
+        assert_eq!(stored.scope_name.as_deref(), Some("my-tracer"));
+        assert_eq!(stored.scope_version.as_deref(), Some("1.2.3"));
+    }
+
+    #[test]
+    fn test_kind_and_status_converted() {
+        let mut request = request_with_span(1);
+        let span = &mut request.resource_spans[0].scope_spans[0].spans[0];
+        span.kind = 2;
+        span.status = Some(ProtoStatus { message: "card declined".into(), code: 2 });
+
+        let spans = engine().convert_request_to_spans(request);
+        let stored = StoredSpan::from(&spans[0]);
+        assert_eq!(stored.kind, "Server");
+        assert_eq!(stored.status, "Error");
+        assert_eq!(stored.status_message.as_deref(), Some("card declined"));
+    }
+
+    #[test]
+    fn test_span_attributes_converted() {
+        let span = Span {
+            trace_id: vec![1; 16],
+            span_id: vec![2; 8],
+            attributes: vec![string_attribute("http.method", "GET")],
+            ..Default::default()
+        };
+
+        let converted = engine().converter.convert_span(span, &Resource::empty(), &Default::default()).unwrap();
+        let stored = StoredSpan::from(&converted);
+        assert_eq!(stored.attributes["http.method"], "GET");
+    }
+
+    #[test]
+    fn test_events_and_links_round_trip() {
+        let span = Span {
+            trace_id: vec![1; 16],
+            span_id: vec![2; 8],
+            name: "checkout".to_string(),
+            events: vec![ProtoEvent {
+                time_unix_nano: 1_500,
+                name: "cache.miss".to_string(),
+                attributes: vec![string_attribute("cache.key", "cart:42")],
+                dropped_attributes_count: 0,
+            }],
+            links: vec![ProtoLink {
+                trace_id: vec![3; 16],
+                span_id: vec![4; 8],
+                trace_state: String::new(),
+                attributes: vec![string_attribute("link.kind", "follows_from")],
+                dropped_attributes_count: 0,
+                flags: 0,
+            }],
+            ..Default::default()
+        };
+
+        let converted = engine().converter.convert_span(span, &Resource::empty(), &Default::default()).unwrap();
+        let json = serde_json::to_string(&StoredSpan::from(&converted)).unwrap();
+        let stored: StoredSpan = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(stored.events.len(), 1);
+        assert_eq!(stored.events[0].name, "cache.miss");
+        assert_eq!(stored.events[0].timestamp, 1_500);
+        assert_eq!(stored.events[0].attributes["cache.key"], "cart:42");
+
+        assert_eq!(stored.links.len(), 1);
+        assert_eq!(stored.links[0].trace_id, "03".repeat(16));
+        assert_eq!(stored.links[0].span_id, "04".repeat(8));
+        assert_eq!(stored.links[0].attributes["link.kind"], "follows_from");
+    }
+
+    fn span_with_ids(trace_id: Vec<u8>, span_id: Vec<u8>) -> Span {
+        Span {
+            trace_id,
+            span_id,
+            name: "checkout".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_span_does_not_drop_request() {
+        let (tx, rx) = mpsc::channel(10);
+        let storage = Arc::new(MockStorage::new());
+        let config = ProcessingConfig {
+            batch_size: 1,
+            batch_timeout_ms: 60_000,
+            ..ProcessingConfig::default()
+        };
+        let mut engine = EngineCore::with_storage(rx, config, storage.clone());
+        let health_check = engine.get_health_check();
+        tokio::spawn(async move { engine.process_messages().await });
+
+        let mut request = request_with_span(1);
+        request.resource_spans[0].scope_spans[0]
+            .spans
+            .push(span_with_ids(vec![1; 3], vec![2; 8]));
+        tx.send(request.into()).await.unwrap();
+        wait_for_spans(&storage, 1).await;
+
+        let spans = storage.written();
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].name, "span-1");
+        assert_eq!(health_check.get_detailed_status().invalid_spans_total, 1);
+    }
+
+    fn validation_message(span: Span) -> String {
+        match engine().converter.convert_span(span, &Resource::empty(), &Default::default()) {
+            Err(ProcessingError::ValidationError(msg)) => msg,
+            other => panic!("Expected ValidationError, got {:?}", other.map(|s| s.name)),
+        }
+    }
+
+    #[test]
+    fn test_valid_ids_accepted() {
+        let converted = engine()
+            .converter.convert_span(span_with_ids(vec![1; 16], vec![2; 8]), &Resource::empty(), &Default::default())
+            .unwrap();
+        assert_eq!(converted.span_context.trace_id().to_string(), "01".repeat(16));
+        assert_eq!(converted.span_context.span_id().to_string(), "02".repeat(8));
+        assert_eq!(converted.parent_span_id, SpanId::INVALID);
+    }
+
+    #[test]
+    fn test_empty_ids_rejected() {
+        assert_eq!(
+            validation_message(span_with_ids(Vec::new(), vec![2; 8])),
+            "trace_id must be 16 bytes, got 0"
+        );
+        assert_eq!(
+            validation_message(span_with_ids(vec![1; 16], Vec::new())),
+            "span_id must be 8 bytes, got 0"
+        );
+    }
+
+    #[test]
+    fn test_wrong_length_ids_rejected() {
+        assert_eq!(
+            validation_message(span_with_ids(vec![1; 8], vec![2; 8])),
+            "trace_id must be 16 bytes, got 8"
+        );
+        assert_eq!(
+            validation_message(span_with_ids(vec![1; 16], vec![2; 9])),
+            "span_id must be 8 bytes, got 9"
+        );
+        let mut span = span_with_ids(vec![1; 16], vec![2; 8]);
+        span.parent_span_id = vec![3; 4];
+        assert_eq!(validation_message(span), "parent_span_id must be 8 bytes, got 4");
+    }
+
+    #[test]
+    fn test_all_zero_ids_rejected() {
+        assert_eq!(
+            validation_message(span_with_ids(vec![0; 16], vec![2; 8])),
+            "trace_id must not be all zeros"
+        );
+        assert_eq!(
+            validation_message(span_with_ids(vec![1; 16], vec![0; 8])),
+            "span_id must not be all zeros"
+        );
+    }
+
+    fn span_starting_at(start_time: SystemTime) -> Span {
+        let start = start_time.duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+        Span {
+            start_time_unix_nano: start,
+            end_time_unix_nano: start + 1_000,
+            ..span_with_ids(vec![1; 16], vec![2; 8])
+        }
+    }
+
+    #[test]
+    fn test_spans_outside_age_window_rejected() {
+        let config = ProcessingConfig {
+            max_span_age_secs: Some(7 * 24 * 3600),
+            max_span_skew_secs: Some(300),
+            ..ProcessingConfig::default()
+        };
+        let (_tx, rx) = mpsc::channel(1);
+        let limited = EngineCore::with_storage(rx, config, Arc::new(MockStorage::new()));
+        let convert = |span| limited.converter.convert_span(span, &Resource::empty(), &Default::default());
+        let now = SystemTime::now();
+
+        let ten_years_ago = now - Duration::from_secs(10 * 365 * 24 * 3600);
+        match convert(span_starting_at(ten_years_ago)) {
+            Err(ProcessingError::ValidationError(msg)) => assert!(msg.contains("max_span_age_secs"), "{}", msg),
+            other => panic!("Expected ValidationError, got {:?}", other.map(|s| s.name)),
+        }
+        match convert(span_starting_at(now + Duration::from_secs(3600))) {
+            Err(ProcessingError::ValidationError(msg)) => assert!(msg.contains("max_span_skew_secs"), "{}", msg),
+            other => panic!("Expected ValidationError, got {:?}", other.map(|s| s.name)),
+        }
+        assert!(convert(span_starting_at(now - Duration::from_secs(3600))).is_ok());
+        assert!(convert(span_starting_at(now + Duration::from_secs(60))).is_ok());
+
+        // Without limits, any start time is accepted
+        assert!(engine().converter.convert_span(span_starting_at(ten_years_ago), &Resource::empty(), &Default::default()).is_ok());
+    }
+
+    #[test]
+    fn test_out_of_range_spans_counted_as_invalid() {
+        let config = ProcessingConfig { max_span_age_secs: Some(3600), ..ProcessingConfig::default() };
+        let (_tx, rx) = mpsc::channel(1);
+        let mut engine = EngineCore::with_storage(rx, config, Arc::new(MockStorage::new()));
+        let mut request = request_with_span(1);
+        request.resource_spans[0].scope_spans[0].spans = vec![
+            span_starting_at(SystemTime::now() - Duration::from_secs(10 * 365 * 24 * 3600)),
+            span_starting_at(SystemTime::now()),
+        ];
+
+        assert_eq!(engine.convert_request_to_spans(request).len(), 1);
+        assert_eq!(engine.get_health_check().get_detailed_status().invalid_spans_total, 1);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_queued_messages() {
+        let (tx, rx) = mpsc::channel(10);
+        let storage = Arc::new(MockStorage::new());
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let config = ProcessingConfig {
+            batch_size: 100,
+            batch_timeout_ms: 60_000,
+            ..ProcessingConfig::default()
+        };
+        let mut engine = EngineCore::with_storage(rx, config, storage.clone())
+            .with_shutdown_signal(shutdown_rx);
+
+        for span_id in 1..=3 {
+            tx.send(request_with_span(span_id).into()).await.unwrap();
+        }
+
+        let handle = tokio::spawn(async move { engine.process_messages().await });
+        shutdown_tx.send(true).unwrap();
+        handle.await.unwrap();
+
+        assert_eq!(storage.written().len(), 3);
+        assert_eq!(storage.calls("flush"), 1);
+        assert!(tx.send(request_with_span(4).into()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_queued_spans_count_as_unwritten_until_written() {
+        let (tx, rx) = mpsc::channel(10);
+        let storage = Arc::new(MockStorage::new());
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let config = ProcessingConfig {
+            batch_size: 100,
+            batch_timeout_ms: 60_000,
+            ..ProcessingConfig::default()
+        };
+        let mut engine = EngineCore::with_storage(rx, config, storage.clone())
+            .with_shutdown_signal(shutdown_rx);
+        let health_check = engine.get_health_check();
+
+        for span_id in 1..=3 {
+            tx.send(request_with_span(span_id).into()).await.unwrap();
+        }
+        let handle = tokio::spawn(async move { engine.process_messages().await });
+
+        // Taken from the channel but held for the batch timer
+        while tx.capacity() < tx.max_capacity() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(health_check.unwritten_spans(), 3);
+        assert!(storage.written().is_empty());
+
+        shutdown_tx.send(true).unwrap();
+        handle.await.unwrap();
+        assert_eq!(health_check.unwritten_spans(), 0);
+        assert_eq!(storage.written().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_channel_close_flushes_queued_messages() {
+        let (tx, rx) = mpsc::channel(10);
+        let storage = Arc::new(MockStorage::new());
+        let config = ProcessingConfig {
+            batch_size: 100,
+            batch_timeout_ms: 60_000,
+            ..ProcessingConfig::default()
+        };
+        let mut engine = EngineCore::with_storage(rx, config, storage.clone());
+        let health_check = engine.get_health_check();
+
+        for span_id in 1..=3 {
+            tx.send(request_with_span(span_id).into()).await.unwrap();
+        }
+        drop(tx);
+
+        // Returns on its own once the channel is closed and drained
+        time::timeout(Duration::from_secs(5), engine.process_messages())
+            .await
+            .expect("engine did not stop after the channel closed");
+
+        assert_eq!(storage.written().len(), 3);
+        assert_eq!(storage.calls("flush"), 1);
+        assert_eq!(health_check.get_health_status().queue_size, 0);
+    }
+
+    #[tokio::test]
+    async fn test_spilled_spans_uploaded_after_outage() {
+        let spill_dir = tempfile::TempDir::new().unwrap();
+        let spill_config = SpillConfig {
+            enabled: true,
+            dir: spill_dir.path().to_string_lossy().into_owned(),
+            ..SpillConfig::default()
+        };
+        let spill = Arc::new(SpillBuffer::open(&spill_config).await.unwrap());
+        let (tx, rx) = mpsc::channel(10);
+        let storage = Arc::new(MockStorage::new());
+        // Storage is down for the first two writes
+        storage.fail_next_writes(2);
+        let config = ProcessingConfig {
+            batch_size: 1,
+            batch_timeout_ms: 60_000,
+            ..ProcessingConfig::default()
+        };
+        let mut engine = EngineCore::with_storage(rx, config, storage.clone())
+            .with_dedup_window(100, Duration::from_secs(60))
+            .with_spill(Arc::clone(&spill), Duration::from_millis(20));
+        let health_check = engine.get_health_check();
+        let handle = tokio::spawn(async move { engine.process_messages().await });
+
+        for span_id in 1..=2 {
+            tx.send(request_with_span(span_id).into()).await.unwrap();
+        }
+        wait_for_spans(&storage, 2).await;
+
+        let mut names = storage.written_names();
+        names.sort();
+        assert_eq!(names, ["span-1", "span-2"]);
+        assert!(spill.is_empty().await);
+        assert!(health_check.get_detailed_status().spans_spilled >= 1);
+
+        drop(tx);
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_writes_mark_unhealthy() {
+        let storage = Arc::new(MockStorage::new());
+        storage.fail_next_writes(3);
+        let health_check = Arc::new(HealthCheck::with_config(&HealthConfig {
+            unhealthy_after_failures: 2,
+            ..HealthConfig::default()
+        }));
+        // Runs an engine over the given requests until they are all processed
+        let process = |span_ids: Vec<u8>| {
+            let (storage, health_check) = (storage.clone(), Arc::clone(&health_check));
+            async move {
+                let (tx, rx) = mpsc::channel(10);
+                let config = ProcessingConfig {
+                    batch_size: 1,
+                    batch_timeout_ms: 60_000,
+                    ..ProcessingConfig::default()
+                };
+                let mut engine = EngineCore::with_storage(rx, config, storage).with_health_check(health_check);
+                for span_id in span_ids {
+                    tx.send(request_with_span(span_id).into()).await.unwrap();
+                }
+                drop(tx);
+                engine.process_messages().await;
+            }
+        };
+
+        process(vec![1, 2]).await;
+        let status = health_check.get_detailed_status();
+        assert_eq!(status.total_failed_writes, 2);
+        assert!(status.is_healthy, "failures up to the threshold are tolerated");
+
+        process(vec![3]).await;
+        let status = health_check.get_detailed_status();
+        assert_eq!((status.total_failed_writes, status.consecutive_failed_writes), (3, 3));
+        assert!(!status.is_healthy);
+
+        process(vec![4]).await;
+        assert!(health_check.get_detailed_status().is_healthy, "a successful write restores health");
+        assert_eq!(storage.written_names(), ["span-4"]);
+    }
+
+    #[tokio::test]
+    async fn test_uncommitted_wal_entry_replayed_on_restart() {
+        let wal_dir = tempfile::TempDir::new().unwrap();
+        let wal_config = WalConfig {
+            enabled: true,
+            dir: wal_dir.path().to_string_lossy().into_owned(),
+            ..WalConfig::default()
+        };
+        let config = ProcessingConfig {
+            batch_size: 1,
+            batch_timeout_ms: 60_000,
+            ..ProcessingConfig::default()
+        };
+
+        // The first write fails and nothing spills it, as if the process
+        // had crashed before storing it
+        let wal = Arc::new(WriteAheadLog::open(&wal_config).await.unwrap());
+        let (tx, rx) = message_channel(&config);
+        let tx = tx.with_wal(Arc::clone(&wal));
+        let storage = Arc::new(MockStorage::new());
+        storage.fail_next_writes(1);
+        let mut engine = EngineCore::with_storage(rx, config.clone(), storage.clone())
+            .with_wal(Arc::clone(&wal));
+        // Logged before the send returns, as an exporter is acknowledged
+        tx.send(request_with_span(1)).await.unwrap();
+        assert_eq!(wal.uncommitted().await, 1);
+        let handle = tokio::spawn(async move { engine.process_messages().await });
+        tx.send(request_with_span(2)).await.unwrap();
+        drop(tx);
+        handle.await.unwrap();
+        assert_eq!(storage.written_names(), ["span-2"]);
+        assert_eq!(wal.uncommitted().await, 1);
+        drop(wal);
+
+        let wal = Arc::new(WriteAheadLog::open(&wal_config).await.unwrap());
+        let (tx, rx) = mpsc::channel(10);
+        let storage = Arc::new(MockStorage::new());
+        let mut engine = EngineCore::with_storage(rx, config, storage.clone())
+            .with_wal(Arc::clone(&wal));
+        drop(tx);
+        engine.process_messages().await;
+
+        assert_eq!(storage.written_names(), ["span-1"]);
+        assert_eq!(wal.uncommitted().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_buffered_wal_entry_committed_once_its_traces_are_written() {
+        for (failed_writes, uncommitted) in [(0, 0), (1, 1)] {
+            let wal_dir = tempfile::TempDir::new().unwrap();
+            let wal_config = WalConfig {
+                enabled: true,
+                dir: wal_dir.path().to_string_lossy().into_owned(),
+                ..WalConfig::default()
+            };
+            let wal = Arc::new(WriteAheadLog::open(&wal_config).await.unwrap());
+            let config = ProcessingConfig { batch_size: 1, ..ProcessingConfig::default() };
+            let (tx, rx) = message_channel(&config);
+            let tx = tx.with_wal(Arc::clone(&wal));
+            let storage = Arc::new(MockStorage::new());
+            storage.fail_next_writes(failed_writes);
+            let mut engine = EngineCore::with_storage(rx, config, storage.clone())
+                .with_wal(Arc::clone(&wal))
+                .with_trace_buffer(Duration::from_secs(60), u64::MAX);
+
+            // One request carrying spans of two traces, both written at shutdown
+            let mut request = request_with_spans(1, 2);
+            request.resource_spans[0].scope_spans[0].spans[1].trace_id = vec![2; 16];
+            tx.send(request).await.unwrap();
+            drop(tx);
+            engine.process_messages().await;
+
+            assert_eq!(storage.written().len(), 2 - failed_writes);
+            assert_eq!(wal.uncommitted().await, uncommitted, "{} failed writes", failed_writes);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_flush_after_each_batch() {
+        for (flush_after_batch, expected_flushes) in [(true, 3), (false, 0)] {
+            let (tx, rx) = mpsc::channel(10);
+            let storage = Arc::new(MockStorage::new().with_write_delay(Duration::from_millis(10)));
+            let config = ProcessingConfig {
+                batch_size: 1,
+                batch_timeout_ms: 60_000,
+                worker_count: 2,
+                flush_after_batch,
+                ..ProcessingConfig::default()
+            };
+            let mut engine = EngineCore::with_storage(rx, config, storage.clone());
+            let handle = tokio::spawn(async move { engine.process_messages().await });
+
+            for span_id in 1..=3 {
+                tx.send(request_with_span(span_id).into()).await.unwrap();
+            }
+            wait_for_spans(&storage, 3).await;
+            time::sleep(Duration::from_millis(50)).await;
+            assert_eq!(storage.calls("flush"), expected_flushes, "flush_after_batch: {}", flush_after_batch);
+
+            // Shutdown flushes once more
+            drop(tx);
+            handle.await.unwrap();
+            assert_eq!(storage.calls("flush"), expected_flushes + 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_not_counted() {
+        let (tx, rx) = mpsc::channel(10);
+        let storage = Arc::new(MockStorage::new());
+        storage.fail_next_writes(1);
+        let config = ProcessingConfig {
+            batch_size: 1,
+            batch_timeout_ms: 60_000,
+            ..ProcessingConfig::default()
+        };
+        let mut engine = EngineCore::with_storage(rx, config, storage.clone());
+        let health_check = engine.get_health_check();
+
+        for span_id in 1..=2 {
+            tx.send(request_with_span(span_id).into()).await.unwrap();
+        }
+        drop(tx);
+        engine.process_messages().await;
+
+        assert_eq!(storage.calls("write_spans"), 2);
+        assert_eq!(storage.written_names(), ["span-2"]);
+        assert_eq!(health_check.get_detailed_status().spans_processed_total, 1);
+    }
+
+    #[tokio::test]
+    async fn test_trace_written_as_one_object_once_idle() {
+        let (tx, rx) = mpsc::channel(10);
+        let storage = Arc::new(MockStorage::new());
+        let config = ProcessingConfig {
+            batch_size: 1,
+            batch_timeout_ms: 60_000,
+            ..ProcessingConfig::default()
+        };
+        let mut engine = EngineCore::with_storage(rx, config, storage.clone())
+            .with_trace_buffer(Duration::from_millis(200), u64::MAX);
+        let handle = tokio::spawn(async move { engine.process_messages().await });
+
+        for span_id in 1..=3 {
+            tx.send(request_with_span(span_id).into()).await.unwrap();
+        }
+        time::sleep(Duration::from_millis(50)).await;
+        assert!(storage.written().is_empty(), "trace written before going idle");
+
+        wait_for_spans(&storage, 3).await;
+        assert_eq!(storage.calls("write_spans"), 1);
+        assert_eq!(storage.written_names(), vec!["span-1", "span-2", "span-3"]);
+
+        // A trace still open at shutdown is written then
+        tx.send(request_with_span(4).into()).await.unwrap();
+        drop(tx);
+        handle.await.unwrap();
+        assert_eq!(storage.calls("write_spans"), 2);
+        assert_eq!(storage.written_names().last().unwrap(), "span-4");
+    }
+
+    #[tokio::test]
+    async fn test_oldest_trace_written_once_buffer_full() {
+        let (tx, rx) = mpsc::channel(10);
+        let storage = Arc::new(MockStorage::new());
+        let config = ProcessingConfig {
+            batch_size: 1,
+            batch_timeout_ms: 60_000,
+            ..ProcessingConfig::default()
+        };
+        let byte_size = request_with_span(1).encoded_len() as u64;
+        let mut engine = EngineCore::with_storage(rx, config, storage.clone())
+            .with_trace_buffer(Duration::from_secs(60), byte_size);
+        let handle = tokio::spawn(async move { engine.process_messages().await });
+
+        tx.send(request_with_span(1).into()).await.unwrap();
+        let mut second = request_with_span(2);
+        second.resource_spans[0].scope_spans[0].spans[0].trace_id = vec![2; 16];
+        tx.send(second.into()).await.unwrap();
+
+        // The first trace is written long before its idle timeout
+        wait_for_spans(&storage, 1).await;
+        assert_eq!(storage.written_names(), ["span-1"]);
+
+        drop(tx);
+        handle.await.unwrap();
+        assert_eq!(storage.written_names(), ["span-1", "span-2"]);
+    }
+
+    /// Waits until storage holds `count` spans
+    async fn wait_for_spans(storage: &MockStorage, count: usize) {
+        time::timeout(Duration::from_secs(5), async {
+            while storage.written().len() < count {
+                time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("spans were not written");
+    }
+
+    #[tokio::test]
+    async fn test_spill_holds_only_kept_spans() {
+        let spill_dir = tempfile::TempDir::new().unwrap();
+        let spill_config = SpillConfig {
+            enabled: true,
+            dir: spill_dir.path().to_string_lossy().into_owned(),
+            ..SpillConfig::default()
+        };
+        let spill = Arc::new(SpillBuffer::open(&spill_config).await.unwrap());
+        let (tx, rx) = mpsc::channel(10);
+        let storage = Arc::new(MockStorage::new());
+        storage.fail_next_writes(1);
+        let config = ProcessingConfig { batch_size: 1, ..ProcessingConfig::default() };
+        let mut engine = EngineCore::with_storage(rx, config, storage)
+            .with_name_filter(SpanNameFilter::new(Vec::new(), vec!["GET /health*".into()]))
+            .with_spill(Arc::clone(&spill), Duration::from_secs(60));
+
+        let mut request = request_with_spans(1, 2);
+        request.resource_spans[0].scope_spans[0].spans[0].name = "GET /healthz".into();
+        request.resource_spans[0].scope_spans[0].spans[1].name = "checkout".into();
+        tx.send(request.into()).await.unwrap();
+        drop(tx);
+        engine.process_messages().await;
+
+        let (_, data) = spill.oldest().await.unwrap().expect("the failed write was spilled");
+        let spilled = ExportTraceServiceRequest::decode(data.as_slice()).unwrap();
+        let names: Vec<&str> = spilled.resource_spans[0].scope_spans[0].spans.iter().map(|span| span.name.as_str()).collect();
+        assert_eq!(names, ["checkout"]);
+    }
+
+    #[tokio::test]
+    async fn test_batch_written_and_spilled_as_one_job() {
+        let spill_dir = tempfile::TempDir::new().unwrap();
+        let spill_config = SpillConfig {
+            enabled: true,
+            dir: spill_dir.path().to_string_lossy().into_owned(),
+            ..SpillConfig::default()
+        };
+        let spill = Arc::new(SpillBuffer::open(&spill_config).await.unwrap());
+        let (tx, rx) = mpsc::channel(10);
+        let storage = Arc::new(MockStorage::new());
+        storage.fail_next_writes(1);
+        let config = ProcessingConfig { batch_size: 3, ..ProcessingConfig::default() };
+        let mut engine = EngineCore::with_storage(rx, config, storage.clone())
+            .with_spill(Arc::clone(&spill), Duration::from_secs(60));
+
+        for span_id in 1..=3 {
+            tx.send(request_with_span(span_id).into()).await.unwrap();
+        }
+        drop(tx);
+        engine.process_messages().await;
+
+        assert_eq!(storage.calls("write_spans"), 1);
+        let (_, data) = spill.oldest().await.unwrap().expect("the failed write was spilled");
+        let spilled = ExportTraceServiceRequest::decode(data.as_slice()).unwrap();
+        assert_eq!(spilled.resource_spans.len(), 3);
+    }
+
+    #[test]
+    fn test_denied_span_names_dropped() {
+        let mut engine = engine().with_name_filter(SpanNameFilter::new(
+            vec!["GET *".into()],
+            vec!["GET /health*".into()],
+        ));
+        let spans = ["GET /healthz", "GET /orders", "checkout"]
+            .into_iter()
+            .map(|name| Span {
+                trace_id: vec![1; 16],
+                span_id: vec![name.len() as u8; 8],
+                name: name.into(),
+                ..Default::default()
+            })
+            .collect();
+        let request = ExportTraceServiceRequest {
+            resource_spans: vec![ResourceSpans {
+                resource: None,
+                scope_spans: vec![ScopeSpans { scope: None, spans, schema_url: String::new() }],
+                schema_url: String::new(),
+            }],
+        };
+
+        let names: Vec<_> = engine.convert_request_to_spans(request).into_iter().map(|span| span.name).collect();
+        assert_eq!(names, ["GET /orders"]);
+        assert_eq!(engine.get_health_check().get_detailed_status().spans_filtered, 2);
+    }
+
+    #[tokio::test]
+    async fn test_redacted_attributes_not_logged_or_stored() {
+        let wal_dir = tempfile::TempDir::new().unwrap();
+        let wal_config = WalConfig {
+            enabled: true,
+            dir: wal_dir.path().to_string_lossy().into_owned(),
+            ..WalConfig::default()
+        };
+        let wal = Arc::new(WriteAheadLog::open(&wal_config).await.unwrap());
+        let config = ProcessingConfig { batch_size: 1, ..ProcessingConfig::default() };
+        let (tx, rx) = message_channel(&config);
+        let tx = tx
+            .with_redactor(AttributeRedactor::new(vec!["password".into()]))
+            .with_wal(Arc::clone(&wal));
+        let storage = Arc::new(MockStorage::new());
+        let mut engine = EngineCore::with_storage(rx, config, storage.clone());
+
+        let mut request = request_with_span(1);
+        request.resource_spans[0].resource = Some(ProtoResource {
+            attributes: vec![string_attribute("password", "hunter2")],
+            dropped_attributes_count: 0,
+        });
+        request.resource_spans[0].scope_spans[0].spans[0].attributes =
+            vec![string_attribute("password", "hunter2"), string_attribute("user", "alice")];
+        tx.send(request).await.unwrap();
+        drop(tx);
+        engine.process_messages().await;
+
+        let written = storage.written();
+        let keys: Vec<String> = written[0].attributes.iter().map(|(key, _)| key.to_string()).collect();
+        assert_eq!(keys, ["user"]);
+        assert_eq!(written[0].resource.len(), 0);
+        drop(wal);
+        let logged = WriteAheadLog::open(&wal_config).await.unwrap().take_uncommitted().await;
+        assert!(logged.iter().all(|(_, data)| !data.windows(7).any(|w| w == b"hunter2")));
+    }
+
+    #[tokio::test]
+    async fn test_resent_span_written_once() {
+        let (tx, rx) = mpsc::channel(10);
+        let storage = Arc::new(MockStorage::new());
+        let config = ProcessingConfig {
+            batch_size: 1,
+            batch_timeout_ms: 60_000,
+            ..ProcessingConfig::default()
+        };
+        let mut engine = EngineCore::with_storage(rx, config, storage.clone())
+            .with_dedup_window(100, Duration::from_secs(10));
+        let health_check = engine.get_health_check();
+
+        for span_id in [1, 1, 2] {
+            tx.send(request_with_span(span_id).into()).await.unwrap();
+        }
+        drop(tx);
+        engine.process_messages().await;
+
+        assert_eq!(storage.written_names(), ["span-1", "span-2"]);
+        assert_eq!(health_check.get_detailed_status().spans_deduplicated, 1);
+    }
+
+    #[tokio::test]
+    async fn test_resend_after_failed_write_stored() {
+        let (tx, rx) = mpsc::channel(10);
+        let storage = Arc::new(MockStorage::new());
+        storage.fail_next_writes(1);
+        let config = ProcessingConfig {
+            batch_size: 1,
+            batch_timeout_ms: 60_000,
+            ..ProcessingConfig::default()
+        };
+        let mut engine = EngineCore::with_storage(rx, config, storage.clone())
+            .with_dedup_window(100, Duration::from_secs(10));
+        let health_check = engine.get_health_check();
+        let handle = tokio::spawn(async move { engine.process_messages().await });
+
+        // The first write fails with no spill to take the span
+        tx.send(request_with_span(1).into()).await.unwrap();
+        while health_check.get_detailed_status().total_failed_writes == 0
+            || health_check.unwritten_spans() > 0
+        {
+            time::sleep(Duration::from_millis(5)).await;
+        }
+
+        tx.send(request_with_span(1).into()).await.unwrap();
+        wait_for_spans(&storage, 1).await;
+        assert_eq!(storage.written_names(), ["span-1"]);
+        assert_eq!(health_check.get_detailed_status().spans_deduplicated, 0);
+
+        drop(tx);
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_batch_size_updated_at_runtime() {
+        let (tx, rx) = mpsc::channel(10);
+        let storage = Arc::new(MockStorage::new());
+        let config = ProcessingConfig {
+            batch_size: 5,
+            batch_timeout_ms: 60_000,
+            ..ProcessingConfig::default()
+        };
+        let mut engine = EngineCore::with_storage(rx, config, storage.clone());
+        let control = engine.control();
+        tokio::spawn(async move { engine.process_messages().await });
+
+        for span_id in 1..=2 {
+            tx.send(request_with_span(span_id).into()).await.unwrap();
+        }
+        time::sleep(Duration::from_millis(50)).await;
+        assert!(storage.written().is_empty());
+
+        // Lowering the threshold below the queue length flushes immediately
+        control
+            .update_processing(ProcessingConfig { batch_size: 2, batch_timeout_ms: 60_000, ..ProcessingConfig::default() })
+            .unwrap();
+        wait_for_spans(&storage, 2).await;
+
+        // The next flush happens at the new threshold
+        tx.send(request_with_span(3).into()).await.unwrap();
+        time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(storage.written().len(), 2);
+        tx.send(request_with_span(4).into()).await.unwrap();
+        wait_for_spans(&storage, 4).await;
+    }
+
+    #[tokio::test]
+    async fn test_channel_buffers_configured_capacity() {
+        let config = ProcessingConfig { channel_capacity: 3, ..ProcessingConfig::default() };
+        let (sender, mut receiver) = message_channel(&config);
+
+        for id in 1..=3 {
+            sender.send(request_with_span(id)).await.unwrap();
+        }
+        assert_eq!(sender.max_capacity(), 3);
+        assert_eq!(sender.capacity(), 0);
+
+        receiver.recv().await.unwrap();
+        assert_eq!(sender.capacity(), 1);
+        assert_eq!(ProcessingConfig::default().channel_capacity, 1000);
+    }
+
+    #[tokio::test]
+    async fn test_backlog_seen_while_storage_stalls() {
+        let config = ProcessingConfig {
+            batch_size: 1,
+            worker_count: 1,
+            channel_capacity: 10,
+            ..ProcessingConfig::default()
+        };
+        let (tx, rx) = message_channel(&config);
+        let storage = Arc::new(MockStorage::new().with_write_delay(Duration::from_secs(60)));
+        let health_check = Arc::new(HealthCheck::with_config(&HealthConfig {
+            degraded_queue_size: 3,
+            degraded_after_ms: 0,
+            ..HealthConfig::default()
+        }));
+        let mut engine = EngineCore::with_storage(rx, config, storage)
+            .with_health_check(Arc::clone(&health_check));
+        let monitor = tx.spawn_backlog_monitor(Arc::clone(&health_check), Duration::from_millis(5));
+        let handle = tokio::spawn(async move { engine.process_messages().await });
+
+        // The engine blocks on the first batches; the rest wait in the channel
+        for span_id in 1..=8 {
+            tx.send(request_with_span(span_id)).await.unwrap();
+        }
+        time::timeout(Duration::from_secs(5), async {
+            while !health_check.get_health_status().degraded {
+                time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("backlog behind a stalled write was not seen");
+        assert!(health_check.get_health_status().queue_size <= 1, "the engine's own queue stays small");
+
+        handle.abort();
+        drop(tx);
+        monitor.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_null_backend_counts_processed_spans() {
+        let (tx, rx) = mpsc::channel(10);
+        let storage = Arc::new(NullStorageWriter::new());
+        let config = ProcessingConfig {
+            batch_size: 2,
+            batch_timeout_ms: 60_000,
+            ..ProcessingConfig::default()
+        };
+        let mut engine = EngineCore::with_storage(rx, config, storage.clone());
+        let health_check = engine.get_health_check();
+
+        tx.send(request_with_spans(1, 3).into()).await.unwrap();
+        tx.send(request_with_spans(4, 2).into()).await.unwrap();
+        tx.send(request_with_span(6).into()).await.unwrap();
+        drop(tx);
+        engine.process_messages().await;
+
+        assert_eq!(storage.spans(), 6);
+        let status = health_check.get_detailed_status();
+        assert_eq!(status.spans_processed_total, 6);
+        assert_eq!(status.total_failed_writes, 0);
+        assert!(status.is_healthy);
+    }
+
+    #[tokio::test]
+    async fn test_span_and_byte_counters() {
+        let (tx, rx) = mpsc::channel(10);
+        let storage = Arc::new(MockStorage::new());
+        let config = ProcessingConfig {
+            batch_size: 1,
+            batch_timeout_ms: 60_000,
+            ..ProcessingConfig::default()
+        };
+        let mut engine = EngineCore::with_storage(rx, config, storage.clone());
+        let health_check = engine.get_health_check();
+        tokio::spawn(async move { engine.process_messages().await });
+
+        let mut request = request_with_span(1);
+        let spans = &mut request.resource_spans[0].scope_spans[0].spans;
+        spans.push(Span { span_id: vec![2; 8], ..spans[0].clone() });
+        spans.push(Span { span_id: vec![3; 8], ..spans[0].clone() });
+        assert_eq!(count_spans(&request), 3);
+        let byte_size = request.encoded_len() as u64;
+
+        tx.send(request.into()).await.unwrap();
+        wait_for_spans(&storage, 3).await;
+
+        let status = health_check.get_detailed_status();
+        assert_eq!(status.spans_processed_total, 3);
+        assert_eq!(status.bytes_written_total, byte_size);
+    }
+
+    fn request_with_spans(first_span_id: u8, count: u8) -> ExportTraceServiceRequest {
+        let mut request = request_with_span(first_span_id);
+        request.resource_spans[0].scope_spans[0].spans = (first_span_id..first_span_id + count)
+            .map(|span_id| span_with_ids(vec![1; 16], vec![span_id; 8]))
+            .collect();
+        request
+    }
+
+    #[tokio::test]
+    async fn test_batch_by_spans_flushes_at_span_threshold() {
+        let (tx, rx) = mpsc::channel(10);
+        let storage = Arc::new(MockStorage::new());
+        let config = ProcessingConfig {
+            batch_size: 10,
+            batch_timeout_ms: 60_000,
+            batch_by: BatchUnit::Spans,
+            ..ProcessingConfig::default()
+        };
+        let mut engine = EngineCore::with_storage(rx, config, storage.clone());
+        tokio::spawn(async move { engine.process_messages().await });
+
+        // Two requests, far below a batch_size of 10 requests
+        tx.send(request_with_spans(1, 4).into()).await.unwrap();
+        time::sleep(Duration::from_millis(50)).await;
+        assert!(storage.written().is_empty());
+
+        tx.send(request_with_spans(5, 6).into()).await.unwrap();
+        wait_for_spans(&storage, 10).await;
+    }
+
+    #[tokio::test]
+    async fn test_queue_bytes_trigger_early_flush() {
+        let (tx, rx) = mpsc::channel(10);
+        let storage = Arc::new(MockStorage::new());
+        let message_size = request_with_span(1).encoded_len();
+        let config = ProcessingConfig {
+            batch_size: 100,
+            batch_timeout_ms: 60_000,
+            max_queue_bytes: message_size * 3,
+            ..ProcessingConfig::default()
+        };
+        let mut engine = EngineCore::with_storage(rx, config, storage.clone());
+        let health_check = engine.get_health_check();
+        tokio::spawn(async move { engine.process_messages().await });
+
+        for span_id in 1..=4 {
+            tx.send(request_with_span(span_id).into()).await.unwrap();
+        }
+        wait_for_spans(&storage, 3).await;
+
+        // The fourth message stays queued below both thresholds
+        time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(storage.written().len(), 3);
+        let status = health_check.get_detailed_status();
+        assert_eq!(status.queue_size, 1);
+        assert_eq!(status.queue_bytes, message_size as u64);
+    }
+
+    /// Returns how long `worker_count` workers take to store `messages` single-span batches
+    async fn time_to_write(worker_count: usize, messages: u8) -> Duration {
+        let (tx, rx) = mpsc::channel(100);
+        let storage = Arc::new(MockStorage::new().with_write_delay(Duration::from_millis(100)));
+        let config = ProcessingConfig {
+            batch_size: 1,
+            batch_timeout_ms: 60_000,
+            worker_count,
+            ..ProcessingConfig::default()
+        };
+        let mut engine = EngineCore::with_storage(rx, config, storage.clone());
+        for span_id in 1..=messages {
+            tx.send(request_with_span(span_id).into()).await.unwrap();
+        }
+
+        let started = Instant::now();
+        tokio::spawn(async move { engine.process_messages().await });
+        while storage.written().len() < messages as usize {
+            time::sleep(Duration::from_millis(1)).await;
+        }
+        started.elapsed()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_workers_overlap_slow_writes() {
+        let serial = time_to_write(1, 8).await;
+        let parallel = time_to_write(4, 8).await;
+
+        assert!(serial >= Duration::from_millis(800), "1 worker took {:?}", serial);
+        assert!(parallel * 3 < serial, "4 workers took {:?}, 1 worker {:?}", parallel, serial);
+    }
+
+    #[test]
+    fn test_invalid_runtime_update_rejected() {
+        let control = engine().control();
+        let result = control.update_processing(ProcessingConfig {
+            batch_size: 0,
+            batch_timeout_ms: 1_000,
+            ..ProcessingConfig::default()
+        });
+        assert!(result.is_err());
+        assert_eq!(control.processing_config(), ProcessingConfig::default());
+    }
+}
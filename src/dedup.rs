@@ -0,0 +1,150 @@
+use std::collections::{HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+use opentelemetry::trace::{SpanId, TraceId};
+
+/// Span identity used for deduplication
+pub type SpanKey = (TraceId, SpanId);
+
+/// Bounded in-memory record of recently received spans, so that spans an
+/// exporter resends within `window` are dropped before any storage call.
+/// Keys are recorded when a span is first seen, so repeats within one batch
+/// are dropped too; a span whose write fails unspilled is `forget`-ten, so
+/// its resend is stored.
+/// Keys expire after `window`; when `max_entries` are held the oldest key
+/// is evicted first. A `max_entries` of 0 disables deduplication.
+#[derive(Debug)]
+pub struct SpanDeduplicator {
+    /// Keys remembered at most
+    max_entries: usize,
+    /// How long a key suppresses repeats of its span
+    window: Duration,
+    /// Keys currently remembered
+    seen: HashSet<SpanKey>,
+    /// Remembered keys with the time they were first seen, oldest first
+    order: VecDeque<(SpanKey, Instant)>,
+}
+
+impl SpanDeduplicator {
+    /// Creates a deduplicator remembering up to `max_entries` spans for `window`
+    pub fn new(max_entries: usize, window: Duration) -> Self {
+        Self {
+            max_entries,
+            window,
+            seen: HashSet::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Returns whether the span was already seen within the window,
+    /// remembering it otherwise
+    pub fn is_duplicate(&mut self, trace_id: TraceId, span_id: SpanId) -> bool {
+        self.is_duplicate_at((trace_id, span_id), Instant::now())
+    }
+
+    fn is_duplicate_at(&mut self, key: SpanKey, now: Instant) -> bool {
+        if self.max_entries == 0 {
+            return false;
+        }
+        self.expire(now);
+        if self.seen.contains(&key) {
+            return true;
+        }
+        if self.seen.len() >= self.max_entries {
+            if let Some((oldest, _)) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.seen.insert(key);
+        self.order.push_back((key, now));
+        false
+    }
+
+    /// Forgets `keys`, so their spans are kept when received again
+    pub fn forget(&mut self, keys: impl IntoIterator<Item = SpanKey>) {
+        if self.max_entries == 0 {
+            return;
+        }
+        let forgotten: HashSet<SpanKey> = keys
+            .into_iter()
+            .filter(|key| self.seen.remove(key))
+            .collect();
+        if !forgotten.is_empty() {
+            self.order.retain(|(key, _)| !forgotten.contains(key));
+        }
+    }
+
+    /// Forgets keys first seen a full window before `now`
+    fn expire(&mut self, now: Instant) {
+        while let Some(&(key, seen_at)) = self.order.front() {
+            if now.duration_since(seen_at) < self.window {
+                break;
+            }
+            self.order.pop_front();
+            self.seen.remove(&key);
+        }
+    }
+}
+
+impl Default for SpanDeduplicator {
+    /// Deduplication disabled
+    fn default() -> Self {
+        Self::new(0, Duration::ZERO)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(span: u64) -> SpanKey {
+        (TraceId::from(1), SpanId::from(span))
+    }
+
+    #[test]
+    fn test_repeats_within_window_detected() {
+        let mut dedup = SpanDeduplicator::new(10, Duration::from_secs(5));
+        let start = Instant::now();
+
+        assert!(!dedup.is_duplicate_at(key(1), start));
+        assert!(dedup.is_duplicate_at(key(1), start + Duration::from_secs(4)));
+        assert!(!dedup.is_duplicate_at(key(2), start + Duration::from_secs(4)));
+        // Expired, so kept again
+        assert!(!dedup.is_duplicate_at(key(1), start + Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_oldest_key_evicted_at_capacity() {
+        let mut dedup = SpanDeduplicator::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+
+        for span in 1..=3 {
+            assert!(!dedup.is_duplicate_at(key(span), now));
+        }
+        assert_eq!(dedup.seen.len(), 2);
+        assert!(!dedup.is_duplicate_at(key(1), now));
+        assert!(dedup.is_duplicate_at(key(3), now));
+    }
+
+    #[test]
+    fn test_forgotten_keys_kept_again() {
+        let mut dedup = SpanDeduplicator::new(10, Duration::from_secs(5));
+        let start = Instant::now();
+
+        assert!(!dedup.is_duplicate_at(key(1), start));
+        assert!(!dedup.is_duplicate_at(key(2), start));
+        dedup.forget([key(1)]);
+        assert!(!dedup.is_duplicate_at(key(1), start + Duration::from_secs(3)));
+        assert!(dedup.is_duplicate_at(key(2), start + Duration::from_secs(3)));
+        // The key expires a window after it was seen again, not the first time
+        assert!(dedup.is_duplicate_at(key(1), start + Duration::from_secs(6)));
+    }
+
+    #[test]
+    fn test_disabled_never_detects_duplicates() {
+        let mut dedup = SpanDeduplicator::default();
+        let now = Instant::now();
+        assert!(!dedup.is_duplicate_at(key(1), now));
+        assert!(!dedup.is_duplicate_at(key(1), now));
+    }
+}
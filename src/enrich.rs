@@ -0,0 +1,8 @@
+use opentelemetry::sdk::export::trace::SpanData;
+
+/// Hook rewriting each span after conversion and before storage, e.g. to
+/// add deployment tags or normalize operation names
+pub trait SpanEnricher: Send + Sync {
+    /// Rewrites a span in place
+    fn enrich(&self, span: &mut SpanData);
+}
@@ -53,6 +53,112 @@ pub enum StorageError {
     /// Error during read operation
     #[error("Read failed: {0}")]
     ReadFailed(String),
+
+    /// Storage request rejected by the backend or left unanswered
+    #[error("Request failed ({kind}): {message}")]
+    Request {
+        /// Category deciding whether the request is worth retrying
+        kind: StorageErrorKind,
+        /// Backend error code such as `SlowDown` or `AccessDenied`, if one was sent
+        code: Option<String>,
+        /// HTTP status of the response, `None` when none was received
+        status: Option<u16>,
+        message: String,
+        /// How long the backend asked clients to wait before retrying
+        retry_after: Option<std::time::Duration>,
+    },
+}
+
+impl StorageError {
+    /// Returns whether the failed operation may succeed when retried:
+    /// throttling, server errors and transport failures
+    pub fn is_retryable(&self) -> bool {
+        self.kind().is_some_and(StorageErrorKind::is_retryable)
+    }
+
+    /// Returns the category of a failed storage request
+    pub fn kind(&self) -> Option<StorageErrorKind> {
+        match self {
+            StorageError::Request { kind, .. } => Some(*kind),
+            _ => None,
+        }
+    }
+
+    /// Returns the `Retry-After` delay the backend sent with a failed request
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        match self {
+            StorageError::Request { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Returns the backend error code of a failed storage request
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            StorageError::Request { code, .. } => code.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// Category of a failed storage request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageErrorKind {
+    /// Request rate exceeded (429, or a throttling error code such as `SlowDown`)
+    Throttled,
+    /// Server-side failure (5xx other than 501)
+    ServerError,
+    /// No response was received: connection, DNS or I/O failure, or an SDK timeout
+    Transport,
+    /// Bucket or object does not exist (404)
+    NotFound,
+    /// Credentials rejected or lacking permission (401, 403)
+    AccessDenied,
+    /// Conditional request lost to a concurrent change (409, 412)
+    PreconditionFailed,
+    /// Request not supported by the backend (501)
+    NotImplemented,
+    /// Any other rejected or unbuildable request
+    InvalidRequest,
+}
+
+impl StorageErrorKind {
+    /// Classifies a response by its HTTP status and backend error code
+    pub fn from_response(status: u16, code: Option<&str>) -> Self {
+        match (status, code) {
+            (429, _) | (_, Some("SlowDown" | "Throttling" | "ThrottlingException" | "RequestLimitExceeded")) => {
+                StorageErrorKind::Throttled
+            }
+            (501, _) => StorageErrorKind::NotImplemented,
+            (500..=599, _) => StorageErrorKind::ServerError,
+            (404, _) | (_, Some("NoSuchKey" | "NoSuchBucket")) => StorageErrorKind::NotFound,
+            (401 | 403, _) | (_, Some("AccessDenied" | "InvalidAccessKeyId" | "SignatureDoesNotMatch")) => {
+                StorageErrorKind::AccessDenied
+            }
+            (409 | 412, _) => StorageErrorKind::PreconditionFailed,
+            _ => StorageErrorKind::InvalidRequest,
+        }
+    }
+
+    /// Returns whether requests failing this way may succeed when retried
+    pub fn is_retryable(self) -> bool {
+        matches!(self, StorageErrorKind::Throttled | StorageErrorKind::ServerError | StorageErrorKind::Transport)
+    }
+}
+
+impl std::fmt::Display for StorageErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            StorageErrorKind::Throttled => "throttled",
+            StorageErrorKind::ServerError => "server error",
+            StorageErrorKind::Transport => "transport",
+            StorageErrorKind::NotFound => "not found",
+            StorageErrorKind::AccessDenied => "access denied",
+            StorageErrorKind::PreconditionFailed => "precondition failed",
+            StorageErrorKind::NotImplemented => "not implemented",
+            StorageErrorKind::InvalidRequest => "invalid request",
+        })
+    }
 }
 
 /// Errors that can occur during configuration
@@ -87,6 +193,8 @@ impl From<StorageError> for ProcessingError {
                 ProcessingError::StorageError(format!("Retry limit exceeded: {}", msg)),
             StorageError::ReadFailed(msg) => 
                 ProcessingError::StorageError(format!("Read failed: {}", msg)),
+            error @ StorageError::Request { .. } =>
+                ProcessingError::StorageError(error.to_string()),
         }
     }
 }
@@ -115,6 +223,47 @@ mod tests {
         }
     }
 
+    fn request_error(status: u16, code: Option<&str>) -> StorageError {
+        StorageError::Request {
+            kind: StorageErrorKind::from_response(status, code),
+            code: code.map(str::to_string),
+            status: Some(status),
+            message: "test".to_string(),
+            retry_after: None,
+        }
+    }
+
+    #[test]
+    fn test_retryable_storage_errors() {
+        for (status, code) in [(429, None), (503, Some("SlowDown")), (500, Some("InternalError")), (502, None)] {
+            assert!(request_error(status, code).is_retryable(), "{} {:?}", status, code);
+        }
+        let transport = StorageError::Request {
+            kind: StorageErrorKind::Transport,
+            code: None,
+            status: None,
+            message: "connection refused".to_string(),
+            retry_after: None,
+        };
+        assert!(transport.is_retryable());
+    }
+
+    #[test]
+    fn test_non_retryable_storage_errors() {
+        let denied = request_error(403, Some("AccessDenied"));
+        assert!(!denied.is_retryable());
+        assert_eq!(denied.kind(), Some(StorageErrorKind::AccessDenied));
+        assert_eq!(denied.code(), Some("AccessDenied"));
+
+        assert_eq!(request_error(404, Some("NoSuchKey")).kind(), Some(StorageErrorKind::NotFound));
+        assert_eq!(request_error(412, None).kind(), Some(StorageErrorKind::PreconditionFailed));
+        assert_eq!(request_error(501, None).kind(), Some(StorageErrorKind::NotImplemented));
+        for error in [request_error(400, Some("InvalidArgument")), request_error(501, None)] {
+            assert!(!error.is_retryable(), "{}", error);
+        }
+        assert!(!StorageError::WriteFailed("test".into()).is_retryable());
+    }
+
     #[test]
     fn test_error_display() {
         let error = ProcessingError::ValidationError("invalid input".to_string());
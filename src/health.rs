@@ -1,7 +1,15 @@
+use std::collections::VecDeque;
 use std::sync::atomic::{AtomicU64, AtomicBool, Ordering};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tracing::warn;
 use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::config::HealthConfig;
+
+/// Number of recent write or flush latencies kept for percentile calculation
+const LATENCY_WINDOW: usize = 1024;
 
 /// Component for monitoring and reporting system health metrics.
 /// Uses atomic types for thread-safe access to health indicators.
@@ -12,21 +20,102 @@ pub struct HealthCheck {
     last_successful_write: AtomicU64,
     /// Current size of the message processing queue
     message_queue_size: AtomicU64,
+    /// Current encoded size in bytes of the queued messages
+    message_queue_bytes: AtomicU64,
+    /// Spans taken from the channel by the engine and not yet written,
+    /// spilled or dropped
+    unwritten_spans: AtomicU64,
     /// Total number of messages processed since startup
     total_messages_processed: AtomicU64,
-    /// Number of failed write operations
-    failed_writes: AtomicU64,
+    /// Failed writes since the last successful write, used for the health threshold
+    consecutive_failed_writes: AtomicU64,
+    /// Failed writes since startup; never reset
+    total_failed_writes: AtomicU64,
+    /// Total number of spans written to storage
+    spans_processed_total: AtomicU64,
+    /// Total OTLP-encoded bytes of requests written to storage
+    bytes_written_total: AtomicU64,
+    /// Writes skipped because an identical object already existed
+    duplicates_skipped: AtomicU64,
+    /// Spans dropped by trace sampling before storage
+    spans_sampled_out: AtomicU64,
+    /// Spans dropped as repeats of recently stored spans
+    spans_deduplicated: AtomicU64,
+    /// Spans dropped by the span name allow/deny lists
+    spans_filtered: AtomicU64,
+    /// Spans written to the local spill after a failed storage write
+    spans_spilled: AtomicU64,
+    /// Spill files deleted unuploaded to keep the spill within its size limit
+    spill_files_dropped: AtomicU64,
+    /// Spans rejected as malformed during conversion
+    invalid_spans_total: AtomicU64,
+    /// Spans that lost attributes, events or links to the configured limits
+    truncated_spans_total: AtomicU64,
+    /// Export requests rejected for exceeding the ingest limits
+    rejected_requests_total: AtomicU64,
+    /// Export requests rejected by the rate limiter
+    rate_limited_requests_total: AtomicU64,
+    /// Storage endpoint writes currently go to, when the writer reports one
+    active_storage_endpoint: Mutex<Option<String>>,
+    /// Times writes switched to another storage endpoint
+    storage_failovers_total: AtomicU64,
+    /// Most recent storage write latencies, oldest first
+    write_latencies: Mutex<VecDeque<Duration>>,
+    /// Most recent storage flush latencies, oldest first
+    flush_latencies: Mutex<VecDeque<Duration>>,
+    /// Failures tolerated before the system is marked unhealthy
+    unhealthy_after_failures: u64,
+    /// Window failures are counted over; `None` counts consecutive failures
+    failure_window: Option<Duration>,
+    /// Times of failures within `failure_window`, oldest first
+    recent_failures: Mutex<VecDeque<Instant>>,
+    /// Channel backlog above which a sustained backlog degrades health; 0 disables this
+    degraded_queue_size: u64,
+    /// How long the backlog must stay above `degraded_queue_size`
+    degraded_after: Duration,
+    /// When the backlog last rose above `degraded_queue_size`, while it stays there
+    queue_backlog_since: Mutex<Option<Instant>>,
 }
 
 impl HealthCheck {
     /// Creates a new HealthCheck instance with default values
     pub fn new() -> Self {
+        Self::with_config(&HealthConfig::default())
+    }
+
+    /// Creates a HealthCheck using the given unhealthy threshold
+    pub fn with_config(config: &HealthConfig) -> Self {
         Self {
             is_healthy: AtomicBool::new(true),
             last_successful_write: AtomicU64::new(0),
             message_queue_size: AtomicU64::new(0),
+            message_queue_bytes: AtomicU64::new(0),
+            unwritten_spans: AtomicU64::new(0),
             total_messages_processed: AtomicU64::new(0),
-            failed_writes: AtomicU64::new(0),
+            consecutive_failed_writes: AtomicU64::new(0),
+            total_failed_writes: AtomicU64::new(0),
+            spans_processed_total: AtomicU64::new(0),
+            bytes_written_total: AtomicU64::new(0),
+            duplicates_skipped: AtomicU64::new(0),
+            spans_sampled_out: AtomicU64::new(0),
+            spans_deduplicated: AtomicU64::new(0),
+            spans_filtered: AtomicU64::new(0),
+            spans_spilled: AtomicU64::new(0),
+            spill_files_dropped: AtomicU64::new(0),
+            invalid_spans_total: AtomicU64::new(0),
+            truncated_spans_total: AtomicU64::new(0),
+            rejected_requests_total: AtomicU64::new(0),
+            rate_limited_requests_total: AtomicU64::new(0),
+            active_storage_endpoint: Mutex::new(None),
+            storage_failovers_total: AtomicU64::new(0),
+            write_latencies: Mutex::new(VecDeque::with_capacity(LATENCY_WINDOW)),
+            flush_latencies: Mutex::new(VecDeque::with_capacity(LATENCY_WINDOW)),
+            unhealthy_after_failures: config.unhealthy_after_failures,
+            failure_window: config.failure_window(),
+            recent_failures: Mutex::new(VecDeque::new()),
+            degraded_queue_size: config.degraded_queue_size,
+            degraded_after: config.degraded_after(),
+            queue_backlog_since: Mutex::new(None),
         }
     }
 
@@ -50,86 +139,342 @@ impl HealthCheck {
         // Increment total processed count
         self.total_messages_processed.fetch_add(1, Ordering::SeqCst);
 
-        // Reset failed writes if any successful write occurs
-        if self.failed_writes.load(Ordering::SeqCst) > 0 {
-            self.failed_writes.store(0, Ordering::SeqCst);
-            self.update_status(true);
+        // Reset consecutive failures if any successful write occurs
+        if self.consecutive_failed_writes.load(Ordering::SeqCst) > 0 {
+            self.consecutive_failed_writes.store(0, Ordering::SeqCst);
+            // With a window, health returns once old failures age out of it
+            let healthy = self.failure_window.is_none()
+                || self.windowed_failures(Instant::now()) <= self.unhealthy_after_failures;
+            self.update_status(healthy);
         }
     }
 
     /// Records a failed write operation
     pub fn record_failed_write(&self) {
-        let failed = self.failed_writes.fetch_add(1, Ordering::SeqCst) + 1;
-        
+        self.total_failed_writes.fetch_add(1, Ordering::SeqCst);
+        let failed = self.consecutive_failed_writes.fetch_add(1, Ordering::SeqCst) + 1;
+
+        if self.failure_window.is_some() {
+            let now = Instant::now();
+            self.recent_failures.lock().unwrap().push_back(now);
+            let windowed = self.windowed_failures(now);
+            if windowed > self.unhealthy_after_failures {
+                warn!("System marked unhealthy due to {} write failures within the failure window", windowed);
+                self.update_status(false);
+            }
+            return;
+        }
+
         // Mark system as unhealthy if too many failures
-        if failed > 5 {
+        if failed > self.unhealthy_after_failures {
             warn!("System marked unhealthy due to {} consecutive write failures", failed);
             self.update_status(false);
         }
     }
 
+    /// Drops failures older than the failure window and returns how many remain
+    fn windowed_failures(&self, now: Instant) -> u64 {
+        let mut failures = self.recent_failures.lock().unwrap();
+        if let Some(window) = self.failure_window {
+            while failures.front().is_some_and(|&failed_at| now.duration_since(failed_at) > window) {
+                failures.pop_front();
+            }
+        }
+        failures.len() as u64
+    }
+
+    /// Adds a successfully written request's span count and encoded size
+    pub fn record_spans_written(&self, spans: u64, bytes: u64) {
+        self.spans_processed_total.fetch_add(spans, Ordering::SeqCst);
+        self.bytes_written_total.fetch_add(bytes, Ordering::SeqCst);
+    }
+
+    /// Records a write skipped because its object already existed
+    pub fn record_duplicate_skipped(&self) {
+        self.duplicates_skipped.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Records spans dropped by trace sampling
+    pub fn record_sampled_out(&self, count: u64) {
+        self.spans_sampled_out.fetch_add(count, Ordering::SeqCst);
+    }
+
+    /// Records spans dropped by the deduplication window
+    pub fn record_deduplicated(&self, count: u64) {
+        self.spans_deduplicated.fetch_add(count, Ordering::SeqCst);
+    }
+
+    /// Records spans dropped by the span name allow/deny lists
+    pub fn record_filtered(&self, count: u64) {
+        self.spans_filtered.fetch_add(count, Ordering::SeqCst);
+    }
+
+    /// Records spans written to the local spill
+    pub fn record_spilled(&self, count: u64) {
+        self.spans_spilled.fetch_add(count, Ordering::SeqCst);
+    }
+
+    /// Records spill files deleted before they could be uploaded
+    pub fn record_spill_dropped(&self, count: u64) {
+        self.spill_files_dropped.fetch_add(count, Ordering::SeqCst);
+    }
+
+    /// Records spans rejected as malformed
+    pub fn record_invalid_spans(&self, count: u64) {
+        self.invalid_spans_total.fetch_add(count, Ordering::SeqCst);
+    }
+
+    /// Records spans truncated to the attribute, event or link limits
+    pub fn record_truncated_spans(&self, count: u64) {
+        self.truncated_spans_total.fetch_add(count, Ordering::SeqCst);
+    }
+
+    /// Records an export request rejected for exceeding the ingest limits
+    pub fn record_rejected_request(&self) {
+        self.rejected_requests_total.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Records an export request rejected by the rate limiter
+    pub fn record_rate_limited(&self) {
+        self.rate_limited_requests_total.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Records the storage endpoint writes go to
+    pub fn set_active_storage_endpoint(&self, endpoint: &str) {
+        *self.active_storage_endpoint.lock().unwrap() = Some(endpoint.to_string());
+    }
+
+    /// Records a switch of writes to another storage endpoint
+    pub fn record_storage_failover(&self, endpoint: &str) {
+        self.storage_failovers_total.fetch_add(1, Ordering::SeqCst);
+        self.set_active_storage_endpoint(endpoint);
+    }
+
+    /// Records how long a storage write took, keeping the most recent samples
+    pub fn record_write_latency(&self, latency: Duration) {
+        record_latency(&self.write_latencies, latency);
+    }
+
+    /// Records how long a storage flush took, keeping the most recent samples
+    pub fn record_flush_latency(&self, latency: Duration) {
+        record_latency(&self.flush_latencies, latency);
+    }
+
     /// Updates the current message queue size
     pub fn update_queue_size(&self, size: u64) {
         self.message_queue_size.store(size, Ordering::SeqCst);
     }
 
+    /// Records how many requests wait in the channel for the engine,
+    /// tracking how long the backlog has been above the degraded threshold
+    pub fn update_channel_backlog(&self, backlog: u64) {
+        if self.degraded_queue_size == 0 {
+            return;
+        }
+        let mut backlog_since = self.queue_backlog_since.lock().unwrap();
+        match (*backlog_since, backlog > self.degraded_queue_size) {
+            (None, true) => *backlog_since = Some(Instant::now()),
+            (Some(_), false) => *backlog_since = None,
+            _ => {}
+        }
+    }
+
+    /// Returns whether the channel backlog has stayed above `degraded_queue_size`
+    /// for `degraded_after`: storage is not keeping up, though writes still succeed
+    pub fn is_degraded(&self) -> bool {
+        self.queue_backlog_since
+            .lock()
+            .unwrap()
+            .is_some_and(|since| since.elapsed() >= self.degraded_after)
+    }
+
+    /// Records spans the engine has taken and must still write
+    pub fn record_spans_taken(&self, count: u64) {
+        self.unwritten_spans.fetch_add(count, Ordering::SeqCst);
+    }
+
+    /// Records spans the engine is done with: written, spilled, dropped or
+    /// failed to write
+    pub fn record_spans_settled(&self, count: u64) {
+        let _ = self.unwritten_spans.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |spans| {
+            Some(spans.saturating_sub(count))
+        });
+    }
+
+    /// Returns how many spans taken by the engine have not been settled
+    pub fn unwritten_spans(&self) -> u64 {
+        self.unwritten_spans.load(Ordering::SeqCst)
+    }
+
+    /// Updates the current encoded size of the message queue
+    pub fn update_queue_bytes(&self, bytes: u64) {
+        self.message_queue_bytes.store(bytes, Ordering::SeqCst);
+    }
+
     /// Returns the current health status and metrics
     pub fn get_health_status(&self) -> HealthStatus {
         HealthStatus {
             is_healthy: self.is_healthy.load(Ordering::SeqCst),
+            degraded: self.is_degraded(),
             last_write: self.last_successful_write.load(Ordering::SeqCst),
             queue_size: self.message_queue_size.load(Ordering::SeqCst),
             total_processed: self.total_messages_processed.load(Ordering::SeqCst),
-            failed_writes: self.failed_writes.load(Ordering::SeqCst),
+            consecutive_failed_writes: self.consecutive_failed_writes.load(Ordering::SeqCst),
+            total_failed_writes: self.total_failed_writes.load(Ordering::SeqCst),
         }
     }
 
     /// Returns a detailed health report
     pub fn get_detailed_status(&self) -> DetailedHealthStatus {
+        let [write_latency_ms_p50, write_latency_ms_p95] =
+            latency_percentiles_ms(&self.write_latencies, [50.0, 95.0]);
+        let [flush_latency_ms_p50, flush_latency_ms_p95] =
+            latency_percentiles_ms(&self.flush_latencies, [50.0, 95.0]);
+
         DetailedHealthStatus {
             is_healthy: self.is_healthy.load(Ordering::SeqCst),
+            degraded: self.is_degraded(),
             last_write: self.last_successful_write.load(Ordering::SeqCst),
             queue_size: self.message_queue_size.load(Ordering::SeqCst),
+            queue_bytes: self.message_queue_bytes.load(Ordering::SeqCst),
             total_processed: self.total_messages_processed.load(Ordering::SeqCst),
-            failed_writes: self.failed_writes.load(Ordering::SeqCst),
+            consecutive_failed_writes: self.consecutive_failed_writes.load(Ordering::SeqCst),
+            total_failed_writes: self.total_failed_writes.load(Ordering::SeqCst),
             uptime_seconds: SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .unwrap_or_default()
                 .as_secs(),
+            spans_processed_total: self.spans_processed_total.load(Ordering::SeqCst),
+            bytes_written_total: self.bytes_written_total.load(Ordering::SeqCst),
+            duplicates_skipped: self.duplicates_skipped.load(Ordering::SeqCst),
+            spans_sampled_out: self.spans_sampled_out.load(Ordering::SeqCst),
+            spans_deduplicated: self.spans_deduplicated.load(Ordering::SeqCst),
+            spans_filtered: self.spans_filtered.load(Ordering::SeqCst),
+            spans_spilled: self.spans_spilled.load(Ordering::SeqCst),
+            spill_files_dropped: self.spill_files_dropped.load(Ordering::SeqCst),
+            invalid_spans_total: self.invalid_spans_total.load(Ordering::SeqCst),
+            truncated_spans_total: self.truncated_spans_total.load(Ordering::SeqCst),
+            rejected_requests_total: self.rejected_requests_total.load(Ordering::SeqCst),
+            rate_limited_requests_total: self.rate_limited_requests_total.load(Ordering::SeqCst),
+            active_storage_endpoint: self.active_storage_endpoint.lock().unwrap().clone(),
+            storage_failovers_total: self.storage_failovers_total.load(Ordering::SeqCst),
+            write_latency_ms_p50,
+            write_latency_ms_p95,
+            flush_latency_ms_p50,
+            flush_latency_ms_p95,
         }
     }
 }
 
+/// Adds a latency sample, dropping the oldest once `LATENCY_WINDOW` are kept
+fn record_latency(latencies: &Mutex<VecDeque<Duration>>, latency: Duration) {
+    let mut latencies = latencies.lock().unwrap();
+    if latencies.len() == LATENCY_WINDOW {
+        latencies.pop_front();
+    }
+    latencies.push_back(latency);
+}
+
+/// Returns the given latency percentiles in milliseconds (0 with no samples)
+fn latency_percentiles_ms<const N: usize>(
+    latencies: &Mutex<VecDeque<Duration>>,
+    percentiles: [f64; N],
+) -> [f64; N] {
+    let mut sorted: Vec<Duration> = latencies.lock().unwrap().iter().copied().collect();
+    sorted.sort_unstable();
+
+    percentiles.map(|p| {
+        if sorted.is_empty() {
+            return 0.0;
+        }
+        // Nearest-rank percentile
+        let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+        sorted[rank.clamp(1, sorted.len()) - 1].as_secs_f64() * 1000.0
+    })
+}
+
+impl Default for HealthCheck {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Represents the current health status and metrics of the system
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct HealthStatus {
     /// Whether the system is currently healthy
     pub is_healthy: bool,
+    /// Whether the channel backlog has stayed above `health.degraded_queue_size` for
+    /// `health.degraded_after_ms`; the system still accepts and writes spans
+    pub degraded: bool,
     /// Timestamp of the last successful write
     pub last_write: u64,
     /// Current size of the message queue
     pub queue_size: u64,
     /// Total number of messages processed
     pub total_processed: u64,
-    /// Number of consecutive write failures
-    pub failed_writes: u64,
+    /// Write failures since the last successful write
+    pub consecutive_failed_writes: u64,
+    /// Write failures since startup; never reset
+    pub total_failed_writes: u64,
 }
 
 #[derive(Debug, Serialize)]
 pub struct DetailedHealthStatus {
     pub is_healthy: bool,
+    /// Whether a sustained queue backlog degrades health
+    pub degraded: bool,
     pub last_write: u64,
     pub queue_size: u64,
+    /// Encoded size in bytes of the queued messages
+    pub queue_bytes: u64,
     pub total_processed: u64,
-    pub failed_writes: u64,
+    /// Write failures since the last successful write
+    pub consecutive_failed_writes: u64,
+    /// Write failures since startup; never reset
+    pub total_failed_writes: u64,
     pub uptime_seconds: u64,
+    /// Total number of spans written to storage
+    pub spans_processed_total: u64,
+    /// Total OTLP-encoded bytes of requests written to storage
+    pub bytes_written_total: u64,
+    /// Writes skipped because an identical object already existed
+    pub duplicates_skipped: u64,
+    /// Spans dropped by trace sampling before storage
+    pub spans_sampled_out: u64,
+    /// Spans dropped as repeats of spans stored within the dedup window
+    pub spans_deduplicated: u64,
+    /// Spans dropped by the span name allow/deny lists
+    pub spans_filtered: u64,
+    /// Spans written to the local spill after a failed storage write
+    pub spans_spilled: u64,
+    /// Spill files deleted unuploaded to keep the spill within its size limit
+    pub spill_files_dropped: u64,
+    /// Spans rejected as malformed during conversion
+    pub invalid_spans_total: u64,
+    /// Spans that lost attributes, events or links to the configured limits
+    pub truncated_spans_total: u64,
+    /// Export requests rejected for exceeding the ingest limits
+    pub rejected_requests_total: u64,
+    /// Export requests rejected by the rate limiter
+    pub rate_limited_requests_total: u64,
+    /// Storage endpoint writes currently go to
+    pub active_storage_endpoint: Option<String>,
+    /// Times writes switched to another storage endpoint after failing
+    pub storage_failovers_total: u64,
+    /// Median storage write latency over recent writes, in milliseconds
+    pub write_latency_ms_p50: f64,
+    /// 95th percentile storage write latency over recent writes, in milliseconds
+    pub write_latency_ms_p95: f64,
+    /// Median storage flush latency over recent flushes, in milliseconds
+    pub flush_latency_ms_p50: f64,
+    /// 95th percentile storage flush latency over recent flushes, in milliseconds
+    pub flush_latency_ms_p95: f64,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::thread;
-    use std::time::Duration;
 
     #[test]
     fn test_successful_write() {
@@ -139,7 +484,34 @@ mod tests {
         let status = health.get_health_status();
         assert!(status.is_healthy);
         assert_eq!(status.total_processed, 1);
-        assert_eq!(status.failed_writes, 0);
+        assert_eq!(status.consecutive_failed_writes, 0);
+    }
+
+    #[test]
+    fn test_sustained_queue_backlog_degrades() {
+        let health = HealthCheck::with_config(&HealthConfig {
+            degraded_queue_size: 100,
+            degraded_after_ms: 20,
+            ..HealthConfig::default()
+        });
+
+        health.update_channel_backlog(150);
+        assert!(!health.get_health_status().degraded, "degraded before the duration elapsed");
+        std::thread::sleep(Duration::from_millis(30));
+        health.update_channel_backlog(200);
+        let status = health.get_health_status();
+        assert!(status.degraded);
+        assert!(status.is_healthy, "a backlog alone is not unhealthy");
+
+        health.update_channel_backlog(0);
+        assert!(!health.get_health_status().degraded);
+
+        // Dipping below the threshold restarts the duration
+        health.update_channel_backlog(150);
+        std::thread::sleep(Duration::from_millis(30));
+        health.update_channel_backlog(50);
+        health.update_channel_backlog(150);
+        assert!(!health.get_detailed_status().degraded);
     }
 
     #[test]
@@ -153,7 +525,55 @@ mod tests {
         
         let status = health.get_health_status();
         assert!(!status.is_healthy);
-        assert_eq!(status.failed_writes, 6);
+        assert_eq!(status.consecutive_failed_writes, 6);
+    }
+
+    #[test]
+    fn test_total_failures_persist_across_success() {
+        let health = HealthCheck::new();
+        for _ in 0..3 {
+            health.record_failed_write();
+        }
+        health.record_successful_write();
+        health.record_failed_write();
+
+        let status = health.get_health_status();
+        assert_eq!(status.consecutive_failed_writes, 1);
+        assert_eq!(status.total_failed_writes, 4);
+    }
+
+    #[test]
+    fn test_custom_failure_threshold() {
+        let health = HealthCheck::with_config(&HealthConfig {
+            unhealthy_after_failures: 2,
+            ..HealthConfig::default()
+        });
+
+        health.record_failed_write();
+        health.record_failed_write();
+        assert!(health.get_health_status().is_healthy);
+
+        health.record_failed_write();
+        assert!(!health.get_health_status().is_healthy);
+    }
+
+    #[test]
+    fn test_failures_counted_within_window() {
+        let health = HealthCheck::with_config(&HealthConfig {
+            unhealthy_after_failures: 2,
+            failure_window_secs: 60,
+            ..HealthConfig::default()
+        });
+
+        for _ in 0..3 {
+            health.record_failed_write();
+        }
+        assert!(!health.get_health_status().is_healthy);
+
+        // A success does not clear failures still inside the window
+        health.record_successful_write();
+        assert!(!health.get_health_status().is_healthy);
+        assert_eq!(health.get_health_status().consecutive_failed_writes, 0);
     }
 
     #[test]
@@ -168,6 +588,36 @@ mod tests {
         
         let status = health.get_health_status();
         assert!(status.is_healthy);
-        assert_eq!(status.failed_writes, 0);
+        assert_eq!(status.consecutive_failed_writes, 0);
+    }
+
+    #[test]
+    fn test_write_latency_percentiles() {
+        let health = HealthCheck::new();
+        let status = health.get_detailed_status();
+        assert_eq!(status.write_latency_ms_p50, 0.0);
+        assert_eq!(status.write_latency_ms_p95, 0.0);
+
+        for ms in 1..=100 {
+            health.record_write_latency(Duration::from_millis(ms));
+        }
+
+        let status = health.get_detailed_status();
+        assert!((49.0..=51.0).contains(&status.write_latency_ms_p50));
+        assert!((94.0..=96.0).contains(&status.write_latency_ms_p95));
+    }
+
+    #[test]
+    fn test_write_latency_window() {
+        let health = HealthCheck::new();
+        for _ in 0..LATENCY_WINDOW {
+            health.record_write_latency(Duration::from_secs(1));
+        }
+        for _ in 0..LATENCY_WINDOW {
+            health.record_write_latency(Duration::from_millis(2));
+        }
+
+        let status = health.get_detailed_status();
+        assert_eq!(status.write_latency_ms_p95, 2.0);
     }
-} 
\ No newline at end of file
+}
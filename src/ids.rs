@@ -0,0 +1,61 @@
+use opentelemetry::trace::{SpanId, TraceId};
+
+/// Length of a trace id in hex characters
+pub const TRACE_ID_HEX_LEN: usize = 32;
+
+/// Length of a span id in hex characters
+pub const SPAN_ID_HEX_LEN: usize = 16;
+
+/// Formats a trace id as 32 lowercase hex characters, the form used in
+/// stored spans and object keys
+pub fn trace_id_hex(trace_id: TraceId) -> String {
+    format!("{:0width$x}", u128::from_be_bytes(trace_id.to_bytes()), width = TRACE_ID_HEX_LEN)
+}
+
+/// Formats a span id as 16 lowercase hex characters, the form used in
+/// stored spans and object keys
+pub fn span_id_hex(span_id: SpanId) -> String {
+    format!("{:0width$x}", u64::from_be_bytes(span_id.to_bytes()), width = SPAN_ID_HEX_LEN)
+}
+
+/// Normalizes a hex trace id from a request or an older object: lowercase,
+/// left-padded with zeros to 32 characters, so 64-bit ids match too.
+/// Returns `None` when it is not 1 to 32 hex characters.
+pub fn normalize_trace_id(id: &str) -> Option<String> {
+    normalize_hex(id, TRACE_ID_HEX_LEN)
+}
+
+/// Normalizes a hex span id like `normalize_trace_id`, to 16 characters
+pub fn normalize_span_id(id: &str) -> Option<String> {
+    normalize_hex(id, SPAN_ID_HEX_LEN)
+}
+
+fn normalize_hex(id: &str, len: usize) -> Option<String> {
+    if id.is_empty() || id.len() > len || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(format!("{:0>len$}", id.to_ascii_lowercase(), len = len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ids_formatted_fixed_width_lowercase() {
+        assert_eq!(trace_id_hex(TraceId::from(0xABC)), format!("{}abc", "0".repeat(29)));
+        assert_eq!(span_id_hex(SpanId::from(0xF)), "000000000000000f");
+        assert_eq!(trace_id_hex(TraceId::from_bytes([0xAB; 16])), "ab".repeat(16));
+    }
+
+    #[test]
+    fn test_normalize_ids() {
+        assert_eq!(normalize_trace_id(&"AB".repeat(16)), Some("ab".repeat(16)));
+        assert_eq!(normalize_trace_id("00F067AA0BA902B7"), Some(format!("{}00f067aa0ba902b7", "0".repeat(16))));
+        assert_eq!(normalize_span_id("Ff"), Some("00000000000000ff".to_string()));
+
+        assert_eq!(normalize_trace_id(""), None);
+        assert_eq!(normalize_trace_id(&"a".repeat(33)), None);
+        assert_eq!(normalize_span_id("not-hex"), None);
+    }
+}
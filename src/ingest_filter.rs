@@ -0,0 +1,191 @@
+use crate::proto::opentelemetry::proto::common::v1::KeyValue;
+use crate::proto::ExportTraceServiceRequest;
+
+/// Span name allow/deny lists applied during ingestion.
+/// Patterns are globs: `*` matches any run of characters and `?` any single
+/// one, so `health*` is a prefix match and a pattern without wildcards must
+/// match the whole name.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpanNameFilter {
+    /// When non-empty, only names matching one of these are stored
+    allow: Vec<String>,
+    /// Names matching any of these are dropped, even when allowed
+    deny: Vec<String>,
+}
+
+impl SpanNameFilter {
+    /// Creates a filter from allow and deny patterns; empty lists filter nothing
+    pub fn new(allow: Vec<String>, deny: Vec<String>) -> Self {
+        Self { allow, deny }
+    }
+
+    /// Returns whether spans with the given name are stored
+    pub fn keeps(&self, name: &str) -> bool {
+        if self.deny.iter().any(|pattern| glob_match(pattern, name)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|pattern| glob_match(pattern, name))
+    }
+
+    /// Returns whether any span can be dropped
+    pub fn is_active(&self) -> bool {
+        !self.allow.is_empty() || !self.deny.is_empty()
+    }
+}
+
+/// Removes attributes whose key matches one of its glob patterns (`*` and
+/// `?`), e.g. `*password*`, from received requests before they are logged,
+/// queued, spilled or stored
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AttributeRedactor {
+    patterns: Vec<String>,
+}
+
+impl AttributeRedactor {
+    /// Creates a redactor removing keys that match any of `patterns`
+    pub fn new(patterns: Vec<String>) -> Self {
+        Self { patterns }
+    }
+
+    /// Returns whether attributes with the given key are removed
+    fn redacts(&self, key: &str) -> bool {
+        self.patterns.iter().any(|pattern| glob_match(pattern, key))
+    }
+
+    /// Removes matching resource, scope, span, event and link attributes
+    pub fn redact(&self, request: &mut ExportTraceServiceRequest) {
+        let keep = |attribute: &KeyValue| !self.redacts(&attribute.key);
+        for resource_spans in &mut request.resource_spans {
+            if let Some(resource) = &mut resource_spans.resource {
+                resource.attributes.retain(keep);
+            }
+            for scope_spans in &mut resource_spans.scope_spans {
+                if let Some(scope) = &mut scope_spans.scope {
+                    scope.attributes.retain(keep);
+                }
+                for span in &mut scope_spans.spans {
+                    span.attributes.retain(keep);
+                    span.events.iter_mut().for_each(|event| event.attributes.retain(keep));
+                    span.links.iter_mut().for_each(|link| link.attributes.retain(keep));
+                }
+            }
+        }
+    }
+}
+
+/// Matches `text` against a glob `pattern` supporting `*` and `?`
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let (pattern, text): (Vec<char>, Vec<char>) = (pattern.chars().collect(), text.chars().collect());
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text position it was tried at
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                backtrack = Some((p, t));
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` absorb one more character
+                Some((star_p, star_t)) => {
+                    p = star_p;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::opentelemetry::proto::common::v1::InstrumentationScope;
+    use crate::proto::opentelemetry::proto::resource::v1::Resource;
+    use crate::proto::opentelemetry::proto::trace::v1::span::{Event, Link};
+    use crate::proto::{ResourceSpans, ScopeSpans, Span};
+
+    fn filter(allow: &[&str], deny: &[&str]) -> SpanNameFilter {
+        let strings = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect();
+        SpanNameFilter::new(strings(allow), strings(deny))
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("GET /health*", "GET /healthz"));
+        assert!(glob_match("*ping", "readiness ping"));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(glob_match("span-?", "span-1"));
+        assert!(glob_match("checkout", "checkout"));
+        assert!(!glob_match("checkout", "checkout-v2"));
+        assert!(!glob_match("a*b", "axxc"));
+    }
+
+    #[test]
+    fn test_name_matching_deny_dropped() {
+        let filter = filter(&["GET *"], &["GET /health*"]);
+        assert!(!filter.keeps("GET /healthz"));
+    }
+
+    #[test]
+    fn test_name_matching_allow_kept() {
+        let filter = filter(&["GET *"], &["GET /health*"]);
+        assert!(filter.keeps("GET /orders"));
+    }
+
+    #[test]
+    fn test_name_matching_neither() {
+        assert!(!filter(&["GET *"], &["GET /health*"]).keeps("checkout"));
+        assert!(filter(&[], &["GET /health*"]).keeps("checkout"));
+        assert!(filter(&[], &[]).keeps("anything"));
+        assert!(!filter(&[], &[]).is_active());
+    }
+
+    fn attributes(keys: &[&str]) -> Vec<KeyValue> {
+        keys.iter().map(|key| KeyValue { key: key.to_string(), value: None }).collect()
+    }
+
+    fn keys(attributes: &[KeyValue]) -> Vec<&str> {
+        attributes.iter().map(|attribute| attribute.key.as_str()).collect()
+    }
+
+    #[test]
+    fn test_matching_attributes_redacted_everywhere() {
+        let sensitive = ["db.password", "user.email", "http.method"];
+        let span = Span {
+            attributes: attributes(&sensitive),
+            events: vec![Event { attributes: attributes(&sensitive), ..Default::default() }],
+            links: vec![Link { attributes: attributes(&sensitive), ..Default::default() }],
+            ..Default::default()
+        };
+        let mut request = ExportTraceServiceRequest {
+            resource_spans: vec![ResourceSpans {
+                resource: Some(Resource { attributes: attributes(&sensitive), ..Default::default() }),
+                scope_spans: vec![ScopeSpans {
+                    scope: Some(InstrumentationScope { attributes: attributes(&sensitive), ..Default::default() }),
+                    spans: vec![span],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        };
+
+        AttributeRedactor::new(vec!["*password*".into(), "user.email".into()]).redact(&mut request);
+
+        let resource_spans = &request.resource_spans[0];
+        let scope_spans = &resource_spans.scope_spans[0];
+        let span = &scope_spans.spans[0];
+        assert_eq!(keys(&resource_spans.resource.as_ref().unwrap().attributes), ["http.method"]);
+        assert_eq!(keys(&scope_spans.scope.as_ref().unwrap().attributes), ["http.method"]);
+        assert_eq!(keys(&span.attributes), ["http.method"]);
+        assert_eq!(keys(&span.events[0].attributes), ["http.method"]);
+        assert_eq!(keys(&span.links[0].attributes), ["http.method"]);
+    }
+}
@@ -0,0 +1,132 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use prost::Message as _;
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::{ClientConfig, Message, Offset, TopicPartitionList};
+use tracing::{info, warn};
+
+use crate::config::KafkaIngestConfig;
+use crate::core::MessageSender;
+use crate::error::ProcessingError;
+use crate::proto::ExportTraceServiceRequest;
+
+/// How long to wait before receiving again after a consumer error
+const RECEIVE_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Message read from the ingest topic
+#[derive(Debug, Clone, PartialEq)]
+pub struct KafkaMessage {
+    /// Encoded `ExportTraceServiceRequest`; empty for a message without payload
+    pub payload: Vec<u8>,
+    /// Partition the message was read from
+    pub partition: i32,
+    /// Offset of the message within its partition
+    pub offset: i64,
+}
+
+/// Source of ingest topic messages, implemented by [`KafkaSource`]
+#[async_trait]
+pub trait MessageSource: Send {
+    /// Waits for the next message
+    async fn recv(&mut self) -> Result<KafkaMessage, ProcessingError>;
+
+    /// Commits a message's offset so the consumer group does not read it again
+    fn commit(&mut self, message: &KafkaMessage) -> Result<(), ProcessingError>;
+}
+
+/// Consumer of the configured topic, committing offsets manually
+pub struct KafkaSource {
+    consumer: StreamConsumer,
+    topic: String,
+}
+
+impl KafkaSource {
+    /// Creates a consumer in the configured group and subscribes it to the topic.
+    /// A group without committed offsets starts at the earliest message.
+    pub fn new(config: &KafkaIngestConfig) -> Result<Self, ProcessingError> {
+        let consumer: StreamConsumer = ClientConfig::new()
+            .set("bootstrap.servers", &config.brokers)
+            .set("group.id", &config.group_id)
+            .set("enable.auto.commit", "false")
+            .set("auto.offset.reset", "earliest")
+            .create()
+            .map_err(|e| ProcessingError::ProcessingFailed(format!("Kafka consumer not created: {}", e)))?;
+        consumer
+            .subscribe(&[&config.topic])
+            .map_err(|e| ProcessingError::ProcessingFailed(format!("Kafka subscribe to {} failed: {}", config.topic, e)))?;
+        Ok(Self { consumer, topic: config.topic.clone() })
+    }
+}
+
+#[async_trait]
+impl MessageSource for KafkaSource {
+    async fn recv(&mut self) -> Result<KafkaMessage, ProcessingError> {
+        let message = self
+            .consumer
+            .recv()
+            .await
+            .map_err(|e| ProcessingError::ProcessingFailed(format!("Kafka receive failed: {}", e)))?;
+        Ok(KafkaMessage {
+            payload: message.payload().unwrap_or_default().to_vec(),
+            partition: message.partition(),
+            offset: message.offset(),
+        })
+    }
+
+    fn commit(&mut self, message: &KafkaMessage) -> Result<(), ProcessingError> {
+        // The committed offset is the next one the group reads
+        let mut offsets = TopicPartitionList::new();
+        offsets
+            .add_partition_offset(&self.topic, message.partition, Offset::Offset(message.offset + 1))
+            .and_then(|_| self.consumer.commit(&offsets, CommitMode::Async))
+            .map_err(|e| ProcessingError::ProcessingFailed(format!("Kafka commit failed: {}", e)))
+    }
+}
+
+/// Feeds export requests read from Kafka into the engine's message channel,
+/// the same one the gRPC server sends to
+pub struct KafkaIngest<S> {
+    source: S,
+    sender: MessageSender,
+}
+
+impl<S: MessageSource> KafkaIngest<S> {
+    /// Creates an ingest loop sending to the engine through `sender`
+    pub fn new(source: S, sender: MessageSender) -> Self {
+        Self { source, sender }
+    }
+
+    /// Queues messages until the engine closes its channel. Offsets are
+    /// committed once a request is queued, so a message received but not
+    /// queued is read again after a restart. Messages that do not decode
+    /// are logged and committed so they do not block the partition.
+    pub async fn run(mut self) {
+        loop {
+            let message = match self.source.recv().await {
+                Ok(message) => message,
+                Err(e) => {
+                    warn!("{}", e);
+                    tokio::time::sleep(RECEIVE_RETRY_DELAY).await;
+                    continue;
+                }
+            };
+
+            match ExportTraceServiceRequest::decode(message.payload.as_slice()) {
+                Ok(request) => {
+                    if self.sender.send(request).await.is_err() {
+                        info!("Engine channel closed, stopping Kafka ingest");
+                        return;
+                    }
+                }
+                Err(e) => warn!(
+                    "Skipping Kafka message at partition {} offset {}: not an export request: {}",
+                    message.partition, message.offset, e
+                ),
+            }
+            if let Err(e) = self.source.commit(&message) {
+                warn!("{}", e);
+            }
+        }
+    }
+}
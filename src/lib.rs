@@ -1,15 +1,36 @@
+pub mod auth;
+pub mod backoff;
 pub mod config;
+pub mod convert;
 pub mod core;
+pub mod dedup;
+pub mod enrich;
 pub mod error;
 pub mod health;
+pub mod ids;
+pub mod ingest_filter;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+pub mod metrics;
+pub mod ops;
 pub mod proto;
+pub mod rate_limit;
 pub mod reader;
+pub mod reload;
+pub mod replay;
+pub mod sampling;
 pub mod server;
+pub mod spill;
 pub mod storage;
+pub mod telemetry;
+pub mod trace_buffer;
+pub mod wal;
+#[cfg(test)]
+pub(crate) mod test_support;
 
 // Re-export commonly used types
 pub use config::{Config, ProcessingConfig};
-pub use core::EngineCore;
+pub use core::{EngineControl, EngineCore};
 pub use error::{ConfigError, ProcessingError, StorageError};
 pub use server::ListenerServer;
 pub use reader::SpanReader;  // Add this
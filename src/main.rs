@@ -1,112 +1,468 @@
 use storage_engine::{
-    proto::TraceServiceServer,
-    config::ProcessingConfig,
+    auth::BearerAuth,
+    config::{Config, OpsConfig, ProcessingConfig, ServerConfig, StorageBackend, WriteMode},
+    convert::SpanConverter,
+    core::{message_channel, MessageSender, BACKLOG_SAMPLE_INTERVAL},
+    ingest_filter::{AttributeRedactor, SpanNameFilter},
+    metrics::{MetricsPusher, StatsdSink},
+    ops,
+    server::{bind_listener, concurrency_limit_layer, message_size_layer},
+    replay::{s3_location, SpanReplayer},
+    spill::SpillBuffer,
+    wal::WriteAheadLog,
+    EngineControl,
     EngineCore,
     ListenerServer,
     SpanReader,
     S3StorageWriter,
     health::HealthCheck,
-    proto::ExportTraceServiceRequest,
+    rate_limit::TokenBucket,
+    reload::ConfigReloader,
+    storage::{null::NullStorageWriter, routing::TenantRouter, S3ClientSettings, StorageWriter},
+    telemetry,
 };
-use tokio::sync::mpsc;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tonic::transport::server::TcpIncoming;
 use tonic::transport::Server as GrpcServer;
 use axum::serve;
-use tracing::{info, warn, Level};
-use tracing_subscriber::{EnvFilter, FmtSubscriber};
+use tracing::{info, warn};
+use tracing_subscriber::layer::SubscriberExt;
 use std::net::SocketAddr;
+use std::path::Path;
 use std::sync::Arc;
 use std::future::Future;
+use std::pin::Pin;
+use futures::future::{self, OptionFuture};
 
 /// Main entry point for the storage engine server.
 /// Sets up and runs both gRPC and HTTP servers for trace collection and querying.
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logging with tracing
-    setup_logging();
+    // Load configuration from file or environment. It decides whether
+    // self-telemetry is exported, so only console logging is active meanwhile
+    let config = tracing::subscriber::with_default(
+        tracing_subscriber::registry().with(telemetry::fmt_layer()),
+        Config::from_env,
+    )?;
+
+    // Initialize logging and optional self-telemetry
+    let log_filter = telemetry::init(&config.self_telemetry, &config.logging)?;
+
+    // `--replay <path>` re-ingests stored spans instead of running the servers
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--replay") {
+        let source = args.get(index + 1).ok_or("--replay requires a path or s3://bucket/prefix")?;
+        return run_replay(&config, source).await;
+    }
 
     // Initialize core components
-    let (_config, message_sender, engine_core) = setup_core_components().await?;
+    let (_config, message_sender, engine_core) = setup_core_components(&config).await?;
 
     // Initialize and spawn the engine core processing
     let health_check = engine_core.get_health_check();
-    spawn_engine_core(engine_core);
+    let engine_control = engine_core.control();
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let engine_handle = spawn_engine_core(engine_core.with_shutdown_signal(shutdown_rx.clone()));
+    let metrics_handle = match &config.metrics.statsd_addr {
+        Some(addr) if config.metrics.enabled => match StatsdSink::connect(addr, config.metrics.prefix.clone()).await {
+            Ok(sink) => {
+                info!("Pushing metrics to StatsD at {} every {}ms", addr, config.metrics.push_interval_ms);
+                let pusher = MetricsPusher::new(Arc::clone(&health_check), Arc::new(sink), config.metrics.push_interval());
+                Some(pusher.spawn(shutdown_rx))
+            }
+            Err(e) => {
+                warn!("Metrics not pushed: {}", e);
+                None
+            }
+        },
+        _ => None,
+    };
+
+    if config.health.degraded_queue_size > 0 {
+        message_sender.spawn_backlog_monitor(Arc::clone(&health_check), BACKLOG_SAMPLE_INTERVAL);
+    }
+
+    // Waits on shutdown for queued spans to be written, sharing the gRPC servers' queue
+    let drain = ListenerServer::new(message_sender.clone(), Arc::clone(&health_check))
+        .with_shutdown_timeout(config.server.shutdown_timeout());
+
+    // Queue export requests read from Kafka alongside those received over gRPC
+    let kafka_handle = setup_kafka_ingest(&config, message_sender.clone())?;
 
     // Initialize gRPC server for trace collection
-    let grpc_server = setup_grpc_server(message_sender, health_check, "[::1]:50051")?;
+    let auth = BearerAuth::new(&config.auth);
+    let rate_limiter = config.rate_limit.limiter().map(Arc::new);
+    if rate_limiter.is_some() {
+        info!(
+            "Limiting exports to {} requests per second (burst {})",
+            config.rate_limit.max_requests_per_sec, config.rate_limit.burst
+        );
+    }
+    let grpc_server = setup_grpc_server(
+        message_sender,
+        Arc::clone(&health_check),
+        &config.server,
+        SpanConverter::from(&config.processing),
+        rate_limiter.clone(),
+        auth.clone(),
+    ).await?;
 
-    // Initialize HTTP server for span querying
-    let (http_server, _http_addr) = setup_http_server().await?;
+    // Apply safe settings from the config file on SIGHUP; /admin/config follows reloads
+    let reloader = std::env::var("CONFIG_FILE").ok().map(|path| {
+        ConfigReloader::new(path, config.clone(), engine_control.clone())
+            .with_rate_limiter(rate_limiter)
+            .with_log_filter(log_filter)
+    });
+    let loaded_config = match &reloader {
+        Some(reloader) => reloader.subscribe(),
+        None => watch::channel(config.clone()).1,
+    };
+    #[cfg(unix)]
+    if let Some(reloader) = reloader {
+        tokio::spawn(async move {
+            if let Err(e) = reloader.run().await {
+                warn!("Config reload on SIGHUP unavailable: {}", e);
+            }
+        });
+    }
+
+    // Initialize HTTP servers for span querying and admin, and for probes
+    let mut http_servers: Vec<HttpServer> = Vec::new();
+    if config.reader.enabled {
+        let (http_server, _http_addr) = setup_http_server(&config, loaded_config, engine_control, auth).await?;
+        http_servers.push(Box::pin(http_server));
+    } else {
+        info!("HTTP query API disabled");
+    }
+    if config.ops.enabled {
+        http_servers.push(Box::pin(setup_ops_server(&config.ops, Arc::clone(&health_check)).await?));
+    }
     
-    // Run both servers and handle shutdown
-    run_servers(grpc_server, http_server).await?;
+    // Run all servers and handle shutdown
+    run_servers(grpc_server, http_servers).await?;
+
+    // Stop reading Kafka; messages not yet committed are read again on restart
+    if let Some(handle) = kafka_handle {
+        handle.abort();
+    }
 
+    // Servers no longer accept requests; the engine writes what it holds before exiting
+    info!("Draining in-flight spans...");
+    let _ = shutdown_tx.send(true);
+    let drained = drain.shutdown().await;
+    match &drained {
+        // Spans are written; let the engine finish flushing storage
+        Ok(()) => {
+            if let Err(e) = engine_handle.await {
+                warn!("Engine task failed during shutdown: {}", e);
+            }
+        }
+        Err(e) => warn!("{}", e),
+    }
+    if let Some(Err(e)) = OptionFuture::from(metrics_handle).await {
+        warn!("Metrics task failed during shutdown: {}", e);
+    }
+    telemetry::shutdown().await;
+
+    // Spans left unwritten fail the process
+    drained?;
     Ok(())
 }
 
-/// Sets up logging with tracing subscriber
-fn setup_logging() {
-    FmtSubscriber::builder()
-        .with_max_level(Level::INFO)
-        .with_env_filter(EnvFilter::from_default_env()
-            .add_directive(Level::INFO.into()))
-        .init();
+/// Replays spans from a local file/directory or an `s3://bucket/prefix` into
+/// configured storage, written with the same writer setup as ingested spans
+async fn run_replay(config: &Config, source: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let health_check = Arc::new(HealthCheck::with_config(&config.health));
+    let replayer = SpanReplayer::new(setup_storage(config, &health_check).await?);
+
+    info!("Replaying spans from {}", source);
+    let summary = match s3_location(source) {
+        Some((bucket, prefix)) => {
+            let reader = S3StorageWriter::new(
+                bucket.to_string(),
+                prefix.to_string(),
+                &S3ClientSettings::from(&config.storage),
+            ).await?;
+            replayer.replay_from(&reader).await?
+        }
+        None => replayer.replay_path(Path::new(source)).await?,
+    };
+
+    info!(
+        "Replayed {} spans ({} skipped, {} failed)",
+        summary.replayed, summary.skipped, summary.failed
+    );
+    Ok(())
 }
 
 /// Initializes core components including channels and processing configuration
-async fn setup_core_components() -> Result<(
+async fn setup_core_components(config: &Config) -> Result<(
     ProcessingConfig, 
-    mpsc::Sender<ExportTraceServiceRequest>, 
+    MessageSender, 
     EngineCore
 ), Box<dyn std::error::Error>> {
-    let (tx, rx) = mpsc::channel(100);
-    
-    let processing_config = ProcessingConfig {
-        batch_size: 10,
-        batch_timeout_ms: 10000,
-    };
+    let processing_config = config.processing.clone();
+    let (mut tx, rx) = message_channel(&processing_config);
 
-    let engine_core = EngineCore::new(rx, processing_config.clone()).await?;
+    let health_check = Arc::new(HealthCheck::with_config(&config.health));
+    let storage = setup_storage(config, &health_check).await?;
+
+    if config.sampling.ratio < 1.0 {
+        info!("Storing {:.1}% of traces", config.sampling.ratio * 100.0);
+    }
+    let name_filter = SpanNameFilter::new(
+        config.ingest_filter.name_allow.clone(),
+        config.ingest_filter.name_deny.clone(),
+    );
+    if name_filter.is_active() {
+        info!(
+            "Filtering spans by name ({} allowed, {} denied patterns)",
+            config.ingest_filter.name_allow.len(), config.ingest_filter.name_deny.len()
+        );
+    }
+    let mut engine_core = EngineCore::with_storage(rx, processing_config.clone(), storage)
+        .with_health_check(health_check)
+        .with_name_filter(name_filter)
+        .with_sampling_ratio(config.sampling.ratio)
+        .with_dedup_window(config.dedup.max_entries, config.dedup.window());
+    if !config.ingest_filter.redact_attributes.is_empty() {
+        info!(
+            "Redacting span attributes matching {} patterns",
+            config.ingest_filter.redact_attributes.len()
+        );
+        let redactor = AttributeRedactor::new(config.ingest_filter.redact_attributes.clone());
+        tx = tx.with_redactor(redactor);
+    }
+    if config.spill.enabled {
+        let spill = SpillBuffer::open(&config.spill).await?;
+        info!(
+            "Spilling failed writes to {} ({} requests pending)",
+            config.spill.dir, spill.len().await
+        );
+        engine_core = engine_core.with_spill(Arc::new(spill), config.spill.retry_interval());
+    }
+    if config.wal.enabled {
+        let wal = WriteAheadLog::open(&config.wal).await?;
+        info!(
+            "Logging received requests to {} ({} uncommitted entries to replay)",
+            config.wal.dir, wal.uncommitted().await
+        );
+        // Requests are logged as they are received and committed by the engine
+        let wal = Arc::new(wal);
+        tx = tx.with_wal(Arc::clone(&wal));
+        engine_core = engine_core.with_wal(wal);
+    }
+    if config.storage.write_mode == WriteMode::PerTrace {
+        info!(
+            "Writing each trace once idle for {}ms",
+            config.storage.trace_idle_timeout_ms
+        );
+        engine_core = engine_core.with_trace_buffer(
+            config.storage.trace_idle_timeout(),
+            config.storage.trace_buffer_max_bytes,
+        );
+    }
     
     Ok((processing_config, tx, engine_core))
 }
 
+/// Creates the configured storage backend
+async fn setup_storage(
+    config: &Config,
+    health_check: &Arc<HealthCheck>,
+) -> Result<Arc<dyn StorageWriter>, Box<dyn std::error::Error>> {
+    match config.storage.backend {
+        StorageBackend::Null => {
+            warn!("Storage backend is null: spans are counted and discarded, not stored");
+            Ok(Arc::new(NullStorageWriter::new()))
+        }
+        StorageBackend::S3 => setup_s3_storage(config, health_check).await,
+    }
+}
+
+/// Connects the default bucket's writer, routing tenants to their own buckets when configured
+async fn setup_s3_storage(
+    config: &Config,
+    health_check: &Arc<HealthCheck>,
+) -> Result<Arc<dyn StorageWriter>, Box<dyn std::error::Error>> {
+    let storage_config = &config.storage;
+    let default_writer = setup_storage_writer(
+        config,
+        &storage_config.bucket,
+        &storage_config.prefix,
+        health_check,
+    ).await?;
+    info!(
+        "Writing spans with {:?} layout (idempotent: {})",
+        storage_config.write_mode, storage_config.idempotent_writes
+    );
+
+    if !storage_config.fallback_endpoints.is_empty() {
+        info!(
+            "Failing over to {} fallback endpoints when writes fail",
+            storage_config.fallback_endpoints.len()
+        );
+    }
+
+    let routing = &storage_config.tenant_routing;
+    if routing.tenants.is_empty() {
+        return Ok(default_writer);
+    }
+    let mut router = TenantRouter::new(routing.attribute.clone(), default_writer);
+    for (tenant, location) in &routing.tenants {
+        info!("Routing spans of tenant {} to {}/{}", tenant, location.bucket, location.prefix);
+        let writer = setup_storage_writer(
+            config,
+            &location.bucket,
+            &location.prefix,
+            health_check,
+        ).await?;
+        router = router.with_tenant(tenant.clone(), writer);
+    }
+    Ok(Arc::new(router))
+}
+
+/// Connects a span writer to one bucket and prefix using the storage and retry settings
+async fn setup_storage_writer(
+    config: &Config,
+    bucket: &str,
+    prefix: &str,
+    health_check: &Arc<HealthCheck>,
+) -> Result<Arc<dyn StorageWriter>, Box<dyn std::error::Error>> {
+    let storage_config = &config.storage;
+    let writer = S3StorageWriter::new(
+        bucket.to_string(),
+        prefix.to_string(),
+        &S3ClientSettings::from(storage_config),
+    ).await?
+        .with_fallback_endpoints(&S3ClientSettings::fallbacks(storage_config)).await?
+        .with_retry(config.retry.clone())
+        .with_write_mode(storage_config.write_mode)
+        .with_format(storage_config.format)
+        .with_key_template(storage_config.key_template()?)
+        .with_idempotent_writes(storage_config.idempotent_writes)
+        .with_search_index(storage_config.search_index)
+        .with_indexed_attributes(storage_config.indexed_attributes.clone())
+        .with_pretty_json(storage_config.pretty_json)
+        .with_timestamp_format(storage_config.timestamp_format)
+        .with_write_timeout(storage_config.write_timeout())
+        .with_compression(storage_config.compression)
+        .with_object_metadata(storage_config.object_metadata.clone())
+        .with_object_tags(storage_config.object_tags()?)
+        .with_health_check(Arc::clone(health_check));
+    Ok(Arc::new(writer))
+}
+
+/// Spawns the task consuming the configured Kafka topic
+#[cfg(feature = "kafka")]
+fn setup_kafka_ingest(
+    config: &Config,
+    sender: MessageSender,
+) -> Result<Option<JoinHandle<()>>, Box<dyn std::error::Error>> {
+    use storage_engine::kafka::{KafkaIngest, KafkaSource};
+
+    let Some(kafka) = &config.kafka else {
+        return Ok(None);
+    };
+    let source = KafkaSource::new(kafka)?;
+    info!("Consuming export requests from Kafka topic {} as group {}", kafka.topic, kafka.group_id);
+    Ok(Some(tokio::spawn(KafkaIngest::new(source, sender).run())))
+}
+
+/// Reports a configured Kafka topic that this build cannot consume
+#[cfg(not(feature = "kafka"))]
+fn setup_kafka_ingest(
+    config: &Config,
+    _sender: MessageSender,
+) -> Result<Option<JoinHandle<()>>, Box<dyn std::error::Error>> {
+    if config.kafka.is_some() {
+        warn!("kafka is configured but this build lacks the kafka feature; the topic is not consumed");
+    }
+    Ok(None)
+}
+
 /// Spawns the engine core processing task
-fn spawn_engine_core(mut engine_core: EngineCore) {
+fn spawn_engine_core(mut engine_core: EngineCore) -> JoinHandle<()> {
     tokio::spawn(async move {
         engine_core.process_messages().await;
-    });
+    })
 }
 
+/// An HTTP server future, boxed so servers of different types can be run together
+type HttpServer = Pin<Box<dyn Future<Output = Result<(), std::io::Error>> + Send>>;
+
 /// Sets up the gRPC server for trace collection
-fn setup_grpc_server(
-    tx: mpsc::Sender<ExportTraceServiceRequest>,
+async fn setup_grpc_server(
+    tx: MessageSender,
     health_check: Arc<HealthCheck>,
-    addr: &str,
+    server_config: &ServerConfig,
+    span_converter: SpanConverter,
+    rate_limiter: Option<Arc<TokenBucket>>,
+    auth: BearerAuth,
 ) -> Result<impl Future<Output = Result<(), tonic::transport::Error>>, Box<dyn std::error::Error>> {
-    let addr = addr.parse()?;
-    let listener_server = ListenerServer::new(tx, health_check);
+    let listener = bind_listener(&server_config.host, server_config.port).await?;
+    let addr = listener.local_addr()?;
+    let incoming = TcpIncoming::from_listener(listener, true, None).map_err(|e| e.to_string())?;
+    let configure = |server: ListenerServer| {
+        let server = server
+            .with_ingest_limits(server_config.max_spans_per_request, server_config.max_resource_spans)
+            .with_validation(server_config.validate_on_ingest.then_some(span_converter));
+        match &rate_limiter {
+            Some(limiter) => server.with_rate_limit(Arc::clone(limiter)),
+            None => server,
+        }
+    };
+    #[cfg(feature = "bulk-export")]
+    let bulk_server = configure(ListenerServer::new(tx.clone(), Arc::clone(&health_check)));
+    let listener_server = configure(ListenerServer::new(tx, health_check));
     
-    info!("gRPC server listening on {}", addr);
-    Ok(GrpcServer::builder()
-        .add_service(TraceServiceServer::new(listener_server))
-        .serve(addr))
+    info!(
+        "gRPC server listening on {} (max concurrent requests: {}, max message size: {} bytes, gzip: {}, auth: {})",
+        addr, server_config.max_connections, server_config.max_decoding_message_size,
+        server_config.accept_gzip, auth.is_enabled()
+    );
+    let router = GrpcServer::builder()
+        .layer(concurrency_limit_layer(server_config.max_connections))
+        .layer(message_size_layer(server_config.max_decoding_message_size))
+        .add_service(listener_server.into_service(server_config, auth.clone()));
+    #[cfg(feature = "bulk-export")]
+    let router = {
+        info!("Bulk streaming export enabled");
+        router.add_service(bulk_server.into_bulk_service(server_config, auth))
+    };
+    Ok(router.serve_with_incoming(incoming))
 }
 
-/// Sets up the HTTP server for span querying
-async fn setup_http_server() -> Result<(
+/// Sets up the HTTP server for span querying and engine administration
+async fn setup_http_server(
+    config: &Config,
+    loaded_config: watch::Receiver<Config>,
+    engine_control: EngineControl,
+    auth: BearerAuth,
+) -> Result<(
     impl Future<Output = Result<(), std::io::Error>>, 
     SocketAddr
 ), Box<dyn std::error::Error>> {
     let storage = Arc::new(S3StorageWriter::new(
-        "my-test-bucket".to_string(),
-        "messages".to_string(),
-    ).await?);
+        config.storage.bucket.clone(),
+        config.storage.prefix.clone(),
+        &S3ClientSettings::from(&config.storage),
+    ).await?
+    .with_write_mode(config.storage.write_mode)
+    .with_format(config.storage.format)
+    .with_key_template(config.storage.key_template()?)
+    .with_search_index(config.storage.search_index));
     
-    let reader = SpanReader::new(Arc::clone(&storage));
+    let reader = SpanReader::new(storage)
+        .with_config(config.reader.clone())
+        .with_engine_control(engine_control)
+        .with_admin_auth(auth)
+        .with_loaded_config(loaded_config);
     let app = reader.router();
     
-    let http_addr = SocketAddr::from(([0, 0, 0, 0], 3000));
-    let listener = tokio::net::TcpListener::bind(http_addr).await?;
+    let listener = bind_listener(&config.reader.host, config.reader.port).await?;
+    let http_addr = listener.local_addr()?;
     
     info!("HTTP server listening on {}", http_addr);
     Ok((
@@ -117,11 +473,29 @@ async fn setup_http_server() -> Result<(
     ))
 }
 
-/// Runs both gRPC and HTTP servers, handling graceful shutdown
+/// Sets up the ops server answering liveness and readiness probes
+async fn setup_ops_server(
+    config: &OpsConfig,
+    health_check: Arc<HealthCheck>,
+) -> Result<impl Future<Output = Result<(), std::io::Error>>, Box<dyn std::error::Error>> {
+    let listener = bind_listener(&config.host, config.port).await?;
+    info!("Ops server listening on {} (/livez, /readyz)", listener.local_addr()?);
+    let app = ops::router(health_check);
+    Ok(async move { serve::serve(listener, app).await })
+}
+
+/// Runs the gRPC server and every HTTP server, handling graceful shutdown
 async fn run_servers(
     grpc_server: impl Future<Output = Result<(), tonic::transport::Error>>,
-    http_server: impl Future<Output = Result<(), std::io::Error>>,
+    http_servers: Vec<HttpServer>,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    // Resolves once any HTTP server stops; never without one
+    let http_server = async move {
+        if http_servers.is_empty() {
+            return future::pending().await;
+        }
+        future::select_all(http_servers).await.0
+    };
     tokio::select! {
         result = grpc_server => {
             if let Err(e) = result {
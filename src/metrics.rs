@@ -0,0 +1,213 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::net::UdpSocket;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::{self, Instant, MissedTickBehavior};
+use tracing::warn;
+
+use crate::error::ProcessingError;
+use crate::health::{DetailedHealthStatus, HealthCheck};
+
+/// Largest StatsD datagram sent, keeping packets within a typical MTU
+const MAX_DATAGRAM_BYTES: usize = 1400;
+
+/// One numeric value of the detailed health status
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metric {
+    /// Field name in `DetailedHealthStatus`, e.g. `spans_processed_total`
+    pub name: String,
+    /// Current value of the field
+    pub value: f64,
+}
+
+/// Flattens the numeric fields of a health status into metrics; flags
+/// such as `is_healthy` become 0 or 1
+pub fn health_metrics(status: &DetailedHealthStatus) -> Vec<Metric> {
+    let Ok(serde_json::Value::Object(fields)) = serde_json::to_value(status) else {
+        return Vec::new();
+    };
+    fields
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let value = match value {
+                serde_json::Value::Number(number) => number.as_f64()?,
+                serde_json::Value::Bool(flag) => f64::from(u8::from(flag)),
+                _ => return None,
+            };
+            Some(Metric { name, value })
+        })
+        .collect()
+}
+
+/// Destination health counters are pushed to
+#[async_trait]
+pub trait MetricsSink: Send + Sync {
+    /// Delivers one snapshot of the metrics
+    async fn push(&self, metrics: &[Metric]) -> Result<(), ProcessingError>;
+}
+
+/// Sends metrics as StatsD gauges (`<prefix>.<name>:<value>|g`) over UDP
+pub struct StatsdSink {
+    socket: UdpSocket,
+    addr: String,
+    prefix: String,
+}
+
+impl StatsdSink {
+    /// Creates a sink sending to the StatsD server at `addr` (`host:port`).
+    /// The address is resolved on every push, so a host that does not resolve
+    /// yet, or moves, fails only the pushes made meanwhile.
+    pub async fn connect(addr: &str, prefix: String) -> Result<Self, ProcessingError> {
+        let socket = UdpSocket::bind("0.0.0.0:0")
+            .await
+            .map_err(|e| ProcessingError::ProcessingFailed(format!("Failed to bind StatsD socket: {}", e)))?;
+        Ok(Self { socket, addr: addr.to_string(), prefix })
+    }
+
+    async fn send(&self, datagram: &str) -> Result<(), ProcessingError> {
+        self.socket
+            .send_to(datagram.as_bytes(), self.addr.as_str())
+            .await
+            .map(|_| ())
+            .map_err(|e| ProcessingError::ProcessingFailed(format!("Failed to send metrics to {}: {}", self.addr, e)))
+    }
+}
+
+#[async_trait]
+impl MetricsSink for StatsdSink {
+    async fn push(&self, metrics: &[Metric]) -> Result<(), ProcessingError> {
+        let mut datagram = String::new();
+        for metric in metrics {
+            let line = format!("{}.{}:{}|g", self.prefix, metric.name, metric.value);
+            if !datagram.is_empty() && datagram.len() + 1 + line.len() > MAX_DATAGRAM_BYTES {
+                self.send(&datagram).await?;
+                datagram.clear();
+            }
+            if !datagram.is_empty() {
+                datagram.push('\n');
+            }
+            datagram.push_str(&line);
+        }
+        if !datagram.is_empty() {
+            self.send(&datagram).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Background task pushing health counters to a sink every interval,
+/// and once more on shutdown
+pub struct MetricsPusher {
+    health_check: Arc<HealthCheck>,
+    sink: Arc<dyn MetricsSink>,
+    interval: Duration,
+}
+
+impl MetricsPusher {
+    /// Creates a pusher of `health_check`'s counters to `sink` every `interval`
+    pub fn new(health_check: Arc<HealthCheck>, sink: Arc<dyn MetricsSink>, interval: Duration) -> Self {
+        Self { health_check, sink, interval }
+    }
+
+    /// Runs the pusher until `shutdown` flips to true
+    pub fn spawn(self, shutdown: watch::Receiver<bool>) -> JoinHandle<()> {
+        tokio::spawn(self.run(shutdown))
+    }
+
+    /// Pushes every interval, the first one interval from now, until `shutdown` flips to true
+    pub async fn run(self, mut shutdown: watch::Receiver<bool>) {
+        let mut timer = time::interval_at(Instant::now() + self.interval, self.interval);
+        timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = timer.tick() => self.push().await,
+                changed = shutdown.changed() => {
+                    if changed.is_err() || *shutdown.borrow() {
+                        self.push().await;
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Pushes the current counters; failures are logged and retried next interval
+    async fn push(&self) {
+        let metrics = health_metrics(&self.health_check.get_detailed_status());
+        if let Err(e) = self.sink.push(&metrics).await {
+            warn!("Failed to push metrics: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Sink keeping every pushed snapshot along with when it arrived
+    #[derive(Default)]
+    struct CapturingSink {
+        pushes: Mutex<Vec<(Instant, Vec<Metric>)>>,
+    }
+
+    #[async_trait]
+    impl MetricsSink for CapturingSink {
+        async fn push(&self, metrics: &[Metric]) -> Result<(), ProcessingError> {
+            self.pushes.lock().unwrap().push((Instant::now(), metrics.to_vec()));
+            Ok(())
+        }
+    }
+
+    fn value(metrics: &[Metric], name: &str) -> f64 {
+        metrics.iter().find(|metric| metric.name == name).unwrap().value
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pushes_at_interval() {
+        let health_check = Arc::new(HealthCheck::new());
+        let sink = Arc::new(CapturingSink::default());
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let start = Instant::now();
+        let handle = MetricsPusher::new(Arc::clone(&health_check), sink.clone(), Duration::from_millis(100))
+            .spawn(shutdown_rx);
+
+        health_check.record_spans_written(3, 300);
+        time::sleep(Duration::from_millis(350)).await;
+        shutdown_tx.send(true).unwrap();
+        handle.await.unwrap();
+
+        let pushes = sink.pushes.lock().unwrap();
+        let offsets: Vec<_> = pushes.iter().map(|(at, _)| (*at - start).as_millis()).collect();
+        assert_eq!(offsets, [100, 200, 300, 350], "three timed pushes and a final one");
+        assert_eq!(value(&pushes[0].1, "spans_processed_total"), 3.0);
+        assert_eq!(value(&pushes[0].1, "is_healthy"), 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_statsd_sink_sends_gauges() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let sink = StatsdSink::connect(&server.local_addr().unwrap().to_string(), "engine".into())
+            .await
+            .unwrap();
+        let metrics = [
+            Metric { name: "queue_size".into(), value: 4.0 },
+            Metric { name: "is_healthy".into(), value: 1.0 },
+        ];
+        sink.push(&metrics).await.unwrap();
+
+        let mut buf = [0; MAX_DATAGRAM_BYTES];
+        let len = server.recv(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"engine.queue_size:4|g\nengine.is_healthy:1|g");
+    }
+
+    #[tokio::test]
+    async fn test_unresolvable_statsd_address_fails_pushes_only() {
+        let sink = StatsdSink::connect("statsd.invalid:8125", "engine".into()).await.unwrap();
+        let metrics = [Metric { name: "queue_size".into(), value: 4.0 }];
+        assert!(sink.push(&metrics).await.is_err());
+    }
+}
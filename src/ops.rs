@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, routing::get, Json, Router};
+
+use crate::health::HealthCheck;
+
+/// Creates the router served on the ops listener: `/livez` answers while
+/// the process runs, `/readyz` answers 200 while the engine is healthy and
+/// not degraded by a queue backlog, and 503 otherwise, both without
+/// exposing any query endpoint
+pub fn router(health_check: Arc<HealthCheck>) -> Router {
+    Router::new()
+        .route("/livez", get(handle_livez))
+        .route("/readyz", get(handle_readyz))
+        .with_state(health_check)
+}
+
+/// Handler for GET /livez
+async fn handle_livez() -> &'static str {
+    "ok"
+}
+
+/// Handler for GET /readyz, returning the health status either way
+async fn handle_readyz(State(health_check): State<Arc<HealthCheck>>) -> impl IntoResponse {
+    let status = health_check.get_health_status();
+    // Shedding load while degraded lets the queue drain before it grows unbounded
+    let code = if status.is_healthy && !status.degraded { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (code, Json(status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::HealthConfig;
+    use crate::server::bind_listener;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    /// Sends a plain HTTP/1.1 GET and returns the response's status code
+    async fn status_of(addr: std::net::SocketAddr, path: &str) -> u16 {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path);
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response.split(' ').nth(1).unwrap().parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_probes_served_without_query_api() {
+        let health_check = Arc::new(HealthCheck::new());
+        let listener = bind_listener("127.0.0.1", 0).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = router(Arc::clone(&health_check));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        assert_eq!(status_of(addr, "/livez").await, 200);
+        assert_eq!(status_of(addr, "/readyz").await, 200);
+        assert_eq!(status_of(addr, "/spans").await, 404);
+        assert_eq!(status_of(addr, "/health").await, 404);
+
+        health_check.update_status(false);
+        assert_eq!(status_of(addr, "/readyz").await, 503);
+        assert_eq!(status_of(addr, "/livez").await, 200);
+    }
+
+    #[tokio::test]
+    async fn test_not_ready_while_degraded() {
+        let health_check = Arc::new(HealthCheck::with_config(&HealthConfig {
+            degraded_queue_size: 10,
+            degraded_after_ms: 0,
+            ..HealthConfig::default()
+        }));
+        let listener = bind_listener("127.0.0.1", 0).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = router(Arc::clone(&health_check));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        health_check.update_channel_backlog(11);
+        assert_eq!(status_of(addr, "/readyz").await, 503);
+        health_check.update_channel_backlog(0);
+        assert_eq!(status_of(addr, "/readyz").await, 200);
+    }
+}
@@ -32,6 +32,16 @@ pub mod opentelemetry {
     }
 }
 
+// Crate-specific bulk ingestion service
+#[cfg(feature = "bulk-export")]
+pub mod storage_engine {
+    pub mod bulk {
+        pub mod v1 {
+            include!("storage_engine.bulk.v1.rs");
+        }
+    }
+}
+
 // Re-export commonly used types
 pub use opentelemetry::proto::collector::trace::v1::{
     ExportTraceServiceRequest,
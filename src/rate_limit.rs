@@ -0,0 +1,106 @@
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Token bucket admitting `rate` requests per second on average, and up
+/// to `burst` at once after a quiet period
+#[derive(Debug)]
+pub struct TokenBucket {
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    /// Tokens added per second
+    rate: f64,
+    /// Most tokens held at once
+    burst: f64,
+    /// Tokens available, fractional between refills
+    tokens: f64,
+    /// When `tokens` was last brought up to date
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    /// Creates a full bucket refilling at `rate` tokens per second
+    pub fn new(rate: f64, burst: u32) -> Self {
+        Self {
+            state: Mutex::new(BucketState {
+                rate,
+                burst: f64::from(burst),
+                tokens: f64::from(burst),
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Returns the refill rate in tokens per second
+    pub fn rate(&self) -> f64 {
+        self.state.lock().unwrap().rate
+    }
+
+    /// Changes the refill rate and burst; tokens already added are kept up to the new burst
+    pub fn set_limits(&self, rate: f64, burst: u32) {
+        let mut state = self.state.lock().unwrap();
+        state.refill();
+        state.rate = rate;
+        state.burst = f64::from(burst);
+        state.tokens = state.tokens.min(state.burst);
+    }
+
+    /// Takes a token, or returns how long until one is available
+    pub fn try_acquire(&self) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap();
+        state.refill();
+
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - state.tokens) / state.rate))
+        }
+    }
+}
+
+impl BucketState {
+    /// Adds the tokens earned since the last refill
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.refilled_at = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_burst_then_refill() {
+        let bucket = TokenBucket::new(10.0, 3);
+        for _ in 0..3 {
+            assert!(bucket.try_acquire().is_ok());
+        }
+        assert_eq!(bucket.try_acquire(), Err(Duration::from_millis(100)));
+
+        tokio::time::advance(Duration::from_millis(250)).await;
+        assert!(bucket.try_acquire().is_ok());
+        assert!(bucket.try_acquire().is_ok());
+        assert_eq!(bucket.try_acquire(), Err(Duration::from_millis(50)));
+
+        // A long pause refills no more than the burst
+        tokio::time::advance(Duration::from_secs(10)).await;
+        let admitted = (0..10).filter(|_| bucket.try_acquire().is_ok()).count();
+        assert_eq!(admitted, 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_limits_changed() {
+        let bucket = TokenBucket::new(10.0, 5);
+        bucket.set_limits(2.0, 1);
+        assert!(bucket.try_acquire().is_ok());
+        assert_eq!(bucket.try_acquire(), Err(Duration::from_millis(500)));
+        assert_eq!(bucket.rate(), 2.0);
+    }
+}
@@ -1,24 +1,298 @@
 use axum::{
-    routing::get,
+    body::{Body, Bytes},
+    extract::Request,
+    routing::{delete, get, post},
     Router,
     Json,
-    extract::{Query, State},
-    response::IntoResponse,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
 };
+use chrono::{DateTime, Utc};
+use futures::stream::{self, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::sync::Arc;
-use crate::storage::{S3StorageWriter, StoredSpan};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tower_http::compression::predicate::{NotForContentType, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
+use tower_http::trace::TraceLayer;
+use tokio::sync::watch;
+use tracing::{info, info_span, Span};
+use utoipa::openapi::{RefOr, Schema};
+use utoipa::{IntoParams, ToSchema};
+use crate::auth::BearerAuth;
+use crate::config::{Config, ProcessingConfig, ReaderConfig};
+use crate::core::EngineControl;
+use crate::ids::{normalize_span_id, normalize_trace_id};
+use crate::storage::index::{attribute_matches, SpanIndexEntry, SpanSearch};
+use crate::storage::{SpanEntry, StorageReader, StoredSpan, READ_CONCURRENCY, SCHEMA_VERSION};
 use crate::error::StorageError;
 
-/// Query parameters for span retrieval
-#[derive(Debug, Deserialize)]
+mod openapi;
+pub mod stats;
+pub mod tree;
+
+use stats::{operation_stats, timeline, OperationStats, TimelineBucket};
+use tree::{TraceSummary, TraceTree};
+
+/// Header set when `/spans` clamped the requested limit
+const LIMIT_CLAMPED_HEADER: &str = "x-limit-clamped";
+
+/// Header echoing the limit originally requested when it was clamped
+const REQUESTED_LIMIT_HEADER: &str = "x-requested-limit";
+
+/// Header counting the objects `/spans` failed to read
+const READ_ERRORS_HEADER: &str = "x-read-errors";
+
+/// Header counting the spans of a trace stored without their parent id,
+/// any of which may be a child missing from `/spans/:span_id/children`
+const UNKNOWN_PARENTS_HEADER: &str = "x-unknown-parents";
+
+/// Format of HTTP dates in `Last-Modified` and `If-Modified-Since`
+const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// Content type of the NDJSON export
+const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Responses smaller than this many bytes are sent uncompressed.
+/// Streamed responses have no known size and are always compressed.
+const COMPRESSION_MIN_BYTES: u16 = 1024;
+
+/// Query parameters for the span export
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    /// Maximum number of spans to export (all spans when omitted)
+    limit: Option<usize>,
+}
+
+/// Prefix of `/spans` query parameters filtering by span attribute
+const ATTRIBUTE_FILTER_PREFIX: &str = "attr.";
+
+/// Query parameters for span retrieval.
+/// `attr.<key>=<value>` parameters are parsed separately as attribute filters.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct SpanQuery {
     /// Maximum number of spans to return
     limit: Option<usize>,
+    /// Only return spans reported by this service
+    service: Option<String>,
+    /// Wrap the spans in a `SpansEnvelope` instead of returning a bare array
+    #[serde(default)]
+    envelope: bool,
+    /// List the objects that failed to read in the envelope; implies `envelope`
+    #[serde(default)]
+    verbose: bool,
+}
+
+/// Response of `GET /spans?envelope=true`
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SpansEnvelope {
+    /// Span summaries, most recent first
+    spans: Vec<SpanSummary>,
+    /// Number of spans in `spans`
+    count: usize,
+    /// Number of objects that failed to read; their spans are missing from `spans`
+    errors: usize,
+    /// The objects that failed to read, with `verbose=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    failed_reads: Option<Vec<FailedRead>>,
+    /// Token for the following page; reserved for paging and always null for now
+    next_page_token: Option<String>,
+}
+
+impl SpansEnvelope {
+    /// Wraps query results, listing failed reads when `verbose`
+    fn new(results: SpanResults, verbose: bool) -> Self {
+        Self {
+            count: results.spans.len(),
+            spans: results.spans,
+            errors: results.failed_reads.len(),
+            failed_reads: verbose.then_some(results.failed_reads),
+            next_page_token: None,
+        }
+    }
+}
+
+/// An object a span query could not read
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FailedRead {
+    /// Key of the object
+    key: String,
+    /// Why the read failed
+    error: String,
+}
+
+/// Spans found by a query, along with the objects that failed to read
+#[derive(Debug, Default)]
+pub struct SpanResults {
+    /// Span summaries, most recent first
+    pub spans: Vec<SpanSummary>,
+    /// Objects whose spans are missing from `spans`
+    pub failed_reads: Vec<FailedRead>,
+}
+
+/// Query parameters for the trace listing
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TracesQuery {
+    /// Maximum number of traces to return
+    limit: Option<usize>,
+}
+
+/// Query parameters for span search.
+/// `attr.<key>=<value>` parameters are parsed separately as attribute filters.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    /// Maximum number of spans to return
+    limit: Option<usize>,
+    /// Only spans reported by this service
+    service: Option<String>,
+    /// Only spans with exactly this operation name
+    name: Option<String>,
+    /// Only spans with this status: `Unset`, `Ok` or `Error`
+    status: Option<String>,
+    /// Only spans of this trace
+    trace_id: Option<String>,
+    /// Only spans lasting at least this many nanoseconds
+    min_duration_ns: Option<u64>,
+    /// Only spans lasting at most this many nanoseconds
+    max_duration_ns: Option<u64>,
+    /// Only spans starting at or after this Unix time (milliseconds)
+    start: Option<u64>,
+    /// Only spans starting before this Unix time (milliseconds)
+    end: Option<u64>,
+}
+
+/// Response of `POST /admin/index/rebuild`
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RebuildIndexResponse {
+    /// Number of spans in the rebuilt index
+    indexed: usize,
+}
+
+/// Response of `POST /admin/index/compact`
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CompactIndexResponse {
+    /// Number of segments merged into fewer ones
+    replaced: usize,
+}
+
+/// Query parameters for a single span lookup
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SpanLookupQuery {
+    /// Trace the span belongs to; required so the span's key can be derived
+    trace_id: Option<String>,
+}
+
+/// Default number of keys scanned by `GET /spans/count`
+const DEFAULT_MAX_SCAN: usize = 10_000;
+
+/// Query parameters for counting spans
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CountQuery {
+    /// Maximum number of keys to scan
+    max_scan: Option<usize>,
+    /// Only count objects written at or after this Unix time (seconds)
+    since: Option<u64>,
+    /// Only count objects written at or before this Unix time (seconds)
+    until: Option<u64>,
+}
+
+/// Query parameters for per-operation statistics
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StatsQuery {
+    /// Only include spans reported by this service
+    service: Option<String>,
+    /// Only read objects written at or after this Unix time (seconds)
+    since: Option<u64>,
+    /// Only read objects written at or before this Unix time (seconds)
+    until: Option<u64>,
+}
+
+/// Bucket width of `GET /stats/timeline` when none is given: one minute
+const DEFAULT_BUCKET_MS: u64 = 60_000;
+
+/// Query parameters for the span timeline
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TimelineQuery {
+    /// Bucket width in milliseconds (default 60000)
+    bucket_ms: Option<u64>,
+    /// Only count objects written at or after this Unix time (milliseconds)
+    start: Option<u64>,
+    /// Only count objects written before this Unix time (milliseconds)
+    end: Option<u64>,
+}
+
+/// Batching changes accepted by `POST /admin/processing`; omitted fields are unchanged
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ProcessingUpdate {
+    /// New number of messages per batch
+    batch_size: Option<usize>,
+    /// New batch timeout in milliseconds
+    batch_timeout_ms: Option<u64>,
+}
+
+/// Response of `DELETE /traces/:trace_id`
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeleteTraceResponse {
+    /// Number of span objects removed
+    deleted: usize,
+}
+
+/// Response of `GET /schema`
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SchemaResponse {
+    /// `schema_version` written on new span objects
+    schema_version: u32,
+    /// Fields of a stored span object, by name
+    fields: Vec<SchemaField>,
+}
+
+/// One field of the stored span object layout
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SchemaField {
+    /// Field name as written in objects
+    name: String,
+    /// Whether every object carries the field; missing optional fields are defaulted on read
+    required: bool,
+    /// What the field holds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+}
+
+impl SchemaResponse {
+    /// Describes the current `StoredSpan` layout from its OpenAPI schema
+    fn current() -> Self {
+        let fields = match StoredSpan::schema().1 {
+            RefOr::T(Schema::Object(object)) => object.properties
+                .iter()
+                .map(|(name, property)| SchemaField {
+                    name: name.clone(),
+                    required: object.required.contains(name),
+                    description: match property {
+                        RefOr::T(Schema::Object(field)) => field.description.clone(),
+                        RefOr::T(Schema::Array(field)) => field.description.clone(),
+                        _ => None,
+                    },
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        Self { schema_version: SCHEMA_VERSION, fields }
+    }
 }
 
 /// Summary of a span for API responses
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct SpanSummary {
     /// Unique identifier for the trace
     trace_id: String,
@@ -28,8 +302,16 @@ pub struct SpanSummary {
     name: String,
     /// Start time in nanoseconds since epoch
     timestamp: u64,
-    /// Duration of the span in nanoseconds
+    /// Duration of the span in nanoseconds (0 when end precedes start)
     duration_ns: u64,
+    /// Whether the span's end time precedes its start time
+    clock_skew: bool,
+    /// Type of span (client, server, etc.)
+    kind: String,
+    /// Status of the operation
+    status: String,
+    /// Name of the service that reported the span
+    service_name: Option<String>,
 }
 
 impl From<StoredSpan> for SpanSummary {
@@ -39,7 +321,11 @@ impl From<StoredSpan> for SpanSummary {
             span_id: span.span_id,
             name: span.name,
             timestamp: span.start_time,
-            duration_ns: span.end_time - span.start_time,
+            duration_ns: span.duration_ns,
+            clock_skew: span.end_time < span.start_time,
+            kind: span.kind,
+            status: span.status,
+            service_name: span.service_name,
         }
     }
 }
@@ -48,52 +334,771 @@ impl From<StoredSpan> for SpanSummary {
 #[derive(Clone)]
 pub struct SpanReader {
     /// Storage backend for retrieving spans
-    storage: Arc<S3StorageWriter>,
+    storage: Arc<dyn StorageReader>,
+    /// Engine handle for runtime reconfiguration, if attached
+    engine_control: Option<EngineControl>,
+    /// Authentication required by the admin endpoints
+    admin_auth: BearerAuth,
+    /// Default and maximum `/spans` limits
+    config: ReaderConfig,
+    /// Configuration in effect, served by `GET /admin/config`
+    loaded_config: Option<watch::Receiver<Config>>,
 }
 
 impl SpanReader {
     /// Creates a new SpanReader with the specified storage backend
-    pub fn new(storage: Arc<S3StorageWriter>) -> Self {
-        Self { storage }
+    pub fn new(storage: Arc<dyn StorageReader>) -> Self {
+        Self {
+            storage,
+            engine_control: None,
+            admin_auth: BearerAuth::default(),
+            config: ReaderConfig::default(),
+            loaded_config: None,
+        }
     }
 
-    /// Retrieves recent spans from storage
-    pub async fn get_recent_spans(&self, limit: usize) -> Result<Vec<SpanSummary>, StorageError> {
-        let spans = self.storage.list_spans(limit).await?;
-        
-        let mut summaries = Vec::new();
-        for span in spans {
-            if let Ok(content) = self.storage.read_span(&span.key).await {
-                summaries.push(SpanSummary::from(content));
+    /// Applies the query API configuration
+    pub fn with_config(mut self, config: ReaderConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Enables the admin endpoints that reconfigure the running engine
+    pub fn with_engine_control(mut self, control: EngineControl) -> Self {
+        self.engine_control = Some(control);
+        self
+    }
+
+    /// Serves the process's effective configuration at `GET /admin/config`,
+    /// following reloads published on `config`
+    pub fn with_loaded_config(mut self, config: watch::Receiver<Config>) -> Self {
+        self.loaded_config = Some(config);
+        self
+    }
+
+    /// Requires a bearer token on admin endpoints
+    pub fn with_admin_auth(mut self, auth: BearerAuth) -> Self {
+        self.admin_auth = auth;
+        self
+    }
+
+    /// Retrieves up to `limit` recent spans from storage, optionally restricted
+    /// to one service.
+    /// Objects that fail to read are reported in the results, not skipped silently.
+    pub async fn get_recent_spans(
+        &self,
+        limit: usize,
+        service: Option<&str>,
+        attributes: &[(String, String)],
+    ) -> Result<SpanResults, StorageError> {
+        let entries = self.recent_entries(limit, service, attributes).await?;
+        Ok(self.summarize(entries, limit, service, attributes).await)
+    }
+
+    /// Lists the objects a `/spans` query reads
+    async fn recent_entries(
+        &self,
+        limit: usize,
+        service: Option<&str>,
+        attributes: &[(String, String)],
+    ) -> Result<Vec<SpanEntry>, StorageError> {
+        // Filters need span bodies, so scan up to `scan_limit` objects
+        let scan = if service.is_none() && attributes.is_empty() { limit } else { self.config.scan_limit };
+        self.storage.list_spans(scan).await
+    }
+
+    /// Reads listed objects into summaries, filtered and capped at `limit`,
+    /// recording the objects that fail to read
+    async fn summarize(
+        &self,
+        entries: Vec<SpanEntry>,
+        limit: usize,
+        service: Option<&str>,
+        attributes: &[(String, String)],
+    ) -> SpanResults {
+        let mut results = SpanResults::default();
+        let mut reads = stream::iter(entries)
+            .map(|entry| async move {
+                let result = self.storage.read_object(&entry.key).await;
+                (entry.key, result)
+            })
+            .buffered(READ_CONCURRENCY);
+
+        // Batch objects may hold many spans, so cap the result at `limit`
+        let mut taken = 0;
+        while taken < limit {
+            let Some((key, result)) = reads.next().await else {
+                break;
+            };
+            let spans = match result {
+                Ok(spans) => spans,
+                Err(e) => {
+                    tracing::warn!("Failed to read {}: {}", key, e);
+                    results.failed_reads.push(FailedRead { key, error: e.to_string() });
+                    continue;
+                }
+            };
+            let matches = spans.into_iter().filter(|span| {
+                (service.is_none() || span.service_name.as_deref() == service) && matches_attributes(span, attributes)
+            });
+            for content in matches.take(limit - taken) {
+                taken += 1;
+                results.spans.push(SpanSummary::from(content));
+            }
+        }
+
+        results
+    }
+
+    /// Finds up to `limit` spans matching `search`, most recent first.
+    /// With a span index only the objects holding matches are read;
+    /// otherwise the `scan_limit` most recent objects are scanned.
+    pub async fn search_spans(
+        &self,
+        search: &SpanSearch,
+        limit: usize,
+    ) -> Result<Vec<SpanSummary>, StorageError> {
+        let Some(entries) = self.storage.search_index(search, limit).await? else {
+            let keys: Vec<String> = self.storage.list_spans(self.config.scan_limit).await?
+                .into_iter()
+                .map(|entry| entry.key)
+                .collect();
+            return Ok(self.storage.read_spans(&keys).await.into_iter().flatten()
+                .filter(|span| search.matches(&SpanIndexEntry::new(span, "").with_attributes(span, &search.attribute_keys())))
+                .take(limit)
+                .map(SpanSummary::from)
+                .collect());
+        };
+
+        // Batch objects hold other spans too, and a span indexed twice is returned once
+        let mut wanted: HashSet<(String, String)> = entries
+            .iter()
+            .map(|entry| (entry.trace_id.clone(), entry.span_id.clone()))
+            .collect();
+        let mut keys: Vec<String> = Vec::new();
+        for entry in entries {
+            if !keys.contains(&entry.key) {
+                keys.push(entry.key);
             }
         }
+        let mut spans: Vec<SpanSummary> = self.storage.read_spans(&keys).await.into_iter().flatten()
+            .filter(|span| wanted.remove(&(span.trace_id.clone(), span.span_id.clone())))
+            .map(SpanSummary::from)
+            .collect();
+        spans.sort_by_key(|span| std::cmp::Reverse(span.timestamp));
+        spans.truncate(limit);
+        Ok(spans)
+    }
+
+    /// Reads the spans of a trace, each once. With a span index only the
+    /// objects holding them are read, as are the objects listed under the
+    /// trace's key prefix when the layout has one; otherwise the
+    /// `scan_limit` most recent objects are scanned.
+    pub async fn get_trace(&self, trace_id: &str) -> Result<Vec<StoredSpan>, StorageError> {
+        let search = SpanSearch { trace_id: Some(trace_id.to_string()), ..SpanSearch::default() };
+        let indexed = self.storage.search_index(&search, self.config.scan_limit).await?
+            .map(|entries| entries.into_iter().map(|entry| entry.key).collect());
+        let listed = match indexed {
+            Some(keys) => Some(keys),
+            None => self.storage.list_trace(trace_id).await?,
+        };
+        let mut keys: Vec<String> = match listed {
+            Some(keys) => keys,
+            None => self.storage.list_spans(self.config.scan_limit).await?
+                .into_iter()
+                .map(|entry| entry.key)
+                .collect(),
+        };
+        let mut seen_keys = HashSet::new();
+        keys.retain(|key| seen_keys.insert(key.clone()));
+
+        let mut seen_spans = HashSet::new();
+        Ok(self.storage.read_spans(&keys).await.into_iter().flatten()
+            .filter(|span| span.trace_id == trace_id && seen_spans.insert(span.span_id.clone()))
+            .collect())
+    }
 
+    /// Summarizes up to `limit` traces with spans in the `scan_limit` most
+    /// recent objects, latest start first. Spans of a trace stored in older
+    /// objects are not counted.
+    pub async fn get_traces(&self, limit: usize) -> Result<Vec<TraceSummary>, StorageError> {
+        let keys: Vec<String> = self.storage.list_spans(self.config.scan_limit).await?
+            .into_iter()
+            .map(|entry| entry.key)
+            .collect();
+
+        let mut traces: HashMap<String, Vec<StoredSpan>> = HashMap::new();
+        let mut seen_spans = HashSet::new();
+        for span in self.storage.read_spans(&keys).await.into_iter().flatten() {
+            if seen_spans.insert((span.trace_id.clone(), span.span_id.clone())) {
+                traces.entry(span.trace_id.clone()).or_default().push(span);
+            }
+        }
+        let mut summaries: Vec<TraceSummary> = traces
+            .iter()
+            .map(|(trace_id, spans)| TraceTree::new(spans).summary(trace_id))
+            .collect();
+        summaries.sort_by_key(|summary| std::cmp::Reverse(summary.start_time));
+        summaries.truncate(limit);
         Ok(summaries)
     }
 
-    /// Creates an Axum router with span query endpoints
+    /// Computes latency and error statistics per operation over the
+    /// `scan_limit` most recent objects written within the time range
+    pub async fn get_operation_stats(
+        &self,
+        service: Option<&str>,
+        since: Option<SystemTime>,
+        until: Option<SystemTime>,
+    ) -> Result<Vec<OperationStats>, StorageError> {
+        let keys: Vec<String> = self.storage.list_spans(self.config.scan_limit).await?
+            .into_iter()
+            .filter(|entry| since.is_none_or(|since| entry.last_modified >= since))
+            .filter(|entry| until.is_none_or(|until| entry.last_modified <= until))
+            .map(|entry| entry.key)
+            .collect();
+
+        let spans = self.storage.read_spans(&keys).await.into_iter().flatten()
+            .filter(|span| service.is_none_or(|service| span.service_name.as_deref() == Some(service)));
+        Ok(operation_stats(spans))
+    }
+
+    /// Counts objects per `bucket_ms` time bucket by modification time,
+    /// from listings of the `scan_limit` most recent objects. In per-batch
+    /// mode each object holds a whole batch, so this counts batches.
+    pub async fn get_timeline(
+        &self,
+        bucket_ms: u64,
+        start: Option<SystemTime>,
+        end: Option<SystemTime>,
+    ) -> Result<Vec<TimelineBucket>, StorageError> {
+        let times = self.storage.list_spans(self.config.scan_limit).await?
+            .into_iter()
+            .filter(|entry| start.is_none_or(|start| entry.last_modified >= start))
+            .filter(|entry| end.is_none_or(|end| entry.last_modified < end))
+            .map(|entry| {
+                let since_epoch = entry.last_modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+                since_epoch.as_millis() as u64
+            });
+        Ok(timeline(times, bucket_ms))
+    }
+
+    /// Streams up to `limit` stored spans as newline-delimited JSON.
+    /// Spans are read lazily, so memory use does not grow with the result size.
+    /// Spans that fail to read or serialize are logged and skipped.
+    pub async fn export_spans(
+        &self,
+        limit: usize,
+    ) -> Result<impl Stream<Item = Result<Bytes, StorageError>>, StorageError> {
+        let keys: Vec<String> = self.storage.list_spans(limit).await?
+            .into_iter()
+            .map(|span| span.key)
+            .collect();
+        let storage = Arc::clone(&self.storage);
+
+        Ok(stream::iter(keys)
+            .map(move |key| {
+                let storage = Arc::clone(&storage);
+                async move { storage.read_object(&key).await }
+            })
+            .buffered(READ_CONCURRENCY)
+            .flat_map(|result| match result {
+                Ok(spans) => stream::iter(spans.into_iter().map(Ok).collect::<Vec<_>>()),
+                Err(e) => stream::iter(vec![Err(e)]),
+            })
+            .take(limit)
+            .filter_map(|result| async move {
+                let line = result.and_then(|span| {
+                    serde_json::to_vec(&span).map_err(|e| StorageError::ReadFailed(e.to_string()))
+                });
+                match line {
+                    Ok(mut line) => {
+                        line.push(b'\n');
+                        Some(Ok(Bytes::from(line)))
+                    }
+                    Err(e) => {
+                        tracing::warn!("Skipping span in export: {}", e);
+                        None
+                    }
+                }
+            }))
+    }
+
+    /// Creates an Axum router with span query endpoints.
+    /// Responses are gzip or brotli compressed when the client's
+    /// `Accept-Encoding` allows it.
     pub fn router(self) -> Router {
         Router::new()
             .route("/spans", get(Self::handle_get_spans))
+            .route("/spans/export", get(Self::handle_export_spans))
+            .route("/spans/count", get(Self::handle_count_spans))
+            .route("/spans/:span_id", get(Self::handle_get_span))
+            .route("/spans/:span_id/raw", get(Self::handle_get_raw_span))
+            .route("/spans/:span_id/children", get(Self::handle_get_children))
+            .route("/search", get(Self::handle_search))
+            .route("/services", get(Self::handle_get_services))
+            .route("/schema", get(Self::handle_get_schema))
+            .route("/stats/operations", get(Self::handle_operation_stats))
+            .route("/stats/timeline", get(Self::handle_timeline))
+            .route("/admin/processing", post(Self::handle_update_processing))
+            .route("/admin/config", get(Self::handle_get_config))
+            .route("/admin/index/rebuild", post(Self::handle_rebuild_index))
+            .route("/admin/index/compact", post(Self::handle_compact_index))
+            .route("/traces", get(Self::handle_get_traces))
+            .route("/traces/:trace_id", delete(Self::handle_delete_trace))
+            .route("/traces/:trace_id/integrity", get(Self::handle_trace_integrity))
             .route("/health", get(Self::handle_health_check))
+            .route("/openapi.json", get(Self::handle_openapi))
+            .layer(
+                CompressionLayer::new()
+                    .compress_when(SizeAbove::new(COMPRESSION_MIN_BYTES).and(NotForContentType::IMAGES)),
+            )
+            .layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(request_span)
+                    .on_response(log_response),
+            )
             .with_state(Arc::new(self))
     }
 
-    /// Handler for GET /spans endpoint
+    /// Handler for GET /spans endpoint.
+    /// Returns a bare array, or a `SpansEnvelope` with `envelope=true`.
+    /// Limits above `max_limit` are clamped and reported via response headers.
+    /// Responses carry an `ETag` and `Last-Modified` derived from the object
+    /// listing; a matching `If-None-Match` or `If-Modified-Since` gets a 304
+    /// without any span bodies being read. When some objects fail to read,
+    /// the remaining spans are returned with 207 and no cache validators.
     async fn handle_get_spans(
         State(reader): State<Arc<SpanReader>>,
+        headers: HeaderMap,
         Query(query): Query<SpanQuery>,
-    ) -> Json<Vec<SpanSummary>> {
-        let limit = query.limit.unwrap_or(5);
-        
-        // Attempt to get spans, return empty list on error
-        let spans = reader.get_recent_spans(limit).await
-            .unwrap_or_else(|e| {
+        Query(params): Query<Vec<(String, String)>>,
+    ) -> Response {
+        let requested = query.limit.unwrap_or(reader.config.default_limit);
+        let limit = requested.min(reader.config.max_limit);
+
+        let attributes = attribute_filters(&params);
+        // Return an uncacheable empty list on error
+        let entries = match reader.recent_entries(limit, query.service.as_deref(), &attributes).await {
+            Ok(entries) => entries,
+            Err(e) => {
                 tracing::error!("Failed to get spans: {}", e);
+                return spans_response(SpanResults::default(), query.envelope, query.verbose);
+            }
+        };
+        let etag = listing_etag(&entries, &params);
+        let last_modified = entries.iter().map(|entry| entry.last_modified).max();
+
+        let mut response = if is_not_modified(&headers, &etag, last_modified) {
+            StatusCode::NOT_MODIFIED.into_response()
+        } else {
+            let results = reader.summarize(entries, limit, query.service.as_deref(), &attributes).await;
+            spans_response(results, query.envelope, query.verbose)
+        };
+        // A partial result must not be revalidated as if it were complete
+        if response.status() != StatusCode::MULTI_STATUS {
+            if let Ok(etag) = header::HeaderValue::from_str(&etag) {
+                response.headers_mut().insert(header::ETAG, etag);
+            }
+            if let Some(last_modified) = last_modified.and_then(|time| http_date(time).parse().ok()) {
+                response.headers_mut().insert(header::LAST_MODIFIED, last_modified);
+            }
+        }
+        if limit < requested {
+            let headers = response.headers_mut();
+            headers.insert(LIMIT_CLAMPED_HEADER, header::HeaderValue::from_static("true"));
+            headers.insert(REQUESTED_LIMIT_HEADER, header::HeaderValue::from(requested));
+        }
+        response
+    }
+
+    /// Handler for GET /spans/:span_id endpoint.
+    /// Requires `?trace_id=`; returns the full stored span, 404 when absent.
+    async fn handle_get_span(
+        State(reader): State<Arc<SpanReader>>,
+        Path(span_id): Path<String>,
+        Query(query): Query<SpanLookupQuery>,
+    ) -> Response {
+        let (trace_id, span_id) = match lookup_ids(query, &span_id) {
+            Ok(ids) => ids,
+            Err(rejection) => return rejection.into_response(),
+        };
+
+        match reader.storage.find_span(&trace_id, &span_id, reader.config.scan_limit).await {
+            Ok(Some(span)) => Json(span).into_response(),
+            Ok(None) => (StatusCode::NOT_FOUND, "Span not found").into_response(),
+            Err(e) => {
+                tracing::error!("Failed to look up span {}: {}", span_id, e);
+                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+            }
+        }
+    }
+
+    /// Handler for GET /spans/:span_id/children endpoint.
+    /// Requires `?trace_id=`; returns the span's direct children, earliest
+    /// first, and 404 when the span itself is not found. Spans stored
+    /// before parent ids were recorded are counted in a response header.
+    async fn handle_get_children(
+        State(reader): State<Arc<SpanReader>>,
+        Path(span_id): Path<String>,
+        Query(query): Query<SpanLookupQuery>,
+    ) -> Response {
+        let (trace_id, span_id) = match lookup_ids(query, &span_id) {
+            Ok(ids) => ids,
+            Err(rejection) => return rejection.into_response(),
+        };
+
+        let spans = match reader.get_trace(&trace_id).await {
+            Ok(spans) => spans,
+            Err(e) => {
+                tracing::error!("Failed to read trace {}: {}", trace_id, e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
+        };
+        let tree = TraceTree::new(&spans);
+        if tree.span(&span_id).is_none() {
+            return (StatusCode::NOT_FOUND, "Span not found").into_response();
+        }
+        let mut response = Json(tree.children(&span_id)).into_response();
+        let unknown_parents = tree.unknown_parents().count();
+        if unknown_parents > 0 {
+            response.headers_mut().insert(UNKNOWN_PARENTS_HEADER, header::HeaderValue::from(unknown_parents));
+        }
+        response
+    }
+
+    /// Handler for GET /spans/:span_id/raw endpoint.
+    /// Requires `?trace_id=`; streams the bytes of the object holding the
+    /// span exactly as stored, with its content type and, for compressed
+    /// objects, its content encoding. In batch layouts the object holds the
+    /// rest of its batch too.
+    async fn handle_get_raw_span(
+        State(reader): State<Arc<SpanReader>>,
+        Path(span_id): Path<String>,
+        Query(query): Query<SpanLookupQuery>,
+    ) -> Response {
+        let (trace_id, span_id) = match lookup_ids(query, &span_id) {
+            Ok(ids) => ids,
+            Err(rejection) => return rejection.into_response(),
+        };
+
+        let raw = match reader.storage.find_span_key(&trace_id, &span_id, reader.config.scan_limit).await {
+            Ok(Some(key)) => reader.storage.read_raw(&key).await,
+            Ok(None) => return (StatusCode::NOT_FOUND, "Span not found").into_response(),
+            Err(e) => Err(e),
+        };
+        match raw {
+            Ok(raw) => {
+                let mut response = ([(header::CONTENT_TYPE, raw.content_type)], Body::from_stream(raw.body)).into_response();
+                if let Some(encoding) = raw.content_encoding.and_then(|encoding| header::HeaderValue::from_str(&encoding).ok()) {
+                    response.headers_mut().insert(header::CONTENT_ENCODING, encoding);
+                }
+                response
+            }
+            Err(StorageError::ConfigError(msg)) => (StatusCode::NOT_IMPLEMENTED, msg).into_response(),
+            Err(e) => {
+                tracing::error!("Failed to read raw span {}: {}", span_id, e);
+                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+            }
+        }
+    }
+
+    /// Handler for GET /search endpoint.
+    /// Returns matching span summaries, most recent first.
+    async fn handle_search(
+        State(reader): State<Arc<SpanReader>>,
+        Query(query): Query<SearchQuery>,
+        Query(params): Query<Vec<(String, String)>>,
+    ) -> Response {
+        let trace_id = match query.trace_id.as_deref().map(normalize_trace_id) {
+            Some(None) => {
+                return (StatusCode::BAD_REQUEST, "trace_id must be up to 32 hex characters").into_response();
+            }
+            Some(trace_id) => trace_id,
+            None => None,
+        };
+        let from_millis = |millis: u64| millis.saturating_mul(1_000_000);
+        let search = SpanSearch {
+            service: query.service,
+            name: query.name,
+            status: query.status,
+            trace_id,
+            min_duration_ns: query.min_duration_ns,
+            max_duration_ns: query.max_duration_ns,
+            start: query.start.map(from_millis),
+            end: query.end.map(from_millis),
+            attributes: attribute_filters(&params),
+        };
+        let limit = query.limit.unwrap_or(reader.config.default_limit).min(reader.config.max_limit);
+
+        match reader.search_spans(&search, limit).await {
+            Ok(spans) => Json(spans).into_response(),
+            Err(e) => {
+                tracing::error!("Failed to search spans: {}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+            }
+        }
+    }
+
+    /// Handler for GET /spans/export endpoint
+    async fn handle_export_spans(
+        State(reader): State<Arc<SpanReader>>,
+        Query(query): Query<ExportQuery>,
+    ) -> Response {
+        match reader.export_spans(query.limit.unwrap_or(usize::MAX)).await {
+            Ok(lines) => (
+                [(header::CONTENT_TYPE, NDJSON_CONTENT_TYPE)],
+                Body::from_stream(lines),
+            )
+                .into_response(),
+            Err(e) => {
+                tracing::error!("Failed to export spans: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+
+    /// Handler for GET /spans/count endpoint
+    async fn handle_count_spans(
+        State(reader): State<Arc<SpanReader>>,
+        Query(query): Query<CountQuery>,
+    ) -> Response {
+        let from_secs = |secs: u64| UNIX_EPOCH + Duration::from_secs(secs);
+        let result = reader.storage.count_spans(
+            query.max_scan.unwrap_or(DEFAULT_MAX_SCAN),
+            query.since.map(from_secs),
+            query.until.map(from_secs),
+        ).await;
+
+        match result {
+            Ok(count) => Json(count).into_response(),
+            Err(e) => {
+                tracing::error!("Failed to count spans: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+
+    /// Handler for GET /stats/operations endpoint
+    async fn handle_operation_stats(
+        State(reader): State<Arc<SpanReader>>,
+        Query(query): Query<StatsQuery>,
+    ) -> Response {
+        let from_secs = |secs: u64| UNIX_EPOCH + Duration::from_secs(secs);
+        let result = reader.get_operation_stats(
+            query.service.as_deref(),
+            query.since.map(from_secs),
+            query.until.map(from_secs),
+        ).await;
+
+        match result {
+            Ok(stats) => Json(stats).into_response(),
+            Err(e) => {
+                tracing::error!("Failed to compute operation stats: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+
+    /// Handler for GET /stats/timeline endpoint
+    async fn handle_timeline(
+        State(reader): State<Arc<SpanReader>>,
+        Query(query): Query<TimelineQuery>,
+    ) -> Response {
+        let bucket_ms = query.bucket_ms.unwrap_or(DEFAULT_BUCKET_MS);
+        if bucket_ms == 0 {
+            return (StatusCode::BAD_REQUEST, "bucket_ms must be > 0").into_response();
+        }
+        if let (Some(start), Some(end)) = (query.start, query.end) {
+            if start >= end {
+                return (StatusCode::BAD_REQUEST, "start must be before end").into_response();
+            }
+        }
+
+        let from_millis = |millis: u64| UNIX_EPOCH + Duration::from_millis(millis);
+        let result = reader.get_timeline(
+            bucket_ms,
+            query.start.map(from_millis),
+            query.end.map(from_millis),
+        ).await;
+
+        match result {
+            Ok(buckets) => Json(buckets).into_response(),
+            Err(e) => {
+                tracing::error!("Failed to compute span timeline: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+
+    /// Handler for POST /admin/processing endpoint
+    async fn handle_update_processing(
+        State(reader): State<Arc<SpanReader>>,
+        headers: HeaderMap,
+        Json(update): Json<ProcessingUpdate>,
+    ) -> Response {
+        if let Err(rejection) = reader.check_admin(&headers) {
+            return rejection.into_response();
+        }
+        let Some(control) = &reader.engine_control else {
+            return (StatusCode::SERVICE_UNAVAILABLE, "Engine control is not available").into_response();
+        };
+
+        let current = control.processing_config();
+        let config = ProcessingConfig {
+            batch_size: update.batch_size.unwrap_or(current.batch_size),
+            batch_timeout_ms: update.batch_timeout_ms.unwrap_or(current.batch_timeout_ms),
+            ..current
+        };
+        match control.update_processing(config.clone()) {
+            Ok(()) => Json(config).into_response(),
+            Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        }
+    }
+
+    /// Handler for GET /admin/config endpoint.
+    /// Returns the loaded configuration with defaults applied; secrets are redacted.
+    async fn handle_get_config(
+        State(reader): State<Arc<SpanReader>>,
+        headers: HeaderMap,
+    ) -> Response {
+        if let Err(rejection) = reader.check_admin(&headers) {
+            return rejection.into_response();
+        }
+        match &reader.loaded_config {
+            Some(config) => Json(config.borrow().clone()).into_response(),
+            None => (StatusCode::SERVICE_UNAVAILABLE, "Loaded configuration is not available").into_response(),
+        }
+    }
+
+    /// Handler for POST /admin/index/rebuild endpoint.
+    /// Rebuilds the span index from stored objects; 501 without an index.
+    async fn handle_rebuild_index(
+        State(reader): State<Arc<SpanReader>>,
+        headers: HeaderMap,
+    ) -> Response {
+        if let Err(rejection) = reader.check_admin(&headers) {
+            return rejection.into_response();
+        }
+        match reader.storage.rebuild_index().await {
+            Ok(indexed) => Json(RebuildIndexResponse { indexed }).into_response(),
+            Err(StorageError::ConfigError(e)) => (StatusCode::NOT_IMPLEMENTED, e).into_response(),
+            Err(e) => {
+                tracing::error!("Failed to rebuild span index: {}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+            }
+        }
+    }
+
+    /// Handler for POST /admin/index/compact endpoint.
+    /// Merges span index segments of past hours; 501 without an index.
+    async fn handle_compact_index(
+        State(reader): State<Arc<SpanReader>>,
+        headers: HeaderMap,
+    ) -> Response {
+        if let Err(rejection) = reader.check_admin(&headers) {
+            return rejection.into_response();
+        }
+        match reader.storage.compact_index().await {
+            Ok(replaced) => Json(CompactIndexResponse { replaced }).into_response(),
+            Err(StorageError::ConfigError(e)) => (StatusCode::NOT_IMPLEMENTED, e).into_response(),
+            Err(e) => {
+                tracing::error!("Failed to compact span index: {}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+            }
+        }
+    }
+
+    /// Handler for DELETE /traces/:trace_id endpoint.
+    /// Removes the trace's span objects; 404 when none exist.
+    async fn handle_delete_trace(
+        State(reader): State<Arc<SpanReader>>,
+        headers: HeaderMap,
+        Path(trace_id): Path<String>,
+    ) -> Response {
+        if let Err(rejection) = reader.check_admin(&headers) {
+            return rejection.into_response();
+        }
+        let Some(trace_id) = normalize_trace_id(&trace_id) else {
+            return (StatusCode::BAD_REQUEST, "trace_id must be up to 32 hex characters").into_response();
+        };
+
+        match reader.storage.delete_trace(&trace_id).await {
+            Ok(0) => (StatusCode::NOT_FOUND, "Trace not found").into_response(),
+            Ok(deleted) => Json(DeleteTraceResponse { deleted }).into_response(),
+            Err(e) => {
+                tracing::error!("Failed to delete trace {}: {}", trace_id, e);
+                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+            }
+        }
+    }
+
+    /// Handler for GET /traces endpoint.
+    /// Returns trace summaries, latest start first.
+    async fn handle_get_traces(
+        State(reader): State<Arc<SpanReader>>,
+        Query(query): Query<TracesQuery>,
+    ) -> Response {
+        let limit = query.limit.unwrap_or(reader.config.default_limit).min(reader.config.max_limit);
+
+        match reader.get_traces(limit).await {
+            Ok(traces) => Json(traces).into_response(),
+            Err(e) => {
+                tracing::error!("Failed to list traces: {}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+            }
+        }
+    }
+
+    /// Handler for GET /traces/:trace_id/integrity endpoint.
+    /// Reports roots, orphaned spans and parent cycles; 404 when no span is found.
+    async fn handle_trace_integrity(
+        State(reader): State<Arc<SpanReader>>,
+        Path(trace_id): Path<String>,
+    ) -> Response {
+        let Some(trace_id) = normalize_trace_id(&trace_id) else {
+            return (StatusCode::BAD_REQUEST, "trace_id must be up to 32 hex characters").into_response();
+        };
+
+        match reader.get_trace(&trace_id).await {
+            Ok(spans) if spans.is_empty() => (StatusCode::NOT_FOUND, "Trace not found").into_response(),
+            Ok(spans) => Json(TraceTree::new(&spans).integrity(&trace_id)).into_response(),
+            Err(e) => {
+                tracing::error!("Failed to read trace {}: {}", trace_id, e);
+                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+            }
+        }
+    }
+
+    /// Checks that the request carries a valid admin bearer token. Admin
+    /// endpoints fail closed: without configured tokens they answer 403.
+    fn check_admin(&self, headers: &HeaderMap) -> Result<(), (StatusCode, &'static str)> {
+        if !self.admin_auth.is_enabled() {
+            return Err((StatusCode::FORBIDDEN, "Admin endpoints require AUTH_BEARER_TOKENS"));
+        }
+        let header = headers.get(header::AUTHORIZATION).and_then(|value| value.to_str().ok());
+        if !self.admin_auth.is_authorized(header) {
+            return Err((StatusCode::UNAUTHORIZED, "Missing or invalid bearer token"));
+        }
+        Ok(())
+    }
+
+    /// Handler for GET /services endpoint
+    async fn handle_get_services(
+        State(reader): State<Arc<SpanReader>>,
+    ) -> Json<Vec<String>> {
+        let services = reader.storage.list_services().await
+            .unwrap_or_else(|e| {
+                tracing::error!("Failed to list services: {}", e);
                 Vec::new()
             });
-            
-        Json(spans)
+
+        Json(services)
+    }
+
+    /// Handler for GET /schema endpoint
+    async fn handle_get_schema() -> Json<SchemaResponse> {
+        Json(SchemaResponse::current())
     }
 
     /// Handler for health check endpoint
@@ -103,24 +1108,1033 @@ impl SpanReader {
         let status = reader.storage.get_health_status();
         Json(status)
     }
+
+    /// Handler for GET /openapi.json endpoint
+    async fn handle_openapi() -> impl IntoResponse {
+        Json(openapi::document())
+    }
+}
+
+/// Validates and normalizes the ids of a `/spans/:span_id` lookup,
+/// returning the 400 response to send when they are missing or malformed
+fn lookup_ids(query: SpanLookupQuery, span_id: &str) -> Result<(String, String), (StatusCode, &'static str)> {
+    let trace_id = query.trace_id
+        .ok_or((StatusCode::BAD_REQUEST, "trace_id query parameter is required"))?;
+    let trace_id = normalize_trace_id(&trace_id)
+        .ok_or((StatusCode::BAD_REQUEST, "trace_id must be up to 32 hex characters"))?;
+    let span_id = normalize_span_id(span_id)
+        .ok_or((StatusCode::BAD_REQUEST, "span_id must be up to 16 hex characters"))?;
+    Ok((trace_id, span_id))
+}
+
+/// Serializes `/spans` results as a bare array or, with `envelope` or
+/// `verbose`, a `SpansEnvelope`. Failed reads turn the status into 207
+/// and are counted in the `x-read-errors` header.
+fn spans_response(results: SpanResults, envelope: bool, verbose: bool) -> Response {
+    let errors = results.failed_reads.len();
+    let mut response = if envelope || verbose {
+        Json(SpansEnvelope::new(results, verbose)).into_response()
+    } else {
+        Json(results.spans).into_response()
+    };
+    if errors > 0 {
+        *response.status_mut() = StatusCode::MULTI_STATUS;
+        response.headers_mut().insert(READ_ERRORS_HEADER, header::HeaderValue::from(errors));
+    }
+    response
+}
+
+/// Returns a strong ETag over the listed keys and modification times,
+/// which change whenever a listed object is written or removed, and over
+/// the query parameters shaping the body, such as `envelope` and filters
+fn listing_etag(entries: &[SpanEntry], params: &[(String, String)]) -> String {
+    let mut hasher = DefaultHasher::new();
+    let mut params: Vec<&(String, String)> = params.iter().collect();
+    params.sort();
+    params.hash(&mut hasher);
+    for entry in entries {
+        entry.key.hash(&mut hasher);
+        entry.last_modified.hash(&mut hasher);
+    }
+    format!("\"{:016x}\"", hasher.finish())
+}
+
+/// Formats a time as an HTTP date, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`
+fn http_date(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).format(HTTP_DATE_FORMAT).to_string()
+}
+
+/// Evaluates conditional request headers. `If-None-Match` takes precedence;
+/// `If-Modified-Since` is only consulted without it, at second precision.
+fn is_not_modified(headers: &HeaderMap, etag: &str, last_modified: Option<SystemTime>) -> bool {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+
+    if let Some(if_none_match) = header(header::IF_NONE_MATCH) {
+        return if_none_match
+            .split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == "*" || tag == etag);
+    }
+
+    let since = header(header::IF_MODIFIED_SINCE)
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok());
+    match (since, last_modified) {
+        (Some(since), Some(last_modified)) => {
+            DateTime::<Utc>::from(last_modified).timestamp() <= since.timestamp()
+        }
+        _ => false,
+    }
+}
+
+/// Returns whether a span carries every `key=value` attribute filter.
+/// Non-string attribute values match the filter value parsed as JSON, e.g. `500` or `true`.
+fn matches_attributes(span: &StoredSpan, filters: &[(String, String)]) -> bool {
+    filters.iter().all(|(key, expected)| attribute_matches(span.attributes.get(key), expected))
+}
+
+/// Collects the `attr.<key>=<value>` query parameters as attribute filters
+fn attribute_filters(params: &[(String, String)]) -> Vec<(String, String)> {
+    params
+        .iter()
+        .filter_map(|(key, value)| {
+            key.strip_prefix(ATTRIBUTE_FILTER_PREFIX).map(|key| (key.to_string(), value.clone()))
+        })
+        .collect()
+}
+
+/// Creates the span for an HTTP request from its method, path and query.
+/// Headers are deliberately left out so credentials never reach the logs.
+fn request_span(request: &Request) -> Span {
+    info_span!(
+        "http_request",
+        method = %request.method(),
+        path = request.uri().path(),
+        query = request.uri().query().unwrap_or(""),
+    )
+}
+
+/// Logs the status and latency of a completed HTTP request
+fn log_response(response: &Response, latency: Duration, _span: &Span) {
+    info!(
+        status = response.status().as_u16(),
+        latency_ms = latency.as_millis() as u64,
+        "HTTP request completed"
+    );
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::storage::StorageWriter;
-    use mockall::predicate::*;
-    use mockall::mock;
+    use crate::test_support::{stored_span, MockStorage};
+    use axum::http::Request;
+    use std::time::SystemTime;
+    use tower::ServiceExt;
 
-    mock! {
-        StorageWriter {
-            fn list_spans(&self, limit: usize) -> Result<Vec<SpanEntry>, StorageError>;
-            fn read_span(&self, key: &str) -> Result<StoredSpan, StorageError>;
+    #[tokio::test]
+    async fn test_get_recent_spans() {
+        let storage = Arc::new(MockStorage::new().with_spans(vec![
+            span_with_attributes("a", serde_json::json!({})),
+            span_with_attributes("b", serde_json::json!({})),
+            span_with_attributes("c", serde_json::json!({})),
+        ]));
+        let spans = get_json(SpanReader::new(storage.clone()), "/spans?limit=2").await;
+
+        let span_ids: Vec<_> = spans.as_array().unwrap().iter().map(|span| span["span_id"].clone()).collect();
+        assert_eq!(span_ids, ["a", "b"]);
+        assert_eq!(storage.calls("list_spans"), 1);
+        assert_eq!(storage.calls("read_span"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_service_filter_fills_limit() {
+        let span = |span_id: &str, service: &str| StoredSpan {
+            span_id: span_id.into(),
+            service_name: Some(service.into()),
+            ..stored_span(1_000, 2_000)
+        };
+        let storage = Arc::new(MockStorage::new().with_spans(vec![
+            span("a", "cart"),
+            span("b", "cart"),
+            span("c", "shop"),
+            span("d", "cart"),
+            span("e", "shop"),
+            span("f", "shop"),
+        ]));
+        let spans = get_json(SpanReader::new(storage.clone()), "/spans?limit=2&service=shop").await;
+
+        let span_ids: Vec<_> = spans.as_array().unwrap().iter().map(|span| span["span_id"].clone()).collect();
+        assert_eq!(span_ids, ["c", "e"]);
+    }
+
+    #[tokio::test]
+    async fn test_get_services() {
+        let storage = Arc::new(MockStorage::new().with_services(&["cart", "checkout"]));
+        let services = get_json(SpanReader::new(storage.clone()), "/services").await;
+
+        assert_eq!(services, serde_json::json!(["cart", "checkout"]));
+        assert_eq!(storage.calls("list_services"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_schema() {
+        let schema = get_json(SpanReader::new(Arc::new(MockStorage::new())), "/schema").await;
+
+        assert_eq!(schema["schema_version"], SCHEMA_VERSION);
+        let fields = schema["fields"].as_array().unwrap();
+        let field = |name: &str| fields.iter().find(|field| field["name"] == name).unwrap().clone();
+        assert_eq!(field("trace_id")["required"], true);
+        assert_eq!(field("schema_version")["required"], false);
+        assert_eq!(field("links")["required"], false);
+        assert!(field("duration_ns")["description"].as_str().unwrap().contains("end_time - start_time"));
+        assert_eq!(fields.len(), serde_json::to_value(stored_span(0, 0)).unwrap().as_object().unwrap().len());
+    }
+
+    /// Storage serving `count` identical spans
+    fn copies(count: usize) -> Arc<MockStorage> {
+        Arc::new(MockStorage::new().with_copies(stored_span(1_000, 2_000), count))
+    }
+
+    async fn export_lines(count: usize, uri: &str) -> Vec<StoredSpan> {
+        let router = SpanReader::new(copies(count)).router();
+        let response = router
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], NDJSON_CONTENT_TYPE);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(body.to_vec())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    async fn post_processing(reader: SpanReader, token: Option<&str>, body: &str) -> Response {
+        let mut request = Request::post("/admin/processing")
+            .header(header::CONTENT_TYPE, "application/json");
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
         }
+        reader
+            .router()
+            .oneshot(request.body(Body::from(body.to_string())).unwrap())
+            .await
+            .unwrap()
+    }
+
+    fn admin_reader() -> (SpanReader, EngineControl) {
+        let (_tx, rx) = tokio::sync::mpsc::channel(1);
+        let engine = crate::EngineCore::with_storage(
+            rx,
+            ProcessingConfig::default(),
+            Arc::new(MockStorage::new()),
+        );
+        let control = engine.control();
+        let reader = SpanReader::new(copies(3))
+            .with_engine_control(control.clone())
+            .with_admin_auth(BearerAuth::new(&crate::config::AuthConfig {
+                bearer_tokens: vec!["admin".into()],
+            }));
+        (reader, control)
     }
 
     #[tokio::test]
-    async fn test_get_recent_spans() {
-        // TODO: Add tests for span retrieval
+    async fn test_update_processing() {
+        let (reader, control) = admin_reader();
+        let response = post_processing(reader, Some("admin"), r#"{"batch_size": 7}"#).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(control.processing_config().batch_size, 7);
+        assert_eq!(
+            control.processing_config().batch_timeout_ms,
+            ProcessingConfig::default().batch_timeout_ms
+        );
+    }
+
+    #[tokio::test]
+    async fn test_admin_endpoints_closed_without_tokens() {
+        let (reader, control) = admin_reader();
+        let reader = reader.with_admin_auth(BearerAuth::default());
+        let router = reader.router();
+        for request in [
+            Request::post("/admin/processing")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(r#"{"batch_size": 7}"#)),
+            Request::get("/admin/config").body(Body::empty()),
+            Request::post("/admin/index/rebuild").body(Body::empty()),
+            Request::post("/admin/index/compact").body(Body::empty()),
+            Request::delete(format!("/traces/{}", "01".repeat(16))).body(Body::empty()),
+        ] {
+            let response = router.clone().oneshot(request.unwrap()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        }
+        assert_eq!(control.processing_config().batch_size, ProcessingConfig::default().batch_size);
+    }
+
+    #[tokio::test]
+    async fn test_update_processing_rejects_invalid_and_unauthorized() {
+        let (reader, control) = admin_reader();
+        let response = post_processing(reader.clone(), Some("admin"), r#"{"batch_size": 0}"#).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = post_processing(reader, None, r#"{"batch_size": 3}"#).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(control.processing_config(), ProcessingConfig::default());
+    }
+
+    #[tokio::test]
+    async fn test_admin_config_redacts_secrets() {
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "server": {"host": "127.0.0.1", "port": 4317},
+            "storage": {"bucket": "loaded-bucket", "prefix": "traces"},
+            "processing": {"batch_size": 42, "batch_timeout_ms": 1000},
+            "auth": {"bearer_tokens": ["admin", "s3cr3t-token"]},
+        }))
+        .unwrap();
+        let auth = BearerAuth::new(&config.auth);
+        let router = SpanReader::new(copies(0))
+            .with_admin_auth(auth)
+            .with_loaded_config(watch::channel(config).1)
+            .router();
+        let get_config = |token: Option<&str>| {
+            let mut request = Request::get("/admin/config");
+            if let Some(token) = token {
+                request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            router.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+
+        let response = get_config(Some("admin")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(!String::from_utf8_lossy(&body).contains("s3cr3t-token"));
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["storage"]["bucket"], "loaded-bucket");
+        assert_eq!(json["processing"]["batch_size"], 42);
+        // Defaults are filled in
+        assert_eq!(json["storage"]["region"], "us-west-2");
+        assert_eq!(json["auth"]["bearer_tokens"], serde_json::json!(["[redacted]", "[redacted]"]));
+
+        assert_eq!(get_config(None).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        let (without_config, _) = admin_reader();
+        let response = without_config.router()
+            .oneshot(Request::get("/admin/config")
+                .header(header::AUTHORIZATION, "Bearer admin")
+                .body(Body::empty())
+                .unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    async fn delete_trace(trace_id: &str, token: Option<&str>) -> Response {
+        let mut request = Request::delete(format!("/traces/{}", trace_id));
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let (reader, _) = admin_reader();
+        reader
+            .router()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_delete_present_trace() {
+        let response = delete_trace(&"01".repeat(16), Some("admin")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["deleted"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_delete_absent_trace() {
+        let response = delete_trace(&"02".repeat(16), Some("admin")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_delete_trace_requires_admin() {
+        let response = delete_trace(&"01".repeat(16), None).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = delete_trace("not-a-trace", Some("admin")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_trace_integrity() {
+        let span = |span_id: &str, parent: Option<&str>| StoredSpan {
+            span_id: span_id.repeat(8),
+            parent_span_id: parent.map(|parent| parent.repeat(8)),
+            ..stored_span(1_000, 2_000)
+        };
+        let other_trace = StoredSpan { trace_id: "09".repeat(16), ..span("05", Some("07")) };
+        let reader = || SpanReader::new(Arc::new(MockStorage::new().with_spans(vec![
+            span("02", None),
+            span("03", Some("02")),
+            span("04", Some("06")),
+            other_trace.clone(),
+        ])));
+
+        let report = get_json(reader(), &format!("/traces/{}/integrity", "01".repeat(16))).await;
+        assert_eq!(report, serde_json::json!({
+            "trace_id": "01".repeat(16),
+            "span_count": 3,
+            "root_count": 1,
+            "unknown_parent_count": 0,
+            "orphan_spans": [{"span_id": "04".repeat(8), "name": "checkout", "parent_span_id": "06".repeat(8)}],
+            "has_cycle": false,
+        }));
+
+        let response = reader()
+            .router()
+            .oneshot(Request::get(format!("/traces/{}/integrity", "08".repeat(16))).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_traces() {
+        let span = |trace_id: &str, span_id: &str, parent: Option<&str>, start_time: u64, status: &str| StoredSpan {
+            trace_id: trace_id.repeat(16),
+            span_id: span_id.repeat(8),
+            parent_span_id: parent.map(|parent| parent.repeat(8)),
+            status: status.into(),
+            ..stored_span(start_time, start_time + 1_000)
+        };
+        let reader = SpanReader::new(Arc::new(MockStorage::new().with_spans(vec![
+            span("01", "02", None, 1_000, "Ok"),
+            span("01", "03", Some("02"), 1_500, "Error"),
+            span("01", "04", Some("02"), 2_500, "Error"),
+            span("05", "06", None, 5_000, "Ok"),
+        ])));
+
+        let traces = get_json(reader, "/traces?limit=10").await;
+        assert_eq!(traces, serde_json::json!([
+            {
+                "trace_id": "05".repeat(16),
+                "root_operation": "checkout",
+                "root_service": null,
+                "span_count": 1,
+                "start_time": 5_000,
+                "duration_ns": 1_000,
+                "error_count": 0,
+            },
+            {
+                "trace_id": "01".repeat(16),
+                "root_operation": "checkout",
+                "root_service": null,
+                "span_count": 3,
+                "start_time": 1_000,
+                "duration_ns": 2_500,
+                "error_count": 2,
+            },
+        ]));
+    }
+
+    #[tokio::test]
+    async fn test_span_children() {
+        let span = |span_id: &str, parent: Option<&str>, start_time: u64| StoredSpan {
+            span_id: span_id.repeat(8),
+            parent_span_id: parent.map(|parent| parent.repeat(8)),
+            ..stored_span(start_time, start_time + 1_000)
+        };
+        let reader = || SpanReader::new(Arc::new(MockStorage::new().with_spans(vec![
+            span("02", None, 1_000),
+            span("04", Some("02"), 3_000),
+            span("03", Some("02"), 2_000),
+            span("05", Some("03"), 2_500),
+        ])));
+        let child_ids = |children: serde_json::Value| -> Vec<String> {
+            children.as_array().unwrap().iter().map(|span| span["span_id"].as_str().unwrap().to_string()).collect()
+        };
+        let uri = |span_id: &str| format!("/spans/{}/children?trace_id={}", span_id.repeat(8), "01".repeat(16));
+
+        assert_eq!(child_ids(get_json(reader(), &uri("02")).await), ["03".repeat(8), "04".repeat(8)]);
+        assert!(child_ids(get_json(reader(), &uri("04")).await).is_empty(), "leaf spans have no children");
+
+        let response = reader()
+            .router()
+            .oneshot(Request::get(uri("06")).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        // A span stored without its parent id may be a missing child
+        let legacy = StoredSpan { schema_version: 2, ..span("06", None, 4_000) };
+        let response = SpanReader::new(Arc::new(MockStorage::new().with_spans(vec![span("02", None, 1_000), legacy])))
+            .router()
+            .oneshot(Request::get(uri("02")).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[UNKNOWN_PARENTS_HEADER], "1");
+    }
+
+    async fn get_json(reader: SpanReader, uri: &str) -> serde_json::Value {
+        let response = reader
+            .router()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn span_with_attributes(span_id: &str, attributes: serde_json::Value) -> StoredSpan {
+        StoredSpan {
+            span_id: span_id.into(),
+            attributes: serde_json::from_value(attributes).unwrap(),
+            ..stored_span(1_000, 2_000)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spans_filtered_by_attributes() {
+        let reader = SpanReader::new(Arc::new(MockStorage::new().with_spans(vec![
+            span_with_attributes("a", serde_json::json!({"http.status_code": 500, "http.method": "GET"})),
+            span_with_attributes("b", serde_json::json!({"http.status_code": 200, "http.method": "GET"})),
+            span_with_attributes("c", serde_json::json!({"http.status_code": 500, "http.method": "POST"})),
+            span_with_attributes("d", serde_json::json!({})),
+        ])));
+        let router = reader.router();
+        let span_ids = |uri: &'static str| {
+            let router = router.clone();
+            async move {
+                let response = router
+                    .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                serde_json::from_slice::<Vec<serde_json::Value>>(&body)
+                    .unwrap()
+                    .iter()
+                    .map(|span| span["span_id"].as_str().unwrap().to_string())
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(span_ids("/spans?limit=10&attr.http.status_code=500").await, vec!["a", "c"]);
+        assert_eq!(
+            span_ids("/spans?limit=10&attr.http.status_code=500&attr.http.method=GET").await,
+            vec!["a"]
+        );
+        assert_eq!(span_ids("/spans?limit=10&attr.http.method=PUT").await, Vec::<String>::new());
+        assert_eq!(span_ids("/spans?limit=1&attr.http.status_code=500").await, vec!["a"]);
+    }
+
+    fn timed_span(name: &str, duration: u64, status: &str, service: &str) -> StoredSpan {
+        StoredSpan {
+            name: name.into(),
+            status: status.into(),
+            service_name: Some(service.into()),
+            ..stored_span(1_000, 1_000 + duration)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_operation_stats_grouped_by_name() {
+        let mut spans: Vec<StoredSpan> = (1..=100)
+            .map(|i| timed_span("checkout", i * 1_000, if i % 10 == 0 { "Error" } else { "Ok" }, "shop"))
+            .collect();
+        spans.extend([10, 20, 30, 40].map(|ms| timed_span("login", ms * 1_000_000, "Ok", "auth")));
+        let router = SpanReader::new(Arc::new(MockStorage::new().with_spans(spans))).router();
+        let stats = |uri: &'static str| {
+            let router = router.clone();
+            async move {
+                let response = router
+                    .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+            }
+        };
+
+        let all = stats("/stats/operations").await;
+        assert_eq!(all, serde_json::json!([
+            {"name": "checkout", "count": 100, "p50_ns": 50_000, "p95_ns": 95_000, "p99_ns": 99_000, "error_rate": 0.1},
+            {"name": "login", "count": 4, "p50_ns": 20_000_000, "p95_ns": 40_000_000, "p99_ns": 40_000_000, "error_rate": 0.0},
+        ]));
+
+        let auth = stats("/stats/operations?service=auth").await;
+        assert_eq!(auth.as_array().unwrap().len(), 1);
+        assert_eq!(auth[0]["name"], "login");
+
+        // Objects 0..=9 were written at or after 999_991 seconds
+        let recent = stats("/stats/operations?since=999991").await;
+        assert_eq!(recent[0]["count"], 10);
+    }
+
+    async fn timeline_response(uri: &str) -> Response {
+        // Objects are written one second apart, the newest at 1_000_000 seconds
+        SpanReader::new(copies(5))
+            .router()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_timeline_counts_per_bucket() {
+        let response = timeline_response("/stats/timeline?bucket_ms=2000").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let buckets: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(buckets, serde_json::json!([
+            {"bucket_start": 999_996_000u64, "count": 2},
+            {"bucket_start": 999_998_000u64, "count": 2},
+            {"bucket_start": 1_000_000_000u64, "count": 1},
+        ]));
+
+        let response = timeline_response("/stats/timeline?bucket_ms=2000&start=999998000&end=1000000000").await;
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let buckets: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(buckets, serde_json::json!([{"bucket_start": 999_998_000u64, "count": 2}]));
+    }
+
+    #[tokio::test]
+    async fn test_timeline_rejects_invalid_ranges() {
+        for uri in ["/stats/timeline?bucket_ms=0", "/stats/timeline?start=2000&end=1000", "/stats/timeline?start=5&end=5"] {
+            assert_eq!(timeline_response(uri).await.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+    }
+
+    async fn conditional_get(spans: Vec<StoredSpan>, name: header::HeaderName, value: &str) -> Response {
+        conditional_get_uri(spans, "/spans", name, value).await
+    }
+
+    async fn conditional_get_uri(spans: Vec<StoredSpan>, uri: &str, name: header::HeaderName, value: &str) -> Response {
+        SpanReader::new(Arc::new(MockStorage::new().with_spans(spans)))
+            .router()
+            .oneshot(Request::get(uri).header(name, value).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_spans_etag_covers_body_shape() {
+        let spans = vec![stored_span(1_000, 2_000)];
+        let response = conditional_get(spans.clone(), header::IF_NONE_MATCH, "\"other\"").await;
+        let etag = response.headers()[header::ETAG].to_str().unwrap().to_string();
+
+        for uri in ["/spans?envelope=true", "/spans?service=shop", "/spans?limit=1"] {
+            let response = conditional_get_uri(spans.clone(), uri, header::IF_NONE_MATCH, &etag).await;
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+            assert_ne!(response.headers()[header::ETAG], etag.as_str(), "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spans_etag_revalidation() {
+        let spans = vec![stored_span(1_000, 2_000), stored_span(3_000, 4_000)];
+        let response = conditional_get(spans.clone(), header::IF_NONE_MATCH, "\"other\"").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[header::ETAG].to_str().unwrap().to_string();
+
+        let response = conditional_get(spans.clone(), header::IF_NONE_MATCH, &etag).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+
+        // A newly written span changes the listing
+        let mut updated = vec![stored_span(5_000, 6_000)];
+        updated.extend(spans);
+        let response = conditional_get(updated, header::IF_NONE_MATCH, &etag).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()[header::ETAG], etag.as_str());
+    }
+
+    #[tokio::test]
+    async fn test_spans_if_modified_since() {
+        let spans = vec![stored_span(1_000, 2_000)];
+        let response = conditional_get(spans.clone(), header::IF_MODIFIED_SINCE, "Thu, 01 Jan 1970 00:00:00 GMT").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let last_modified = response.headers()[header::LAST_MODIFIED].to_str().unwrap().to_string();
+        assert_eq!(last_modified, http_date(UNIX_EPOCH + Duration::from_secs(1_000_000)));
+
+        let response = conditional_get(spans, header::IF_MODIFIED_SINCE, &last_modified).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    async fn get_span(uri: &str) -> Response {
+        let reader = SpanReader::new(Arc::new(MockStorage::new().with_spans(vec![
+            span_with_attributes(&"03".repeat(8), serde_json::json!({})),
+            span_with_attributes(&"02".repeat(8), serde_json::json!({"http.method": "GET"})),
+        ])));
+        reader
+            .router()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_span_found() {
+        let uri = format!("/spans/{}?trace_id={}", "02".repeat(8), "01".repeat(16));
+        let response = get_span(&uri).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let span: StoredSpan = serde_json::from_slice(&body).unwrap();
+        assert_eq!(span.span_id, "02".repeat(8));
+        assert_eq!(span.attributes["http.method"], "GET");
+    }
+
+    #[tokio::test]
+    async fn test_get_span_accepts_unpadded_ids() {
+        // Leading zeros dropped, as in 64-bit or integer-formatted ids
+        let uri = format!("/spans/2{}?trace_id=1{}", "02".repeat(7), "01".repeat(15));
+        assert_eq!(get_span(&uri).await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_get_span_not_found() {
+        let uri = format!("/spans/{}?trace_id={}", "04".repeat(8), "01".repeat(16));
+        assert_eq!(get_span(&uri).await.status(), StatusCode::NOT_FOUND);
+
+        let uri = format!("/spans/{}", "02".repeat(8));
+        assert_eq!(get_span(&uri).await.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_get_raw_span() {
+        let uri = format!("/spans/{}/raw?trace_id={}", "02".repeat(8), "01".repeat(16));
+        let response = get_span(&uri).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+
+        let written = span_with_attributes(&"02".repeat(8), serde_json::json!({"http.method": "GET"}));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, serde_json::to_vec(&written).unwrap());
+
+        let uri = format!("/spans/{}/raw?trace_id={}", "04".repeat(8), "01".repeat(16));
+        assert_eq!(get_span(&uri).await.status(), StatusCode::NOT_FOUND);
+        let uri = format!("/spans/{}/raw", "02".repeat(8));
+        assert_eq!(get_span(&uri).await.status(), StatusCode::BAD_REQUEST);
+    }
+
+    async fn get_spans(uri: &str) -> Response {
+        let config = ReaderConfig {
+            default_limit: 3,
+            max_limit: 10,
+            ..ReaderConfig::default()
+        };
+        SpanReader::new(copies(40))
+            .with_config(config)
+            .router()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn span_count(response: Response) -> usize {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice::<Vec<serde_json::Value>>(&body).unwrap().len()
+    }
+
+    /// Log sink shared between a test subscriber and its assertions
+    #[derive(Clone, Default)]
+    struct LogBuffer(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for LogBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for LogBuffer {
+        type Writer = Self;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_logging() {
+        let logs = LogBuffer::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(logs.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let request = Request::get("/spans?limit=2")
+            .header(header::AUTHORIZATION, "Bearer secret-token")
+            .body(Body::empty())
+            .unwrap();
+        let response = SpanReader::new(copies(5))
+            .router()
+            .oneshot(request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        for field in ["method=GET", "path=\"/spans\"", "query=\"limit=2\"", "status=200", "latency_ms="] {
+            assert!(output.contains(field), "missing {} in {}", field, output);
+        }
+        assert!(!output.contains("secret-token"));
+    }
+
+    #[tokio::test]
+    async fn test_spans_default_limit() {
+        let response = get_spans("/spans").await;
+        assert!(!response.headers().contains_key(LIMIT_CLAMPED_HEADER));
+        assert_eq!(span_count(response).await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_spans_limit_at_max_not_clamped() {
+        let response = get_spans("/spans?limit=10").await;
+        assert!(!response.headers().contains_key(LIMIT_CLAMPED_HEADER));
+        assert_eq!(span_count(response).await, 10);
+    }
+
+    #[tokio::test]
+    async fn test_spans_limit_above_max_clamped() {
+        let response = get_spans("/spans?limit=10000000").await;
+        assert_eq!(response.headers()[LIMIT_CLAMPED_HEADER], "true");
+        assert_eq!(response.headers()[REQUESTED_LIMIT_HEADER], "10000000");
+        assert_eq!(span_count(response).await, 10);
+    }
+
+    #[tokio::test]
+    async fn test_spans_bare_array_by_default() {
+        let body = get_json(SpanReader::new(copies(5)), "/spans?limit=2").await;
+        assert_eq!(body.as_array().unwrap().len(), 2);
+        assert_eq!(body[0]["name"], "checkout");
+    }
+
+    #[tokio::test]
+    async fn test_spans_envelope() {
+        let body = get_json(SpanReader::new(copies(5)), "/spans?limit=2&envelope=true").await;
+        assert_eq!(body["count"], 2);
+        assert_eq!(body["spans"].as_array().unwrap().len(), 2);
+        assert_eq!(body["spans"][0]["name"], "checkout");
+        assert_eq!(body["errors"], 0);
+        assert!(body.get("failed_reads").is_none());
+        assert!(body["next_page_token"].is_null());
+
+        let body = get_json(SpanReader::new(copies(5)), "/spans?envelope=false").await;
+        assert!(body.is_array());
+    }
+
+    #[tokio::test]
+    async fn test_spans_partial_read_failures() {
+        let storage = copies(4);
+        storage.fail_next_reads(2);
+        let response = SpanReader::new(storage.clone())
+            .router()
+            .oneshot(Request::get("/spans?limit=4").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+        assert_eq!(response.headers()[READ_ERRORS_HEADER], "2");
+        assert!(!response.headers().contains_key(header::ETAG));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let spans: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(spans.as_array().unwrap().len(), 2);
+
+        storage.fail_next_reads(2);
+        let response = SpanReader::new(storage)
+            .router()
+            .oneshot(Request::get("/spans?limit=4&verbose=true").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["count"], 2);
+        assert_eq!(body["errors"], 2);
+        let failed = body["failed_reads"].as_array().unwrap();
+        assert_eq!(failed.len(), 2);
+        assert!(failed[0]["error"].as_str().unwrap().contains("injected read failure"));
+    }
+
+    #[tokio::test]
+    async fn test_count_spans() {
+        let reader = SpanReader::new(copies(40));
+
+        let count = get_json(reader.clone(), "/spans/count").await;
+        assert_eq!(count, serde_json::json!({ "count": 40, "truncated": false }));
+
+        let count = get_json(reader.clone(), "/spans/count?max_scan=10").await;
+        assert_eq!(count, serde_json::json!({ "count": 10, "truncated": true }));
+
+        let future = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() + 3600;
+        let count = get_json(reader, &format!("/spans/count?since={}", future)).await;
+        assert_eq!(count, serde_json::json!({ "count": 0, "truncated": false }));
+    }
+
+    #[tokio::test]
+    async fn test_export_streams_ndjson() {
+        let spans = export_lines(40, "/spans/export").await;
+        assert_eq!(spans.len(), 40);
+        assert_eq!(spans[0].name, "checkout");
+    }
+
+    async fn get_with_encoding(uri: &str, encoding: &str) -> Response {
+        SpanReader::new(copies(40))
+            .router()
+            .oneshot(Request::get(uri).header(header::ACCEPT_ENCODING, encoding).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_large_response_gzip_compressed() {
+        let response = get_with_encoding("/spans?limit=40", "gzip").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body[..2], [0x1f, 0x8b]);
+    }
+
+    #[tokio::test]
+    async fn test_small_or_unnegotiated_response_uncompressed() {
+        let small = get_with_encoding("/spans?limit=1", "gzip").await;
+        assert!(!small.headers().contains_key(header::CONTENT_ENCODING));
+
+        let identity = get_with_encoding("/spans?limit=40", "identity").await;
+        assert!(!identity.headers().contains_key(header::CONTENT_ENCODING));
+    }
+
+    #[tokio::test]
+    async fn test_export_stream_compressed() {
+        let response = get_with_encoding("/spans/export", "br").await;
+        assert_eq!(response.headers()[header::CONTENT_TYPE], NDJSON_CONTENT_TYPE);
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "br");
+        assert!(!response.headers().contains_key(header::CONTENT_LENGTH));
+    }
+
+    #[tokio::test]
+    async fn test_export_respects_limit() {
+        assert_eq!(export_lines(40, "/spans/export?limit=7").await.len(), 7);
+    }
+
+    fn search_storage() -> MockStorage {
+        MockStorage::new().with_spans(vec![
+            StoredSpan { span_id: "a".into(), status: "Error".into(), ..stored_span(3_000_000, 9_000_000) },
+            StoredSpan { span_id: "b".into(), ..stored_span(2_000_000, 3_000_000) },
+            StoredSpan { span_id: "c".into(), status: "Error".into(), ..stored_span(1_000_000, 1_500_000) },
+        ])
+    }
+
+    fn span_ids(spans: &serde_json::Value) -> Vec<&str> {
+        spans.as_array().unwrap().iter().map(|span| span["span_id"].as_str().unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_search_uses_index() {
+        let storage = Arc::new(search_storage().with_search_index());
+        let reader = SpanReader::new(storage.clone());
+
+        let spans = get_json(reader.clone(), "/search?status=error").await;
+        assert_eq!(span_ids(&spans), ["a", "c"]);
+        let spans = get_json(reader.clone(), "/search?status=error&min_duration_ns=1000000").await;
+        assert_eq!(span_ids(&spans), ["a"]);
+        let spans = get_json(reader, "/search?start=2&end=3").await;
+        assert_eq!(span_ids(&spans), ["b"]);
+
+        assert_eq!(storage.calls("search_index"), 3);
+        assert_eq!(storage.calls("list_spans"), 0, "objects are not scanned");
+    }
+
+    #[tokio::test]
+    async fn test_search_scans_without_index() {
+        let storage = Arc::new(search_storage());
+        let spans = get_json(SpanReader::new(storage.clone()), "/search?status=Error&limit=1").await;
+
+        assert_eq!(span_ids(&spans), ["a"]);
+        assert_eq!(storage.calls("list_spans"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_search_filtered_by_attributes() {
+        let spans = || vec![
+            span_with_attributes("d", serde_json::json!({"http.status_code": 500})),
+            span_with_attributes("e", serde_json::json!({"http.status_code": 200})),
+        ];
+        for storage in [MockStorage::new().with_spans(spans()), MockStorage::new().with_spans(spans()).with_search_index()] {
+            let spans = get_json(SpanReader::new(Arc::new(storage)), "/search?attr.http.status_code=500").await;
+            assert_eq!(span_ids(&spans), ["d"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_rejects_invalid_trace_id() {
+        let response = get_spans("/search?trace_id=not-hex").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_index_maintenance_requires_admin_and_support() {
+        let post = |uri: &'static str, token: Option<&'static str>| async move {
+            let (reader, _control) = admin_reader();
+            let mut request = Request::post(uri);
+            if let Some(token) = token {
+                request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            reader.router().oneshot(request.body(Body::empty()).unwrap()).await.unwrap().status()
+        };
+
+        for uri in ["/admin/index/rebuild", "/admin/index/compact"] {
+            assert_eq!(post(uri, None).await, StatusCode::UNAUTHORIZED, "{}", uri);
+            assert_eq!(post(uri, Some("admin")).await, StatusCode::NOT_IMPLEMENTED, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_openapi_document() {
+        let response = get_spans("/openapi.json").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let doc: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(doc["openapi"].as_str().unwrap().starts_with("3."));
+        let spans = &doc["paths"]["/spans"]["get"];
+        assert!(spans["parameters"].as_array().unwrap().iter().any(|param| param["name"] == "limit"));
+        for schema in ["SpanSummary", "StoredSpan", "HealthStatus"] {
+            assert!(doc["components"]["schemas"][schema].is_object(), "missing {} schema", schema);
+        }
+        let raw = &doc["paths"]["/spans/{span_id}/raw"]["get"]["responses"]["200"]["content"];
+        for content_type in ["application/json", "application/vnd.apache.parquet", "application/octet-stream"] {
+            assert_eq!(raw[content_type]["schema"]["format"], "binary", "{}", content_type);
+        }
+    }
+
+    #[test]
+    fn test_summary_duration() {
+        let summary = SpanSummary::from(stored_span(1_000, 3_500));
+        assert_eq!(summary.duration_ns, 2_500);
+        assert!(!summary.clock_skew);
+        assert_eq!(summary.kind, "Server");
+        assert_eq!(summary.status, "Ok");
+    }
+
+    #[test]
+    fn test_summary_clock_skew() {
+        let summary = SpanSummary::from(stored_span(5_000, 1_000));
+        assert_eq!(summary.duration_ns, 0);
+        assert!(summary.clock_skew);
     }
 }
\ No newline at end of file
@@ -0,0 +1,219 @@
+use utoipa::openapi::path::{
+    OperationBuilder, Parameter, ParameterBuilder, ParameterIn, PathItem, PathItemType,
+};
+use utoipa::openapi::request_body::RequestBodyBuilder;
+use utoipa::openapi::schema::OneOfBuilder;
+use utoipa::openapi::{
+    Content, ContentBuilder, KnownFormat, ObjectBuilder, OpenApi as OpenApiDocument, PathsBuilder, Ref, Required,
+    Response, ResponseBuilder, SchemaFormat, SchemaType,
+};
+use utoipa::{IntoParams, OpenApi};
+
+use crate::health::HealthStatus;
+use crate::storage::columnar::PARQUET_CONTENT_TYPE;
+use crate::storage::{SpanCount, StoredEvent, StoredLink, StoredSpan};
+use super::stats::{OperationStats, TimelineBucket};
+use super::tree::{OrphanSpan, TraceIntegrity, TraceSummary};
+use super::{
+    CompactIndexResponse, CountQuery, DeleteTraceResponse, ExportQuery, FailedRead, ProcessingUpdate,
+    RebuildIndexResponse, SchemaField, SchemaResponse, SearchQuery,
+    SpanLookupQuery, SpanQuery, SpanSummary, SpansEnvelope, StatsQuery, TimelineQuery, TracesQuery,
+    NDJSON_CONTENT_TYPE,
+};
+
+/// Content type of JSON request and response bodies
+const JSON: &str = "application/json";
+/// Content type of stored objects in any other encoding
+const OCTET_STREAM: &str = "application/octet-stream";
+
+/// Schemas and metadata of the reader API. Paths are added by `document`,
+/// as the handlers are methods that `#[utoipa::path]` cannot annotate.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "storage-engine reader API"),
+    components(schemas(
+        SpanSummary,
+        SpansEnvelope,
+        FailedRead,
+        StoredSpan,
+        StoredEvent,
+        StoredLink,
+        SpanCount,
+        HealthStatus,
+        DeleteTraceResponse,
+        RebuildIndexResponse,
+        CompactIndexResponse,
+        ProcessingUpdate,
+        SchemaResponse,
+        SchemaField,
+        OperationStats,
+        TimelineBucket,
+        TraceIntegrity,
+        OrphanSpan,
+        TraceSummary,
+    ))
+)]
+struct ApiDoc;
+
+/// Builds the OpenAPI 3 document served at `GET /openapi.json`
+pub(super) fn document() -> OpenApiDocument {
+    let mut doc = ApiDoc::openapi();
+    doc.paths = PathsBuilder::new()
+        .path("/spans", get(
+            "List recent span summaries. `attr.<key>=<value>` parameters filter by span attribute. \
+             Returns a bare array, or a `SpansEnvelope` with `envelope=true`. \
+             Answers 207 with an `x-read-errors` count when some objects failed to read.",
+            SpanQuery::into_params(|| None),
+            ok(JSON, ContentBuilder::new()
+                .schema(OneOfBuilder::new()
+                    .item(Ref::from_schema_name("SpanSummary").to_array_builder())
+                    .item(Ref::from_schema_name("SpansEnvelope")))
+                .build()),
+        ))
+        .path("/spans/export", get(
+            "Stream stored spans as newline-delimited JSON",
+            ExportQuery::into_params(|| None),
+            ok(NDJSON_CONTENT_TYPE, json("StoredSpan")),
+        ))
+        .path("/spans/count", get(
+            "Count stored span objects from key listings",
+            CountQuery::into_params(|| None),
+            ok(JSON, json("SpanCount")),
+        ))
+        .path("/spans/{span_id}", get(
+            "Look up a single stored span",
+            [vec![path_param("span_id")], SpanLookupQuery::into_params(|| None)].concat(),
+            ok(JSON, json("StoredSpan")),
+        ))
+        .path("/spans/{span_id}/raw", get(
+            "Return the object holding a span exactly as stored, with its content type",
+            [vec![path_param("span_id")], SpanLookupQuery::into_params(|| None)].concat(),
+            ResponseBuilder::new()
+                .description("The stored object's bytes; compressed objects keep their `Content-Encoding`")
+                .content(JSON, binary())
+                .content(PARQUET_CONTENT_TYPE, binary())
+                .content(OCTET_STREAM, binary())
+                .build(),
+        ))
+        .path("/spans/{span_id}/children", get(
+            "List the direct children of a span, earliest start first; 404 when the span is not found",
+            [vec![path_param("span_id")], SpanLookupQuery::into_params(|| None)].concat(),
+            ok(JSON, json_array("StoredSpan")),
+        ))
+        .path("/search", get(
+            "Search spans by service, name, status, trace, duration, start time and \
+             `attr.<key>=<value>` attributes, using the span index when enabled",
+            SearchQuery::into_params(|| None),
+            ok(JSON, json_array("SpanSummary")),
+        ))
+        .path("/services", get(
+            "List service names that have reported spans",
+            Vec::new(),
+            ok(JSON, ContentBuilder::new()
+                .schema(ObjectBuilder::new().schema_type(SchemaType::String).to_array_builder())
+                .build()),
+        ))
+        .path("/schema", get(
+            "Schema version written on new span objects and the fields of the stored span layout; \
+             objects of older versions are read with their missing fields defaulted",
+            Vec::new(),
+            ok(JSON, json("SchemaResponse")),
+        ))
+        .path("/stats/operations", get(
+            "Latency percentiles and error rate per operation over recent spans",
+            StatsQuery::into_params(|| None),
+            ok(JSON, json_array("OperationStats")),
+        ))
+        .path("/stats/timeline", get(
+            "Objects written per time bucket over recent objects; empty buckets are omitted",
+            TimelineQuery::into_params(|| None),
+            ok(JSON, json_array("TimelineBucket")),
+        ))
+        .path("/admin/processing", PathItem::new(PathItemType::Post, OperationBuilder::new()
+            .summary(Some("Adjust batching on the running engine"))
+            .request_body(Some(RequestBodyBuilder::new()
+                .content(JSON, json("ProcessingUpdate"))
+                .required(Some(Required::True))
+                .build()))
+            .response("200", ResponseBuilder::new().description("Applied processing config").build())
+            .response("401", ResponseBuilder::new().description("Missing or invalid bearer token").build())))
+        .path("/admin/config", PathItem::new(PathItemType::Get, OperationBuilder::new()
+            .summary(Some("Effective configuration of the running process, with secrets redacted"))
+            .response("200", ok(JSON, ContentBuilder::new()
+                .schema(ObjectBuilder::new().schema_type(SchemaType::Object))
+                .build()))
+            .response("401", ResponseBuilder::new().description("Missing or invalid bearer token").build())
+            .response("503", ResponseBuilder::new().description("No configuration attached").build())))
+        .path("/admin/index/rebuild", PathItem::new(PathItemType::Post, OperationBuilder::new()
+            .summary(Some("Rebuild the span index from stored objects"))
+            .response("200", ok(JSON, json("RebuildIndexResponse")))
+            .response("401", ResponseBuilder::new().description("Missing or invalid bearer token").build())
+            .response("501", ResponseBuilder::new().description("Storage keeps no span index").build())))
+        .path("/admin/index/compact", PathItem::new(PathItemType::Post, OperationBuilder::new()
+            .summary(Some("Merge the span index segments of each past hour"))
+            .response("200", ok(JSON, json("CompactIndexResponse")))
+            .response("401", ResponseBuilder::new().description("Missing or invalid bearer token").build())
+            .response("501", ResponseBuilder::new().description("Storage keeps no span index").build())))
+        .path("/traces", get(
+            "List recent traces with their root operation, span count, duration and error count, \
+             latest start first",
+            TracesQuery::into_params(|| None),
+            ok(JSON, json_array("TraceSummary")),
+        ))
+        .path("/traces/{trace_id}", PathItem::new(PathItemType::Delete, OperationBuilder::new()
+            .summary(Some("Delete the span objects of a trace"))
+            .parameter(path_param("trace_id"))
+            .response("200", ok(JSON, json("DeleteTraceResponse")))
+            .response("401", ResponseBuilder::new().description("Missing or invalid bearer token").build())
+            .response("404", ResponseBuilder::new().description("Trace not found").build())))
+        .path("/traces/{trace_id}/integrity", get(
+            "Report a trace's root count, spans whose parent is missing, and parent cycles",
+            vec![path_param("trace_id")],
+            ok(JSON, json("TraceIntegrity")),
+        ))
+        .path("/health", get("System health status", Vec::new(), ok(JSON, json("HealthStatus"))))
+        .build();
+    doc
+}
+
+/// Builds a GET path item with the given success response
+fn get(summary: &str, parameters: Vec<Parameter>, response: Response) -> PathItem {
+    PathItem::new(PathItemType::Get, OperationBuilder::new()
+        .summary(Some(summary))
+        .parameters(Some(parameters))
+        .response("200", response))
+}
+
+/// Success response with a body of the given content type
+fn ok(content_type: &str, content: Content) -> Response {
+    ResponseBuilder::new().description("Success").content(content_type, content).build()
+}
+
+/// Content of unspecified bytes, such as a stored object
+fn binary() -> Content {
+    ContentBuilder::new()
+        .schema(ObjectBuilder::new()
+            .schema_type(SchemaType::String)
+            .format(Some(SchemaFormat::KnownFormat(KnownFormat::Binary))))
+        .build()
+}
+
+/// JSON content holding one schema component
+fn json(name: &str) -> Content {
+    ContentBuilder::new().schema(Ref::from_schema_name(name)).build()
+}
+
+/// JSON content holding an array of a schema component
+fn json_array(name: &str) -> Content {
+    ContentBuilder::new().schema(Ref::from_schema_name(name).to_array_builder()).build()
+}
+
+/// Required string path parameter
+fn path_param(name: &str) -> Parameter {
+    ParameterBuilder::new()
+        .name(name)
+        .parameter_in(ParameterIn::Path)
+        .required(Required::True)
+        .schema(Some(ObjectBuilder::new().schema_type(SchemaType::String)))
+        .build()
+}
@@ -0,0 +1,103 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+use crate::storage::StoredSpan;
+
+/// Latency and error statistics of one operation
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct OperationStats {
+    /// Span name the statistics are grouped by
+    pub name: String,
+    /// Number of spans read
+    pub count: usize,
+    /// Median duration in nanoseconds
+    pub p50_ns: u64,
+    /// 95th percentile duration in nanoseconds
+    pub p95_ns: u64,
+    /// 99th percentile duration in nanoseconds
+    pub p99_ns: u64,
+    /// Fraction of spans with an `Error` status, from 0.0 to 1.0
+    pub error_rate: f64,
+}
+
+/// Number of objects written within one time bucket
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct TimelineBucket {
+    /// Start of the bucket in milliseconds since the Unix epoch
+    pub bucket_start: u64,
+    /// Objects written within the bucket
+    pub count: usize,
+}
+
+/// Counts timestamps, in milliseconds since the Unix epoch, per
+/// `bucket_ms`-wide bucket aligned to the epoch. Buckets are sorted by
+/// start time; buckets without timestamps are omitted.
+pub fn timeline(times_ms: impl IntoIterator<Item = u64>, bucket_ms: u64) -> Vec<TimelineBucket> {
+    let mut buckets: BTreeMap<u64, usize> = BTreeMap::new();
+    for time in times_ms {
+        *buckets.entry(time - time % bucket_ms).or_default() += 1;
+    }
+    buckets
+        .into_iter()
+        .map(|(bucket_start, count)| TimelineBucket { bucket_start, count })
+        .collect()
+}
+
+/// Groups spans by name into per-operation statistics, sorted by name
+pub fn operation_stats(spans: impl IntoIterator<Item = StoredSpan>) -> Vec<OperationStats> {
+    let mut operations: BTreeMap<String, (Vec<u64>, usize)> = BTreeMap::new();
+    for span in spans {
+        let (durations, errors) = operations.entry(span.name).or_default();
+        durations.push(span.duration_ns);
+        if span.status == "Error" {
+            *errors += 1;
+        }
+    }
+
+    operations
+        .into_iter()
+        .map(|(name, (mut durations, errors))| {
+            durations.sort_unstable();
+            OperationStats {
+                name,
+                count: durations.len(),
+                p50_ns: percentile(&durations, 50.0),
+                p95_ns: percentile(&durations, 95.0),
+                p99_ns: percentile(&durations, 99.0),
+                error_rate: errors as f64 / durations.len() as f64,
+            }
+        })
+        .collect()
+}
+
+/// Nearest-rank percentile of sorted, non-empty values
+fn percentile(sorted: &[u64], percent: f64) -> u64 {
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearest_rank_percentiles() {
+        let values: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentile(&values, 50.0), 50);
+        assert_eq!(percentile(&values, 95.0), 95);
+        assert_eq!(percentile(&values, 99.0), 99);
+        assert_eq!(percentile(&[7], 99.0), 7);
+        assert_eq!(percentile(&[1, 2], 0.0), 1);
+    }
+
+    #[test]
+    fn test_timeline_buckets_aligned_to_epoch() {
+        let buckets = timeline([61_000, 5_000, 59_999, 180_000], 60_000);
+        assert_eq!(buckets, [
+            TimelineBucket { bucket_start: 0, count: 2 },
+            TimelineBucket { bucket_start: 60_000, count: 1 },
+            TimelineBucket { bucket_start: 180_000, count: 1 },
+        ]);
+    }
+}
@@ -0,0 +1,271 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use utoipa::ToSchema;
+
+use crate::storage::StoredSpan;
+
+/// First `schema_version` recording `parent_span_id`; older spans read
+/// without a parent whether or not they had one
+const PARENT_RECORDED_VERSION: u32 = 3;
+
+/// Parent/child structure of the spans of one trace, linked by `parent_span_id`
+#[derive(Debug)]
+pub struct TraceTree<'a> {
+    spans: &'a [StoredSpan],
+    /// Spans by span id; the first of duplicated ids wins
+    by_id: HashMap<&'a str, &'a StoredSpan>,
+}
+
+/// Structural report of one trace
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct TraceIntegrity {
+    /// Trace the report describes
+    pub trace_id: String,
+    /// Spans read for the trace
+    pub span_count: usize,
+    /// Spans without a parent; a complete trace has exactly one
+    pub root_count: usize,
+    /// Spans stored before parent ids were recorded (`schema_version` below
+    /// 3), counted neither as roots nor as orphans
+    pub unknown_parent_count: usize,
+    /// Spans whose parent was not found in the trace, e.g. because it was
+    /// dropped or not yet stored
+    pub orphan_spans: Vec<OrphanSpan>,
+    /// Whether following parent ids from some span leads back to it
+    pub has_cycle: bool,
+}
+
+/// Aggregates of one trace, as listed by `GET /traces`
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct TraceSummary {
+    /// Trace the summary describes
+    pub trace_id: String,
+    /// Name of the earliest root span; `None` when no root span was found
+    pub root_operation: Option<String>,
+    /// Service that reported the root span
+    pub root_service: Option<String>,
+    /// Spans read for the trace
+    pub span_count: usize,
+    /// Earliest span start in nanoseconds since epoch
+    pub start_time: u64,
+    /// Nanoseconds from the earliest span start to the latest span end
+    pub duration_ns: u64,
+    /// Spans with an `Error` status
+    pub error_count: usize,
+}
+
+/// Span referencing a parent missing from its trace
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct OrphanSpan {
+    /// Id of the orphaned span
+    pub span_id: String,
+    /// Name of the orphaned span
+    pub name: String,
+    /// Parent id that no span of the trace carries
+    pub parent_span_id: String,
+}
+
+impl<'a> TraceTree<'a> {
+    /// Links the spans of one trace
+    pub fn new(spans: &'a [StoredSpan]) -> Self {
+        let mut by_id = HashMap::new();
+        for span in spans {
+            by_id.entry(span.span_id.as_str()).or_insert(span);
+        }
+        Self { spans, by_id }
+    }
+
+    /// Returns the spans without a parent, in the order given; spans whose
+    /// parent was not recorded are left out
+    pub fn roots(&self) -> impl Iterator<Item = &'a StoredSpan> + '_ {
+        self.spans.iter().filter(|span| span.parent_span_id.is_none() && parent_recorded(span))
+    }
+
+    /// Returns the spans stored before parent ids were recorded, whose
+    /// place in the trace is unknown
+    pub fn unknown_parents(&self) -> impl Iterator<Item = &'a StoredSpan> + '_ {
+        self.spans.iter().filter(|span| !parent_recorded(span))
+    }
+
+    /// Returns the spans whose parent is not part of the trace
+    pub fn orphans(&self) -> impl Iterator<Item = &'a StoredSpan> + '_ {
+        self.spans.iter().filter(|span| {
+            span.parent_span_id.as_deref().is_some_and(|parent| !self.by_id.contains_key(parent))
+        })
+    }
+
+    /// Returns the span with the given id, if it is part of the trace
+    pub fn span(&self, span_id: &str) -> Option<&'a StoredSpan> {
+        self.by_id.get(span_id).copied()
+    }
+
+    /// Returns the direct children of a span, earliest start first
+    pub fn children(&self, span_id: &str) -> Vec<&'a StoredSpan> {
+        let mut children: Vec<_> = self.spans
+            .iter()
+            .filter(|span| span.parent_span_id.as_deref() == Some(span_id))
+            .collect();
+        children.sort_by_key(|span| span.start_time);
+        children
+    }
+
+    /// Returns whether the parent links of any span form a cycle
+    pub fn has_cycle(&self) -> bool {
+        // Spans whose parent chain is known to end at a root or an orphan
+        let mut acyclic = HashSet::new();
+        for start in self.by_id.keys() {
+            let mut path = HashSet::new();
+            let mut current = Some(*start);
+            while let Some(span_id) = current {
+                if acyclic.contains(span_id) {
+                    break;
+                }
+                if !path.insert(span_id) {
+                    return true;
+                }
+                current = self.by_id
+                    .get(span_id)
+                    .and_then(|span| span.parent_span_id.as_deref())
+                    .filter(|parent| self.by_id.contains_key(parent));
+            }
+            acyclic.extend(path);
+        }
+        false
+    }
+
+    /// Aggregates span count, duration and errors of the trace, named after
+    /// its earliest root span
+    pub fn summary(&self, trace_id: &str) -> TraceSummary {
+        let root = self.roots().min_by_key(|span| span.start_time);
+        let start_time = self.spans.iter().map(|span| span.start_time).min().unwrap_or_default();
+        let end_time = self.spans.iter().map(|span| span.end_time).max().unwrap_or_default();
+        TraceSummary {
+            trace_id: trace_id.to_string(),
+            root_operation: root.map(|span| span.name.clone()),
+            root_service: root.and_then(|span| span.service_name.clone()),
+            span_count: self.spans.len(),
+            start_time,
+            duration_ns: end_time.saturating_sub(start_time),
+            error_count: self.spans.iter().filter(|span| span.status == "Error").count(),
+        }
+    }
+
+    /// Reports roots, orphans and cycles of the trace
+    pub fn integrity(&self, trace_id: &str) -> TraceIntegrity {
+        TraceIntegrity {
+            trace_id: trace_id.to_string(),
+            span_count: self.spans.len(),
+            root_count: self.roots().count(),
+            unknown_parent_count: self.unknown_parents().count(),
+            orphan_spans: self
+                .orphans()
+                .map(|span| OrphanSpan {
+                    span_id: span.span_id.clone(),
+                    name: span.name.clone(),
+                    parent_span_id: span.parent_span_id.clone().unwrap_or_default(),
+                })
+                .collect(),
+            has_cycle: self.has_cycle(),
+        }
+    }
+}
+
+/// Returns whether a span's object records `parent_span_id`
+fn parent_recorded(span: &StoredSpan) -> bool {
+    span.schema_version >= PARENT_RECORDED_VERSION
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::stored_span;
+
+    fn span(span_id: &str, parent_span_id: Option<&str>) -> StoredSpan {
+        StoredSpan {
+            span_id: span_id.to_string(),
+            parent_span_id: parent_span_id.map(str::to_string),
+            name: format!("op-{}", span_id),
+            ..stored_span(1_000, 2_000)
+        }
+    }
+
+    #[test]
+    fn test_healthy_trace() {
+        let spans = [span("a", None), span("b", Some("a")), span("c", Some("a")), span("d", Some("c"))];
+        let report = TraceTree::new(&spans).integrity("t");
+
+        assert_eq!((report.span_count, report.root_count), (4, 1));
+        assert!(report.orphan_spans.is_empty());
+        assert!(!report.has_cycle);
+    }
+
+    #[test]
+    fn test_orphaned_spans_reported() {
+        let spans = [span("a", None), span("b", Some("x")), span("c", Some("b"))];
+        let report = TraceTree::new(&spans).integrity("t");
+
+        assert_eq!(report.root_count, 1);
+        assert_eq!(report.orphan_spans, vec![OrphanSpan {
+            span_id: "b".into(),
+            name: "op-b".into(),
+            parent_span_id: "x".into(),
+        }]);
+        assert!(!report.has_cycle);
+    }
+
+    #[test]
+    fn test_spans_without_recorded_parent_not_roots() {
+        let legacy = StoredSpan { schema_version: 2, ..span("b", None) };
+        let spans = [span("a", None), legacy];
+        let tree = TraceTree::new(&spans);
+        let report = tree.integrity("t");
+
+        assert_eq!((report.root_count, report.unknown_parent_count), (1, 1));
+        assert!(report.orphan_spans.is_empty());
+        assert_eq!(tree.summary("t").root_operation.as_deref(), Some("op-a"));
+    }
+
+    #[test]
+    fn test_cycle_detected() {
+        let spans = [span("a", None), span("b", Some("d")), span("c", Some("b")), span("d", Some("c"))];
+        let report = TraceTree::new(&spans).integrity("t");
+
+        assert!(report.has_cycle);
+        assert_eq!(report.root_count, 1);
+        assert!(report.orphan_spans.is_empty(), "spans in a cycle have their parents");
+
+        let self_parent = [span("a", Some("a"))];
+        assert!(TraceTree::new(&self_parent).has_cycle());
+    }
+
+    #[test]
+    fn test_trace_summary() {
+        let mut root = span("a", None);
+        root.start_time = 500;
+        let mut failed = span("b", Some("a"));
+        failed.status = "Error".into();
+        failed.end_time = 4_000;
+        let spans = [span("c", Some("b")), failed, root];
+        let summary = TraceTree::new(&spans).summary("t");
+
+        assert_eq!(summary.root_operation.as_deref(), Some("op-a"));
+        assert_eq!((summary.span_count, summary.error_count), (3, 1));
+        assert_eq!((summary.start_time, summary.duration_ns), (500, 3_500));
+
+        let orphans = [span("b", Some("x"))];
+        assert_eq!(TraceTree::new(&orphans).summary("t").root_operation, None);
+    }
+
+    #[test]
+    fn test_children_of_span() {
+        let mut late = span("c", Some("a"));
+        late.start_time = 1_500;
+        let spans = [span("a", None), late, span("b", Some("a")), span("d", Some("b"))];
+        let tree = TraceTree::new(&spans);
+
+        let ids = |children: Vec<&StoredSpan>| children.iter().map(|span| span.span_id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(tree.children("a")), ["b", "c"]);
+        assert!(tree.children("d").is_empty());
+        assert!(tree.span("x").is_none());
+    }
+}
@@ -0,0 +1,327 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::config::{Config, ProcessingConfig};
+use crate::core::EngineControl;
+use crate::error::ConfigError;
+use crate::rate_limit::TokenBucket;
+use crate::telemetry::LogFilterHandle;
+
+/// Outcome of applying a reloaded configuration
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReloadReport {
+    /// Settings changed at runtime, as `name: old -> new`
+    pub applied: Vec<String>,
+    /// Changed settings left as they were, with the reason
+    pub rejected: Vec<String>,
+}
+
+/// Re-reads the config file and applies the settings that are safe to change
+/// while running: batch size and timeout, log level and rate limits. Other
+/// changes, such as bind addresses or the storage backend, are rejected and
+/// take effect only after a restart.
+pub struct ConfigReloader {
+    path: PathBuf,
+    /// Configuration in effect: the loaded one with applied changes
+    current: watch::Sender<Config>,
+    engine: EngineControl,
+    rate_limiter: Option<Arc<TokenBucket>>,
+    log_filter: Option<LogFilterHandle>,
+}
+
+impl ConfigReloader {
+    /// Creates a reloader for the file `current` was loaded from
+    pub fn new(path: impl Into<PathBuf>, current: Config, engine: EngineControl) -> Self {
+        Self {
+            path: path.into(),
+            current: watch::channel(current).0,
+            engine,
+            rate_limiter: None,
+            log_filter: None,
+        }
+    }
+
+    /// Returns a receiver of the configuration in effect, updated by each reload
+    pub fn subscribe(&self) -> watch::Receiver<Config> {
+        self.current.subscribe()
+    }
+
+    /// Sets the limiter of gRPC exports, whose limits reloads update
+    pub fn with_rate_limiter(mut self, rate_limiter: Option<Arc<TokenBucket>>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    /// Sets the handle through which reloads change the log level
+    pub fn with_log_filter(mut self, log_filter: LogFilterHandle) -> Self {
+        self.log_filter = Some(log_filter);
+        self
+    }
+
+    /// Loads the config file and applies it; an invalid file changes nothing
+    pub fn reload(&self) -> Result<ReloadReport, ConfigError> {
+        let config = Config::from_file(&self.path)?;
+        Ok(self.apply(config))
+    }
+
+    /// Applies the safe changes in `config` and reports the rest as rejected
+    pub fn apply(&self, config: Config) -> ReloadReport {
+        let mut report = ReloadReport::default();
+        let mut current = self.current.borrow().clone();
+        self.apply_batching(&mut current, &config, &mut report);
+        self.apply_rate_limit(&mut current, &config, &mut report);
+        self.apply_log_level(&mut current, &config, &mut report);
+        reject_unsafe(&current, &config, &mut report);
+        self.current.send_replace(current);
+        report
+    }
+
+    /// Diffs batching against the engine, which may run values set through
+    /// `POST /admin/processing` rather than those last loaded
+    fn apply_batching(&self, current: &mut Config, config: &Config, report: &mut ReloadReport) {
+        let running = self.engine.processing_config();
+        let new = &config.processing;
+        let mut changes = Vec::new();
+        if running.batch_size != new.batch_size {
+            changes.push(format!("processing.batch_size: {} -> {}", running.batch_size, new.batch_size));
+        }
+        if running.batch_timeout_ms != new.batch_timeout_ms {
+            changes.push(format!("processing.batch_timeout_ms: {} -> {}", running.batch_timeout_ms, new.batch_timeout_ms));
+        }
+        if changes.is_empty() {
+            return;
+        }
+        let processing = ProcessingConfig {
+            batch_size: new.batch_size,
+            batch_timeout_ms: new.batch_timeout_ms,
+            ..running
+        };
+        match self.engine.update_processing(processing) {
+            Ok(()) => {
+                report.applied.extend(changes);
+                current.processing.batch_size = new.batch_size;
+                current.processing.batch_timeout_ms = new.batch_timeout_ms;
+            }
+            Err(e) => report.rejected.push(format!("processing batching: {}", e)),
+        }
+    }
+
+    fn apply_rate_limit(&self, current: &mut Config, config: &Config, report: &mut ReloadReport) {
+        let (old, new) = (&current.rate_limit, &config.rate_limit);
+        if old == new {
+            return;
+        }
+        // A limiter is only installed when the engine starts limited, and stays installed
+        match &self.rate_limiter {
+            Some(limiter) if new.max_requests_per_sec > 0.0 => {
+                limiter.set_limits(new.max_requests_per_sec, new.burst);
+                report.applied.push(format!(
+                    "rate_limit: {}/s (burst {}) -> {}/s (burst {})",
+                    old.max_requests_per_sec, old.burst, new.max_requests_per_sec, new.burst
+                ));
+                current.rate_limit = new.clone();
+            }
+            _ => report.rejected.push("rate_limit: enabling or disabling the limit requires a restart".into()),
+        }
+    }
+
+    fn apply_log_level(&self, current: &mut Config, config: &Config, report: &mut ReloadReport) {
+        let (old, new) = (&current.logging.level, &config.logging.level);
+        if old == new {
+            return;
+        }
+        let result = match &self.log_filter {
+            Some(handle) => handle.set(new.as_deref()),
+            None => Err("no reloadable log filter installed".into()),
+        };
+        match result {
+            Ok(()) => {
+                report.applied.push(format!("logging.level: {:?} -> {:?}", old, new));
+                current.logging.level = new.clone();
+            }
+            Err(e) => report.rejected.push(format!("logging.level: {}", e)),
+        }
+    }
+
+    /// Reloads on every SIGHUP, logging applied and rejected changes
+    #[cfg(unix)]
+    pub async fn run(self) -> std::io::Result<()> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangups = signal(SignalKind::hangup())?;
+        info!("Reloading {} on SIGHUP", self.path.display());
+        while hangups.recv().await.is_some() {
+            match self.reload() {
+                Ok(report) => {
+                    for change in &report.applied {
+                        info!("Config reload applied {}", change);
+                    }
+                    for change in &report.rejected {
+                        warn!("Config reload ignored {}", change);
+                    }
+                    if report.applied.is_empty() && report.rejected.is_empty() {
+                        info!("Config reload found no changes");
+                    }
+                }
+                Err(e) => warn!("Config reload failed, keeping current settings: {}", e),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reports unsafe changes and any other changed section as rejected
+fn reject_unsafe(current: &Config, config: &Config, report: &mut ReloadReport) {
+    let addresses = [
+        ("server", (&current.server.host, current.server.port), (&config.server.host, config.server.port)),
+        ("reader", (&current.reader.host, current.reader.port), (&config.reader.host, config.reader.port)),
+        ("ops", (&current.ops.host, current.ops.port), (&config.ops.host, config.ops.port)),
+    ];
+    for (section, old, new) in addresses {
+        if old != new {
+            report.rejected.push(format!(
+                "{} bind address: {}:{} -> {}:{} requires a restart",
+                section, old.0, old.1, new.0, new.1
+            ));
+        }
+    }
+    if current.storage.backend != config.storage.backend {
+        report.rejected.push(format!(
+            "storage.backend: {} -> {} requires a restart",
+            current.storage.backend, config.storage.backend
+        ));
+    }
+
+    // Any other changed section is left as it is, including unsafe changes
+    // already reported and reloadable settings that failed to apply
+    let mut remaining = config.clone();
+    remaining.processing.batch_size = current.processing.batch_size;
+    remaining.processing.batch_timeout_ms = current.processing.batch_timeout_ms;
+    remaining.rate_limit = current.rate_limit.clone();
+    remaining.logging = current.logging.clone();
+    (remaining.server.host, remaining.server.port) = (current.server.host.clone(), current.server.port);
+    (remaining.reader.host, remaining.reader.port) = (current.reader.host.clone(), current.reader.port);
+    (remaining.ops.host, remaining.ops.port) = (current.ops.host.clone(), current.ops.port);
+    remaining.storage.backend = current.storage.backend;
+    let (Ok(serde_json::Value::Object(old)), Ok(serde_json::Value::Object(new))) =
+        (serde_json::to_value(current), serde_json::to_value(&remaining))
+    else {
+        return;
+    };
+    for (section, value) in new {
+        if old.get(&section) != Some(&value) {
+            report.rejected.push(format!("{}: changes require a restart", section));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ProcessingConfig, StorageBackend};
+    use crate::core::EngineCore;
+    use crate::test_support::MockStorage;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+    use tokio::sync::mpsc;
+
+    fn base_config() -> Config {
+        Config::from_file("config/production.yaml").unwrap()
+    }
+
+    fn reloader(config: &Config) -> (ConfigReloader, EngineControl) {
+        let (_tx, rx) = mpsc::channel(1);
+        let engine = EngineCore::with_storage(rx, config.processing.clone(), Arc::new(MockStorage::new()));
+        let control = engine.control();
+        (ConfigReloader::new("unused.yaml", config.clone(), control.clone()), control)
+    }
+
+    fn write_config(config: &Config) -> NamedTempFile {
+        let mut file = NamedTempFile::with_suffix(".yaml").unwrap();
+        file.write_all(serde_yaml::to_string(config).unwrap().as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_reload_applies_batch_size() {
+        let config = base_config();
+        let (reloader, control) = reloader(&config);
+        let mut changed = config.clone();
+        changed.processing.batch_size = 250;
+        let file = write_config(&changed);
+        let reloader = ConfigReloader { path: file.path().into(), ..reloader };
+
+        let report = reloader.reload().unwrap();
+        assert_eq!(report.applied, vec!["processing.batch_size: 100 -> 250".to_string()]);
+        assert!(report.rejected.is_empty(), "{:?}", report.rejected);
+        assert_eq!(control.processing_config().batch_size, 250);
+        assert_eq!(control.processing_config().batch_timeout_ms, config.processing.batch_timeout_ms);
+
+        assert_eq!(reloader.subscribe().borrow().processing.batch_size, 250);
+
+        // Reloading the same file again changes nothing
+        assert_eq!(reloader.reload().unwrap(), ReloadReport::default());
+    }
+
+    #[test]
+    fn test_batching_diffed_against_engine() {
+        let config = base_config();
+        let (reloader, control) = reloader(&config);
+        // As if changed through POST /admin/processing
+        control.update_processing(ProcessingConfig { batch_size: 7, ..config.processing.clone() }).unwrap();
+        let mut changed = config.clone();
+        changed.processing.batch_timeout_ms = 1000;
+
+        let report = reloader.apply(changed);
+        assert_eq!(report.applied, vec![
+            "processing.batch_size: 7 -> 100".to_string(),
+            "processing.batch_timeout_ms: 5000 -> 1000".to_string(),
+        ]);
+        assert_eq!((control.processing_config().batch_size, control.processing_config().batch_timeout_ms), (100, 1000));
+    }
+
+    #[test]
+    fn test_unsafe_changes_rejected() {
+        let config = base_config();
+        let (reloader, control) = reloader(&config);
+        let mut changed = config.clone();
+        changed.server.port = 4317;
+        changed.storage.backend = StorageBackend::Null;
+        changed.processing.worker_count = 8;
+        changed.processing.batch_timeout_ms = 1000;
+
+        let report = reloader.apply(changed);
+        assert_eq!(report.applied, vec!["processing.batch_timeout_ms: 5000 -> 1000".to_string()]);
+        assert_eq!(
+            report.rejected,
+            vec![
+                "server bind address: 0.0.0.0:50051 -> 0.0.0.0:4317 requires a restart".to_string(),
+                "storage.backend: s3 -> null requires a restart".to_string(),
+                "processing: changes require a restart".to_string(),
+            ]
+        );
+        assert_eq!(control.processing_config(), ProcessingConfig { batch_timeout_ms: 1000, ..config.processing });
+    }
+
+    #[test]
+    fn test_rate_limit_reloaded() {
+        let config = base_config();
+        let limiter = config.rate_limit.limiter().map(Arc::new);
+        let (reloader, _control) = reloader(&config);
+        let reloader = reloader.with_rate_limiter(limiter.clone());
+        let mut changed = config.clone();
+        changed.rate_limit.max_requests_per_sec = 50.0;
+
+        let report = reloader.apply(changed.clone());
+        assert_eq!(report.applied, vec!["rate_limit: 2000/s (burst 500) -> 50/s (burst 500)".to_string()]);
+        assert_eq!(limiter.unwrap().rate(), 50.0);
+
+        changed.rate_limit.max_requests_per_sec = 0.0;
+        let report = reloader.apply(changed);
+        assert!(report.applied.is_empty());
+        assert_eq!(report.rejected, vec!["rate_limit: enabling or disabling the limit requires a restart".to_string()]);
+    }
+}
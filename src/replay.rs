@@ -0,0 +1,245 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tracing::{info, warn};
+
+use crate::error::StorageError;
+use crate::storage::{StorageReader, StorageWriter, StoredSpan};
+
+/// Spans written to the destination per call
+const CHUNK_SPANS: usize = 1000;
+
+/// Objects listed and read at a time from a source backend
+const PAGE_KEYS: usize = 1000;
+
+/// Counts reported at the end of a replay
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReplaySummary {
+    /// Spans written back to storage
+    pub replayed: usize,
+    /// Records that could not be parsed and were skipped
+    pub skipped: usize,
+    /// Spans that failed to write
+    pub failed: usize,
+}
+
+/// Re-ingests previously stored or exported spans into a storage backend.
+/// Accepts NDJSON files (one `StoredSpan` per line, as produced by
+/// `GET /spans/export`), per-object JSON files, directories of either,
+/// or another storage backend such as a bucket prefix. Spans are written
+/// in chunks through the writer's normal layout, so only one chunk (or,
+/// from a backend, one listing page) is held in memory at a time.
+pub struct SpanReplayer {
+    /// Destination for replayed spans
+    writer: Arc<dyn StorageWriter>,
+    /// Objects listed and read at a time from a source backend
+    page_size: usize,
+}
+
+impl SpanReplayer {
+    /// Creates a new SpanReplayer writing to the given backend
+    pub fn new(writer: Arc<dyn StorageWriter>) -> Self {
+        Self { writer, page_size: PAGE_KEYS }
+    }
+
+    /// Sets how many objects are listed and read at a time from a source backend
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Replays a single file or every `.json`/`.ndjson` file under a directory.
+    /// NDJSON files are read line by line.
+    pub async fn replay_path(&self, path: &Path) -> Result<ReplaySummary, StorageError> {
+        let mut summary = ReplaySummary::default();
+        let mut pending = Vec::new();
+
+        for file in collect_files(path)? {
+            let read_error = |e: std::io::Error| StorageError::ReadFailed(format!("{}: {}", file.display(), e));
+            if is_ndjson(&file) {
+                let mut lines = BufReader::new(File::open(&file).await.map_err(read_error)?).lines();
+                while let Some(line) = lines.next_line().await.map_err(read_error)? {
+                    if !line.trim().is_empty() {
+                        parse_record(&line, &file, &mut pending, &mut summary);
+                    }
+                    if pending.len() >= CHUNK_SPANS {
+                        self.write_chunk(&mut pending, &mut summary).await;
+                    }
+                }
+            } else {
+                let contents = tokio::fs::read_to_string(&file).await.map_err(read_error)?;
+                parse_record(&contents, &file, &mut pending, &mut summary);
+                if pending.len() >= CHUNK_SPANS {
+                    self.write_chunk(&mut pending, &mut summary).await;
+                }
+            }
+        }
+        self.write_chunk(&mut pending, &mut summary).await;
+
+        info!(
+            "Replay finished: {} replayed, {} skipped, {} failed",
+            summary.replayed, summary.skipped, summary.failed
+        );
+        Ok(summary)
+    }
+
+    /// Replays every span stored in another backend, one listing page at a time
+    pub async fn replay_from(&self, source: &dyn StorageReader) -> Result<ReplaySummary, StorageError> {
+        let mut summary = ReplaySummary::default();
+        let mut continuation = None;
+
+        loop {
+            let (entries, next) = source.list_span_page(continuation, self.page_size).await?;
+            let keys: Vec<String> = entries.into_iter().map(|entry| entry.key).collect();
+
+            let mut pending = Vec::new();
+            for result in source.read_spans(&keys).await {
+                match result {
+                    Ok(span) => pending.push(span),
+                    Err(e) => {
+                        warn!("Skipping unreadable object: {}", e);
+                        summary.skipped += 1;
+                    }
+                }
+                if pending.len() >= CHUNK_SPANS {
+                    self.write_chunk(&mut pending, &mut summary).await;
+                }
+            }
+            self.write_chunk(&mut pending, &mut summary).await;
+
+            match next {
+                Some(next) => continuation = Some(next),
+                None => break,
+            }
+        }
+
+        info!(
+            "Replay finished: {} replayed, {} skipped, {} failed",
+            summary.replayed, summary.skipped, summary.failed
+        );
+        Ok(summary)
+    }
+
+    /// Writes the pending spans through the writer's write path, updating the summary
+    async fn write_chunk(&self, pending: &mut Vec<StoredSpan>, summary: &mut ReplaySummary) {
+        if pending.is_empty() {
+            return;
+        }
+        let count = pending.len();
+        match self.writer.write_stored_spans(std::mem::take(pending)).await {
+            Ok(()) => {
+                summary.replayed += count;
+                info!(
+                    "Replayed {} spans so far ({} skipped, {} failed)",
+                    summary.replayed, summary.skipped, summary.failed
+                );
+            }
+            Err(e) => {
+                warn!("Failed to replay {} spans: {}", count, e);
+                summary.failed += count;
+            }
+        }
+    }
+}
+
+/// Splits an `s3://bucket/prefix` source into its bucket and prefix,
+/// `None` for other sources
+pub fn s3_location(source: &str) -> Option<(&str, &str)> {
+    let location = source.strip_prefix("s3://")?;
+    let (bucket, prefix) = location.split_once('/').unwrap_or((location, ""));
+    Some((bucket, prefix.trim_end_matches('/')))
+}
+
+/// Parses one record into `pending`, counting it as skipped when it is not a span
+fn parse_record(record: &str, file: &Path, pending: &mut Vec<StoredSpan>, summary: &mut ReplaySummary) {
+    match serde_json::from_str::<StoredSpan>(record) {
+        Ok(mut span) => {
+            span.normalize_ids();
+            pending.push(span);
+        }
+        Err(e) => {
+            warn!("Skipping unparseable record in {}: {}", file.display(), e);
+            summary.skipped += 1;
+        }
+    }
+}
+
+/// Returns whether a file holds newline-delimited records
+fn is_ndjson(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("ndjson") | Some("jsonl")
+    )
+}
+
+/// Lists the replayable files at `path`, recursing into directories in sorted order
+fn collect_files(path: &Path) -> Result<Vec<PathBuf>, StorageError> {
+    let read_error = |e: std::io::Error| StorageError::ReadFailed(format!("{}: {}", path.display(), e));
+
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(path)
+        .map_err(read_error)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()
+        .map_err(read_error)?;
+    entries.sort();
+
+    let mut files = Vec::new();
+    for entry in entries {
+        if entry.is_dir() {
+            files.extend(collect_files(&entry)?);
+        } else if is_ndjson(&entry) || entry.extension().and_then(|ext| ext.to_str()) == Some("json") {
+            files.push(entry);
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockStorage;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn span_line(span_id: &str) -> String {
+        format!(
+            r#"{{"trace_id":"{}","span_id":"{}","name":"checkout","kind":"Server","start_time":1,"end_time":2,"status":"Ok"}}"#,
+            "01".repeat(16),
+            span_id
+        )
+    }
+
+    #[tokio::test]
+    async fn test_replay_ndjson_file() {
+        let mut file = NamedTempFile::with_suffix(".ndjson").unwrap();
+        writeln!(file, "{}", span_line("0000000000000001")).unwrap();
+        writeln!(file, "not json").unwrap();
+        writeln!(file).unwrap();
+        writeln!(file, "{}", span_line("0000000000000002")).unwrap();
+
+        let storage = Arc::new(MockStorage::new());
+        let replayer = SpanReplayer::new(storage.clone());
+        let summary = replayer.replay_path(file.path()).await.unwrap();
+
+        assert_eq!(summary, ReplaySummary { replayed: 2, skipped: 1, failed: 0 });
+
+        let objects = storage.objects();
+        assert_eq!(objects[0].0, format!("{}/0000000000000001.json", "01".repeat(16)));
+        let span: StoredSpan = serde_json::from_slice(&objects[1].1).unwrap();
+        assert_eq!(span.span_id, "0000000000000002");
+        assert_eq!(span.name, "checkout");
+    }
+
+    #[test]
+    fn test_s3_location() {
+        assert_eq!(s3_location("s3://backup/spans/"), Some(("backup", "spans")));
+        assert_eq!(s3_location("s3://backup/a/b"), Some(("backup", "a/b")));
+        assert_eq!(s3_location("s3://backup"), Some(("backup", "")));
+        assert_eq!(s3_location("exports/spans.ndjson"), None);
+    }
+}
@@ -0,0 +1,71 @@
+/// Trace-consistent sampler keeping a fixed fraction of traces.
+/// The decision is a hash of the trace id, so every span of a trace is
+/// kept or dropped together, across batches and engine restarts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TraceSampler {
+    /// Fraction of traces kept, from 0.0 to 1.0
+    ratio: f64,
+}
+
+impl TraceSampler {
+    /// Creates a sampler keeping `ratio` of all traces
+    pub fn new(ratio: f64) -> Self {
+        Self { ratio }
+    }
+
+    /// Returns whether spans of the given trace are stored
+    pub fn keeps(&self, trace_id: &[u8]) -> bool {
+        if self.ratio >= 1.0 {
+            return true;
+        }
+        (trace_hash(trace_id) as f64) < self.ratio * u64::MAX as f64
+    }
+}
+
+impl Default for TraceSampler {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+/// Hashes a trace id uniformly over `u64`: FNV-1a, stable across builds
+/// unlike `DefaultHasher`, followed by the MurmurHash3 finalizer so ids
+/// differing only in their last bytes still spread over the high bits
+pub(crate) fn trace_hash(bytes: &[u8]) -> u64 {
+    let mut hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    hash ^ (hash >> 33)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace_id(n: u32) -> Vec<u8> {
+        let mut id = vec![0; 16];
+        id[12..].copy_from_slice(&n.to_be_bytes());
+        id
+    }
+
+    #[test]
+    fn test_ratio_bounds() {
+        let (all, none) = (TraceSampler::new(1.0), TraceSampler::new(0.0));
+        for n in 0..100 {
+            assert!(all.keeps(&trace_id(n)));
+            assert!(!none.keeps(&trace_id(n)));
+        }
+    }
+
+    #[test]
+    fn test_decision_is_deterministic() {
+        let sampler = TraceSampler::new(0.3);
+        for n in 0..100 {
+            assert_eq!(sampler.keeps(&trace_id(n)), TraceSampler::new(0.3).keeps(&trace_id(n)));
+        }
+    }
+}
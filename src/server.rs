@@ -1,50 +1,259 @@
+use crate::auth::BearerAuth;
+use crate::config::ServerConfig;
+use crate::convert::SpanConverter;
+use crate::core::MessageSender;
 use crate::error::ProcessingError;
 use crate::proto::{
     TraceService,
+    TraceServiceServer,
     ExportTraceServiceRequest,
     ExportTraceServiceResponse,
+    ResourceSpans,
 };
-use tokio::sync::mpsc;
-use tonic::{Request, Response, Status};
+#[cfg(feature = "bulk-export")]
+use crate::proto::storage_engine::bulk::v1::{
+    bulk_trace_service_server::{BulkTraceService, BulkTraceServiceServer},
+    ExportChunk,
+    ExportSummary,
+};
+use tokio::net::TcpListener;
+use tonic::body::BoxBody;
+use tonic::codec::CompressionEncoding;
+use tonic::service::interceptor::InterceptedService;
+use tonic::metadata::MetadataValue;
+use tonic::{Code, Request, Response, Status};
+use tower::limit::ConcurrencyLimitLayer;
+use tower::util::MapResponseLayer;
 use std::sync::Arc;
 use crate::health::{HealthCheck, HealthStatus};
-use tracing::{info, warn, error};
-use std::time::Duration;
+use crate::rate_limit::TokenBucket;
+use tracing::{info, instrument, warn, error};
+use std::time::{Duration, SystemTime};
+
+/// Default for how long `shutdown` waits for queued spans to be written
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often `shutdown` checks whether queued spans have been written
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Invalid spans named in a rejection; further ones are only counted
+const MAX_LISTED_INVALID_SPANS: usize = 10;
+
+/// Metadata telling a rate-limited client how many milliseconds to wait,
+/// as defined for gRPC retry pushback
+pub const RETRY_PUSHBACK_METADATA: &str = "grpc-retry-pushback-ms";
 
 /// Server component that handles gRPC trace collection requests.
 /// Forwards received traces to the processing engine via channels.
 pub struct ListenerServer {
     /// Channel for sending messages to the processing engine
-    message_sender: mpsc::Sender<ExportTraceServiceRequest>,
+    message_sender: MessageSender,
     /// Health monitoring for the server
     health_check: Arc<HealthCheck>,
+    /// How long `shutdown` waits for the engine to write queued spans
+    shutdown_timeout: Duration,
+    /// Most spans accepted in one request
+    max_spans_per_request: usize,
+    /// Most `resource_spans` entries accepted in one request
+    max_resource_spans: usize,
+    /// Checks spans before their request is queued, when validating on ingest
+    validator: Option<SpanConverter>,
+    /// Limit on export requests per second, shared by every service using it
+    rate_limiter: Option<Arc<TokenBucket>>,
 }
 
 impl ListenerServer {
     /// Creates a new ListenerServer instance
     pub fn new(
-        sender: mpsc::Sender<ExportTraceServiceRequest>,
+        sender: MessageSender,
         health_check: Arc<HealthCheck>,
     ) -> Self {
         Self {
             message_sender: sender,
             health_check,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            max_spans_per_request: usize::MAX,
+            max_resource_spans: usize::MAX,
+            validator: None,
+            rate_limiter: None,
         }
     }
 
+    /// Rejects requests carrying more than `max_spans_per_request` spans or
+    /// `max_resource_spans` resource entries; unlimited by default
+    pub fn with_ingest_limits(mut self, max_spans_per_request: usize, max_resource_spans: usize) -> Self {
+        self.max_spans_per_request = max_spans_per_request;
+        self.max_resource_spans = max_resource_spans;
+        self
+    }
+
+    /// Rejects requests containing spans `validator` would drop at conversion
+    /// with `INVALID_ARGUMENT` naming each offending span; `None` queues them
+    pub fn with_validation(mut self, validator: Option<SpanConverter>) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Rejects requests with `RESOURCE_EXHAUSTED` once `limiter` runs out of
+    /// tokens; unlimited by default
+    pub fn with_rate_limit(mut self, limiter: Arc<TokenBucket>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Sets how long `shutdown` waits for queued spans to be written
+    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
     /// Returns the current health status of the server
     pub fn get_health_status(&self) -> HealthStatus {
         self.health_check.get_health_status()
     }
 
+    /// Wraps the listener in a `TraceServiceServer` configured from `ServerConfig`,
+    /// guarded by the bearer-token interceptor
+    pub fn into_service(
+        self,
+        config: &ServerConfig,
+        auth: BearerAuth,
+    ) -> InterceptedService<TraceServiceServer<Self>, BearerAuth> {
+        let mut service = TraceServiceServer::new(self)
+            .max_decoding_message_size(config.max_decoding_message_size);
+
+        if config.accept_gzip {
+            service = service.accept_compressed(CompressionEncoding::Gzip);
+        }
+
+        InterceptedService::new(service, auth)
+    }
+
+    /// Wraps the listener in a `BulkTraceServiceServer`, configured and
+    /// guarded like `into_service`; the size limit applies per chunk
+    #[cfg(feature = "bulk-export")]
+    pub fn into_bulk_service(
+        self,
+        config: &ServerConfig,
+        auth: BearerAuth,
+    ) -> InterceptedService<BulkTraceServiceServer<Self>, BearerAuth> {
+        let mut service = BulkTraceServiceServer::new(self)
+            .max_decoding_message_size(config.max_decoding_message_size);
+
+        if config.accept_gzip {
+            service = service.accept_compressed(CompressionEncoding::Gzip);
+        }
+
+        InterceptedService::new(service, auth)
+    }
+
+    /// Marks the server unhealthy and waits until every queued message has
+    /// been taken by the engine and each of its spans written, spilled or
+    /// dropped, failing with what is left once `shutdown_timeout` elapses.
+    /// The engine must have been told to shut down, or buffered traces and
+    /// a partial batch are not written until their timers fire.
     pub async fn shutdown(&self) -> Result<(), ProcessingError> {
         info!("Server shutting down gracefully...");
         self.health_check.update_status(false);
-        
-        // Wait for pending messages to be processed
-        tokio::time::sleep(Duration::from_secs(1)).await;
-        
-        Ok(())
+
+        let deadline = tokio::time::Instant::now() + self.shutdown_timeout;
+        loop {
+            let (messages, spans) = self.unwritten();
+            if messages == 0 && spans == 0 {
+                info!("Queued spans written");
+                return Ok(());
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(ProcessingError::ShutdownError(format!(
+                    "{} messages and {} spans still unwritten after {:?}",
+                    messages, spans, self.shutdown_timeout
+                )));
+            }
+            tokio::time::sleep(SHUTDOWN_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Takes a token from the rate limiter, if any, returning the rejection
+    /// when none is left; it carries the wait until the next token in
+    /// `RETRY_PUSHBACK_METADATA` and is counted
+    fn rate_limited(&self) -> Option<Status> {
+        let limiter = self.rate_limiter.as_ref()?;
+        let wait = limiter.try_acquire().err()?;
+
+        self.health_check.record_rate_limited();
+        let wait_ms = wait.as_millis().max(1) as u64;
+        let mut status = Status::from(ProcessingError::RateLimitExceeded(format!(
+            "export rate above {} requests per second; retry after {}ms",
+            limiter.rate(), wait_ms
+        )));
+        status.metadata_mut().insert(RETRY_PUSHBACK_METADATA, MetadataValue::from(wait_ms));
+        Some(status)
+    }
+
+    /// Checks a request against the ingest limits before it is queued,
+    /// counting rejections
+    fn check_limits(&self, resource_spans: &[ResourceSpans]) -> Result<(), ProcessingError> {
+        let violation = if resource_spans.len() > self.max_resource_spans {
+            Some(format!(
+                "request has {} resource_spans, above max_resource_spans of {}",
+                resource_spans.len(), self.max_resource_spans
+            ))
+        } else {
+            let spans: usize = resource_spans
+                .iter()
+                .flat_map(|resource_spans| &resource_spans.scope_spans)
+                .map(|scope_spans| scope_spans.spans.len())
+                .sum();
+            (spans > self.max_spans_per_request).then(|| format!(
+                "request has {} spans, above max_spans_per_request of {}",
+                spans, self.max_spans_per_request
+            ))
+        };
+
+        match violation {
+            Some(message) => {
+                self.health_check.record_rejected_request();
+                Err(ProcessingError::ValidationError(message))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Checks every span's ids and start time against the conversion limits
+    /// when validating on ingest, counting rejections
+    fn check_spans(&self, resource_spans: &[ResourceSpans]) -> Result<(), ProcessingError> {
+        let Some(validator) = &self.validator else {
+            return Ok(());
+        };
+        let now = SystemTime::now();
+        let mut invalid = Vec::new();
+        for (r, resource_spans) in resource_spans.iter().enumerate() {
+            for (s, scope_spans) in resource_spans.scope_spans.iter().enumerate() {
+                for (i, span) in scope_spans.spans.iter().enumerate() {
+                    if let Err(ProcessingError::ValidationError(reason)) = validator.validate(span, now) {
+                        invalid.push(format!("resource_spans[{}].scope_spans[{}].spans[{}]: {}", r, s, i, reason));
+                    }
+                }
+            }
+        }
+        if invalid.is_empty() {
+            return Ok(());
+        }
+
+        self.health_check.record_rejected_request();
+        let listed = &invalid[..invalid.len().min(MAX_LISTED_INVALID_SPANS)];
+        let mut message = format!("{} invalid spans: {}", invalid.len(), listed.join("; "));
+        if invalid.len() > MAX_LISTED_INVALID_SPANS {
+            message.push_str("; ...");
+        }
+        Err(ProcessingError::ValidationError(message))
+    }
+
+    /// Messages still waiting in the channel, and spans the engine has
+    /// taken but not finished writing
+    fn unwritten(&self) -> (u64, u64) {
+        let in_channel = self.message_sender.max_capacity() - self.message_sender.capacity();
+        (in_channel as u64, self.health_check.unwritten_spans())
     }
 }
 
@@ -58,11 +267,17 @@ impl TraceService for ListenerServer {
     /// # Returns
     /// * `Ok(Response)` - If the traces were successfully queued
     /// * `Err(Status)` - If there was an error processing the request
+    #[instrument(skip_all)]
     async fn export(
         &self,
         request: Request<ExportTraceServiceRequest>,
     ) -> Result<Response<ExportTraceServiceResponse>, Status> {
+        if let Some(status) = self.rate_limited() {
+            return Err(status);
+        }
         let message = request.into_inner();
+        self.check_limits(&message.resource_spans)?;
+        self.check_spans(&message.resource_spans)?;
 
         // Attempt to send message to processing engine
         match self.message_sender.send(message).await {
@@ -71,13 +286,104 @@ impl TraceService for ListenerServer {
                 Ok(Response::new(ExportTraceServiceResponse {}))
             }
             Err(e) => {
+                // The engine closes its channel while draining for shutdown
                 warn!("Failed to queue trace data: {}", e);
-                Err(Status::internal("Failed to queue trace data"))
+                Err(Status::unavailable("Engine is shutting down; trace data not queued"))
             }
         }
     }
 }
 
+#[cfg(feature = "bulk-export")]
+#[tonic::async_trait]
+impl BulkTraceService for ListenerServer {
+    /// Queues each chunk of a streamed export as its own request as soon as
+    /// it arrives, so only one chunk is held in memory at a time. Chunks
+    /// queued before a failure stay queued; the error reports how many.
+    #[instrument(skip_all)]
+    async fn export_stream(
+        &self,
+        request: Request<tonic::Streaming<ExportChunk>>,
+    ) -> Result<Response<ExportSummary>, Status> {
+        // A stream takes one token however many chunks it carries
+        if let Some(status) = self.rate_limited() {
+            return Err(status);
+        }
+        let mut chunks = request.into_inner();
+        let mut summary = ExportSummary::default();
+
+        while let Some(chunk) = chunks.message().await? {
+            // Limits apply per chunk; earlier chunks stay queued
+            self.check_limits(&chunk.resource_spans)?;
+            self.check_spans(&chunk.resource_spans)?;
+            let spans: usize = chunk.resource_spans
+                .iter()
+                .flat_map(|resource_spans| &resource_spans.scope_spans)
+                .map(|scope_spans| scope_spans.spans.len())
+                .sum();
+            let resource_spans = chunk.resource_spans.len();
+            let message = ExportTraceServiceRequest { resource_spans: chunk.resource_spans };
+
+            if let Err(e) = self.message_sender.send(message).await {
+                warn!("Failed to queue chunk {} of bulk export: {}", summary.chunks + 1, e);
+                return Err(Status::unavailable(format!(
+                    "Engine is shutting down; {} chunks were queued before it stopped",
+                    summary.chunks
+                )));
+            }
+            summary.chunks += 1;
+            summary.resource_spans += resource_spans as u64;
+            summary.spans += spans as u64;
+        }
+
+        info!(
+            "Queued bulk export of {} chunks ({} spans) for processing",
+            summary.chunks, summary.spans
+        );
+        Ok(Response::new(summary))
+    }
+}
+
+/// Builds a layer that reports oversized requests as `RESOURCE_EXHAUSTED`.
+///
+/// Tonic rejects messages above `max_decoding_message_size` with `OUT_OF_RANGE`
+/// before the handler runs; this rewrites that rejection into a status naming
+/// the configured limit so exporters can be tuned accordingly.
+pub fn message_size_layer(
+    limit: usize,
+) -> MapResponseLayer<impl Fn(http::Response<BoxBody>) -> http::Response<BoxBody> + Clone> {
+    MapResponseLayer::new(move |response: http::Response<BoxBody>| {
+        let out_of_range = Status::from_header_map(response.headers())
+            .map(|status| status.code() == Code::OutOfRange)
+            .unwrap_or(false);
+
+        if !out_of_range {
+            return response;
+        }
+
+        Status::resource_exhausted(format!(
+            "Request exceeds max_decoding_message_size of {} bytes",
+            limit
+        ))
+        .to_http()
+    })
+}
+
+/// Builds a layer admitting at most `max_requests` concurrent export
+/// requests across all connections. Requests beyond the limit wait for a
+/// slot instead of piling up in the engine channel and task scheduler.
+pub fn concurrency_limit_layer(max_requests: usize) -> ConcurrencyLimitLayer {
+    ConcurrencyLimitLayer::new(max_requests)
+}
+
+/// Binds a TCP listener to `host:port`; port 0 picks an ephemeral port.
+/// The error names the address, so a failed startup says what was taken.
+pub async fn bind_listener(host: &str, port: u16) -> std::io::Result<TcpListener> {
+    TcpListener::bind((host, port)).await.map_err(|e| {
+        std::io::Error::new(e.kind(), format!("failed to bind {}:{}: {}", host, port, e))
+    })
+}
+
 /// Converts processing errors to gRPC status codes
 impl From<ProcessingError> for Status {
     fn from(error: ProcessingError) -> Self {
@@ -113,13 +419,25 @@ impl From<ProcessingError> for Status {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::ProcessingConfig;
     use tokio::sync::mpsc;
 
+    #[tokio::test]
+    async fn test_bind_ephemeral_port() {
+        let listener = bind_listener("127.0.0.1", 0).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert_eq!(addr.ip().to_string(), "127.0.0.1");
+        assert_ne!(addr.port(), 0);
+
+        let error = bind_listener("127.0.0.1", addr.port()).await.unwrap_err();
+        assert!(error.to_string().contains(&format!("127.0.0.1:{}", addr.port())));
+    }
+
     #[tokio::test]
     async fn test_export_success() {
         let (tx, mut rx) = mpsc::channel(1);
         let health_check = Arc::new(HealthCheck::new());
-        let server = ListenerServer::new(tx, health_check);
+        let server = ListenerServer::new(tx.into(), health_check);
 
         // Create test request
         let request = Request::new(ExportTraceServiceRequest {
@@ -139,7 +457,7 @@ mod tests {
     async fn test_export_channel_closed() {
         let (tx, _rx) = mpsc::channel(1);
         let health_check = Arc::new(HealthCheck::new());
-        let server = ListenerServer::new(tx, health_check);
+        let server = ListenerServer::new(tx.into(), health_check);
 
         // Drop receiver to close channel
         drop(_rx);
@@ -153,4 +471,159 @@ mod tests {
         let response = server.export(request).await;
         assert!(response.is_err());
     }
+
+    fn request_with(resource_spans: usize, spans_each: usize) -> ExportTraceServiceRequest {
+        use crate::proto::opentelemetry::proto::trace::v1::{ScopeSpans, Span};
+        ExportTraceServiceRequest {
+            resource_spans: (0..resource_spans)
+                .map(|_| ResourceSpans {
+                    scope_spans: vec![ScopeSpans {
+                        spans: vec![Span::default(); spans_each],
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_oversized_requests_rejected() {
+        let (tx, mut rx) = mpsc::channel(10);
+        let health_check = Arc::new(HealthCheck::new());
+        let server = ListenerServer::new(tx.into(), Arc::clone(&health_check)).with_ingest_limits(10, 3);
+
+        for (request, field) in [
+            (request_with(4, 0), "max_resource_spans"),
+            (request_with(3, 4), "max_spans_per_request"),
+        ] {
+            let status = server.export(Request::new(request)).await.unwrap_err();
+            assert_eq!(status.code(), Code::InvalidArgument);
+            assert!(status.message().contains(field), "{}", status.message());
+        }
+        assert!(rx.try_recv().is_err());
+        assert_eq!(health_check.get_detailed_status().rejected_requests_total, 2);
+
+        // At the limits
+        server.export(Request::new(request_with(2, 5))).await.unwrap();
+        assert!(rx.try_recv().is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_requests_above_rate_rejected() {
+        let (tx, mut rx) = mpsc::channel(10);
+        let health_check = Arc::new(HealthCheck::new());
+        let server = ListenerServer::new(tx.into(), Arc::clone(&health_check))
+            .with_rate_limit(Arc::new(TokenBucket::new(10.0, 2)));
+        let export = || server.export(Request::new(ExportTraceServiceRequest { resource_spans: vec![] }));
+
+        // The burst is admitted at once, the rest is pushed back
+        assert!(export().await.is_ok());
+        assert!(export().await.is_ok());
+        let status = export().await.unwrap_err();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert_eq!(status.metadata().get(RETRY_PUSHBACK_METADATA).unwrap(), "100");
+        assert!(export().await.is_err());
+
+        // Requests spaced at the rate all succeed
+        for _ in 0..5 {
+            tokio::time::advance(Duration::from_millis(100)).await;
+            assert!(export().await.is_ok());
+        }
+
+        let mut queued = 0;
+        while rx.try_recv().is_ok() {
+            queued += 1;
+        }
+        assert_eq!(queued, 7);
+        assert_eq!(health_check.get_detailed_status().rate_limited_requests_total, 2);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_spans_rejected_on_ingest() {
+        use crate::proto::opentelemetry::proto::trace::v1::Span;
+        let (tx, mut rx) = mpsc::channel(10);
+        let health_check = Arc::new(HealthCheck::new());
+        let config = ProcessingConfig { max_span_age_secs: Some(3600), ..ProcessingConfig::default() };
+        let server = ListenerServer::new(tx.into(), Arc::clone(&health_check))
+            .with_validation(Some(SpanConverter::from(&config)));
+        let now = SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos() as u64;
+        let valid = Span {
+            trace_id: vec![1; 16],
+            span_id: vec![2; 8],
+            start_time_unix_nano: now,
+            end_time_unix_nano: now + 1_000,
+            ..Default::default()
+        };
+        let mut request = request_with(1, 4);
+        request.resource_spans[0].scope_spans[0].spans = vec![
+            valid.clone(),
+            Span { trace_id: vec![1; 3], ..valid.clone() },
+            Span { start_time_unix_nano: 1_000, ..valid.clone() },
+            // Accepted by conversion, so accepted here
+            Span { start_time_unix_nano: now, end_time_unix_nano: now - 1_000, ..valid.clone() },
+        ];
+
+        let status = server.export(Request::new(request.clone())).await.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(status.message().starts_with(
+            "2 invalid spans: resource_spans[0].scope_spans[0].spans[1]: trace_id must be 16 bytes, got 3; \
+             resource_spans[0].scope_spans[0].spans[2]: start_time is "
+        ), "{}", status.message());
+        assert!(status.message().ends_with("beyond max_span_age_secs of 3600"), "{}", status.message());
+        assert!(rx.try_recv().is_err());
+        assert_eq!(health_check.get_detailed_status().rejected_requests_total, 1);
+
+        // Without validation, the request is queued and bad spans are dropped later
+        let (tx, mut rx) = mpsc::channel(10);
+        ListenerServer::new(tx.into(), health_check).export(Request::new(request)).await.unwrap();
+        assert!(rx.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_waits_for_spans_to_be_written() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let health_check = Arc::new(HealthCheck::new());
+        let server = ListenerServer::new(tx.into(), Arc::clone(&health_check))
+            .with_shutdown_timeout(Duration::from_secs(5));
+        server.export(Request::new(ExportTraceServiceRequest::default())).await.unwrap();
+
+        // Stand in for the engine taking the message, then writing its spans
+        let (taken_tx, taken_rx) = tokio::sync::oneshot::channel();
+        let engine_health = Arc::clone(&health_check);
+        tokio::spawn(async move {
+            rx.recv().await.unwrap();
+            engine_health.record_spans_taken(2);
+            let _ = taken_tx.send(());
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            engine_health.record_spans_settled(2);
+            rx
+        });
+        taken_rx.await.unwrap();
+
+        // Nothing left in the channel, but the spans are still being written
+        let started = std::time::Instant::now();
+        server.shutdown().await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(40));
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(health_check.unwritten_spans(), 0);
+        assert!(!server.get_health_status().is_healthy);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_times_out_with_remaining_count() {
+        let (tx, _rx) = mpsc::channel(4);
+        let health_check = Arc::new(HealthCheck::new());
+        let server = ListenerServer::new(tx.into(), Arc::clone(&health_check))
+            .with_shutdown_timeout(Duration::from_millis(50));
+        server.export(Request::new(ExportTraceServiceRequest::default())).await.unwrap();
+        health_check.record_spans_taken(2);
+
+        match server.shutdown().await {
+            Err(ProcessingError::ShutdownError(msg)) => {
+                assert!(msg.starts_with("1 messages and 2 spans"), "{}", msg)
+            }
+            other => panic!("expected shutdown timeout, got {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,197 @@
+use std::collections::VecDeque;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tokio::fs;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+use crate::config::SpillConfig;
+use crate::error::StorageError;
+
+/// Extension of spill files; each holds one encoded export request
+const SPILL_EXTENSION: &str = "otlp";
+
+/// Spill files known to the buffer, oldest first
+#[derive(Debug, Default)]
+struct SpillFiles {
+    /// Paths with their sizes in bytes, oldest first
+    files: VecDeque<(PathBuf, u64)>,
+    /// Total size of `files`
+    total_bytes: u64,
+    /// Distinguishes files spilled within the same nanosecond
+    sequence: u64,
+}
+
+/// Local directory holding requests that could not be written to storage,
+/// kept until they can be uploaded. File names sort in spill order, so
+/// files left by a previous run are picked up oldest first. The directory
+/// is bounded by `max_bytes`: spilling into a full buffer deletes the
+/// oldest files first.
+#[derive(Debug)]
+pub struct SpillBuffer {
+    /// Directory spill files are written to
+    dir: PathBuf,
+    /// Total size of spill files kept at most
+    max_bytes: u64,
+    files: Mutex<SpillFiles>,
+}
+
+impl SpillBuffer {
+    /// Opens the spill directory, creating it if needed and indexing
+    /// files spilled by a previous run
+    pub async fn open(config: &SpillConfig) -> Result<Self, StorageError> {
+        let dir = PathBuf::from(&config.dir);
+        fs::create_dir_all(&dir).await.map_err(|e| io_error(&dir, e))?;
+
+        let mut found = Vec::new();
+        let mut entries = fs::read_dir(&dir).await.map_err(|e| io_error(&dir, e))?;
+        while let Some(entry) = entries.next_entry().await.map_err(|e| io_error(&dir, e))? {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == SPILL_EXTENSION) {
+                let size = entry.metadata().await.map_err(|e| io_error(&path, e))?.len();
+                found.push((path, size));
+            }
+        }
+        found.sort();
+
+        let files = SpillFiles {
+            total_bytes: found.iter().map(|(_, size)| size).sum(),
+            files: found.into(),
+            sequence: 0,
+        };
+        Ok(Self {
+            dir,
+            max_bytes: config.max_bytes,
+            files: Mutex::new(files),
+        })
+    }
+
+    /// Writes one encoded request to the buffer, deleting the oldest files
+    /// to stay within `max_bytes`. Returns the number of files deleted; a
+    /// request larger than `max_bytes` is not kept and counts as one.
+    pub async fn spill(&self, data: &[u8]) -> Result<u64, StorageError> {
+        let size = data.len() as u64;
+        if size > self.max_bytes {
+            return Ok(1);
+        }
+
+        let mut files = self.files.lock().await;
+        let mut dropped = 0;
+        while files.total_bytes + size > self.max_bytes {
+            let Some((oldest, oldest_size)) = files.files.pop_front() else { break };
+            files.total_bytes -= oldest_size;
+            remove_file(&oldest).await?;
+            dropped += 1;
+        }
+
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+        files.sequence += 1;
+        let path = self.dir.join(format!("{:020}-{:010}.{}", nanos, files.sequence, SPILL_EXTENSION));
+        fs::write(&path, data).await.map_err(|e| io_error(&path, e))?;
+        files.files.push_back((path, size));
+        files.total_bytes += size;
+        Ok(dropped)
+    }
+
+    /// Reads the oldest spilled request, if any
+    pub async fn oldest(&self) -> Result<Option<(PathBuf, Vec<u8>)>, StorageError> {
+        let files = self.files.lock().await;
+        let Some((path, _)) = files.files.front() else { return Ok(None) };
+        let data = fs::read(path).await.map_err(|e| io_error(path, e))?;
+        Ok(Some((path.clone(), data)))
+    }
+
+    /// Deletes a spill file once its request has been uploaded
+    pub async fn remove(&self, path: &Path) -> Result<(), StorageError> {
+        let mut files = self.files.lock().await;
+        if let Some(index) = files.files.iter().position(|(file, _)| file == path) {
+            if let Some((_, size)) = files.files.remove(index) {
+                files.total_bytes -= size;
+            }
+            remove_file(path).await?;
+        }
+        Ok(())
+    }
+
+    /// Number of spilled requests waiting to be uploaded
+    pub async fn len(&self) -> usize {
+        self.files.lock().await.files.len()
+    }
+
+    /// Returns whether no requests are waiting to be uploaded
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+
+    /// Total size in bytes of the spill files
+    pub async fn size_bytes(&self) -> u64 {
+        self.files.lock().await.total_bytes
+    }
+}
+
+/// Deletes a spill file; one already gone is not an error
+async fn remove_file(path: &Path) -> Result<(), StorageError> {
+    match fs::remove_file(path).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            warn!("Spill file {} already removed", path.display());
+            Ok(())
+        }
+        Err(e) => Err(io_error(path, e)),
+    }
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> StorageError {
+    StorageError::WriteFailed(format!("spill file {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn config(dir: &TempDir, max_bytes: u64) -> SpillConfig {
+        SpillConfig {
+            enabled: true,
+            dir: dir.path().to_string_lossy().into_owned(),
+            max_bytes,
+            ..SpillConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_oldest_files_dropped_when_full() {
+        let dir = TempDir::new().unwrap();
+        let buffer = SpillBuffer::open(&config(&dir, 10)).await.unwrap();
+
+        assert_eq!(buffer.spill(b"aaaa").await.unwrap(), 0);
+        assert_eq!(buffer.spill(b"bbbb").await.unwrap(), 0);
+        assert_eq!(buffer.spill(b"cccc").await.unwrap(), 1);
+        assert_eq!(buffer.len().await, 2);
+        assert_eq!(buffer.size_bytes().await, 8);
+        // Too large to keep at all
+        assert_eq!(buffer.spill(&[0; 11]).await.unwrap(), 1);
+
+        let (path, data) = buffer.oldest().await.unwrap().unwrap();
+        assert_eq!(data, b"bbbb");
+        buffer.remove(&path).await.unwrap();
+        assert_eq!(buffer.oldest().await.unwrap().unwrap().1, b"cccc");
+    }
+
+    #[tokio::test]
+    async fn test_files_from_previous_run_reloaded_in_order() {
+        let dir = TempDir::new().unwrap();
+        {
+            let buffer = SpillBuffer::open(&config(&dir, 100)).await.unwrap();
+            buffer.spill(b"first").await.unwrap();
+            buffer.spill(b"second").await.unwrap();
+        }
+
+        let buffer = SpillBuffer::open(&config(&dir, 100)).await.unwrap();
+        assert_eq!(buffer.len().await, 2);
+        assert_eq!(buffer.size_bytes().await, 11);
+        assert_eq!(buffer.oldest().await.unwrap().unwrap().1, b"first");
+    }
+}
@@ -0,0 +1,217 @@
+use arrow_array::{Array, ArrayRef, RecordBatch, StringArray, UInt64Array};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use bytes::Bytes;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::sync::Arc;
+
+use crate::error::StorageError;
+use super::StoredSpan;
+
+/// File extension of Parquet batch objects
+pub const PARQUET_EXTENSION: &str = "parquet";
+
+/// Content type of Parquet batch objects
+pub const PARQUET_CONTENT_TYPE: &str = "application/vnd.apache.parquet";
+
+/// Column schema of Parquet batch objects. Columns are only ever appended,
+/// so queries written against it keep working. Events, links and attributes
+/// are stored as JSON to keep the schema flat.
+pub fn span_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("trace_id", DataType::Utf8, false),
+        Field::new("span_id", DataType::Utf8, false),
+        Field::new("name", DataType::Utf8, false),
+        Field::new("kind", DataType::Utf8, false),
+        Field::new("start_time", DataType::UInt64, false),
+        Field::new("end_time", DataType::UInt64, false),
+        Field::new("status", DataType::Utf8, false),
+        Field::new("status_message", DataType::Utf8, true),
+        Field::new("service_name", DataType::Utf8, true),
+        Field::new("events", DataType::Utf8, false),
+        Field::new("links", DataType::Utf8, false),
+        Field::new("attributes", DataType::Utf8, false),
+        Field::new("scope_name", DataType::Utf8, true),
+        Field::new("scope_version", DataType::Utf8, true),
+        Field::new("duration_ns", DataType::UInt64, false),
+        Field::new("schema_version", DataType::UInt64, false),
+        Field::new("parent_span_id", DataType::Utf8, true),
+    ]))
+}
+
+/// Encodes spans as a single snappy-compressed Parquet file
+pub fn encode_parquet(spans: &[StoredSpan]) -> Result<Vec<u8>, StorageError> {
+    let write_error = |e: &dyn std::fmt::Display| StorageError::WriteFailed(format!("Parquet encoding failed: {}", e));
+
+    let events = spans.iter()
+        .map(|span| serde_json::to_string(&span.events))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| write_error(&e))?;
+    let links = spans.iter()
+        .map(|span| serde_json::to_string(&span.links))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| write_error(&e))?;
+    let attributes = spans.iter()
+        .map(|span| serde_json::to_string(&span.attributes))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| write_error(&e))?;
+
+    let columns: Vec<ArrayRef> = vec![
+        strings(spans.iter().map(|span| Some(span.trace_id.as_str()))),
+        strings(spans.iter().map(|span| Some(span.span_id.as_str()))),
+        strings(spans.iter().map(|span| Some(span.name.as_str()))),
+        strings(spans.iter().map(|span| Some(span.kind.as_str()))),
+        Arc::new(UInt64Array::from_iter_values(spans.iter().map(|span| span.start_time))),
+        Arc::new(UInt64Array::from_iter_values(spans.iter().map(|span| span.end_time))),
+        strings(spans.iter().map(|span| Some(span.status.as_str()))),
+        strings(spans.iter().map(|span| span.status_message.as_deref())),
+        strings(spans.iter().map(|span| span.service_name.as_deref())),
+        strings(events.iter().map(|events| Some(events.as_str()))),
+        strings(links.iter().map(|links| Some(links.as_str()))),
+        strings(attributes.iter().map(|attributes| Some(attributes.as_str()))),
+        strings(spans.iter().map(|span| span.scope_name.as_deref())),
+        strings(spans.iter().map(|span| span.scope_version.as_deref())),
+        Arc::new(UInt64Array::from_iter_values(spans.iter().map(|span| span.duration_ns))),
+        Arc::new(UInt64Array::from_iter_values(spans.iter().map(|span| u64::from(span.schema_version)))),
+        strings(spans.iter().map(|span| span.parent_span_id.as_deref())),
+    ];
+    let batch = RecordBatch::try_new(span_schema(), columns).map_err(|e| write_error(&e))?;
+
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut data = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut data, span_schema(), Some(properties))
+        .map_err(|e| write_error(&e))?;
+    writer.write(&batch).map_err(|e| write_error(&e))?;
+    writer.close().map_err(|e| write_error(&e))?;
+    Ok(data)
+}
+
+/// Decodes the spans of a Parquet batch object
+pub fn decode_parquet(data: Bytes) -> Result<Vec<StoredSpan>, StorageError> {
+    let read_error = |e: &dyn std::fmt::Display| StorageError::ReadFailed(format!("Parquet decoding failed: {}", e));
+
+    let reader = ParquetRecordBatchReaderBuilder::try_new(data)
+        .map_err(|e| read_error(&e))?
+        .build()
+        .map_err(|e| read_error(&e))?;
+
+    let mut spans = Vec::new();
+    for batch in reader {
+        let batch = batch.map_err(|e| read_error(&e))?;
+        let text = |name: &str| string_column(&batch, name);
+        let (trace_ids, span_ids, names, kinds) = (text("trace_id")?, text("span_id")?, text("name")?, text("kind")?);
+        let (statuses, messages, services) = (text("status")?, text("status_message")?, text("service_name")?);
+        let (events, links, attributes) = (text("events")?, text("links")?, text("attributes")?);
+        let (starts, ends) = (u64_column(&batch, "start_time")?, u64_column(&batch, "end_time")?);
+        // Absent from objects written before scope columns were added
+        let scope_names = string_column(&batch, "scope_name").ok();
+        let scope_versions = string_column(&batch, "scope_version").ok();
+        // Absent from objects written before durations were stored
+        let durations = u64_column(&batch, "duration_ns").ok();
+        // Absent from objects written before schema versions were recorded
+        let versions = u64_column(&batch, "schema_version").ok();
+        // Absent from objects written before parent ids were stored
+        let parent_span_ids = string_column(&batch, "parent_span_id").ok();
+
+        for row in 0..batch.num_rows() {
+            let optional = |column: &StringArray| (!column.is_null(row)).then(|| column.value(row).to_string());
+            spans.push(StoredSpan {
+                schema_version: versions.map_or(1, |column| column.value(row) as u32),
+                trace_id: trace_ids.value(row).to_string(),
+                span_id: span_ids.value(row).to_string(),
+                parent_span_id: parent_span_ids.and_then(optional),
+                name: names.value(row).to_string(),
+                kind: kinds.value(row).to_string(),
+                start_time: starts.value(row),
+                end_time: ends.value(row),
+                duration_ns: durations
+                    .map_or_else(|| ends.value(row).saturating_sub(starts.value(row)), |column| column.value(row)),
+                status: statuses.value(row).to_string(),
+                status_message: optional(messages),
+                service_name: optional(services),
+                scope_name: scope_names.and_then(optional),
+                scope_version: scope_versions.and_then(optional),
+                attributes: serde_json::from_str(attributes.value(row)).map_err(|e| read_error(&e))?,
+                events: serde_json::from_str(events.value(row)).map_err(|e| read_error(&e))?,
+                links: serde_json::from_str(links.value(row)).map_err(|e| read_error(&e))?,
+            });
+        }
+    }
+    Ok(spans)
+}
+
+/// Builds a string column
+fn strings<'a>(values: impl Iterator<Item = Option<&'a str>>) -> ArrayRef {
+    Arc::new(values.collect::<StringArray>())
+}
+
+/// Looks up a string column by name
+fn string_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a StringArray, StorageError> {
+    batch
+        .column_by_name(name)
+        .and_then(|column| column.as_any().downcast_ref::<StringArray>())
+        .ok_or_else(|| StorageError::ReadFailed(format!("Parquet object has no string column {}", name)))
+}
+
+/// Looks up an unsigned integer column by name
+fn u64_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a UInt64Array, StorageError> {
+    batch
+        .column_by_name(name)
+        .and_then(|column| column.as_any().downcast_ref::<UInt64Array>())
+        .ok_or_else(|| StorageError::ReadFailed(format!("Parquet object has no integer column {}", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{StoredEvent, StoredLink};
+    use std::collections::BTreeMap;
+    use crate::test_support::stored_span;
+
+    fn span(span_id: &str, service_name: Option<&str>) -> StoredSpan {
+        StoredSpan {
+            span_id: span_id.to_string(),
+            parent_span_id: service_name.map(|_| "05".repeat(8)),
+            status: "Error".into(),
+            status_message: service_name.map(|_| "timeout".to_string()),
+            service_name: service_name.map(str::to_string),
+            scope_name: service_name.map(|_| "my-tracer".to_string()),
+            scope_version: service_name.map(|_| "1.2.3".to_string()),
+            attributes: BTreeMap::from([("http.status_code".to_string(), serde_json::json!(500))]),
+            events: vec![StoredEvent {
+                name: "retry".into(),
+                timestamp: 1_500,
+                attributes: BTreeMap::from([("attempt".to_string(), serde_json::json!(2))]),
+            }],
+            links: vec![StoredLink {
+                trace_id: "03".repeat(16),
+                span_id: "04".repeat(8),
+                attributes: BTreeMap::new(),
+            }],
+            ..stored_span(1_000, 2_500)
+        }
+    }
+
+    #[test]
+    fn test_parquet_round_trip() {
+        let spans = vec![span(&"02".repeat(8), Some("payments")), span(&"05".repeat(8), None)];
+        let data = encode_parquet(&spans).unwrap();
+        assert!(data.starts_with(b"PAR1"));
+
+        let decoded = decode_parquet(Bytes::from(data)).unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&spans).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_empty_batch_round_trip() {
+        let data = encode_parquet(&[]).unwrap();
+        assert!(decode_parquet(Bytes::from(data)).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,76 @@
+use std::io::{Read, Write};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+
+use crate::config::{CompressionAlgorithm, CompressionConfig};
+use crate::error::StorageError;
+
+/// `Content-Encoding` recorded on objects compressed with `algorithm`,
+/// `None` when objects are stored as written
+pub fn content_encoding(algorithm: CompressionAlgorithm) -> Option<&'static str> {
+    match algorithm {
+        CompressionAlgorithm::None => None,
+        CompressionAlgorithm::Gzip => Some("gzip"),
+        CompressionAlgorithm::Zstd => Some("zstd"),
+    }
+}
+
+/// Compresses an object body with the configured algorithm and level
+pub fn compress(data: &[u8], config: &CompressionConfig) -> Result<Vec<u8>, StorageError> {
+    let write_error = |e: std::io::Error| {
+        StorageError::WriteFailed(format!("{} compression failed: {}", config.algorithm, e))
+    };
+    match config.algorithm {
+        CompressionAlgorithm::None => Ok(data.to_vec()),
+        CompressionAlgorithm::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::new(config.level as u32));
+            encoder.write_all(data).map_err(write_error)?;
+            encoder.finish().map_err(write_error)
+        }
+        CompressionAlgorithm::Zstd => zstd::encode_all(data, config.level).map_err(write_error),
+    }
+}
+
+/// Decompresses an object body according to its `Content-Encoding`;
+/// bodies without one, or with an unknown one, are returned as stored
+pub fn decompress(data: Vec<u8>, content_encoding: Option<&str>) -> Result<Vec<u8>, StorageError> {
+    let read_error = |e: std::io::Error| {
+        StorageError::ReadFailed(format!("{} decompression failed: {}", content_encoding.unwrap_or_default(), e))
+    };
+    match content_encoding {
+        Some("gzip") => {
+            let mut decoded = Vec::new();
+            GzDecoder::new(data.as_slice()).read_to_end(&mut decoded).map_err(read_error)?;
+            Ok(decoded)
+        }
+        Some("zstd") => zstd::decode_all(data.as_slice()).map_err(read_error),
+        _ => Ok(data),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_per_algorithm() {
+        let data = br#"{"trace_id":"0101","name":"checkout"}"#.repeat(100);
+        for config in [
+            CompressionConfig { algorithm: CompressionAlgorithm::Gzip, level: 9 },
+            CompressionConfig { algorithm: CompressionAlgorithm::Zstd, level: 11 },
+        ] {
+            let compressed = compress(&data, &config).unwrap();
+            assert!(compressed.len() < data.len() / 10, "{} barely compressed", config.algorithm);
+
+            let encoding = content_encoding(config.algorithm);
+            assert_eq!(decompress(compressed, encoding).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_unencoded_bodies_returned_as_stored() {
+        assert_eq!(decompress(b"{}".to_vec(), None).unwrap(), b"{}");
+        assert!(decompress(b"{}".to_vec(), Some("gzip")).is_err());
+    }
+}
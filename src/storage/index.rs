@@ -0,0 +1,248 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use uuid::Uuid;
+
+use super::StoredSpan;
+
+/// Key (relative to the storage prefix) of the service-name index object
+pub const SERVICE_INDEX_KEY: &str = "_index/services.json";
+
+/// Path segment marking index objects, which are skipped when listing spans
+pub const INDEX_SEGMENT: &str = "_index/";
+
+/// Index of distinct service names that have reported spans.
+///
+/// The index is maintained with a read-modify-write on every write that
+/// introduces a previously unseen service. The PUT is conditional on the
+/// ETag returned by the read (or on the object not existing yet), so
+/// concurrent writers never overwrite each other's additions; a writer that
+/// loses the race re-reads the index and merges again.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceIndex {
+    /// Distinct service names, kept sorted
+    pub services: BTreeSet<String>,
+}
+
+impl ServiceIndex {
+    /// Adds the given service names, returning whether the index changed
+    pub fn merge<'a, I>(&mut self, services: I) -> bool
+    where
+        I: IntoIterator<Item = &'a String>,
+    {
+        let before = self.services.len();
+        self.services.extend(services.into_iter().cloned());
+        self.services.len() != before
+    }
+}
+
+/// Key prefix (relative to the storage prefix) of span search index segments
+pub const SPAN_INDEX_PREFIX: &str = "_index/spans/";
+
+/// Number of entries per segment written when rebuilding or compacting the index
+pub const REBUILD_SEGMENT_ENTRIES: usize = 10_000;
+
+/// How far before a search's `start` segments are still read. Segments are
+/// partitioned by the hour they were written, which for spans arriving on
+/// time is no earlier than their start; the margin covers clock skew
+/// between the spans' source and the writer.
+pub const PARTITION_SKEW_MARGIN: TimeDelta = TimeDelta::hours(1);
+
+/// Compact description of one stored span, enough to filter searches
+/// without reading the span itself
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpanIndexEntry {
+    /// Trace id, 32 lowercase hex characters
+    pub trace_id: String,
+    /// Span id, 16 lowercase hex characters
+    pub span_id: String,
+    /// Value of the `service.name` resource attribute, if reported
+    #[serde(default)]
+    pub service: Option<String>,
+    /// Name of the operation
+    pub name: String,
+    /// Start time in nanoseconds since epoch
+    pub start_time: u64,
+    /// Duration in nanoseconds
+    pub duration_ns: u64,
+    /// `Unset`, `Ok` or `Error`
+    pub status: String,
+    /// Full key of the object holding the span
+    pub key: String,
+    /// Values of the span's attributes named in `storage.indexed_attributes`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, serde_json::Value>,
+}
+
+impl SpanIndexEntry {
+    /// Describes a span stored in the object at `key`
+    pub fn new(span: &StoredSpan, key: &str) -> Self {
+        Self {
+            trace_id: span.trace_id.clone(),
+            span_id: span.span_id.clone(),
+            service: span.service_name.clone(),
+            name: span.name.clone(),
+            start_time: span.start_time,
+            duration_ns: span.duration_ns,
+            status: span.status.clone(),
+            key: key.to_string(),
+            attributes: BTreeMap::new(),
+        }
+    }
+
+    /// Records the span's attributes with the given keys; keys the span
+    /// does not carry are skipped
+    pub fn with_attributes(mut self, span: &StoredSpan, keys: &[String]) -> Self {
+        for key in keys {
+            if let Some(value) = span.attributes.get(key) {
+                self.attributes.insert(key.clone(), value.clone());
+            }
+        }
+        self
+    }
+}
+
+/// One span index object, a JSON document stored under
+/// `_index/spans/YYYY/MM/DD/HH/<uuid>.json` by the hour it was written.
+/// Each write of spans adds one segment describing the spans it stored;
+/// compaction merges the segments of a past hour, and a rebuild replaces
+/// all segments with ones derived from stored objects.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpanIndexSegment {
+    /// Entries of the indexed spans, in write order
+    pub entries: Vec<SpanIndexEntry>,
+}
+
+/// Returns the key, relative to the storage prefix, of a segment written at `now`
+pub fn span_index_key(now: DateTime<Utc>) -> String {
+    format!("{}/{}.json", span_index_partition(now), Uuid::new_v4())
+}
+
+/// Returns the partition, relative to the storage prefix and without a
+/// trailing slash, holding segments written in the hour of `time`.
+/// Partitions sort in time order.
+pub fn span_index_partition(time: DateTime<Utc>) -> String {
+    format!("{}{}", SPAN_INDEX_PREFIX, time.format("%Y/%m/%d/%H"))
+}
+
+/// Returns the partition of a segment's key, the key up to its file name
+pub fn segment_partition(key: &str) -> &str {
+    key.rsplit_once('/').map_or(key, |(partition, _)| partition)
+}
+
+/// Conditions a searched span must meet; unset fields match any span
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SpanSearch {
+    /// Reporting service
+    pub service: Option<String>,
+    /// Exact operation name
+    pub name: Option<String>,
+    /// Status, compared case-insensitively
+    pub status: Option<String>,
+    /// Normalized trace id
+    pub trace_id: Option<String>,
+    /// Minimum duration in nanoseconds
+    pub min_duration_ns: Option<u64>,
+    /// Maximum duration in nanoseconds
+    pub max_duration_ns: Option<u64>,
+    /// Earliest start time in nanoseconds since epoch
+    pub start: Option<u64>,
+    /// Latest start time (exclusive) in nanoseconds since epoch
+    pub end: Option<u64>,
+    /// `key=value` attribute filters, checked against the entry's attributes
+    pub attributes: Vec<(String, String)>,
+}
+
+impl SpanSearch {
+    /// Returns the keys of the attribute filters
+    pub fn attribute_keys(&self) -> Vec<String> {
+        self.attributes.iter().map(|(key, _)| key.clone()).collect()
+    }
+
+    /// Returns whether an indexed span meets every condition
+    pub fn matches(&self, entry: &SpanIndexEntry) -> bool {
+        self.service.as_ref().is_none_or(|service| entry.service.as_ref() == Some(service))
+            && self.name.as_ref().is_none_or(|name| &entry.name == name)
+            && self.status.as_ref().is_none_or(|status| entry.status.eq_ignore_ascii_case(status))
+            && self.trace_id.as_ref().is_none_or(|trace_id| &entry.trace_id == trace_id)
+            && self.min_duration_ns.is_none_or(|min| entry.duration_ns >= min)
+            && self.max_duration_ns.is_none_or(|max| entry.duration_ns <= max)
+            && self.start.is_none_or(|start| entry.start_time >= start)
+            && self.end.is_none_or(|end| entry.start_time < end)
+            && self.attributes.iter().all(|(key, expected)| attribute_matches(entry.attributes.get(key), expected))
+    }
+}
+
+/// Returns whether an attribute value equals a filter value. Non-string
+/// values match the filter value parsed as JSON, e.g. `500` or `true`.
+pub fn attribute_matches(value: Option<&serde_json::Value>, expected: &str) -> bool {
+    match value {
+        Some(serde_json::Value::String(value)) => value == expected,
+        Some(value) => serde_json::from_str::<serde_json::Value>(expected).is_ok_and(|expected| expected == *value),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_reports_changes() {
+        let mut index = ServiceIndex::default();
+        let checkout = vec!["checkout".to_string()];
+
+        assert!(index.merge(&checkout));
+        assert!(!index.merge(&checkout));
+        assert!(index.merge(&vec!["payments".to_string()]));
+        assert_eq!(
+            index.services.iter().cloned().collect::<Vec<_>>(),
+            vec!["checkout".to_string(), "payments".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_search_matches_all_conditions() {
+        let entry = SpanIndexEntry {
+            trace_id: "01".repeat(16),
+            span_id: "02".repeat(8),
+            service: Some("checkout".into()),
+            name: "GET /cart".into(),
+            start_time: 5_000,
+            duration_ns: 300,
+            status: "Error".into(),
+            key: "traces/a.json".into(),
+            attributes: BTreeMap::from([("http.status_code".to_string(), serde_json::json!(500))]),
+        };
+
+        assert!(SpanSearch::default().matches(&entry));
+        let search = SpanSearch {
+            service: Some("checkout".into()),
+            status: Some("error".into()),
+            min_duration_ns: Some(300),
+            start: Some(5_000),
+            end: Some(5_001),
+            ..SpanSearch::default()
+        };
+        assert!(search.matches(&entry));
+        assert!(!SpanSearch { name: Some("GET /".into()), ..search.clone() }.matches(&entry));
+        assert!(!SpanSearch { max_duration_ns: Some(299), ..search.clone() }.matches(&entry));
+        assert!(!SpanSearch { end: Some(5_000), ..search.clone() }.matches(&entry));
+
+        let status_code = |value: &str| vec![("http.status_code".to_string(), value.to_string())];
+        assert!(SpanSearch { attributes: status_code("500"), ..search.clone() }.matches(&entry));
+        assert!(!SpanSearch { attributes: status_code("404"), ..search.clone() }.matches(&entry));
+        let method = vec![("http.method".to_string(), "GET".to_string())];
+        assert!(!SpanSearch { attributes: method, ..search }.matches(&entry));
+    }
+
+    #[test]
+    fn test_span_index_key_layout() {
+        let now = DateTime::parse_from_rfc3339("2024-03-05T07:30:00Z").unwrap().with_timezone(&Utc);
+        let key = span_index_key(now);
+        assert!(key.starts_with("_index/spans/2024/03/05/07/"), "{}", key);
+        assert!(key.ends_with(".json"));
+        assert_eq!(segment_partition(&key), span_index_partition(now));
+        assert!(span_index_partition(now - TimeDelta::hours(1)).as_str() < key.as_str());
+    }
+}
@@ -0,0 +1,424 @@
+use std::borrow::Cow;
+
+use chrono::{DateTime, Utc};
+
+use crate::error::ConfigError;
+use crate::sampling::trace_hash;
+
+/// Per-span key layout used unless `storage.key_template` is set
+pub const DEFAULT_KEY_TEMPLATE: &str = "{prefix}/{trace_id}/{span_id}.json";
+
+/// Rendered in place of `{service}` for spans without a `service.name`
+const UNKNOWN_SERVICE: &str = "unknown";
+
+/// Format of `{date}`; always 10 characters so keys can be parsed back
+const DATE_FORMAT: &str = "%Y/%m/%d";
+
+/// Length of a rendered `{date}`
+const DATE_LEN: usize = 10;
+
+/// Length of a rendered `{hash}`: two hex chars, so 256 buckets
+const HASH_LEN: usize = 2;
+
+/// Placeholder in a key template
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placeholder {
+    Prefix,
+    TraceId,
+    SpanId,
+    Date,
+    Service,
+    Hash,
+}
+
+impl Placeholder {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "prefix" => Some(Self::Prefix),
+            "trace_id" => Some(Self::TraceId),
+            "span_id" => Some(Self::SpanId),
+            "date" => Some(Self::Date),
+            "service" => Some(Self::Service),
+            "hash" => Some(Self::Hash),
+            _ => None,
+        }
+    }
+
+    /// Rendered length, when fixed
+    fn fixed_len(self) -> Option<usize> {
+        match self {
+            Self::TraceId => Some(32),
+            Self::SpanId => Some(16),
+            Self::Date => Some(DATE_LEN),
+            Self::Hash => Some(HASH_LEN),
+            Self::Prefix | Self::Service => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+/// Values substituted into a key template for one span
+#[derive(Debug, Clone, Copy)]
+pub struct KeyFields<'a> {
+    /// Writer's key prefix
+    pub prefix: &'a str,
+    /// Hex trace id
+    pub trace_id: &'a str,
+    /// Hex span id
+    pub span_id: &'a str,
+    /// Span start time, rendered as `YYYY/MM/DD`
+    pub date: DateTime<Utc>,
+    /// `service.name` of the span, if reported
+    pub service: Option<&'a str>,
+}
+
+/// Per-span object key layout with `{prefix}`, `{trace_id}`, `{span_id}`,
+/// `{date}`, `{service}` and `{hash}` placeholders. `{trace_id}` and
+/// `{span_id}` are required so every span gets its own key and keys can be
+/// traced back. `{hash}` is derived from the trace id, so it can always be
+/// recomputed when looking a span up.
+/// An empty `{prefix}` drops the `/` following it, so keys never start
+/// with a slash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyTemplate {
+    segments: Vec<Segment>,
+}
+
+impl KeyTemplate {
+    /// Parses a template, rejecting unknown or missing placeholders
+    pub fn parse(template: &str) -> Result<Self, ConfigError> {
+        let invalid = |reason: String| ConfigError::InvalidValue(format!("key_template {:?} {}", template, reason));
+
+        let mut segments = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                segments.push(Segment::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..].find('}').ok_or_else(|| invalid("has an unclosed '{'".into()))?;
+            let name = &rest[start + 1..start + end];
+            let placeholder = Placeholder::parse(name)
+                .ok_or_else(|| invalid(format!("has unknown placeholder {{{}}}", name)))?;
+            if segments.last() == Some(&Segment::Placeholder(Placeholder::Service)) {
+                return Err(invalid("needs a separator after {service}".into()));
+            }
+            segments.push(Segment::Placeholder(placeholder));
+            rest = &rest[start + end + 1..];
+        }
+        if rest.contains('}') {
+            return Err(invalid("has an unmatched '}'".into()));
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+
+        for required in [Placeholder::TraceId, Placeholder::SpanId] {
+            if !segments.contains(&Segment::Placeholder(required)) {
+                return Err(invalid(format!("must contain {{{}}}", name_of(required))));
+            }
+        }
+        Ok(Self { segments })
+    }
+
+    /// With `enabled`, inserts a `{hash}/` segment right after a leading
+    /// `{prefix}/`, or at the start otherwise, spreading keys over 256
+    /// key prefixes (and so S3 partitions). Templates already using
+    /// `{hash}` are left as they are.
+    pub fn with_prefix_hash(mut self, enabled: bool) -> Self {
+        if !enabled || self.segments.contains(&Segment::Placeholder(Placeholder::Hash)) {
+            return self;
+        }
+        let at = match self.segments.as_slice() {
+            [Segment::Placeholder(Placeholder::Prefix), Segment::Literal(text), ..] if text.starts_with('/') => {
+                // Split the literal after its slash
+                let rest = text[1..].to_string();
+                self.segments[1] = Segment::Literal("/".into());
+                if !rest.is_empty() {
+                    self.segments.insert(2, Segment::Literal(rest));
+                }
+                2
+            }
+            _ => 0,
+        };
+        self.segments.splice(at..at, [Segment::Placeholder(Placeholder::Hash), Segment::Literal("/".into())]);
+        // Keep adjacent literals merged so keys parse back unambiguously
+        let mut merged: Vec<Segment> = Vec::with_capacity(self.segments.len());
+        for segment in self.segments {
+            match (merged.last_mut(), segment) {
+                (Some(Segment::Literal(last)), Segment::Literal(text)) => last.push_str(&text),
+                (_, segment) => merged.push(segment),
+            }
+        }
+        self.segments = merged;
+        self
+    }
+
+    /// Places a batch object's key, relative to the prefix, under a hash of
+    /// that key when the template uses `{hash}`, so batches spread over
+    /// partitions like per-span objects
+    pub fn hash_batch_key(&self, key: String) -> String {
+        if self.segments.contains(&Segment::Placeholder(Placeholder::Hash)) {
+            format!("{}/{}", key_hash(&key), key)
+        } else {
+            key
+        }
+    }
+
+    /// Returns whether rendered keys include the `{prefix}`
+    pub fn includes_prefix(&self) -> bool {
+        self.segments.contains(&Segment::Placeholder(Placeholder::Prefix))
+    }
+
+    /// Renders the key of one span
+    pub fn render(&self, fields: &KeyFields) -> String {
+        self.segments_for(fields.prefix).iter().map(|segment| match segment {
+            Segment::Literal(text) => text.clone(),
+            Segment::Placeholder(Placeholder::Prefix) => fields.prefix.to_string(),
+            Segment::Placeholder(Placeholder::TraceId) => fields.trace_id.to_string(),
+            Segment::Placeholder(Placeholder::SpanId) => fields.span_id.to_string(),
+            Segment::Placeholder(Placeholder::Date) => fields.date.format(DATE_FORMAT).to_string(),
+            Segment::Placeholder(Placeholder::Service) => {
+                fields.service.unwrap_or(UNKNOWN_SERVICE).to_string()
+            }
+            Segment::Placeholder(Placeholder::Hash) => key_hash(fields.trace_id),
+        }).collect()
+    }
+
+    /// Renders a span's key from its ids alone; `None` when the template
+    /// also depends on `{date}` or `{service}`
+    pub fn span_key(&self, prefix: &str, trace_id: &str, span_id: &str) -> Option<String> {
+        self.render_ids(&self.segments_for(prefix), prefix, trace_id, span_id)
+    }
+
+    /// Returns the key prefix shared by every span of a trace: the template
+    /// up to `{trace_id}` and the literal following it. `None` when a
+    /// `{date}` or `{service}` comes first.
+    pub fn trace_prefix(&self, prefix: &str, trace_id: &str) -> Option<String> {
+        let segments = self.segments_for(prefix);
+        let trace = segments.iter()
+            .position(|segment| *segment == Segment::Placeholder(Placeholder::TraceId))?;
+        let end = match segments.get(trace + 1) {
+            Some(Segment::Literal(_)) => trace + 2,
+            _ => trace + 1,
+        };
+        self.render_ids(&segments[..end], prefix, trace_id, "")
+    }
+
+    /// Extracts the trace id from a key rendered with this template
+    pub fn trace_id_of(&self, prefix: &str, key: &str) -> Option<String> {
+        let segments = self.segments_for(prefix);
+        let mut rest = key;
+        let mut trace_id = None;
+        let mut hash = None;
+        for (i, segment) in segments.iter().enumerate() {
+            let value_len = match segment {
+                Segment::Literal(text) => text.len(),
+                Segment::Placeholder(Placeholder::Prefix) => prefix.len(),
+                Segment::Placeholder(placeholder) => match placeholder.fixed_len() {
+                    Some(len) => len,
+                    // Variable values run up to the next literal, or the end of the key
+                    None => match segments.get(i + 1) {
+                        Some(Segment::Literal(next)) => rest.find(next.as_str())?,
+                        _ => rest.len(),
+                    },
+                },
+            };
+            let value = rest.get(..value_len)?;
+            match segment {
+                Segment::Literal(text) if value != text => return None,
+                Segment::Placeholder(Placeholder::Prefix) if value != prefix => return None,
+                Segment::Placeholder(Placeholder::TraceId) => trace_id = Some(value.to_string()),
+                Segment::Placeholder(Placeholder::Hash) => hash = Some(value),
+                _ => {}
+            }
+            rest = &rest[value_len..];
+        }
+        let trace_id = trace_id.filter(|trace_id| hash.is_none_or(|hash| hash == key_hash(trace_id)));
+        if rest.is_empty() { trace_id } else { None }
+    }
+
+    /// Returns the segments keys are rendered from for `prefix`; an empty
+    /// prefix drops the `/` following `{prefix}`
+    fn segments_for(&self, prefix: &str) -> Cow<'_, [Segment]> {
+        if !prefix.is_empty() || !self.includes_prefix() {
+            return Cow::Borrowed(&self.segments);
+        }
+        let mut segments = Vec::with_capacity(self.segments.len());
+        let mut after_prefix = false;
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) if after_prefix => {
+                    let text = text.strip_prefix('/').unwrap_or(text);
+                    if !text.is_empty() {
+                        segments.push(Segment::Literal(text.to_string()));
+                    }
+                }
+                segment => segments.push(segment.clone()),
+            }
+            after_prefix = *segment == Segment::Placeholder(Placeholder::Prefix);
+        }
+        Cow::Owned(segments)
+    }
+
+    /// Renders segments that only use `{prefix}`, `{trace_id}` and `{span_id}`
+    fn render_ids(&self, segments: &[Segment], prefix: &str, trace_id: &str, span_id: &str) -> Option<String> {
+        let hash = key_hash(trace_id);
+        segments.iter().map(|segment| match segment {
+            Segment::Literal(text) => Some(text.as_str()),
+            Segment::Placeholder(Placeholder::Prefix) => Some(prefix),
+            Segment::Placeholder(Placeholder::TraceId) => Some(trace_id),
+            Segment::Placeholder(Placeholder::SpanId) => Some(span_id),
+            Segment::Placeholder(Placeholder::Hash) => Some(hash.as_str()),
+            Segment::Placeholder(Placeholder::Date | Placeholder::Service) => None,
+        }).collect()
+    }
+}
+
+impl Default for KeyTemplate {
+    fn default() -> Self {
+        Self::parse(DEFAULT_KEY_TEMPLATE).expect("default key template is valid")
+    }
+}
+
+/// Placeholder name as written in templates
+fn name_of(placeholder: Placeholder) -> &'static str {
+    match placeholder {
+        Placeholder::Prefix => "prefix",
+        Placeholder::TraceId => "trace_id",
+        Placeholder::SpanId => "span_id",
+        Placeholder::Date => "date",
+        Placeholder::Service => "service",
+        Placeholder::Hash => "hash",
+    }
+}
+
+/// Renders `{hash}`: the top byte of a stable hash of the hex trace id,
+/// or of a batch object's key
+fn key_hash(value: &str) -> String {
+    format!("{:02x}", trace_hash(value.as_bytes()) >> 56)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn fields(service: Option<&'static str>) -> KeyFields<'static> {
+        KeyFields {
+            prefix: "traces",
+            trace_id: "0af7651916cd43dd8448eb211c80319c",
+            span_id: "b7ad6b7169203331",
+            date: Utc.with_ymd_and_hms(2024, 3, 7, 12, 0, 0).unwrap(),
+            service,
+        }
+    }
+
+    #[test]
+    fn test_render_templates() {
+        let render = |template: &str, service| KeyTemplate::parse(template).unwrap().render(&fields(service));
+
+        assert_eq!(
+            render(DEFAULT_KEY_TEMPLATE, None),
+            "traces/0af7651916cd43dd8448eb211c80319c/b7ad6b7169203331.json"
+        );
+        assert_eq!(
+            render("{prefix}/{service}/{date}/{trace_id}-{span_id}.json", Some("checkout")),
+            "traces/checkout/2024/03/07/0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331.json"
+        );
+        assert_eq!(
+            render("spans/{service}/{trace_id}{span_id}", None),
+            "spans/unknown/0af7651916cd43dd8448eb211c80319cb7ad6b7169203331"
+        );
+    }
+
+    #[test]
+    fn test_invalid_templates_rejected() {
+        for (template, reason) in [
+            ("{prefix}/{trace_id}.json", "must contain {span_id}"),
+            ("{prefix}/{span_id}.json", "must contain {trace_id}"),
+            ("{prefix}/{trace}/{span_id}", "unknown placeholder {trace}"),
+            ("{prefix}/{trace_id/{span_id}", "unknown placeholder {trace_id/{span_id}"),
+            ("{prefix}/{trace_id}/{span_id", "unclosed"),
+            ("{service}{trace_id}/{span_id}", "separator after {service}"),
+        ] {
+            match KeyTemplate::parse(template) {
+                Err(ConfigError::InvalidValue(msg)) => assert!(msg.contains(reason), "{}: {}", template, msg),
+                other => panic!("{}: expected InvalidValue, got {:?}", template, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_keys_parse_back() {
+        let f = fields(Some("checkout"));
+        for template in [
+            DEFAULT_KEY_TEMPLATE,
+            "{prefix}/{service}/{date}/{trace_id}-{span_id}.json",
+            "{date}/{trace_id}{span_id}",
+        ] {
+            let template = KeyTemplate::parse(template).unwrap();
+            let key = template.render(&f);
+            assert_eq!(template.trace_id_of("traces", &key).as_deref(), Some(f.trace_id), "{}", key);
+            assert_eq!(template.trace_id_of("traces", &format!("{}.tmp", key)), None);
+        }
+        assert_eq!(KeyTemplate::default().trace_id_of("other", &KeyTemplate::default().render(&f)), None);
+    }
+
+    #[test]
+    fn test_empty_prefix_drops_separator() {
+        let template = KeyTemplate::default();
+        let key = template.render(&KeyFields { prefix: "", ..fields(None) });
+
+        assert_eq!(key, "0af7651916cd43dd8448eb211c80319c/b7ad6b7169203331.json");
+        assert_eq!(template.trace_id_of("", &key).as_deref(), Some("0af7651916cd43dd8448eb211c80319c"));
+        assert_eq!(template.span_key("", "t", "s").as_deref(), Some("t/s.json"));
+        assert_eq!(template.trace_prefix("", "t").as_deref(), Some("t/"));
+    }
+
+    #[test]
+    fn test_prefix_hash_inserted_after_prefix() {
+        let f = fields(None);
+        let hash = key_hash(f.trace_id);
+        for (template, expected) in [
+            (DEFAULT_KEY_TEMPLATE, format!("traces/{}/{}/{}.json", hash, f.trace_id, f.span_id)),
+            ("{prefix}/spans/{trace_id}/{span_id}", format!("traces/{}/spans/{}/{}", hash, f.trace_id, f.span_id)),
+            ("{trace_id}/{span_id}", format!("{}/{}/{}", hash, f.trace_id, f.span_id)),
+            ("{hash}-{trace_id}/{span_id}", format!("{}-{}/{}", hash, f.trace_id, f.span_id)),
+        ] {
+            let template = KeyTemplate::parse(template).unwrap().with_prefix_hash(true);
+            let key = template.render(&f);
+            assert_eq!(key, expected);
+            assert_eq!(template.trace_id_of("traces", &key).as_deref(), Some(f.trace_id), "{}", key);
+        }
+
+        let template = KeyTemplate::default().with_prefix_hash(true);
+        assert_eq!(template.span_key("traces", f.trace_id, f.span_id), Some(template.render(&f)));
+        assert_eq!(
+            template.trace_prefix("traces", f.trace_id),
+            Some(format!("traces/{}/{}/", hash, f.trace_id))
+        );
+        // A key whose hash does not match its trace id is not one of ours
+        let wrong = format!("traces/{}/{}/{}.json", if hash == "00" { "01" } else { "00" }, f.trace_id, f.span_id);
+        assert_eq!(template.trace_id_of("traces", &wrong), None);
+        assert_eq!(KeyTemplate::default().with_prefix_hash(false), KeyTemplate::default());
+    }
+
+    #[test]
+    fn test_lookup_keys_need_ids_only() {
+        let default = KeyTemplate::default();
+        assert_eq!(default.span_key("p", "t", "s").as_deref(), Some("p/t/s.json"));
+        assert_eq!(default.trace_prefix("p", "t").as_deref(), Some("p/t/"));
+
+        let dated = KeyTemplate::parse("{prefix}/{trace_id}/{date}/{span_id}").unwrap();
+        assert_eq!(dated.span_key("p", "t", "s"), None);
+        assert_eq!(dated.trace_prefix("p", "t").as_deref(), Some("p/t/"));
+
+        let by_service = KeyTemplate::parse("{service}/{trace_id}/{span_id}").unwrap();
+        assert_eq!(by_service.trace_prefix("p", "t"), None);
+    }
+}
@@ -1,21 +1,146 @@
 use aws_sdk_s3::Client as S3Client;
-use aws_sdk_s3::config::{Credentials, Region};
+use aws_sdk_s3::config::Region;
 use aws_sdk_s3::config::Builder as S3Builder;
-use tracing::{info, error};
+use aws_sdk_s3::config::http::HttpResponse;
+use aws_sdk_s3::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
+use aws_sdk_s3::operation::put_object::builders::PutObjectFluentBuilder;
+use aws_sdk_s3::types::{Delete, ObjectIdentifier};
+use tracing::{info, error, warn};
 use opentelemetry::sdk::export::trace::SpanData;
-use serde::{Deserialize, Serialize};
+use opentelemetry::trace::{SpanId, SpanKind, Status};
+use opentelemetry::{Array, Key, KeyValue, Value};
+use serde::{de, Deserialize, Deserializer, Serialize};
 use serde_json::{self, json};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::time::{SystemTime, UNIX_EPOCH, Duration};
 use std::convert::TryInto;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use bytes::Bytes;
+use chrono::{DateTime, SecondsFormat, Utc};
+use uuid::Uuid;
+use utoipa::ToSchema;
 
-use crate::error::StorageError;
+use crate::backoff::Backoff;
+use crate::config::{CompressionAlgorithm, CompressionConfig, RetryConfig, StorageConfig, StorageFormat, TimestampFormat, WriteMode};
+use crate::error::{StorageError, StorageErrorKind};
 use async_trait::async_trait;
-use crate::health::HealthStatus;
+use futures::stream::{self, BoxStream, StreamExt};
+use crate::health::{HealthCheck, HealthStatus};
+use crate::ids::{normalize_span_id, normalize_trace_id, span_id_hex, trace_id_hex};
+
+pub mod columnar;
+pub mod compression;
+pub mod index;
+pub mod key_template;
+pub mod null;
+pub mod routing;
+pub mod tagging;
+
+use columnar::{decode_parquet, encode_parquet, PARQUET_CONTENT_TYPE, PARQUET_EXTENSION};
+use index::{
+    segment_partition, span_index_key, span_index_partition, ServiceIndex, SpanIndexEntry, SpanIndexSegment,
+    SpanSearch, INDEX_SEGMENT, PARTITION_SKEW_MARGIN, REBUILD_SEGMENT_ENTRIES, SERVICE_INDEX_KEY, SPAN_INDEX_PREFIX,
+};
+use key_template::{KeyFields, KeyTemplate};
+use tagging::{ObjectTags, TagFields};
+
+/// Content type of stored span objects
+pub(crate) const JSON_CONTENT_TYPE: &str = "application/json";
+
+/// S3-compatible endpoint used when none is configured, e.g. LocalStack
+const LOCAL_ENDPOINT: &str = "http://localhost:4566";
+
+/// Region, endpoint and bucket addressing of the S3 client
+#[derive(Debug, Clone, PartialEq)]
+pub struct S3ClientSettings {
+    /// Region requests are signed for and routed to
+    pub region: String,
+    /// Custom S3-compatible endpoint; `None` uses the AWS endpoint of `region`
+    pub endpoint: Option<String>,
+    /// Address buckets as `endpoint/bucket` rather than `bucket.endpoint`
+    pub force_path_style: bool,
+}
+
+impl S3ClientSettings {
+    /// Applies the region, endpoint and addressing style to an S3 config
+    pub fn apply(&self, builder: S3Builder) -> S3Builder {
+        let builder = builder
+            .region(Region::new(self.region.clone()))
+            .force_path_style(self.force_path_style);
+        match &self.endpoint {
+            Some(endpoint) => builder.endpoint_url(endpoint),
+            None => builder,
+        }
+    }
+}
+
+impl Default for S3ClientSettings {
+    /// A local S3-compatible endpoint such as LocalStack
+    fn default() -> Self {
+        Self {
+            region: "us-east-1".to_string(),
+            endpoint: Some(LOCAL_ENDPOINT.to_string()),
+            force_path_style: true,
+        }
+    }
+}
+
+impl From<&StorageConfig> for S3ClientSettings {
+    fn from(config: &StorageConfig) -> Self {
+        Self {
+            region: config.region.clone(),
+            endpoint: config.endpoint.clone(),
+            force_path_style: config.path_style(),
+        }
+    }
+}
+
+impl S3ClientSettings {
+    /// Settings of the configured fallback endpoints, in failover order
+    pub fn fallbacks(config: &StorageConfig) -> Vec<Self> {
+        config
+            .fallback_endpoints
+            .iter()
+            .map(|fallback| Self {
+                region: fallback.region.clone().unwrap_or_else(|| config.region.clone()),
+                endpoint: Some(fallback.endpoint.clone()),
+                force_path_style: config.force_path_style.unwrap_or(true),
+            })
+            .collect()
+    }
+
+    /// Names the endpoint in logs and health reports
+    pub fn endpoint_name(&self) -> String {
+        match &self.endpoint {
+            Some(endpoint) => endpoint.clone(),
+            None => format!("s3.{}.amazonaws.com", self.region),
+        }
+    }
+}
+
+/// Layout version of span objects written by this build, recorded in each
+/// object's `schema_version`. Version 1 objects predate the field; version 2
+/// records it and version 3 adds `parent_span_id`. Readers default any field
+/// an older object lacks.
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// How long a single object PUT may take unless configured otherwise
+pub const DEFAULT_WRITE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Maximum number of keys per `DeleteObjects` request
+const DELETE_BATCH_SIZE: usize = 1000;
+
+/// Most failed keys named in the error of a batched delete
+const MAX_LISTED_DELETE_FAILURES: usize = 10;
+
+/// Maximum number of concurrent GETs issued by `read_spans`
+pub(crate) const READ_CONCURRENCY: usize = 16;
 
 /// Trait defining storage operations for the engine.
 /// Implementations should handle data persistence and retrieval.
 #[async_trait]
-pub trait StorageWriter {
+pub trait StorageWriter: Send + Sync {
     /// Writes a single data entry with the given key
     async fn write(&self, key: &str, data: &[u8]) -> Result<(), StorageError>;
     
@@ -27,25 +152,357 @@ pub trait StorageWriter {
     
     /// Writes a collection of spans to storage
     async fn write_spans(&self, spans: Vec<SpanData>) -> Result<(), StorageError>;
+
+    /// Writes spans read back from storage or an export, e.g. when
+    /// replaying. Backends with a write layout store them as they store
+    /// `write_spans` batches; by default each span is written as its own
+    /// JSON object keyed `<trace_id>/<span_id>.json`.
+    async fn write_stored_spans(&self, spans: Vec<StoredSpan>) -> Result<(), StorageError> {
+        for span in spans {
+            let data = serde_json::to_vec(&span).map_err(|e| StorageError::WriteFailed(e.to_string()))?;
+            self.write(&format!("{}/{}.json", span.trace_id, span.span_id), &data).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Trait defining read operations over stored spans.
+/// Implementations should return spans most recent first.
+#[async_trait]
+pub trait StorageReader: Send + Sync {
+    /// Lists up to `limit` stored spans, most recent first
+    async fn list_spans(&self, limit: usize) -> Result<Vec<SpanEntry>, StorageError>;
+
+    /// Lists one page of at most `max_keys` stored objects, continuing
+    /// after the page `continuation` was returned with. Returns the page and
+    /// the continuation of the next one, `None` after the last page. Backends
+    /// that cannot page return their whole listing as one page.
+    async fn list_span_page(
+        &self,
+        continuation: Option<String>,
+        _max_keys: usize,
+    ) -> Result<(Vec<SpanEntry>, Option<String>), StorageError> {
+        if continuation.is_some() {
+            return Ok((Vec::new(), None));
+        }
+        Ok((self.list_spans(usize::MAX).await?, None))
+    }
+
+    /// Reads a stored span by its key
+    async fn read_span(&self, key: &str) -> Result<StoredSpan, StorageError>;
+
+    /// Reads every span stored in an object. Per-span objects yield one span;
+    /// batch objects written with `WriteMode::PerBatch` yield all of theirs.
+    async fn read_object(&self, key: &str) -> Result<Vec<StoredSpan>, StorageError> {
+        Ok(vec![self.read_span(key).await?])
+    }
+
+    /// Reads several objects concurrently with bounded parallelism.
+    /// Results follow the order of `keys`; a batch object contributes one
+    /// result per span it holds, and a failed read contributes one error.
+    async fn read_spans(&self, keys: &[String]) -> Vec<Result<StoredSpan, StorageError>> {
+        stream::iter(keys.iter().cloned())
+            .map(|key| async move { self.read_object(&key).await })
+            .buffered(READ_CONCURRENCY)
+            .flat_map(|result| match result {
+                Ok(spans) => stream::iter(spans.into_iter().map(Ok).collect::<Vec<_>>()),
+                Err(e) => stream::iter(vec![Err(e)]),
+            })
+            .collect()
+            .await
+    }
+
+    /// Counts stored objects last modified within `[since, until]` without
+    /// reading their bodies, scanning at most `max_scan` keys. In per-batch
+    /// mode each object holds a whole batch, so this counts batches.
+    async fn count_spans(
+        &self,
+        max_scan: usize,
+        since: Option<SystemTime>,
+        until: Option<SystemTime>,
+    ) -> Result<SpanCount, StorageError> {
+        let entries = self.list_spans(max_scan.saturating_add(1)).await?;
+        let truncated = entries.len() > max_scan;
+        let count = entries
+            .iter()
+            .take(max_scan)
+            .filter(|entry| since.is_none_or(|since| entry.last_modified >= since))
+            .filter(|entry| until.is_none_or(|until| entry.last_modified <= until))
+            .count();
+
+        Ok(SpanCount { count, truncated })
+    }
+
+    /// Lists the distinct service names that have reported spans
+    async fn list_services(&self) -> Result<Vec<String>, StorageError>;
+
+    /// Deletes every per-span object stored for a trace, returning how many
+    /// were removed. Spans inside per-batch objects are not affected.
+    async fn delete_trace(&self, _trace_id: &str) -> Result<usize, StorageError> {
+        Err(StorageError::ConfigError("Deleting traces is not supported by this backend".into()))
+    }
+
+    /// Lists the keys of every object holding spans of a trace without
+    /// reading them. Returns `None` when the layout does not place a trace's
+    /// objects under a key prefix of their own.
+    async fn list_trace(&self, _trace_id: &str) -> Result<Option<Vec<String>>, StorageError> {
+        Ok(None)
+    }
+
+    /// Looks up to `limit` spans matching `search` in the span index, most
+    /// recent first. Returns `None` when the backend keeps no index.
+    async fn search_index(
+        &self,
+        _search: &SpanSearch,
+        _limit: usize,
+    ) -> Result<Option<Vec<SpanIndexEntry>>, StorageError> {
+        Ok(None)
+    }
+
+    /// Rebuilds the span index from every stored object, returning the
+    /// number of spans indexed
+    async fn rebuild_index(&self) -> Result<usize, StorageError> {
+        Err(StorageError::ConfigError("The span index is not supported by this backend".into()))
+    }
+
+    /// Merges span index segments of past hours, returning the number of
+    /// segments replaced
+    async fn compact_index(&self) -> Result<usize, StorageError> {
+        Err(StorageError::ConfigError("The span index is not supported by this backend".into()))
+    }
+
+    /// Finds a span by trace and span id, scanning at most `max_scan` of the
+    /// most recent objects. Backends that can derive a span's key override this.
+    async fn find_span(
+        &self,
+        trace_id: &str,
+        span_id: &str,
+        max_scan: usize,
+    ) -> Result<Option<StoredSpan>, StorageError> {
+        scan_for_span(self, trace_id, span_id, max_scan).await
+    }
+
+    /// Finds the key of the object holding a span, scanning like `find_span`
+    async fn find_span_key(
+        &self,
+        trace_id: &str,
+        span_id: &str,
+        max_scan: usize,
+    ) -> Result<Option<String>, StorageError> {
+        scan_for_span_key(self, trace_id, span_id, max_scan).await
+    }
+
+    /// Reads an object's bytes as stored, without parsing them
+    async fn read_raw(&self, _key: &str) -> Result<RawObject, StorageError> {
+        Err(StorageError::ConfigError("Raw reads are not supported by this backend".into()))
+    }
+
+    /// Returns the health status of the storage backend
+    fn get_health_status(&self) -> HealthStatus;
+}
+
+/// Searches the `max_scan` most recent objects for a span, skipping unreadable ones
+async fn scan_for_span<R: StorageReader + ?Sized>(
+    reader: &R,
+    trace_id: &str,
+    span_id: &str,
+    max_scan: usize,
+) -> Result<Option<StoredSpan>, StorageError> {
+    let keys: Vec<String> = reader.list_spans(max_scan).await?
+        .into_iter()
+        .map(|entry| entry.key)
+        .collect();
+
+    for result in reader.read_spans(&keys).await {
+        match result {
+            Ok(span) if span.trace_id == trace_id && span.span_id == span_id => return Ok(Some(span)),
+            Ok(_) => {}
+            Err(e) => warn!("Skipping unreadable object during span lookup: {}", e),
+        }
+    }
+    Ok(None)
+}
+
+/// Searches the `max_scan` most recent objects for the one holding a span,
+/// skipping unreadable ones
+async fn scan_for_span_key<R: StorageReader + ?Sized>(
+    reader: &R,
+    trace_id: &str,
+    span_id: &str,
+    max_scan: usize,
+) -> Result<Option<String>, StorageError> {
+    let entries = reader.list_spans(max_scan).await?;
+    let mut reads = stream::iter(entries)
+        .map(|entry| async move {
+            let result = reader.read_object(&entry.key).await;
+            (entry.key, result)
+        })
+        .buffered(READ_CONCURRENCY);
+
+    while let Some((key, result)) = reads.next().await {
+        match result {
+            Ok(spans) if spans.iter().any(|span| span.trace_id == trace_id && span.span_id == span_id) => {
+                return Ok(Some(key));
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Skipping unreadable object during span lookup: {}", e),
+        }
+    }
+    Ok(None)
+}
+
+/// An object's bytes exactly as stored, streamed as they are read
+pub struct RawObject {
+    /// Content type of the stored encoding
+    pub content_type: &'static str,
+    /// `Content-Encoding` of a compressed object, which is not decompressed
+    pub content_encoding: Option<String>,
+    /// Object bytes
+    pub body: BoxStream<'static, Result<Bytes, StorageError>>,
+}
+
+impl RawObject {
+    /// Wraps bytes already in memory
+    pub fn from_bytes(content_type: &'static str, body: Vec<u8>) -> Self {
+        Self {
+            content_type,
+            content_encoding: None,
+            body: stream::once(async move { Ok(body.into()) }).boxed(),
+        }
+    }
+
+    /// Reads the whole body into memory
+    pub async fn into_bytes(self) -> Result<Vec<u8>, StorageError> {
+        let mut data = Vec::new();
+        let mut body = self.body;
+        while let Some(chunk) = body.next().await {
+            data.extend_from_slice(&chunk?);
+        }
+        Ok(data)
+    }
 }
 
-/// Represents a stored span with serializable fields
-#[derive(Debug, Serialize, Deserialize)]
+/// Represents a stored span with serializable fields.
+///
+/// Spans are always written with the field names below. When reading, the
+/// camelCase names used by other systems (`traceID`/`traceId`, `spanID`/`spanId`,
+/// `parentSpanID`/`parentSpanId`, `operationName`, `startTimeUnixNano`, `endTimeUnixNano`, `statusMessage`,
+/// `serviceName`, `scopeName`, `scopeVersion`) are accepted as well, so
+/// migrated data can be queried without rewriting it. Only names carrying
+/// the same unit are aliased; Jaeger's microsecond `startTime` is not.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct StoredSpan {
+    /// Layout version of the object; 1 for objects written before versions were recorded
+    #[serde(default = "legacy_schema_version")]
+    pub schema_version: u32,
     /// Unique identifier for the trace this span belongs to
+    #[serde(alias = "traceID", alias = "traceId")]
     pub trace_id: String,
     /// Unique identifier for this span
+    #[serde(alias = "spanID", alias = "spanId")]
     pub span_id: String,
+    /// Identifier of the parent span; `None` for root spans and for objects
+    /// written before schema version 3
+    #[serde(default, alias = "parentSpanID", alias = "parentSpanId")]
+    pub parent_span_id: Option<String>,
     /// Name of the operation this span represents
+    #[serde(alias = "operationName")]
     pub name: String,
-    /// Type of span (client, server, etc.)
+    /// Type of span: `Client`, `Server`, `Producer`, `Consumer` or `Internal`
     pub kind: String,
-    /// Start time in nanoseconds since epoch
+    /// Start time in nanoseconds since epoch; stored objects may hold it as
+    /// an RFC3339 string instead
+    #[serde(alias = "startTimeUnixNano", deserialize_with = "deserialize_timestamp")]
     pub start_time: u64,
-    /// End time in nanoseconds since epoch
+    /// End time in nanoseconds since epoch; stored objects may hold it as
+    /// an RFC3339 string instead
+    #[serde(alias = "endTimeUnixNano", deserialize_with = "deserialize_timestamp")]
     pub end_time: u64,
-    /// Status of the operation (success, error, etc.)
+    /// `end_time - start_time`, computed when written; 0 when end precedes start
+    #[serde(default)]
+    pub duration_ns: u64,
+    /// Status of the operation: `Unset`, `Ok` or `Error`
     pub status: String,
+    /// Description attached to an `Error` status
+    #[serde(default, alias = "statusMessage")]
+    pub status_message: Option<String>,
+    /// Value of the `service.name` resource attribute, if reported
+    #[serde(default, alias = "serviceName")]
+    pub service_name: Option<String>,
+    /// Name of the instrumentation scope (tracer) that produced the span
+    #[serde(default, alias = "scopeName")]
+    pub scope_name: Option<String>,
+    /// Version of the instrumentation scope, if reported
+    #[serde(default, alias = "scopeVersion")]
+    pub scope_version: Option<String>,
+    /// Attributes describing the operation
+    #[serde(default)]
+    pub attributes: BTreeMap<String, serde_json::Value>,
+    /// Timestamped events recorded during the span
+    #[serde(default)]
+    pub events: Vec<StoredEvent>,
+    /// Links to related spans, possibly in other traces
+    #[serde(default)]
+    pub links: Vec<StoredLink>,
+}
+
+impl StoredSpan {
+    /// Rewrites the span's and its links' ids in canonical form, lowercase
+    /// and zero-padded, for objects written by other systems. Ids that are
+    /// not hex are left as they are.
+    pub fn normalize_ids(&mut self) {
+        normalize_id(&mut self.trace_id, normalize_trace_id);
+        normalize_id(&mut self.span_id, normalize_span_id);
+        if let Some(parent_span_id) = &mut self.parent_span_id {
+            normalize_id(parent_span_id, normalize_span_id);
+        }
+        for link in &mut self.links {
+            normalize_id(&mut link.trace_id, normalize_trace_id);
+            normalize_id(&mut link.span_id, normalize_span_id);
+        }
+    }
+}
+
+fn normalize_id(id: &mut String, normalize: fn(&str) -> Option<String>) {
+    if let Some(normalized) = normalize(id) {
+        *id = normalized;
+    }
+}
+
+/// Represents a timestamped event recorded within a span
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StoredEvent {
+    /// Name of the event
+    pub name: String,
+    /// Event time in nanoseconds since epoch
+    #[serde(alias = "timeUnixNano")]
+    pub timestamp: u64,
+    /// Attributes describing the event
+    #[serde(default)]
+    pub attributes: BTreeMap<String, serde_json::Value>,
+}
+
+/// Represents a link from a span to another span
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StoredLink {
+    /// Trace the linked span belongs to
+    #[serde(alias = "traceID", alias = "traceId")]
+    pub trace_id: String,
+    /// Identifier of the linked span
+    #[serde(alias = "spanID", alias = "spanId")]
+    pub span_id: String,
+    /// Attributes describing the link
+    #[serde(default)]
+    pub attributes: BTreeMap<String, serde_json::Value>,
+}
+
+/// Result of counting stored spans
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+pub struct SpanCount {
+    /// Number of matching objects found
+    pub count: usize,
+    /// Whether scanning stopped at `max_scan` before every key was seen
+    pub truncated: bool,
 }
 
 /// Represents a span entry in storage with metadata
@@ -59,47 +516,269 @@ pub struct SpanEntry {
 
 /// S3-compatible storage implementation
 pub struct S3StorageWriter {
-    /// S3 client for storage operations
-    client: S3Client,
+    /// Endpoints holding the bucket, the primary first
+    endpoints: Vec<StorageEndpoint>,
+    /// Index in `endpoints` of the endpoint object writes currently go to
+    active_endpoint: AtomicUsize,
     /// Target bucket name
     bucket: String,
-    /// Key prefix for all stored objects
+    /// Key prefix for all stored objects, without leading or trailing slashes
     prefix: String,
+    /// Service names already recorded in the service index
+    known_services: Mutex<HashSet<String>>,
+    /// Object layout used by `write_spans`
+    write_mode: WriteMode,
+    /// Whether writes skip objects that already exist
+    idempotent_writes: bool,
+    /// Health monitor receiving duplicate-skip counts, if attached
+    health_check: Option<Arc<HealthCheck>>,
+    /// Metadata attached to every object in addition to per-object tags
+    object_metadata: HashMap<String, String>,
+    /// S3 tags attached to every object, filled in per object
+    object_tags: ObjectTags,
+    /// Encoding of written objects
+    format: StorageFormat,
+    /// Backoff policy for retried writes and index updates
+    retry: RetryConfig,
+    /// Key layout of per-span objects
+    key_template: KeyTemplate,
+    /// Whether writes add segments to the span search index
+    search_index: bool,
+    /// Whether JSON span objects are written indented
+    pretty_json: bool,
+    /// How span start and end times are written in JSON objects
+    timestamp_format: TimestampFormat,
+    /// How long one PUT attempt may take before it is abandoned
+    write_timeout: Duration,
+    /// Compression of JSON objects
+    compression: CompressionConfig,
+    /// Attribute keys recorded in span index entries
+    indexed_attributes: Vec<String>,
+}
+
+/// S3 client of one endpoint, with the name it is logged under
+struct StorageEndpoint {
+    name: String,
+    client: S3Client,
+    /// Cleared once the endpoint rejects conditional PUTs; a HEAD check is used instead
+    conditional_put_supported: AtomicBool,
+}
+
+impl StorageEndpoint {
+    fn new(name: String, client: S3Client) -> Self {
+        Self { name, client, conditional_put_supported: AtomicBool::new(true) }
+    }
+}
+
+/// Metadata, tags and encoding sent along with an object's body
+#[derive(Debug, Default)]
+struct PutOptions {
+    metadata: HashMap<String, String>,
+    /// `x-amz-tagging` query string, if the object is tagged
+    tagging: Option<String>,
+    /// `Content-Encoding` of a compressed body
+    content_encoding: Option<&'static str>,
 }
 
 impl S3StorageWriter {
     /// Creates a new S3StorageWriter instance
-    pub async fn new(bucket: String, prefix: String) -> Result<Self, StorageError> {
+    pub async fn new(
+        bucket: String,
+        prefix: String,
+        settings: &S3ClientSettings,
+    ) -> Result<Self, StorageError> {
         info!("Initializing S3 storage writer for bucket: {}", bucket);
         
-        let client = Self::create_s3_client().await?;
+        let client = Self::create_s3_client(settings).await?;
         Self::verify_bucket_access(&client, &bucket).await?;
 
-        Ok(Self { client, bucket, prefix })
+        let mut writer = Self::from_client(client, bucket, prefix);
+        writer.endpoints[0].name = settings.endpoint_name();
+        Ok(writer)
     }
 
-    /// Creates and configures an S3 client
-    async fn create_s3_client() -> Result<S3Client, StorageError> {
-        let credentials = Credentials::new(
-            "test", 
-            "test", 
-            None, 
-            None, 
-            "dummy"
-        );
+    /// Creates a writer around an existing S3 client without verifying bucket access
+    pub fn from_client(client: S3Client, bucket: String, prefix: String) -> Self {
+        Self {
+            endpoints: vec![StorageEndpoint::new("primary".to_string(), client)],
+            active_endpoint: AtomicUsize::new(0),
+            bucket,
+            prefix: normalize_prefix(&prefix),
+            known_services: Mutex::new(HashSet::new()),
+            write_mode: WriteMode::default(),
+            idempotent_writes: false,
+            health_check: None,
+            object_metadata: HashMap::new(),
+            object_tags: ObjectTags::default(),
+            format: StorageFormat::default(),
+            retry: RetryConfig::default(),
+            key_template: KeyTemplate::default(),
+            search_index: false,
+            pretty_json: false,
+            timestamp_format: TimestampFormat::default(),
+            write_timeout: DEFAULT_WRITE_TIMEOUT,
+            compression: CompressionConfig::default(),
+            indexed_attributes: Vec::new(),
+        }
+    }
 
-        let config = aws_config::defaults(aws_config::BehaviorVersion::latest())
-            .credentials_provider(credentials)
-            .endpoint_url("http://localhost:4566")
-            .region(Region::new("us-east-1"))
-            .load()
-            .await;
+    /// Sets the object layout used by `write_spans`
+    pub fn with_write_mode(mut self, write_mode: WriteMode) -> Self {
+        self.write_mode = write_mode;
+        self
+    }
 
-        let s3_config = S3Builder::from(&config)
-            .force_path_style(true)
-            .build();
+    /// Sets the backoff policy for retried writes and index updates
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Sets the key layout of per-span objects
+    pub fn with_key_template(mut self, key_template: KeyTemplate) -> Self {
+        self.key_template = key_template;
+        self
+    }
+
+    /// Makes writes idempotent: an object that already exists is left untouched.
+    /// Per-span keys are derived from trace and span ids and per-batch keys from
+    /// the span ids they contain, so a retried export maps onto the same keys.
+    pub fn with_idempotent_writes(mut self, enabled: bool) -> Self {
+        self.idempotent_writes = enabled;
+        self
+    }
+
+    /// Sets the encoding of written objects. Parquet always writes one file
+    /// per batch; objects of either format remain readable.
+    pub fn with_format(mut self, format: StorageFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Compresses JSON objects written from now on; reads decompress any
+    /// object by its `Content-Encoding`, whatever the current setting
+    pub fn with_compression(mut self, compression: CompressionConfig) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Attaches fixed metadata (e.g. an environment tag) to every written object
+    pub fn with_object_metadata(mut self, metadata: HashMap<String, String>) -> Self {
+        self.object_metadata = metadata;
+        self
+    }
+
+    /// Sets the S3 tags of written objects, e.g. to drive lifecycle rules
+    pub fn with_object_tags(mut self, tags: ObjectTags) -> Self {
+        self.object_tags = tags;
+        self
+    }
+
+    /// Maintains the span search index: each write of spans adds an index
+    /// segment describing them, which `search_index` reads
+    pub fn with_search_index(mut self, enabled: bool) -> Self {
+        self.search_index = enabled;
+        self
+    }
+
+    /// Writes JSON span objects indented, for reading them in an S3 browser;
+    /// compact by default. Reads accept either.
+    pub fn with_pretty_json(mut self, enabled: bool) -> Self {
+        self.pretty_json = enabled;
+        self
+    }
+
+    /// Sets the attribute keys recorded in span index entries, so searches
+    /// filtering on them need not read span objects; none by default
+    pub fn with_indexed_attributes(mut self, keys: Vec<String>) -> Self {
+        self.indexed_attributes = keys;
+        self
+    }
+
+    /// Sets how span start and end times are written in JSON objects;
+    /// `duration_ns` stays numeric. Reads accept either format.
+    pub fn with_timestamp_format(mut self, format: TimestampFormat) -> Self {
+        self.timestamp_format = format;
+        self
+    }
+
+    /// Sets how long one PUT attempt may take; a timed-out attempt is
+    /// retried like a transport error, so a hung connection cannot block
+    /// a batch worker indefinitely
+    pub fn with_write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// Adds an endpoint holding the same bucket that object writes fail over
+    /// to after those before it; reads, listings, deletes and index updates
+    /// stay on the primary. Bucket access is not verified, as a fallback may
+    /// well be down while the primary is up.
+    pub fn with_fallback_endpoint(mut self, name: impl Into<String>, client: S3Client) -> Self {
+        self.endpoints.push(StorageEndpoint::new(name.into(), client));
+        self
+    }
+
+    /// Adds fallback endpoints with the given settings, in failover order
+    pub async fn with_fallback_endpoints(mut self, settings: &[S3ClientSettings]) -> Result<Self, StorageError> {
+        for settings in settings {
+            let client = Self::create_s3_client(settings).await?;
+            self = self.with_fallback_endpoint(settings.endpoint_name(), client);
+        }
+        Ok(self)
+    }
+
+    /// Reports skipped duplicate writes and the active endpoint to the given health monitor
+    pub fn with_health_check(mut self, health_check: Arc<HealthCheck>) -> Self {
+        health_check.set_active_storage_endpoint(&self.active().name);
+        self.health_check = Some(health_check);
+        self
+    }
+
+    /// Returns the endpoint object writes currently go to
+    fn active(&self) -> &StorageEndpoint {
+        &self.endpoints[self.active_endpoint.load(Ordering::SeqCst)]
+    }
+
+    /// Returns the primary endpoint
+    fn primary(&self) -> &StorageEndpoint {
+        &self.endpoints[0]
+    }
+
+    /// Returns the client of the primary endpoint, which serves reads,
+    /// listings, deletes and index updates whichever endpoint writes go to
+    fn client(&self) -> &S3Client {
+        &self.primary().client
+    }
 
-        Ok(S3Client::from_conf(s3_config))
+    /// Switches object writes from the endpoint at `from` to the next one, cycling
+    /// back to the primary after the last. Does nothing if a concurrent write
+    /// already switched away from `from`.
+    fn fail_over(&self, from: usize, error: &StorageError) {
+        let to = (from + 1) % self.endpoints.len();
+        if self.active_endpoint.compare_exchange(from, to, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            return;
+        }
+        warn!(
+            "Writes to {} failed ({}), failing over to {}",
+            self.endpoints[from].name, error, self.endpoints[to].name
+        );
+        if let Some(health_check) = &self.health_check {
+            health_check.record_storage_failover(&self.endpoints[to].name);
+        }
+    }
+
+    /// Creates and configures an S3 client. Credentials come from the default
+    /// credential chain (environment, profile, instance role) for AWS and
+    /// custom endpoints alike.
+    async fn create_s3_client(settings: &S3ClientSettings) -> Result<S3Client, StorageError> {
+        let config = aws_config::defaults(aws_config::BehaviorVersion::latest()).load().await;
+        Ok(Self::client_for(&config, settings))
+    }
+
+    /// Builds an S3 client from loaded SDK config, keeping its credentials
+    fn client_for(config: &aws_config::SdkConfig, settings: &S3ClientSettings) -> S3Client {
+        S3Client::from_conf(settings.apply(S3Builder::from(config)).build())
     }
 
     /// Verifies access to the target bucket
@@ -116,138 +795,2693 @@ impl S3StorageWriter {
         }
     }
 
-    /// Constructs a full storage key with prefix
+    /// Constructs a full storage key from one relative to the prefix.
+    /// Every key written, listed or read is built here, so `get_full_key("")`
+    /// is the listing prefix of all objects.
     fn get_full_key(&self, key: &str) -> String {
         if self.prefix.is_empty() {
             key.to_string()
         } else {
-            format!("{}/{}", self.prefix.trim_end_matches('/'), key)
+            format!("{}/{}", self.prefix, key)
         }
     }
 
-    /// Lists spans in storage with pagination
-    pub async fn list_spans(&self, limit: usize) -> Result<Vec<SpanEntry>, StorageError> {
-        let objects = self.client
-            .list_objects_v2()
-            .bucket(&self.bucket)
-            .prefix(&format!("{}/", self.prefix))
-            .max_keys(limit as i32)
-            .send()
-            .await
-            .map_err(|e| StorageError::ReadFailed(e.to_string()))?;
+    /// Returns the full key of a key rendered from the key template;
+    /// templates without `{prefix}` are placed under the prefix
+    fn template_key(&self, rendered: &str) -> String {
+        if self.key_template.includes_prefix() {
+            rendered.to_string()
+        } else {
+            self.get_full_key(rendered)
+        }
+    }
 
-        let mut spans = Vec::new();
-        for object in objects.contents() {
-            if let (Some(key), Some(last_modified)) = (object.key(), object.last_modified()) {
-                let seconds: u64 = last_modified.secs()
-                    .try_into()
-                    .map_err(|_| StorageError::ReadFailed("Invalid timestamp".into()))?;
-                let system_time = UNIX_EPOCH + Duration::from_secs(seconds);
-                
-                spans.push(SpanEntry {
-                    key: key.to_string(),
-                    last_modified: system_time,
-                });
+    /// Returns the trace id of a per-span object from its full key
+    fn key_trace_id(&self, full_key: &str) -> Option<String> {
+        let key = if self.key_template.includes_prefix() {
+            full_key
+        } else {
+            full_key.strip_prefix(&self.get_full_key(""))?
+        };
+        self.key_template.trace_id_of(&self.prefix, key)
+    }
+
+    /// Stores spans as one batch object in the configured format,
+    /// returning the object's full key
+    async fn store_batch(&self, spans: &[StoredSpan]) -> Result<Option<String>, StorageError> {
+        if spans.is_empty() {
+            return Ok(None);
+        }
+        let extension = match self.format {
+            StorageFormat::Json => "json",
+            StorageFormat::Parquet => PARQUET_EXTENSION,
+        };
+        let key = self.key_template.hash_batch_key(if self.idempotent_writes {
+            idempotent_batch_key(spans, extension)
+        } else {
+            batch_key(Utc::now(), extension)
+        });
+        let data = match self.format {
+            StorageFormat::Json => encode_json(&spans, self.pretty_json, self.timestamp_format)?,
+            StorageFormat::Parquet => encode_parquet(spans)?,
+        };
+        let full_key = self.get_full_key(&key);
+        self.store(&full_key, &data, span_metadata(spans), &tag_fields(spans)).await?;
+        Ok(Some(full_key))
+    }
+
+    /// Returns index entries for spans stored in the object at `full_key`,
+    /// none when the search index is disabled
+    fn index_entries(&self, spans: &[StoredSpan], full_key: Option<&str>) -> Vec<SpanIndexEntry> {
+        match full_key {
+            Some(full_key) if self.search_index => spans
+                .iter()
+                .map(|span| self.index_entry(span, full_key))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Describes a span stored in the object at `full_key` with its indexed attributes
+    fn index_entry(&self, span: &StoredSpan, full_key: &str) -> SpanIndexEntry {
+        SpanIndexEntry::new(span, full_key).with_attributes(span, &self.indexed_attributes)
+    }
+
+    /// Writes one span index segment holding `entries` to the current partition
+    async fn write_index_segment(&self, entries: Vec<SpanIndexEntry>) -> Result<(), StorageError> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        self.put_index_segment(&self.get_full_key(&span_index_key(Utc::now())), entries).await
+    }
+
+    /// Stores a span index segment holding `entries` under its full key,
+    /// replacing any segment already there
+    async fn put_index_segment(&self, full_key: &str, entries: Vec<SpanIndexEntry>) -> Result<(), StorageError> {
+        let data = serde_json::to_vec(&SpanIndexSegment { entries })
+            .map_err(|e| StorageError::WriteFailed(e.to_string()))?;
+        self.put(self.primary(), full_key, &data, &PutOptions::default()).await
+    }
+
+    /// Lists the full keys of span index segments, newest partition first.
+    /// With `since` (nanoseconds since epoch), partitions written more than
+    /// `PARTITION_SKEW_MARGIN` before that hour are not listed, as they
+    /// cannot hold spans starting after it.
+    async fn index_segment_keys(&self, since: Option<u64>) -> Result<Vec<String>, StorageError> {
+        let start_after = since.map(|since| {
+            let since = DateTime::<Utc>::from(UNIX_EPOCH + Duration::from_nanos(since));
+            self.get_full_key(&span_index_partition(since - PARTITION_SKEW_MARGIN))
+        });
+        let mut keys: Vec<String> = self.list_from(&self.get_full_key(SPAN_INDEX_PREFIX), start_after)
+            .await?
+            .into_iter()
+            .map(|segment| segment.key)
+            .collect();
+        keys.sort_by(|a, b| segment_partition(b).cmp(segment_partition(a)));
+        Ok(keys)
+    }
+
+    /// Removes the entries pointing at any of `keys` from the span index,
+    /// rewriting each segment holding one in place and deleting segments
+    /// left empty. Unreadable segments are skipped.
+    async fn drop_index_entries(&self, keys: &HashSet<String>) -> Result<(), StorageError> {
+        let segments = self.index_segment_keys(None).await?;
+        let mut emptied = Vec::new();
+        let mut reads = stream::iter(segments)
+            .map(|key| async move { (self.read_index_segment(&key).await, key) })
+            .buffered(READ_CONCURRENCY);
+        while let Some((result, segment_key)) = reads.next().await {
+            let mut segment = match result {
+                Ok(segment) => segment,
+                Err(e) => {
+                    warn!("Skipping unreadable index segment: {}", e);
+                    continue;
+                }
+            };
+            let before = segment.entries.len();
+            segment.entries.retain(|entry| !keys.contains(&entry.key));
+            if segment.entries.is_empty() {
+                emptied.push(segment_key);
+            } else if segment.entries.len() < before {
+                self.put_index_segment(&segment_key, segment.entries).await?;
             }
         }
+        self.delete_objects(&emptied).await
+    }
 
-        spans.sort_by(|a, b| b.last_modified.cmp(&a.last_modified));
-        Ok(spans.into_iter().take(limit).collect())
+    /// Reads one span index segment by its full key
+    async fn read_index_segment(&self, full_key: &str) -> Result<SpanIndexSegment, StorageError> {
+        let data = self.get_bytes(full_key).await?;
+        serde_json::from_slice(&data).map_err(|e| StorageError::ReadFailed(format!("{}: {}", full_key, e)))
     }
 
-    /// Reads a stored span by its key
-    pub async fn read_span(&self, key: &str) -> Result<StoredSpan, StorageError> {
-        let response = self.client
+    /// Reads an object's body by its full key
+    async fn get_bytes(&self, full_key: &str) -> Result<Vec<u8>, StorageError> {
+        let response = self.client()
             .get_object()
             .bucket(&self.bucket)
-            .key(key)
+            .key(full_key)
             .send()
             .await
-            .map_err(|e| StorageError::ReadFailed(e.to_string()))?;
+            .map_err(StorageError::from)?;
 
+        let content_encoding = response.content_encoding().map(str::to_string);
         let data = response
             .body
             .collect()
             .await
             .map_err(|e| StorageError::ReadFailed(e.to_string()))?;
+        compression::decompress(data.into_bytes().to_vec(), content_encoding.as_deref())
+            .map_err(|e| StorageError::ReadFailed(format!("{}: {}", full_key, e)))
+    }
 
-        serde_json::from_slice(&data.into_bytes())
-            .map_err(|e| StorageError::ReadFailed(e.to_string()))
+    /// Lists every object under a full key prefix, most recent first
+    async fn list_under(&self, prefix: &str) -> Result<Vec<SpanEntry>, StorageError> {
+        self.list_from(prefix, None).await
     }
 
-    pub fn get_health_status(&self) -> HealthStatus {
-        HealthStatus {
-            is_healthy: true,  // TODO: Implement proper health check
-            last_write: 0,     // TODO: Track last write
-            queue_size: 0,     // TODO: Track queue size
-            total_processed: 0, // TODO: Track processed count
-            failed_writes: 0,  // TODO: Track failed writes
+    /// Lists the objects under a full key prefix whose keys sort after
+    /// `start_after`, most recent first
+    async fn list_from(&self, prefix: &str, start_after: Option<String>) -> Result<Vec<SpanEntry>, StorageError> {
+        let mut entries = Vec::new();
+        let mut continuation_token = None;
+
+        loop {
+            let page = self.client()
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(prefix)
+                .set_start_after(start_after.clone())
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .map_err(StorageError::from)?;
+
+            for object in page.contents() {
+                if let (Some(key), Some(last_modified)) = (object.key(), object.last_modified()) {
+                    let seconds = u64::try_from(last_modified.secs())
+                        .map_err(|_| StorageError::ReadFailed("Invalid timestamp".into()))?;
+                    entries.push(SpanEntry {
+                        key: key.to_string(),
+                        last_modified: UNIX_EPOCH + Duration::from_secs(seconds),
+                    });
+                }
+            }
+
+            continuation_token = page.next_continuation_token().map(str::to_string);
+            if continuation_token.is_none() {
+                break;
+            }
         }
+
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.last_modified));
+        Ok(entries)
     }
-}
 
-#[async_trait]
-impl StorageWriter for S3StorageWriter {
-    async fn write(&self, key: &str, data: &[u8]) -> Result<(), StorageError> {
-        let full_key = self.get_full_key(key);
-        
-        info!("Writing object to S3: {}/{}", self.bucket, full_key);
-        
-        match self.client
-            .put_object()
+    /// Deletes objects by full key with one `DeleteObjects` request per
+    /// `DELETE_BATCH_SIZE` keys. Every batch is attempted; keys that could not
+    /// be deleted are counted and named in a `StorageError::BatchWriteFailed`.
+    pub async fn delete_objects(&self, keys: &[String]) -> Result<(), StorageError> {
+        let mut failures = Vec::new();
+        for chunk in keys.chunks(DELETE_BATCH_SIZE) {
+            match self.delete_batch(chunk).await {
+                Ok(failed) => failures.extend(failed),
+                Err(e) => failures.extend(chunk.iter().map(|key| format!("{}: {}", key, e))),
+            }
+        }
+        if failures.is_empty() {
+            return Ok(());
+        }
+        let listed: Vec<_> = failures.iter().take(MAX_LISTED_DELETE_FAILURES).cloned().collect();
+        Err(StorageError::BatchWriteFailed(format!(
+            "{} of {} objects not deleted: {}{}",
+            failures.len(),
+            keys.len(),
+            listed.join("; "),
+            if failures.len() > listed.len() { "; ..." } else { "" }
+        )))
+    }
+
+    /// Deletes up to `DELETE_BATCH_SIZE` objects in one request, returning
+    /// `key: reason` for each key S3 reports as not deleted
+    async fn delete_batch(&self, keys: &[String]) -> Result<Vec<String>, StorageError> {
+        let objects = keys
+            .iter()
+            .map(|key| ObjectIdentifier::builder().key(key).build())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| StorageError::WriteFailed(e.to_string()))?;
+        let delete = Delete::builder()
+            .set_objects(Some(objects))
+            .quiet(true)
+            .build()
+            .map_err(|e| StorageError::WriteFailed(e.to_string()))?;
+        let output = self.client()
+            .delete_objects()
             .bucket(&self.bucket)
-            .key(&full_key)
-            .body(data.to_vec().into())
+            .delete(delete)
             .send()
             .await
-        {
-            Ok(_) => {
+            .map_err(StorageError::from)?;
+
+        Ok(output
+            .errors()
+            .iter()
+            .map(|failure| format!(
+                "{}: {}",
+                failure.key().unwrap_or_default(),
+                failure.message().or(failure.code()).unwrap_or_default()
+            ))
+            .collect())
+    }
+
+    /// Stores an object under its full key, tagged with the configured
+    /// metadata plus `metadata` and the configured tags rendered from `fields`.
+    /// JSON bodies are compressed as configured. A write that still fails
+    /// after retries is tried again on the next fallback endpoint, if any.
+    async fn store(
+        &self,
+        full_key: &str,
+        data: &[u8],
+        metadata: HashMap<String, String>,
+        fields: &TagFields,
+    ) -> Result<(), StorageError> {
+        let mut options = PutOptions {
+            metadata: self.object_metadata.clone(),
+            tagging: self.object_tags.render(fields),
+            content_encoding: None,
+        };
+        options.metadata.extend(metadata);
+
+        let compressed;
+        let data = if self.compression.algorithm != CompressionAlgorithm::None && !is_parquet(full_key) {
+            compressed = compression::compress(data, &self.compression)?;
+            options.content_encoding = compression::content_encoding(self.compression.algorithm);
+            &compressed
+        } else {
+            data
+        };
+
+        // Each endpoint is tried at most once, after its own retries
+        let mut failovers = 0;
+        loop {
+            let endpoint = self.active_endpoint.load(Ordering::SeqCst);
+            let result = if self.idempotent_writes {
+                self.put_if_absent(&self.endpoints[endpoint], full_key, data, &options).await
+            } else {
+                self.put(&self.endpoints[endpoint], full_key, data, &options).await
+            };
+            match result {
+                Err(e) if failovers + 1 < self.endpoints.len() => {
+                    self.fail_over(endpoint, &e);
+                    failovers += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Builds a PUT request for an object to `endpoint`, typed by its extension
+    fn put_request(
+        &self,
+        endpoint: &StorageEndpoint,
+        full_key: &str,
+        data: &[u8],
+        options: &PutOptions,
+    ) -> PutObjectFluentBuilder {
+        endpoint.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(full_key)
+            .content_type(content_type(full_key))
+            .set_content_encoding(options.content_encoding.map(str::to_string))
+            .set_metadata(Some(options.metadata.clone()))
+            .set_tagging(options.tagging.clone())
+            .body(data.to_vec().into())
+    }
+
+    /// Unconditionally stores an object under its full key on `endpoint`.
+    /// Throttling, server and transport errors are retried with backoff.
+    async fn put(
+        &self,
+        endpoint: &StorageEndpoint,
+        full_key: &str,
+        data: &[u8],
+        options: &PutOptions,
+    ) -> Result<(), StorageError> {
+        info!("Writing object to {}: {}/{}", endpoint.name, self.bucket, full_key);
+
+        match self.send_put(full_key, || self.put_request(endpoint, full_key, data, options)).await {
+            Ok(()) => {
                 info!("Successfully wrote object: {}/{}", self.bucket, full_key);
                 Ok(())
             }
             Err(e) => {
                 error!("Failed to write object {}/{}: {}", self.bucket, full_key, e);
-                Err(StorageError::WriteFailed(e.to_string()))
+                Err(e)
             }
         }
     }
 
-    async fn write_batch(&self, entries: Vec<(&str, &[u8])>) -> Result<(), StorageError> {
-        for (key, data) in entries {
-            self.write(key, data).await?;
+    /// Stores an object on `endpoint` only if its key does not exist there yet.
+    /// Uses a conditional PUT (`If-None-Match: *`), retried like `put`;
+    /// endpoints that reject it fall back to a HEAD before the PUT, which can
+    /// race with concurrent writers.
+    async fn put_if_absent(
+        &self,
+        endpoint: &StorageEndpoint,
+        full_key: &str,
+        data: &[u8],
+        options: &PutOptions,
+    ) -> Result<(), StorageError> {
+        if endpoint.conditional_put_supported.load(Ordering::SeqCst) {
+            info!("Writing object to {} if absent: {}/{}", endpoint.name, self.bucket, full_key);
+            let request = || self.put_request(endpoint, full_key, data, options).if_none_match("*");
+
+            match self.send_put(full_key, request).await {
+                Ok(()) => {
+                    info!("Successfully wrote object: {}/{}", self.bucket, full_key);
+                    return Ok(());
+                }
+                // Also seen when an earlier attempt that timed out did store the object
+                Err(e) if e.kind() == Some(StorageErrorKind::PreconditionFailed) => {
+                    self.record_duplicate(full_key);
+                    return Ok(());
+                }
+                Err(e) if e.kind() == Some(StorageErrorKind::NotImplemented) => {
+                    warn!("{} does not support conditional PUT, falling back to HEAD checks", endpoint.name);
+                    endpoint.conditional_put_supported.store(false, Ordering::SeqCst);
+                }
+                Err(e) => {
+                    error!("Failed to write object {}/{}: {}", self.bucket, full_key, e);
+                    return Err(e);
+                }
+            }
         }
-        Ok(())
-    }
 
-    async fn flush(&self) -> Result<(), StorageError> {
-        // S3 writes are immediate, no need to flush
-        Ok(())
+        if self.object_exists(endpoint, full_key).await? {
+            self.record_duplicate(full_key);
+            return Ok(());
+        }
+        self.put(endpoint, full_key, data, options).await
     }
 
-    async fn write_spans(&self, spans: Vec<SpanData>) -> Result<(), StorageError> {
-        for span in spans {
-            let key = format!("{}/{}/{}.json", 
-                self.prefix,
-                span.span_context.trace_id(),
-                span.span_context.span_id()
-            );
+    /// Sends the PUT built by `request`, retrying attempts that time out or
+    /// fail with throttling, server or transport errors with backoff. Other
+    /// errors are returned as they are, for the caller to log.
+    async fn send_put(
+        &self,
+        full_key: &str,
+        request: impl Fn() -> PutObjectFluentBuilder,
+    ) -> Result<(), StorageError> {
+        let mut backoff = Backoff::new(&self.retry);
+        loop {
+            let e = match self.timed_write(full_key, request().send()).await {
+                Ok(Ok(_)) => return Ok(()),
+                Ok(Err(e)) => StorageError::from(e),
+                Err(timeout) => match backoff.next() {
+                    Some(delay) => {
+                        warn!("{}, retrying in {:?}", timeout, delay);
+                        tokio::time::sleep(delay).await;
+                        continue;
+                    }
+                    None => return Err(timeout),
+                },
+            };
+            if !e.is_retryable() {
+                return Err(e);
+            }
+            match backoff.next_after(e.retry_after()) {
+                Some(delay) => {
+                    warn!("Failed to write object {}/{}, retrying in {:?}: {}", self.bucket, full_key, delay, e);
+                    tokio::time::sleep(delay).await;
+                }
+                None => {
+                    return Err(StorageError::RetryLimitExceeded(format!(
+                        "Write of {} failed after {} retries: {}", full_key, self.retry.max_retries, e
+                    )));
+                }
+            }
+        }
+    }
 
-            // Convert SpanData to a serializable format
-            let span_json = json!({
-                "trace_id": span.span_context.trace_id().to_string(),
-                "span_id": span.span_context.span_id().to_string(),
-                "name": span.name,
-                "kind": format!("{:?}", span.span_kind),
-                "start_time": span.start_time.duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos(),
-                "end_time": span.end_time.duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos(),
-                "status": format!("{:?}", span.status),
-            });
+    /// Runs one write request, failing with `StorageError::WriteFailed` once
+    /// `write_timeout` elapses
+    async fn timed_write<F: std::future::Future>(&self, full_key: &str, request: F) -> Result<F::Output, StorageError> {
+        tokio::time::timeout(self.write_timeout, request).await.map_err(|_| {
+            StorageError::WriteFailed(format!(
+                "Write of {}/{} timed out after {}ms",
+                self.bucket, full_key, self.write_timeout.as_millis()
+            ))
+        })
+    }
+
+    /// Returns whether an object exists under the full key on `endpoint`. The
+    /// HEAD is bounded by `write_timeout` and retried like the PUT it precedes.
+    async fn object_exists(&self, endpoint: &StorageEndpoint, full_key: &str) -> Result<bool, StorageError> {
+        let mut backoff = Backoff::new(&self.retry);
+        loop {
+            let request = endpoint.client.head_object().bucket(&self.bucket).key(full_key).send();
+            let e = match self.timed_write(full_key, request).await {
+                Ok(Ok(_)) => return Ok(true),
+                Ok(Err(e)) => match StorageError::from(e) {
+                    e if e.kind() == Some(StorageErrorKind::NotFound) => return Ok(false),
+                    e if !e.is_retryable() => return Err(e),
+                    e => e,
+                },
+                Err(timeout) => timeout,
+            };
+            match backoff.next_after(e.retry_after()) {
+                Some(delay) => {
+                    warn!("Failed to check object {}/{}, retrying in {:?}: {}", self.bucket, full_key, delay, e);
+                    tokio::time::sleep(delay).await;
+                }
+                None => return Err(e),
+            }
+        }
+    }
+
+    /// Logs and counts a write skipped because the object already exists
+    fn record_duplicate(&self, full_key: &str) {
+        info!("Skipping duplicate object: {}/{}", self.bucket, full_key);
+        if let Some(health_check) = &self.health_check {
+            health_check.record_duplicate_skipped();
+        }
+    }
+
+    /// Reads the service index along with its ETag, if it exists
+    async fn read_service_index(&self) -> Result<(ServiceIndex, Option<String>), StorageError> {
+        let result = self.client()
+            .get_object()
+            .bucket(&self.bucket)
+            .key(self.get_full_key(SERVICE_INDEX_KEY))
+            .send()
+            .await;
+
+        match result {
+            Ok(response) => {
+                let e_tag = response.e_tag().map(str::to_string);
+                let data = response
+                    .body
+                    .collect()
+                    .await
+                    .map_err(|e| StorageError::ReadFailed(e.to_string()))?;
+                let index = serde_json::from_slice(&data.into_bytes())
+                    .map_err(|e| StorageError::ReadFailed(e.to_string()))?;
+                Ok((index, e_tag))
+            }
+            Err(e) => match StorageError::from(e) {
+                e if e.kind() == Some(StorageErrorKind::NotFound) => Ok((ServiceIndex::default(), None)),
+                e => Err(e),
+            },
+        }
+    }
+
+    /// Records newly seen service names in the service index.
+    /// Names already known to this writer are skipped without touching storage.
+    async fn record_services(&self, services: BTreeSet<String>) -> Result<(), StorageError> {
+        let new_services: BTreeSet<String> = {
+            let known = self.known_services.lock().unwrap();
+            services.into_iter().filter(|s| !known.contains(s)).collect()
+        };
+        if new_services.is_empty() {
+            return Ok(());
+        }
+
+        self.update_service_index(&new_services).await?;
+        self.known_services.lock().unwrap().extend(new_services);
+        Ok(())
+    }
+
+    /// Merges service names into the index with a read-modify-write.
+    /// The PUT is conditional on the ETag that was read, so a concurrent
+    /// update causes a precondition failure and another merge attempt after a backoff.
+    async fn update_service_index(&self, services: &BTreeSet<String>) -> Result<(), StorageError> {
+        let key = self.get_full_key(SERVICE_INDEX_KEY);
+
+        let mut backoff = Backoff::new(&self.retry);
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let (mut index, e_tag) = self.read_service_index().await?;
+            if !index.merge(services) {
+                return Ok(());
+            }
 
-            let data = serde_json::to_vec(&span_json)
+            let data = serde_json::to_vec(&index)
                 .map_err(|e| StorageError::WriteFailed(e.to_string()))?;
+            let request = self.client()
+                .put_object()
+                .bucket(&self.bucket)
+                .key(&key)
+                .body(data.into());
+            let request = match e_tag {
+                Some(e_tag) => request.if_match(e_tag),
+                None => request.if_none_match("*"),
+            };
+
+            match request.send().await.map_err(StorageError::from) {
+                Ok(_) => {
+                    info!("Updated service index with {} services", index.services.len());
+                    return Ok(());
+                }
+                Err(e) if e.kind() == Some(StorageErrorKind::PreconditionFailed) => match backoff.next() {
+                    Some(delay) => {
+                        warn!("Service index changed concurrently (attempt {}), retrying in {:?}", attempt, delay);
+                        tokio::time::sleep(delay).await;
+                    }
+                    None => {
+                        return Err(StorageError::RetryLimitExceeded(format!(
+                            "Service index update conflicted {} times", attempt
+                        )));
+                    }
+                },
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl StorageReader for S3StorageWriter {
+    /// Lists spans in storage with pagination
+    async fn list_spans(&self, limit: usize) -> Result<Vec<SpanEntry>, StorageError> {
+        let mut spans = Vec::new();
+        let mut continuation_token = None;
+
+        loop {
+            let objects = self.client()
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(self.get_full_key(""))
+                .max_keys(limit.min(i32::MAX as usize) as i32)
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .map_err(StorageError::from)?;
+
+            spans.extend(span_entries(objects.contents())?);
+
+            continuation_token = objects.next_continuation_token().map(str::to_string);
+            if continuation_token.is_none() || spans.len() >= limit {
+                break;
+            }
+        }
+
+        spans.sort_by_key(|span| std::cmp::Reverse(span.last_modified));
+        Ok(spans.into_iter().take(limit).collect())
+    }
+
+    /// Lists one `ListObjectsV2` page in key order; the continuation is the
+    /// page's continuation token
+    async fn list_span_page(
+        &self,
+        continuation: Option<String>,
+        max_keys: usize,
+    ) -> Result<(Vec<SpanEntry>, Option<String>), StorageError> {
+        let objects = self.client()
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(self.get_full_key(""))
+            .max_keys(max_keys.min(i32::MAX as usize) as i32)
+            .set_continuation_token(continuation)
+            .send()
+            .await
+            .map_err(StorageError::from)?;
+
+        let continuation = objects.next_continuation_token().map(str::to_string);
+        Ok((span_entries(objects.contents())?, continuation))
+    }
+
+    /// Reads a stored span by its key; fails for batch objects holding several spans
+    async fn read_span(&self, key: &str) -> Result<StoredSpan, StorageError> {
+        let mut spans = self.read_object(key).await?;
+        if spans.len() != 1 {
+            return Err(StorageError::ReadFailed(format!(
+                "Object {} holds {} spans", key, spans.len()
+            )));
+        }
+        Ok(spans.remove(0))
+    }
+
+    /// Reads a per-span object or a batch object by its key
+    async fn read_object(&self, key: &str) -> Result<Vec<StoredSpan>, StorageError> {
+        let data = self.get_bytes(key).await?;
+        if is_parquet(key) {
+            return decode_parquet(data.into());
+        }
+        parse_stored_spans(&data)
+    }
+
+    /// Lists the distinct service names recorded in the service index
+    async fn list_services(&self) -> Result<Vec<String>, StorageError> {
+        let (index, _) = self.read_service_index().await?;
+        Ok(index.services.into_iter().collect())
+    }
+
+    /// Per-span JSON objects of a trace share the key template's trace
+    /// prefix; batch layouts mix traces in one object
+    async fn list_trace(&self, trace_id: &str) -> Result<Option<Vec<String>>, StorageError> {
+        if (self.format, self.write_mode) != (StorageFormat::Json, WriteMode::PerSpan) {
+            return Ok(None);
+        }
+        let Some(prefix) = self.key_template.trace_prefix(&self.prefix, trace_id) else {
+            return Ok(None);
+        };
+        let entries = self.list_under(&self.template_key(&prefix)).await?;
+        Ok(Some(entries.into_iter().map(|entry| entry.key).collect()))
+    }
+
+    /// Lists the trace's objects page by page, removing each page with one
+    /// `DeleteObjects` call, then drops their span index entries
+    async fn delete_trace(&self, trace_id: &str) -> Result<usize, StorageError> {
+        // Without a per-trace key prefix, list everything and parse keys back
+        let (prefix, parse_keys) = match self.key_template.trace_prefix(&self.prefix, trace_id) {
+            Some(prefix) => (self.template_key(&prefix), false),
+            None => (self.get_full_key(""), true),
+        };
+        let mut deleted = HashSet::new();
+        let mut continuation_token = None;
+
+        loop {
+            let page = self.client()
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(&prefix)
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .map_err(StorageError::from)?;
+
+            let keys: Vec<String> = page.contents()
+                .iter()
+                .filter_map(|object| object.key())
+                .filter(|key| !parse_keys || self.key_trace_id(key).as_deref() == Some(trace_id))
+                .map(str::to_string)
+                .collect();
+
+            self.delete_objects(&keys).await?;
+            deleted.extend(keys);
+
+            continuation_token = page.next_continuation_token().map(str::to_string);
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+
+        if self.search_index && !deleted.is_empty() {
+            self.drop_index_entries(&deleted).await?;
+        }
+        info!("Deleted {} objects for trace {}", deleted.len(), trace_id);
+        Ok(deleted.len())
+    }
+
+    /// Reads index segments newest partition first until `limit` entries
+    /// match, skipping partitions written before a search's `start`. The
+    /// partition reaching `limit` is read whole, as its segments are not in
+    /// start time order, before the newest `limit` entries are kept.
+    /// Searches filtering on attributes the index does not record are
+    /// left to a scan.
+    async fn search_index(
+        &self,
+        search: &SpanSearch,
+        limit: usize,
+    ) -> Result<Option<Vec<SpanIndexEntry>>, StorageError> {
+        let indexed = |key: &String| self.indexed_attributes.contains(key);
+        if !self.search_index || !search.attributes.iter().all(|(key, _)| indexed(key)) {
+            return Ok(None);
+        }
+        let segments = self.index_segment_keys(search.start).await?;
+
+        let mut entries = Vec::new();
+        let mut partition = None;
+        let mut reads = stream::iter(segments)
+            .map(|key| async move { (self.read_index_segment(&key).await, key) })
+            .buffered(READ_CONCURRENCY);
+        while let Some((result, key)) = reads.next().await {
+            let segment_partition = segment_partition(&key).to_string();
+            // Older partitions only hold spans that started earlier
+            if entries.len() >= limit && partition.as_ref() != Some(&segment_partition) {
+                break;
+            }
+            partition = Some(segment_partition);
+            match result {
+                Ok(segment) => entries.extend(segment.entries.into_iter().filter(|entry| search.matches(entry))),
+                Err(e) => warn!("Skipping unreadable index segment: {}", e),
+            }
+        }
+
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.start_time));
+        entries.truncate(limit);
+        Ok(Some(entries))
+    }
+
+    /// Indexes every stored object into new segments, then deletes the
+    /// segments that existed before; searches meanwhile may see duplicates
+    async fn rebuild_index(&self) -> Result<usize, StorageError> {
+        let previous: Vec<String> = self.list_under(&self.get_full_key(SPAN_INDEX_PREFIX)).await?
+            .into_iter()
+            .map(|segment| segment.key)
+            .collect();
+        let keys: Vec<String> = self.list_spans(usize::MAX).await?
+            .into_iter()
+            .map(|entry| entry.key)
+            .collect();
+
+        let mut indexed = 0;
+        let mut entries = Vec::new();
+        let mut reads = stream::iter(keys)
+            .map(|key| async move { (self.read_object(&key).await, key) })
+            .buffered(READ_CONCURRENCY);
+        while let Some((result, key)) = reads.next().await {
+            match result {
+                Ok(spans) => entries.extend(spans.iter().map(|span| self.index_entry(span, &key))),
+                Err(e) => warn!("Skipping unreadable object {} during index rebuild: {}", key, e),
+            }
+            if entries.len() >= REBUILD_SEGMENT_ENTRIES {
+                indexed += entries.len();
+                self.write_index_segment(std::mem::take(&mut entries)).await?;
+            }
+        }
+        indexed += entries.len();
+        self.write_index_segment(entries).await?;
+
+        self.delete_objects(&previous).await?;
+        info!("Rebuilt span index with {} spans, replacing {} segments", indexed, previous.len());
+        Ok(indexed)
+    }
+
+    /// Merges the segments of each partition older than `PARTITION_SKEW_MARGIN`
+    /// into segments of up to `REBUILD_SEGMENT_ENTRIES` entries in the same
+    /// partition, then deletes the originals. Partitions holding an
+    /// unreadable segment are left as they are.
+    async fn compact_index(&self) -> Result<usize, StorageError> {
+        let open = self.get_full_key(&span_index_partition(Utc::now() - PARTITION_SKEW_MARGIN));
+        let mut partitions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for key in self.index_segment_keys(None).await? {
+            let partition = segment_partition(&key).to_string();
+            if partition < open {
+                partitions.entry(partition).or_default().push(key);
+            }
+        }
+
+        let mut replaced = 0;
+        for (partition, segments) in partitions.into_iter().filter(|(_, segments)| segments.len() > 1) {
+            let reads: Result<Vec<SpanIndexSegment>, StorageError> = stream::iter(segments.clone())
+                .map(|key| async move { self.read_index_segment(&key).await })
+                .buffered(READ_CONCURRENCY)
+                .collect::<Vec<_>>()
+                .await
+                .into_iter()
+                .collect();
+            let entries: Vec<SpanIndexEntry> = match reads {
+                Ok(reads) => reads.into_iter().flat_map(|segment| segment.entries).collect(),
+                Err(e) => {
+                    warn!("Not compacting index partition {}: {}", partition, e);
+                    continue;
+                }
+            };
+            if entries.len().div_ceil(REBUILD_SEGMENT_ENTRIES) >= segments.len() {
+                continue;
+            }
+
+            for chunk in entries.chunks(REBUILD_SEGMENT_ENTRIES) {
+                self.put_index_segment(&format!("{}/{}.json", partition, Uuid::new_v4()), chunk.to_vec()).await?;
+            }
+            self.delete_objects(&segments).await?;
+            replaced += segments.len();
+        }
+        info!("Compacted span index, replacing {} segments", replaced);
+        Ok(replaced)
+    }
+
+    /// Fetches per-span JSON objects directly by key; batch layouts and
+    /// key templates using `{date}` or `{service}` are scanned
+    async fn find_span(
+        &self,
+        trace_id: &str,
+        span_id: &str,
+        max_scan: usize,
+    ) -> Result<Option<StoredSpan>, StorageError> {
+        let key = self.key_template.span_key(&self.prefix, trace_id, span_id);
+        let key = match key {
+            Some(key) if self.format == StorageFormat::Json && self.write_mode == WriteMode::PerSpan => key,
+            _ => return scan_for_span(self, trace_id, span_id, max_scan).await,
+        };
+
+        let full_key = self.template_key(&key);
+        if !self.object_exists(self.primary(), &full_key).await? {
+            return Ok(None);
+        }
+        self.read_span(&full_key).await.map(Some)
+    }
+
+    /// Derives per-span JSON keys like `find_span`; other layouts are scanned
+    async fn find_span_key(
+        &self,
+        trace_id: &str,
+        span_id: &str,
+        max_scan: usize,
+    ) -> Result<Option<String>, StorageError> {
+        let key = self.key_template.span_key(&self.prefix, trace_id, span_id);
+        let key = match key {
+            Some(key) if self.format == StorageFormat::Json && self.write_mode == WriteMode::PerSpan => key,
+            _ => return scan_for_span_key(self, trace_id, span_id, max_scan).await,
+        };
+
+        let full_key = self.template_key(&key);
+        Ok(self.object_exists(self.primary(), &full_key).await?.then_some(full_key))
+    }
+
+    /// Streams the object's body as S3 returns it; compressed objects are
+    /// not decompressed and keep their `Content-Encoding`
+    async fn read_raw(&self, key: &str) -> Result<RawObject, StorageError> {
+        let response = self.client()
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(StorageError::from)?;
+
+        let content_encoding = response.content_encoding().map(str::to_string);
+        let body = stream::unfold(response.body, |mut body| async move {
+            let chunk = body.next().await?;
+            Some((chunk.map_err(|e| StorageError::ReadFailed(e.to_string())), body))
+        });
+        Ok(RawObject { content_type: content_type(key), content_encoding, body: body.boxed() })
+    }
+
+    fn get_health_status(&self) -> HealthStatus {
+        HealthStatus {
+            is_healthy: true,  // TODO: Implement proper health check
+            degraded: false,
+            last_write: 0,     // TODO: Track last write
+            queue_size: 0,     // TODO: Track queue size
+            total_processed: 0, // TODO: Track processed count
+            consecutive_failed_writes: 0,  // TODO: Track failed writes
+            total_failed_writes: 0,
+        }
+    }
+}
+
+#[async_trait]
+impl StorageWriter for S3StorageWriter {
+    async fn write(&self, key: &str, data: &[u8]) -> Result<(), StorageError> {
+        self.store(&self.get_full_key(key), data, HashMap::new(), &TagFields::default()).await
+    }
+
+    async fn write_batch(&self, entries: Vec<(&str, &[u8])>) -> Result<(), StorageError> {
+        for (key, data) in entries {
+            self.write(key, data).await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<(), StorageError> {
+        // S3 writes are immediate, no need to flush
+        Ok(())
+    }
+
+    async fn write_spans(&self, spans: Vec<SpanData>) -> Result<(), StorageError> {
+        self.write_stored_spans(spans.iter().map(StoredSpan::from).collect()).await
+    }
+
+    /// Stores spans in the configured format and write mode, then records
+    /// their services and index entries
+    async fn write_stored_spans(&self, spans: Vec<StoredSpan>) -> Result<(), StorageError> {
+        let services: BTreeSet<String> = spans.iter().filter_map(|span| span.service_name.clone()).collect();
+        let mut index_entries = Vec::new();
+
+        match (self.format, self.write_mode) {
+            (StorageFormat::Json, WriteMode::PerSpan) => {
+                for span in spans {
+                    let key = self.key_template.render(&KeyFields {
+                        prefix: &self.prefix,
+                        trace_id: &span.trace_id,
+                        span_id: &span.span_id,
+                        date: DateTime::<Utc>::from(UNIX_EPOCH + Duration::from_nanos(span.start_time)),
+                        service: span.service_name.as_deref(),
+                    });
+                    let data = encode_json(&span, self.pretty_json, self.timestamp_format)?;
+
+                    let full_key = self.template_key(&key);
+                    let span = [span];
+                    self.store(&full_key, &data, span_metadata(&span), &tag_fields(&span)).await?;
+                    if self.search_index {
+                        index_entries.push(self.index_entry(&span[0], &full_key));
+                    }
+                }
+            }
+            (_, WriteMode::PerTrace) => {
+                let mut traces: HashMap<String, Vec<StoredSpan>> = HashMap::new();
+                for span in spans {
+                    traces.entry(span.trace_id.clone()).or_default().push(span);
+                }
+                for trace in traces.into_values() {
+                    let key = self.store_batch(&trace).await?;
+                    index_entries.extend(self.index_entries(&trace, key.as_deref()));
+                }
+            }
+            // Parquet files always hold a whole batch
+            _ => {
+                let key = self.store_batch(&spans).await?;
+                index_entries.extend(self.index_entries(&spans, key.as_deref()));
+            }
+        }
 
-            self.write(&key, &data).await?;
+        // Spans are already persisted; an index failure is retried on the next write
+        if let Err(e) = self.record_services(services).await {
+            error!("Failed to update service index: {}", e);
+        }
+        // Spans missing from the index are found again by a rebuild
+        if let Err(e) = self.write_index_segment(index_entries).await {
+            error!("Failed to write span index segment: {}", e);
         }
         Ok(())
     }
 }
+
+/// Strips leading and trailing slashes from a key prefix, so `traces/`,
+/// `/traces` and `traces` name the same location and `/` means none
+pub fn normalize_prefix(prefix: &str) -> String {
+    prefix.trim_matches('/').to_string()
+}
+
+/// Returns the per-object metadata for a set of spans: `span-count`, plus
+/// `trace-id` when every span belongs to the same trace
+fn span_metadata(spans: &[StoredSpan]) -> HashMap<String, String> {
+    let mut metadata = HashMap::from([("span-count".to_string(), spans.len().to_string())]);
+    let mut trace_ids = spans.iter().map(|span| &span.trace_id);
+    if let Some(first) = trace_ids.next() {
+        if trace_ids.all(|trace_id| trace_id == first) {
+            metadata.insert("trace-id".to_string(), first.clone());
+        }
+    }
+    metadata
+}
+
+/// Returns the values tags are rendered from for an object holding `spans`:
+/// their service when all share one, and the date of the earliest start
+fn tag_fields(spans: &[StoredSpan]) -> TagFields {
+    let mut services = spans.iter().map(|span| span.service_name.as_ref());
+    let service = services.next().flatten().filter(|first| services.all(|s| s == Some(*first))).cloned();
+    TagFields {
+        service,
+        date: spans.iter()
+            .map(|span| span.start_time)
+            .min()
+            .map(|start| DateTime::<Utc>::from(UNIX_EPOCH + Duration::from_nanos(start))),
+    }
+}
+
+/// Returns the key of a new batch object: `YYYY/MM/DD/HH/<uuid>.<extension>`
+fn batch_key(now: DateTime<Utc>, extension: &str) -> String {
+    format!("{}/{}.{}", now.format("%Y/%m/%d/%H"), Uuid::new_v4(), extension)
+}
+
+/// Returns a deterministic batch key for idempotent writes: the hour of the
+/// earliest span start and a name-based UUID of the batch's trace/span ids
+fn idempotent_batch_key(spans: &[StoredSpan], extension: &str) -> String {
+    let mut ids: Vec<String> = spans
+        .iter()
+        .map(|span| format!("{}/{}", span.trace_id, span.span_id))
+        .collect();
+    ids.sort();
+    let start = UNIX_EPOCH + Duration::from_nanos(spans.iter().map(|span| span.start_time).min().unwrap_or(0));
+
+    format!(
+        "{}/{}.{}",
+        DateTime::<Utc>::from(start).format("%Y/%m/%d/%H"),
+        Uuid::new_v5(&Uuid::NAMESPACE_OID, ids.join("\n").as_bytes()),
+        extension
+    )
+}
+
+/// Returns the span objects of a listing page, skipping index objects
+fn span_entries(objects: &[aws_sdk_s3::types::Object]) -> Result<Vec<SpanEntry>, StorageError> {
+    let mut spans = Vec::new();
+    for object in objects {
+        if object.key().map(|key| key.contains(INDEX_SEGMENT)).unwrap_or(false) {
+            continue;
+        }
+        if let (Some(key), Some(last_modified)) = (object.key(), object.last_modified()) {
+            let seconds: u64 = last_modified.secs()
+                .try_into()
+                .map_err(|_| StorageError::ReadFailed("Invalid timestamp".into()))?;
+            spans.push(SpanEntry {
+                key: key.to_string(),
+                last_modified: UNIX_EPOCH + Duration::from_secs(seconds),
+            });
+        }
+    }
+    Ok(spans)
+}
+
+/// Returns whether a key names a Parquet batch object
+fn is_parquet(key: &str) -> bool {
+    key.rsplit_once('.').is_some_and(|(_, extension)| extension == PARQUET_EXTENSION)
+}
+
+/// Returns the content type of an object from its key
+fn content_type(key: &str) -> &'static str {
+    if is_parquet(key) {
+        PARQUET_CONTENT_TYPE
+    } else {
+        JSON_CONTENT_TYPE
+    }
+}
+
+/// Serializes a span object, or an array of them, as compact or, with
+/// `pretty`, indented JSON, with start and end times in the given format
+fn encode_json<T: Serialize>(
+    value: &T,
+    pretty: bool,
+    timestamps: TimestampFormat,
+) -> Result<Vec<u8>, StorageError> {
+    let encoded = match timestamps {
+        TimestampFormat::UnixNanos if pretty => serde_json::to_vec_pretty(value),
+        TimestampFormat::UnixNanos => serde_json::to_vec(value),
+        TimestampFormat::Rfc3339 => {
+            let mut json = serde_json::to_value(value).map_err(|e| StorageError::WriteFailed(e.to_string()))?;
+            match &mut json {
+                serde_json::Value::Array(spans) => spans.iter_mut().for_each(rfc3339_times),
+                span => rfc3339_times(span),
+            }
+            if pretty {
+                serde_json::to_vec_pretty(&json)
+            } else {
+                serde_json::to_vec(&json)
+            }
+        }
+    };
+    encoded.map_err(|e| StorageError::WriteFailed(e.to_string()))
+}
+
+/// Rewrites the nanosecond `start_time` and `end_time` of a serialized span as RFC3339 strings
+fn rfc3339_times(span: &mut serde_json::Value) {
+    for field in ["start_time", "end_time"] {
+        if let Some(time) = span.get_mut(field) {
+            if let Some(nanos) = time.as_u64() {
+                *time = json!(format_rfc3339(nanos));
+            }
+        }
+    }
+}
+
+/// Formats nanoseconds since epoch as RFC3339 in UTC, keeping every digit
+fn format_rfc3339(nanos: u64) -> String {
+    DateTime::<Utc>::from(UNIX_EPOCH + Duration::from_nanos(nanos)).to_rfc3339_opts(SecondsFormat::Nanos, true)
+}
+
+/// Schema version of objects written before `schema_version` existed
+fn legacy_schema_version() -> u32 {
+    1
+}
+
+/// Reads a timestamp stored as nanoseconds since epoch, either a number or
+/// a string of digits as in OTLP JSON, or as an RFC3339 string
+fn deserialize_timestamp<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Timestamp {
+        Nanos(u64),
+        Text(String),
+    }
+
+    match Timestamp::deserialize(deserializer)? {
+        Timestamp::Nanos(nanos) => Ok(nanos),
+        Timestamp::Text(text) => text
+            .parse()
+            .ok()
+            .or_else(|| {
+                let time = DateTime::parse_from_rfc3339(&text).ok()?;
+                u64::try_from(time.timestamp_nanos_opt()?).ok()
+            })
+            .ok_or_else(|| de::Error::custom(format!("invalid timestamp {}", text))),
+    }
+}
+
+/// Parses an object holding either a single span or an array of spans
+fn parse_stored_spans(data: &[u8]) -> Result<Vec<StoredSpan>, StorageError> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StoredObject {
+        Batch(Vec<StoredSpan>),
+        Single(Box<StoredSpan>),
+    }
+
+    let mut spans = match serde_json::from_slice(data).map_err(|e| StorageError::ReadFailed(e.to_string()))? {
+        StoredObject::Batch(spans) => spans,
+        StoredObject::Single(span) => vec![*span],
+    };
+    // Objects written by other systems or before durations were stored
+    for span in &mut spans {
+        span.normalize_ids();
+        if span.duration_ns == 0 {
+            span.duration_ns = span.end_time.saturating_sub(span.start_time);
+        }
+    }
+    Ok(spans)
+}
+
+impl<E> From<SdkError<E, HttpResponse>> for StorageError
+where
+    E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
+{
+    /// Classifies a failed S3 request by its response status and error code;
+    /// requests without a response are transport failures
+    fn from(error: SdkError<E, HttpResponse>) -> Self {
+        let status = error.raw_response().map(|response| response.status().as_u16());
+        let retry_after = error.raw_response()
+            .and_then(|response| response.headers().get("retry-after"))
+            .and_then(parse_retry_after);
+        let code = error.code().map(str::to_string);
+        let kind = match (&error, status) {
+            (SdkError::ConstructionFailure(_), _) => StorageErrorKind::InvalidRequest,
+            (_, Some(status)) => StorageErrorKind::from_response(status, code.as_deref()),
+            (_, None) => StorageErrorKind::Transport,
+        };
+        StorageError::Request {
+            kind,
+            code,
+            status,
+            message: DisplayErrorContext(&error).to_string(),
+            retry_after,
+        }
+    }
+}
+
+/// Parses a `Retry-After` header, either delay seconds or an HTTP date;
+/// a date in the past means no wait
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    Some((date.with_timezone(&Utc) - Utc::now()).to_std().unwrap_or_default())
+}
+
+/// Returns the `service.name` resource attribute of a span, if present
+pub fn service_name(span: &SpanData) -> Option<String> {
+    resource_attribute(span, "service.name")
+}
+
+/// Returns a resource attribute of a span as a string, if present
+pub fn resource_attribute(span: &SpanData, key: &str) -> Option<String> {
+    span.resource
+        .get(Key::from(key.to_string()))
+        .map(|value| value.to_string())
+}
+
+impl From<&SpanData> for StoredSpan {
+    fn from(span: &SpanData) -> Self {
+        let (status, status_message) = match &span.status {
+            Status::Unset => ("Unset", None),
+            Status::Ok => ("Ok", None),
+            Status::Error { description } => ("Error", Some(description.to_string())),
+        };
+
+        let start_time = unix_nanos(span.start_time);
+        let end_time = unix_nanos(span.end_time);
+        Self {
+            schema_version: SCHEMA_VERSION,
+            trace_id: trace_id_hex(span.span_context.trace_id()),
+            span_id: span_id_hex(span.span_context.span_id()),
+            parent_span_id: (span.parent_span_id != SpanId::INVALID).then(|| span_id_hex(span.parent_span_id)),
+            name: span.name.to_string(),
+            kind: span_kind_name(&span.span_kind).to_string(),
+            start_time,
+            end_time,
+            duration_ns: end_time.saturating_sub(start_time),
+            status: status.to_string(),
+            status_message,
+            service_name: service_name(span),
+            scope_name: Some(span.instrumentation_lib.name.to_string()).filter(|name| !name.is_empty()),
+            scope_version: span.instrumentation_lib.version.as_ref().map(|version| version.to_string()),
+            attributes: span.attributes.iter()
+                .map(|(key, value)| (key.to_string(), value_to_json(value)))
+                .collect(),
+            events: span.events.iter()
+                .map(|event| StoredEvent {
+                    name: event.name.to_string(),
+                    timestamp: unix_nanos(event.timestamp),
+                    attributes: attributes_to_json(&event.attributes),
+                })
+                .collect(),
+            links: span.links.iter()
+                .map(|link| StoredLink {
+                    trace_id: trace_id_hex(link.span_context.trace_id()),
+                    span_id: span_id_hex(link.span_context.span_id()),
+                    attributes: attributes_to_json(&link.attributes),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Returns the stored name of a span kind
+fn span_kind_name(kind: &SpanKind) -> &'static str {
+    match kind {
+        SpanKind::Client => "Client",
+        SpanKind::Server => "Server",
+        SpanKind::Producer => "Producer",
+        SpanKind::Consumer => "Consumer",
+        SpanKind::Internal => "Internal",
+    }
+}
+
+/// Converts a timestamp into nanoseconds since epoch
+fn unix_nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64
+}
+
+/// Converts OpenTelemetry attributes into a JSON map
+fn attributes_to_json(attributes: &[KeyValue]) -> BTreeMap<String, serde_json::Value> {
+    attributes
+        .iter()
+        .map(|kv| (kv.key.to_string(), value_to_json(&kv.value)))
+        .collect()
+}
+
+/// Converts an OpenTelemetry attribute value into JSON
+fn value_to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Bool(v) => json!(v),
+        Value::I64(v) => json!(v),
+        Value::F64(v) => json!(v),
+        Value::String(v) => json!(v.as_str()),
+        Value::Array(Array::Bool(v)) => json!(v),
+        Value::Array(Array::I64(v)) => json!(v),
+        Value::Array(Array::F64(v)) => json!(v),
+        Value::Array(Array::String(v)) => json!(v.iter().map(|s| s.as_str()).collect::<Vec<_>>()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::SpanReplayer;
+    use crate::test_support::span_data;
+    use aws_sdk_s3::config::Credentials;
+    use opentelemetry::sdk::Resource;
+    use opentelemetry::trace::{SpanId, TraceId};
+    use opentelemetry::KeyValue;
+    use std::borrow::Cow;
+
+    fn span_with_resource(resource: Resource) -> SpanData {
+        let mut span = span_data(TraceId::from_bytes([1; 16]), SpanId::from_bytes([2; 8]), "charge");
+        span.resource = Cow::Owned(resource);
+        span
+    }
+
+    #[test]
+    fn test_stored_span_records_service_name() {
+        let span = span_with_resource(Resource::new(vec![KeyValue::new(
+            "service.name",
+            "checkout",
+        )]));
+
+        let stored = StoredSpan::from(&span);
+        assert_eq!(stored.service_name.as_deref(), Some("checkout"));
+    }
+
+    #[test]
+    fn test_stored_span_without_service_name() {
+        let span = span_with_resource(Resource::empty());
+
+        let stored = StoredSpan::from(&span);
+        assert_eq!(stored.service_name, None);
+    }
+
+    #[test]
+    fn test_stable_kind_and_status_names() {
+        let kinds = [
+            (SpanKind::Client, "Client"),
+            (SpanKind::Server, "Server"),
+            (SpanKind::Producer, "Producer"),
+            (SpanKind::Consumer, "Consumer"),
+            (SpanKind::Internal, "Internal"),
+        ];
+        for (kind, name) in kinds {
+            let mut span = span_with_resource(Resource::empty());
+            span.span_kind = kind;
+            assert_eq!(StoredSpan::from(&span).kind, name);
+        }
+
+        let statuses = [
+            (Status::Unset, "Unset", None),
+            (Status::Ok, "Ok", None),
+            (Status::error("card declined"), "Error", Some("card declined")),
+        ];
+        for (status, name, message) in statuses {
+            let mut span = span_with_resource(Resource::empty());
+            span.status = status;
+            let stored = StoredSpan::from(&span);
+            assert_eq!(stored.status, name);
+            assert_eq!(stored.status_message.as_deref(), message);
+        }
+    }
+
+    #[test]
+    fn test_stored_span_fields() {
+        let mut span = span_with_resource(Resource::empty());
+        span.attributes.insert(KeyValue::new("http.status_code", 500_i64));
+        let stored = StoredSpan::from(&span);
+        let json = serde_json::to_value(&stored).unwrap();
+
+        assert_eq!(json["trace_id"], "01".repeat(16));
+        assert_eq!(json["span_id"], "02".repeat(8));
+        assert_eq!(json["name"], "charge");
+        assert_eq!(json["start_time"], 1_000);
+        assert_eq!(json["end_time"], 2_000);
+        assert_eq!(json["attributes"]["http.status_code"], 500);
+    }
+
+    #[test]
+    fn test_stored_span_duration() {
+        let mut span = span_with_resource(Resource::empty());
+        let stored = StoredSpan::from(&span);
+        assert_eq!(stored.duration_ns, stored.end_time - stored.start_time);
+
+        // End before start, e.g. clock skew between hosts
+        span.end_time = span.start_time - Duration::from_nanos(500);
+        assert_eq!(StoredSpan::from(&span).duration_ns, 0);
+    }
+
+    /// Reader whose GETs take a fixed delay and record peak concurrency
+    #[derive(Default)]
+    struct SlowReader {
+        in_flight: std::sync::atomic::AtomicUsize,
+        max_in_flight: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl StorageReader for SlowReader {
+        async fn list_spans(&self, _limit: usize) -> Result<Vec<SpanEntry>, StorageError> {
+            Ok(Vec::new())
+        }
+
+        async fn read_span(&self, key: &str) -> Result<StoredSpan, StorageError> {
+            use std::sync::atomic::Ordering;
+
+            let current = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(current, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+            if key == "missing" {
+                return Err(StorageError::ReadFailed("not found".into()));
+            }
+            serde_json::from_value(json!({
+                "trace_id": "01".repeat(16),
+                "span_id": key,
+                "name": "op",
+                "kind": "Internal",
+                "start_time": 0,
+                "end_time": 0,
+                "status": "Unset",
+            }))
+            .map_err(|e| StorageError::ReadFailed(e.to_string()))
+        }
+
+        async fn list_services(&self) -> Result<Vec<String>, StorageError> {
+            Ok(Vec::new())
+        }
+
+        fn get_health_status(&self) -> HealthStatus {
+            HealthStatus {
+                is_healthy: true,
+                degraded: false,
+                last_write: 0,
+                queue_size: 0,
+                total_processed: 0,
+                consecutive_failed_writes: 0,
+                total_failed_writes: 0,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_spans_concurrent_and_ordered() {
+        let reader = SlowReader::default();
+        let mut keys: Vec<String> = (0..8).map(|i| format!("span-{}", i)).collect();
+        keys.insert(3, "missing".to_string());
+
+        let started = std::time::Instant::now();
+        let results = reader.read_spans(&keys).await;
+        let elapsed = started.elapsed();
+
+        // Nine sequential 50ms GETs would take at least 450ms
+        assert!(elapsed < Duration::from_millis(300), "reads took {:?}", elapsed);
+        assert!(reader.max_in_flight.load(std::sync::atomic::Ordering::SeqCst) > 1);
+
+        assert_eq!(results.len(), keys.len());
+        for (key, result) in keys.iter().zip(&results) {
+            match result {
+                Ok(span) => assert_eq!(&span.span_id, key),
+                Err(_) => assert_eq!(key, "missing"),
+            }
+        }
+    }
+
+    fn span_with_id(span_id: u8) -> SpanData {
+        span_data(TraceId::from_bytes([1; 16]), SpanId::from_bytes([span_id; 8]), "charge")
+    }
+
+    #[test]
+    fn test_batch_object_round_trip() {
+        let spans: Vec<SpanData> = (1..=3).map(span_with_id).collect();
+
+        let batch: Vec<StoredSpan> = spans.iter().map(StoredSpan::from).collect();
+        let data = encode_json(&batch, false, TimestampFormat::UnixNanos).unwrap();
+        let stored = parse_stored_spans(&data).unwrap();
+
+        assert_eq!(stored.len(), 3);
+        for (span_id, span) in (1..=3u8).zip(&stored) {
+            assert_eq!(span.span_id, format!("{:02x}", span_id).repeat(8));
+            assert_eq!(span.name, "charge");
+        }
+    }
+
+    #[test]
+    fn test_parse_single_span_object() {
+        let data = serde_json::to_vec(&StoredSpan::from(&span_with_id(7))).unwrap();
+
+        let stored = parse_stored_spans(&data).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].span_id, "07".repeat(8));
+    }
+
+    #[test]
+    fn test_parse_legacy_field_names() {
+        let data = serde_json::json!({
+            "traceID": "0af7651916cd43dd8448eb211c80319c",
+            "spanId": "b7ad6b7169203331",
+            "parentSpanId": "00f067aa0ba902b7",
+            "operationName": "GET /cart",
+            "kind": "Server",
+            "startTimeUnixNano": 1_000,
+            "endTimeUnixNano": 4_000,
+            "status": "Error",
+            "statusMessage": "timeout",
+            "serviceName": "cart",
+            "events": [{"name": "retry", "timeUnixNano": 2_000}],
+            "links": [{"traceId": "01".repeat(16), "spanID": "02".repeat(8)}],
+        });
+
+        let stored = parse_stored_spans(&serde_json::to_vec(&data).unwrap()).unwrap().remove(0);
+        assert_eq!(stored.trace_id, "0af7651916cd43dd8448eb211c80319c");
+        assert_eq!(stored.span_id, "b7ad6b7169203331");
+        assert_eq!(stored.parent_span_id.as_deref(), Some("00f067aa0ba902b7"));
+        assert_eq!(stored.name, "GET /cart");
+        assert_eq!((stored.start_time, stored.end_time, stored.duration_ns), (1_000, 4_000, 3_000));
+        assert_eq!(stored.status_message.as_deref(), Some("timeout"));
+        assert_eq!(stored.service_name.as_deref(), Some("cart"));
+        assert_eq!(stored.events[0].timestamp, 2_000);
+        assert_eq!(stored.links[0].span_id, "02".repeat(8));
+
+        // Written back with the canonical names
+        let json = serde_json::to_value(&stored).unwrap();
+        assert_eq!(json["trace_id"], "0af7651916cd43dd8448eb211c80319c");
+        assert!(json.get("traceID").is_none());
+    }
+
+    #[test]
+    fn test_v1_object_read_with_defaults() {
+        // Layout written before schema versions, durations, scopes, status messages and links
+        let v1 = br#"{"trace_id":"0101","span_id":"0202","name":"GET /","kind":"Server",
+            "start_time":1000,"end_time":4000,"status":"Ok","attributes":{"http.method":"GET"},"events":[]}"#;
+        let span = parse_stored_spans(v1).unwrap().remove(0);
+
+        assert_eq!(span.schema_version, 1);
+        assert_eq!(span.parent_span_id, None);
+        assert_eq!(span.duration_ns, 3_000);
+        assert_eq!((span.status_message, span.service_name), (None, None));
+        assert_eq!((span.scope_name, span.scope_version), (None, None));
+        assert!(span.links.is_empty());
+        assert_eq!(span.attributes["http.method"], "GET");
+
+        let mut child = span_with_id(2);
+        child.parent_span_id = SpanId::from_bytes([1; 8]);
+        let written = StoredSpan::from(&child);
+        assert_eq!(written.schema_version, SCHEMA_VERSION);
+        assert_eq!(written.parent_span_id, Some("01".repeat(8)));
+        assert_eq!(StoredSpan::from(&span_with_id(3)).parent_span_id, None, "roots have no parent");
+        let json = serde_json::to_vec(&written).unwrap();
+        assert_eq!(parse_stored_spans(&json).unwrap()[0].schema_version, SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_duration_derived_for_older_objects() {
+        let mut json = serde_json::to_value(StoredSpan::from(&span_with_id(7))).unwrap();
+        json.as_object_mut().unwrap().remove("duration_ns");
+
+        let stored = parse_stored_spans(&serde_json::to_vec(&json).unwrap()).unwrap();
+        assert_eq!(stored[0].duration_ns, stored[0].end_time - stored[0].start_time);
+    }
+
+    #[tokio::test]
+    async fn test_batch_keys_hashed() {
+        let fake = FakeS3::default();
+        let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+            .with_write_mode(WriteMode::PerBatch)
+            .with_key_template(KeyTemplate::default().with_prefix_hash(true));
+        for trace in 1..=16u8 {
+            writer.write_spans(vec![span_data(TraceId::from_bytes([trace; 16]), SpanId::from_bytes([2; 8]), "charge")]).await.unwrap();
+        }
+
+        let buckets: BTreeSet<String> = fake.keys().iter()
+            .filter_map(|key| key.strip_prefix("/bucket/spans/"))
+            .map(|key| key.split('/').next().unwrap().to_string())
+            .collect();
+        assert!(buckets.len() > 4, "batches landed in {:?}", buckets);
+        assert!(buckets.iter().all(|bucket| bucket.len() == 2), "{:?}", buckets);
+        assert_eq!(writer.list_spans(100).await.unwrap().len(), 16);
+    }
+
+    #[test]
+    fn test_batch_key_layout() {
+        let now = DateTime::parse_from_rfc3339("2024-03-05T07:30:00Z").unwrap().with_timezone(&Utc);
+        let key = batch_key(now, "json");
+
+        assert!(key.starts_with("2024/03/05/07/"), "unexpected key {}", key);
+        assert!(key.ends_with(".json"));
+        assert_ne!(key, batch_key(now, "json"));
+    }
+
+    /// Reader serving one batch object and one per-span object
+    struct MixedLayoutReader;
+
+    #[async_trait]
+    impl StorageReader for MixedLayoutReader {
+        async fn list_spans(&self, _limit: usize) -> Result<Vec<SpanEntry>, StorageError> {
+            Ok(Vec::new())
+        }
+
+        async fn read_span(&self, key: &str) -> Result<StoredSpan, StorageError> {
+            let mut spans = self.read_object(key).await?;
+            Ok(spans.remove(0))
+        }
+
+        async fn read_object(&self, key: &str) -> Result<Vec<StoredSpan>, StorageError> {
+            let data = match key {
+                "batch" => {
+                    let batch = [StoredSpan::from(&span_with_id(1)), StoredSpan::from(&span_with_id(2))];
+                    encode_json(&batch, false, TimestampFormat::UnixNanos)?
+                }
+                _ => serde_json::to_vec(&StoredSpan::from(&span_with_id(3)))
+                    .map_err(|e| StorageError::ReadFailed(e.to_string()))?,
+            };
+            parse_stored_spans(&data)
+        }
+
+        async fn list_services(&self) -> Result<Vec<String>, StorageError> {
+            Ok(Vec::new())
+        }
+
+        fn get_health_status(&self) -> HealthStatus {
+            HealthStatus {
+                is_healthy: true,
+                degraded: false,
+                last_write: 0,
+                queue_size: 0,
+                total_processed: 0,
+                consecutive_failed_writes: 0,
+                total_failed_writes: 0,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_spans_expands_batch_objects() {
+        let keys = vec!["batch".to_string(), "single".to_string()];
+        let span_ids: Vec<String> = MixedLayoutReader
+            .read_spans(&keys)
+            .await
+            .into_iter()
+            .map(|result| result.unwrap().span_id)
+            .collect();
+
+        assert_eq!(span_ids, vec!["01".repeat(8), "02".repeat(8), "03".repeat(8)]);
+    }
+
+    /// In-memory S3 stand-in handling path-style PUT, HEAD, GET,
+    /// DeleteObjects and single-page ListObjectsV2 of bucket `bucket`
+    #[derive(Clone, Default)]
+    struct FakeS3 {
+        objects: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+        /// Headers of the last successful PUT to each key
+        headers: Arc<Mutex<HashMap<String, http::HeaderMap>>>,
+        /// Whether `If-None-Match` PUTs are rejected with 501, like older S3-compatible stores
+        reject_conditional_puts: bool,
+        /// Number of upcoming PUTs answered with 503 Slow Down
+        failing_puts: Arc<std::sync::atomic::AtomicU32>,
+        /// `Retry-After` header sent with 503 responses
+        retry_after: Option<&'static str>,
+        /// Whether PUTs are rejected with 403 AccessDenied
+        deny_puts: bool,
+        /// Number of PUT requests received
+        put_requests: Arc<std::sync::atomic::AtomicU32>,
+        /// Keys reported as AccessDenied by DeleteObjects
+        undeletable: Arc<Mutex<HashSet<String>>>,
+        /// Number of DeleteObjects requests received
+        delete_requests: Arc<std::sync::atomic::AtomicU32>,
+    }
+
+    impl FakeS3 {
+        fn client(&self) -> S3Client {
+            use aws_sdk_s3::config::{BehaviorVersion, retry::RetryConfig};
+            use aws_smithy_runtime::client::http::test_util::infallible_client_fn;
+
+            let fake = self.clone();
+            let config = S3Builder::new()
+                .behavior_version(BehaviorVersion::latest())
+                .region(Region::new("us-east-1"))
+                .credentials_provider(Credentials::new("test", "test", None, None, "test"))
+                .endpoint_url("http://localhost:4566")
+                .force_path_style(true)
+                .retry_config(RetryConfig::disabled())
+                .http_client(infallible_client_fn(move |request| fake.handle(request)))
+                .build();
+            S3Client::from_conf(config)
+        }
+
+        fn handle(
+            &self,
+            request: http::Request<aws_sdk_s3::primitives::SdkBody>,
+        ) -> http::Response<aws_sdk_s3::primitives::SdkBody> {
+            use aws_sdk_s3::primitives::SdkBody;
+
+            let key = request.uri().path().to_string();
+            let mut objects = self.objects.lock().unwrap();
+            let (status, body): (u16, SdkBody) = match *request.method() {
+                http::Method::PUT => {
+                    use std::collections::hash_map::Entry;
+
+                    let conditional = request.headers().contains_key("if-none-match");
+                    let data = request.body().bytes().unwrap_or_default().to_vec();
+                    let headers = request.headers().clone();
+                    self.put_requests.fetch_add(1, Ordering::SeqCst);
+                    let failing = self.failing_puts
+                        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                        .is_ok();
+                    match objects.entry(key.clone()) {
+                        _ if failing => (503, "<Error><Code>SlowDown</Code></Error>".into()),
+                        _ if self.deny_puts => (403, "<Error><Code>AccessDenied</Code></Error>".into()),
+                        _ if conditional && self.reject_conditional_puts => {
+                            (501, "<Error><Code>NotImplemented</Code></Error>".into())
+                        }
+                        Entry::Occupied(_) if conditional => {
+                            (412, "<Error><Code>PreconditionFailed</Code></Error>".into())
+                        }
+                        entry => {
+                            *entry.or_default() = data;
+                            self.headers.lock().unwrap().insert(key.clone(), headers);
+                            (200, SdkBody::empty())
+                        }
+                    }
+                }
+                http::Method::POST if request.uri().query().is_some_and(|q| q.contains("delete")) => {
+                    self.delete_requests.fetch_add(1, Ordering::SeqCst);
+                    let body = String::from_utf8_lossy(request.body().bytes().unwrap_or_default()).to_string();
+                    let undeletable = self.undeletable.lock().unwrap();
+                    let mut errors = String::new();
+                    for deleted in body.split("<Key>").skip(1).filter_map(|part| part.split("</Key>").next()) {
+                        if undeletable.contains(deleted) {
+                            errors.push_str(&format!(
+                                "<Error><Key>{}</Key><Code>AccessDenied</Code><Message>Access Denied</Message></Error>",
+                                deleted
+                            ));
+                        } else {
+                            objects.remove(&format!("/bucket/{}", deleted));
+                        }
+                    }
+                    (200, format!("<DeleteResult>{}</DeleteResult>", errors).into())
+                }
+                http::Method::HEAD if objects.contains_key(&key) => (200, SdkBody::empty()),
+                http::Method::GET if request.uri().query().is_some_and(|q| q.contains("list-type=2")) => {
+                    (200, list_objects(&objects, request.uri().query().unwrap_or_default()).into())
+                }
+                http::Method::GET => match objects.get(&key) {
+                    Some(data) => (200, data.clone().into()),
+                    None => (404, "<Error><Code>NoSuchKey</Code></Error>".into()),
+                },
+                _ => (404, SdkBody::empty()),
+            };
+            let mut response = http::Response::builder().status(status).header("ETag", "\"fake\"");
+            if let (503, Some(retry_after)) = (status, self.retry_after) {
+                response = response.header("Retry-After", retry_after);
+            }
+            if request.method() == http::Method::GET {
+                if let Some(encoding) = self.headers.lock().unwrap().get(&key).and_then(|h| h.get("content-encoding")) {
+                    response = response.header("Content-Encoding", encoding.clone());
+                }
+            }
+            response.body(body).unwrap()
+        }
+
+        fn keys(&self) -> Vec<String> {
+            let mut keys: Vec<String> = self.objects.lock().unwrap().keys().cloned().collect();
+            keys.sort();
+            keys
+        }
+    }
+
+    /// Answers a ListObjectsV2 query with up to `max-keys` matching keys;
+    /// the continuation token is the last key of the previous page
+    fn list_objects(objects: &HashMap<String, Vec<u8>>, query: &str) -> String {
+        let param = |name: &str| query.split('&')
+            .find_map(|param| param.strip_prefix(name))
+            .map(|value| value.replace("%2F", "/"));
+        let prefix = param("prefix=").unwrap_or_default();
+        let start_after = param("continuation-token=").or_else(|| param("start-after=")).unwrap_or_default();
+        let max_keys = param("max-keys=").and_then(|value| value.parse().ok()).unwrap_or(usize::MAX);
+        let mut keys: Vec<&str> = objects.keys()
+            .filter_map(|key| key.strip_prefix("/bucket/"))
+            .filter(|key| key.starts_with(&prefix) && *key > start_after.as_str())
+            .collect();
+        keys.sort();
+        let truncated = keys.len() > max_keys;
+        keys.truncate(max_keys);
+        let next = match keys.last() {
+            Some(last) if truncated => format!("<NextContinuationToken>{}</NextContinuationToken>", last),
+            _ => String::new(),
+        };
+        let contents: String = keys.iter()
+            .map(|key| format!(
+                "<Contents><Key>{}</Key><LastModified>2024-01-01T00:00:00.000Z</LastModified></Contents>",
+                key
+            ))
+            .collect();
+        format!(
+            "<ListBucketResult><Name>bucket</Name><KeyCount>{}</KeyCount><IsTruncated>{}</IsTruncated>{}{}</ListBucketResult>",
+            keys.len(), truncated, next, contents
+        )
+    }
+
+    #[test]
+    fn test_normalize_prefix() {
+        for (prefix, normalized) in [("spans", "spans"), ("spans/", "spans"), ("/a/b//", "a/b"), ("/", ""), ("", "")] {
+            assert_eq!(normalize_prefix(prefix), normalized, "{:?}", prefix);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_written_keys_listed_for_any_prefix() {
+        for (prefix, key_start) in [("spans", "spans/"), ("spans/", "spans/"), ("", ""), ("/", "")] {
+            for template in [key_template::DEFAULT_KEY_TEMPLATE, "{trace_id}/{span_id}.json"] {
+                let fake = FakeS3::default();
+                let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), prefix.into())
+                    .with_key_template(KeyTemplate::parse(template).unwrap());
+                writer.write_spans(vec![span_with_id(2), span_with_id(3)]).await.unwrap();
+
+                let listed: Vec<String> = writer.list_spans(100).await.unwrap()
+                    .into_iter()
+                    .map(|entry| entry.key)
+                    .collect();
+                let written: Vec<String> = fake.keys().iter()
+                    .map(|key| key.strip_prefix("/bucket/").unwrap().to_string())
+                    .filter(|key| !key.contains(INDEX_SEGMENT))
+                    .collect();
+                let case = format!("prefix {:?}, template {:?}: {:?}", prefix, template, written);
+                assert_eq!(listed.len(), 2, "{}", case);
+                for key in &listed {
+                    assert!(written.contains(key), "{}", case);
+                    assert!(key.starts_with(key_start) && !key.starts_with('/') && !key.contains("//"), "{}", case);
+                    assert_eq!(writer.read_span(key).await.unwrap().trace_id, "01".repeat(16));
+                    assert_eq!(writer.key_trace_id(key), Some("01".repeat(16)), "{}", case);
+                }
+                assert!(writer.find_span(&"01".repeat(16), &"03".repeat(8), 0).await.unwrap().is_some(), "{}", case);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find_per_span_object_by_key() {
+        let fake = FakeS3::default();
+        let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into());
+        writer.write_spans(vec![span_with_id(2), span_with_id(3)]).await.unwrap();
+
+        let trace_id = "01".repeat(16);
+        let span = writer.find_span(&trace_id, &"03".repeat(8), 0).await.unwrap().unwrap();
+        assert_eq!(span.span_id, "03".repeat(8));
+        assert!(writer.find_span(&trace_id, &"04".repeat(8), 0).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_hung_write_times_out() {
+        use aws_sdk_s3::config::{BehaviorVersion, retry::RetryConfig as SdkRetryConfig};
+
+        // Accepts connections and never answers, like a hung S3 endpoint
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let accepted = Arc::clone(&connections);
+        tokio::spawn(async move {
+            let mut open = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                accepted.fetch_add(1, Ordering::SeqCst);
+                open.push(socket);
+            }
+        });
+        let config = S3Builder::new()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("test", "test", None, None, "test"))
+            .endpoint_url(format!("http://{}", addr))
+            .force_path_style(true)
+            .retry_config(SdkRetryConfig::disabled())
+            .build();
+        let writer = S3StorageWriter::from_client(S3Client::from_conf(config), "bucket".into(), "spans".into())
+            .with_retry(RetryConfig { max_retries: 1, initial_backoff_ms: 1, max_backoff_ms: 1, ..RetryConfig::default() })
+            .with_write_timeout(Duration::from_millis(100));
+
+        let started = std::time::Instant::now();
+        match writer.write_spans(vec![span_with_id(2)]).await {
+            Err(StorageError::WriteFailed(msg)) => assert!(msg.contains("timed out after 100ms"), "{}", msg),
+            other => panic!("Expected WriteFailed, got {:?}", other),
+        }
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(connections.load(Ordering::SeqCst), 2, "the timed-out attempt is retried once");
+    }
+
+    #[tokio::test]
+    async fn test_delete_objects_batched() {
+        let fake = FakeS3::default();
+        let keys: Vec<String> = (0..1500).map(|i| format!("spans/{:04}.json", i)).collect();
+        for key in &keys {
+            fake.objects.lock().unwrap().insert(format!("/bucket/{}", key), b"{}".to_vec());
+        }
+        fake.undeletable.lock().unwrap().extend([keys[10].clone(), keys[1200].clone()]);
+        let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into());
+
+        match writer.delete_objects(&keys).await {
+            Err(StorageError::BatchWriteFailed(msg)) => {
+                assert!(msg.starts_with("2 of 1500 objects not deleted"), "{}", msg);
+                assert!(msg.contains("spans/0010.json: Access Denied"), "{}", msg);
+                assert!(msg.contains("spans/1200.json: Access Denied"), "{}", msg);
+            }
+            other => panic!("Expected BatchWriteFailed, got {:?}", other),
+        }
+        assert_eq!(fake.delete_requests.load(Ordering::SeqCst), 2, "1000 keys per request");
+        assert_eq!(fake.keys(), ["/bucket/spans/0010.json", "/bucket/spans/1200.json"]);
+
+        fake.undeletable.lock().unwrap().clear();
+        writer.delete_objects(&keys[..2]).await.unwrap();
+        writer.delete_objects(&[]).await.unwrap();
+        assert_eq!(fake.delete_requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_raw_object_read_verbatim() {
+        let fake = FakeS3::default();
+        let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+            .with_pretty_json(true);
+        writer.write_spans(vec![span_with_id(2)]).await.unwrap();
+
+        let (trace_id, span_id) = ("01".repeat(16), "02".repeat(8));
+        let key = writer.find_span_key(&trace_id, &span_id, 0).await.unwrap().unwrap();
+        let written = fake.objects.lock().unwrap()[&format!("/bucket/{}", key)].clone();
+        let raw = writer.read_raw(&key).await.unwrap();
+        assert_eq!(raw.content_type, JSON_CONTENT_TYPE);
+        assert_eq!(raw.content_encoding, None);
+        assert_eq!(raw.into_bytes().await.unwrap(), written);
+
+        // Fields StoredSpan does not model are kept
+        let extended = br#"{"trace_id":"0101","span_id":"0202","name":"x","kind":"Server","start_time":1,"end_time":2,"status":"Ok","custom":{"a":1}}"#;
+        fake.objects.lock().unwrap().insert(format!("/bucket/{}", key), extended.to_vec());
+        assert_eq!(writer.read_raw(&key).await.unwrap().into_bytes().await.unwrap(), extended);
+
+        // Batch layouts are scanned for the object holding the span
+        let fake = FakeS3::default();
+        let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+            .with_write_mode(WriteMode::PerBatch);
+        writer.write_spans(vec![span_with_id(2), span_with_id(3)]).await.unwrap();
+        let key = writer.find_span_key(&trace_id, &"03".repeat(8), 10).await.unwrap().unwrap();
+        let raw = writer.read_raw(&key).await.unwrap().into_bytes().await.unwrap();
+        assert_eq!(raw, fake.objects.lock().unwrap()[&format!("/bucket/{}", key)]);
+        assert!(writer.find_span_key(&trace_id, &"04".repeat(8), 10).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_indexed_attributes_searched_from_the_index() {
+        let fake = FakeS3::default();
+        let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+            .with_search_index(true)
+            .with_indexed_attributes(vec!["http.method".into(), "http.route".into()]);
+        let mut get = span_with_id(2);
+        get.attributes.insert(KeyValue::new("http.method", "GET"));
+        get.attributes.insert(KeyValue::new("http.status_code", 200_i64));
+        let mut put = span_with_id(3);
+        put.attributes.insert(KeyValue::new("http.method", "PUT"));
+        writer.write_spans(vec![get, put]).await.unwrap();
+
+        let filter = |key: &str, value: &str| SpanSearch {
+            attributes: vec![(key.to_string(), value.to_string())],
+            ..SpanSearch::default()
+        };
+        let entries = writer.search_index(&filter("http.method", "GET"), 10).await.unwrap().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].span_id, "02".repeat(8));
+        assert_eq!(entries[0].attributes, BTreeMap::from([("http.method".to_string(), "GET".into())]));
+        // Keys left out of the index are searched by scanning instead
+        assert!(writer.search_index(&filter("http.status_code", "200"), 10).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pretty_json_objects() {
+        for (write_mode, pretty) in [
+            (WriteMode::PerSpan, false),
+            (WriteMode::PerSpan, true),
+            (WriteMode::PerBatch, true),
+        ] {
+            let fake = FakeS3::default();
+            let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+                .with_write_mode(write_mode)
+                .with_pretty_json(pretty);
+            writer.write_spans(vec![span_with_id(2)]).await.unwrap();
+
+            let entries = writer.list_spans(10).await.unwrap();
+            assert_eq!(entries.len(), 1);
+            let body = fake.objects.lock().unwrap()[&format!("/bucket/{}", entries[0].key)].clone();
+            assert_eq!(body.contains(&b'\n'), pretty, "{:?}: {}", write_mode, String::from_utf8_lossy(&body));
+            let spans = writer.read_object(&entries[0].key).await.unwrap();
+            assert_eq!(spans[0].span_id, "02".repeat(8));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timestamp_formats_round_trip() {
+        let span = span_with_id(2);
+        let expected = StoredSpan::from(&span);
+        for (write_mode, format) in [
+            (WriteMode::PerSpan, TimestampFormat::UnixNanos),
+            (WriteMode::PerSpan, TimestampFormat::Rfc3339),
+            (WriteMode::PerBatch, TimestampFormat::Rfc3339),
+        ] {
+            let fake = FakeS3::default();
+            let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+                .with_write_mode(write_mode)
+                .with_timestamp_format(format);
+            writer.write_spans(vec![span.clone()]).await.unwrap();
+
+            let key = writer.list_spans(10).await.unwrap().remove(0).key;
+            let body = fake.objects.lock().unwrap()[&format!("/bucket/{}", key)].clone();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            let object = if json.is_array() { &json[0] } else { &json };
+            match format {
+                TimestampFormat::UnixNanos => assert_eq!(object["start_time"], expected.start_time),
+                TimestampFormat::Rfc3339 => {
+                    assert_eq!(object["start_time"], format_rfc3339(expected.start_time));
+                    assert_eq!(object["end_time"], format_rfc3339(expected.end_time));
+                }
+            }
+            assert_eq!(object["duration_ns"], expected.duration_ns);
+
+            let read = writer.read_object(&key).await.unwrap().remove(0);
+            assert_eq!(
+                (read.start_time, read.end_time, read.duration_ns),
+                (expected.start_time, expected.end_time, expected.duration_ns),
+                "{:?} {:?}", write_mode, format
+            );
+        }
+    }
+
+    #[test]
+    fn test_timestamp_parsed_in_any_format() {
+        let span = |start_time: &str| {
+            let json = format!(
+                r#"{{"trace_id":"01","span_id":"02","name":"x","kind":"Server","start_time":{},"end_time":0,"status":"Ok"}}"#,
+                start_time
+            );
+            serde_json::from_str::<StoredSpan>(&json).map(|span| span.start_time)
+        };
+        assert_eq!(span("1500000000123456789").unwrap(), 1_500_000_000_123_456_789);
+        assert_eq!(span(r#""1500000000123456789""#).unwrap(), 1_500_000_000_123_456_789);
+        assert_eq!(span(r#""2017-07-14T02:40:00.123456789Z""#).unwrap(), 1_500_000_000_123_456_789);
+        assert_eq!(span(r#""2017-07-14T04:40:00.5+02:00""#).unwrap(), 1_500_000_000_500_000_000);
+        assert!(span(r#""yesterday""#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_hashed_keys_spread_and_readable() {
+        let fake = FakeS3::default();
+        let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+            .with_key_template(KeyTemplate::default().with_prefix_hash(true));
+        let spans: Vec<SpanData> = (1..=32u8)
+            .map(|trace| span_data(TraceId::from_bytes([trace; 16]), SpanId::from_bytes([2; 8]), "charge"))
+            .collect();
+        writer.write_spans(spans).await.unwrap();
+
+        let buckets: BTreeSet<String> = fake.keys().iter()
+            .filter_map(|key| key.strip_prefix("/bucket/spans/"))
+            .map(|key| key[..2].to_string())
+            .collect();
+        assert!(buckets.len() > 8, "keys landed in {} hash buckets: {:?}", buckets.len(), fake.keys());
+
+        for entry in writer.list_spans(100).await.unwrap() {
+            assert!(writer.read_span(&entry.key).await.is_ok(), "{}", entry.key);
+        }
+        for trace in [1u8, 17, 32] {
+            let trace_id = format!("{:02x}", trace).repeat(16);
+            let span = writer.find_span(&trace_id, &"02".repeat(8), 0).await.unwrap().unwrap();
+            assert_eq!(span.trace_id, trace_id);
+        }
+
+        // Replayed spans take the same keys
+        let replayed = FakeS3::default();
+        let replay_writer = S3StorageWriter::from_client(replayed.client(), "bucket".into(), "spans".into())
+            .with_key_template(KeyTemplate::default().with_prefix_hash(true));
+        let summary = SpanReplayer::new(Arc::new(replay_writer)).replay_from(&writer).await.unwrap();
+        assert_eq!(summary.replayed, fake.keys().len());
+        assert_eq!(replayed.keys(), fake.keys());
+
+        // A trace's objects are listed under its hashed prefix
+        let listed = writer.list_trace(&"11".repeat(16)).await.unwrap().unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(writer.read_span(&listed[0]).await.unwrap().trace_id, "11".repeat(16));
+        let batches = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+            .with_write_mode(WriteMode::PerBatch);
+        assert!(batches.list_trace(&"11".repeat(16)).await.unwrap().is_none());
+
+        assert_eq!(writer.delete_trace(&"11".repeat(16)).await.unwrap(), 1);
+        assert!(writer.find_span(&"11".repeat(16), &"02".repeat(8), 0).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_per_span_keys_follow_template() {
+        let fake = FakeS3::default();
+        let template = KeyTemplate::parse("{service}/{date}/{trace_id}-{span_id}.json").unwrap();
+        let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+            .with_key_template(template.clone());
+        let span = span_with_resource(Resource::new(vec![KeyValue::new("service.name", "checkout")]));
+        writer.write_spans(vec![span]).await.unwrap();
+
+        let key = format!("checkout/1970/01/01/{}-{}.json", "01".repeat(16), "02".repeat(8));
+        assert!(fake.keys().contains(&format!("/bucket/spans/{}", key)), "{:?}", fake.keys());
+        assert_eq!(writer.key_trace_id(&format!("spans/{}", key)), Some("01".repeat(16)));
+    }
+
+    fn retrying_writer(fake: &FakeS3, max_retries: u32) -> S3StorageWriter {
+        S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+            .with_retry(RetryConfig {
+                max_retries,
+                initial_backoff_ms: 1,
+                max_backoff_ms: 5,
+                ..RetryConfig::default()
+            })
+    }
+
+    #[tokio::test]
+    async fn test_write_retried_after_server_errors() {
+        let fake = FakeS3::default();
+        fake.failing_puts.store(2, Ordering::SeqCst);
+
+        retrying_writer(&fake, 3).write("trace/span.json", b"data").await.unwrap();
+        assert_eq!(fake.keys(), vec!["/bucket/spans/trace/span.json".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_write_gives_up_after_max_retries() {
+        let fake = FakeS3::default();
+        fake.failing_puts.store(4, Ordering::SeqCst);
+
+        let result = retrying_writer(&fake, 3).write("trace/span.json", b"data").await;
+        assert!(matches!(result, Err(StorageError::RetryLimitExceeded(_))), "{:?}", result);
+        assert!(fake.keys().is_empty());
+        assert_eq!(fake.failing_puts.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_write_waits_for_retry_after() {
+        let fake = FakeS3 { retry_after: Some("2"), ..Default::default() };
+        fake.failing_puts.store(1, Ordering::SeqCst);
+        let writer = |max_backoff_ms| {
+            S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+                .with_retry(RetryConfig { max_retries: 3, initial_backoff_ms: 1, max_backoff_ms, ..RetryConfig::default() })
+        };
+
+        let started = tokio::time::Instant::now();
+        writer(10_000).write("trace/a.json", b"data").await.unwrap();
+        assert!(started.elapsed() >= Duration::from_secs(2), "waited {:?}", started.elapsed());
+
+        // The hint is capped at max_backoff_ms
+        fake.failing_puts.store(1, Ordering::SeqCst);
+        let started = tokio::time::Instant::now();
+        writer(500).write("trace/b.json", b"data").await.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(1), "waited {:?}", started.elapsed());
+        assert_eq!(fake.keys().len(), 2);
+    }
+
+    #[test]
+    fn test_retry_after_parsed() {
+        assert_eq!(parse_retry_after("3"), Some(Duration::from_secs(3)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+        let later = (Utc::now() + chrono::Duration::seconds(60)).to_rfc2822();
+        assert!(parse_retry_after(&later).is_some_and(|delay| delay > Duration::from_secs(50)));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[tokio::test]
+    async fn test_access_denied_write_not_retried() {
+        let fake = FakeS3 { deny_puts: true, ..Default::default() };
+
+        let error = retrying_writer(&fake, 3).write("trace/span.json", b"data").await.unwrap_err();
+        assert_eq!(error.kind(), Some(StorageErrorKind::AccessDenied));
+        assert_eq!(error.code(), Some("AccessDenied"));
+        assert!(!error.is_retryable());
+        assert_eq!(fake.put_requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_writes_fail_over_to_fallback_endpoint() {
+        let (primary, secondary) = (FakeS3::default(), FakeS3::default());
+        primary.failing_puts.store(1000, Ordering::SeqCst);
+        let health_check = Arc::new(HealthCheck::new());
+        let writer = retrying_writer(&primary, 1)
+            .with_fallback_endpoint("secondary", secondary.client())
+            .with_health_check(Arc::clone(&health_check));
+        assert_eq!(health_check.get_detailed_status().active_storage_endpoint.as_deref(), Some("primary"));
+
+        writer.write_spans(vec![span_with_id(2)]).await.unwrap();
+        writer.write_spans(vec![span_with_id(3)]).await.unwrap();
+
+        assert!(primary.keys().is_empty());
+        assert_eq!(secondary.keys().len(), 2, "{:?}", secondary.keys());
+        // The second write went straight to the secondary
+        assert_eq!(primary.failing_puts.load(Ordering::SeqCst), 998);
+        let status = health_check.get_detailed_status();
+        assert_eq!(status.active_storage_endpoint.as_deref(), Some("secondary"));
+        assert_eq!(status.storage_failovers_total, 1);
+
+        // Once the secondary fails too, writes cycle back to the primary
+        primary.failing_puts.store(0, Ordering::SeqCst);
+        secondary.failing_puts.store(1000, Ordering::SeqCst);
+        writer.write_spans(vec![span_with_id(4)]).await.unwrap();
+        assert_eq!(primary.keys().len(), 1);
+        assert_eq!(health_check.get_detailed_status().active_storage_endpoint.as_deref(), Some("primary"));
+    }
+
+    #[tokio::test]
+    async fn test_failover_keeps_reads_and_conditional_puts_per_endpoint() {
+        let primary = FakeS3::default();
+        let secondary = FakeS3 { reject_conditional_puts: true, ..Default::default() };
+        primary.failing_puts.store(1000, Ordering::SeqCst);
+        primary.objects.lock().unwrap().insert("/bucket/spans/old/span.json".into(), b"old".to_vec());
+        let health_check = Arc::new(HealthCheck::new());
+        let writer = idempotent_writer(&primary, &health_check)
+            .with_retry(RetryConfig { max_retries: 1, initial_backoff_ms: 1, ..RetryConfig::default() })
+            .with_fallback_endpoint("secondary", secondary.client());
+
+        writer.write("trace/span.json", b"data").await.unwrap();
+        assert_eq!(secondary.keys(), vec!["/bucket/spans/trace/span.json".to_string()]);
+        assert!(writer.endpoints[0].conditional_put_supported.load(Ordering::SeqCst));
+        assert!(!writer.endpoints[1].conditional_put_supported.load(Ordering::SeqCst));
+
+        // Reads still go to the primary while writes go to the secondary
+        assert_eq!(writer.read_raw("spans/old/span.json").await.unwrap().into_bytes().await.unwrap(), b"old");
+    }
+
+    fn idempotent_writer(fake: &FakeS3, health_check: &Arc<HealthCheck>) -> S3StorageWriter {
+        S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+            .with_idempotent_writes(true)
+            .with_health_check(Arc::clone(health_check))
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_write_skips_duplicate() {
+        let fake = FakeS3::default();
+        let health_check = Arc::new(HealthCheck::new());
+        let writer = idempotent_writer(&fake, &health_check);
+
+        writer.write("trace/span.json", b"first").await.unwrap();
+        writer.write("trace/span.json", b"second").await.unwrap();
+
+        assert_eq!(fake.keys(), vec!["/bucket/spans/trace/span.json".to_string()]);
+        assert_eq!(fake.objects.lock().unwrap()["/bucket/spans/trace/span.json"], b"first");
+        assert_eq!(health_check.get_detailed_status().duplicates_skipped, 1);
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_write_retried_after_server_errors() {
+        let fake = FakeS3::default();
+        fake.failing_puts.store(2, Ordering::SeqCst);
+        let health_check = Arc::new(HealthCheck::new());
+        let writer = idempotent_writer(&fake, &health_check)
+            .with_retry(RetryConfig { max_retries: 3, initial_backoff_ms: 1, ..RetryConfig::default() });
+
+        writer.write("trace/span.json", b"data").await.unwrap();
+        assert_eq!(fake.keys(), vec!["/bucket/spans/trace/span.json".to_string()]);
+        assert!(writer.endpoints[0].conditional_put_supported.load(Ordering::SeqCst));
+        assert_eq!(health_check.get_detailed_status().duplicates_skipped, 0);
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_write_falls_back_to_head() {
+        let fake = FakeS3 {
+            reject_conditional_puts: true,
+            ..Default::default()
+        };
+        let health_check = Arc::new(HealthCheck::new());
+        let writer = idempotent_writer(&fake, &health_check);
+
+        writer.write("trace/span.json", b"first").await.unwrap();
+        writer.write("trace/span.json", b"second").await.unwrap();
+
+        assert!(!writer.endpoints[0].conditional_put_supported.load(Ordering::SeqCst));
+        assert_eq!(fake.objects.lock().unwrap()["/bucket/spans/trace/span.json"], b"first");
+        assert_eq!(health_check.get_detailed_status().duplicates_skipped, 1);
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_batch_written_once() {
+        let fake = FakeS3::default();
+        let health_check = Arc::new(HealthCheck::new());
+        let writer = idempotent_writer(&fake, &health_check).with_write_mode(WriteMode::PerBatch);
+
+        for _ in 0..2 {
+            writer.write_spans(vec![span_with_id(1), span_with_id(2)]).await.unwrap();
+        }
+
+        let keys = fake.keys();
+        assert_eq!(keys.len(), 1);
+        assert!(keys[0].starts_with("/bucket/spans/1970/01/01/00/"), "unexpected key {}", keys[0]);
+        assert_eq!(health_check.get_detailed_status().duplicates_skipped, 1);
+    }
+
+    #[tokio::test]
+    async fn test_objects_carry_content_type_and_metadata() {
+        let fake = FakeS3::default();
+        let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+            .with_object_metadata(HashMap::from([("environment".to_string(), "staging".to_string())]));
+
+        writer.write_spans(vec![span_with_id(1)]).await.unwrap();
+
+        let headers = fake.headers.lock().unwrap();
+        assert_eq!(headers.len(), 1);
+        let headers = headers.values().next().unwrap();
+        assert_eq!(headers["content-type"], "application/json");
+        assert_eq!(headers["x-amz-meta-trace-id"], "01".repeat(16).as_str());
+        assert_eq!(headers["x-amz-meta-span-count"], "1");
+        assert_eq!(headers["x-amz-meta-environment"], "staging");
+    }
+
+    #[tokio::test]
+    async fn test_objects_carry_configured_and_span_tags() {
+        let fake = FakeS3::default();
+        let tags = HashMap::from([
+            ("retention".to_string(), "30d".to_string()),
+            ("service".to_string(), "{service}".to_string()),
+            ("date".to_string(), "{date}".to_string()),
+        ]);
+        let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+            .with_object_tags(ObjectTags::parse(&tags).unwrap());
+
+        let span = span_with_resource(Resource::new(vec![KeyValue::new("service.name", "checkout")]));
+        writer.write_spans(vec![span]).await.unwrap();
+        writer.write("raw.json", b"{}").await.unwrap();
+
+        let headers = fake.headers.lock().unwrap();
+        let span_key = format!("/bucket/spans/{}/{}.json", "01".repeat(16), "02".repeat(8));
+        assert_eq!(headers[&span_key]["x-amz-tagging"], "date=1970-01-01&retention=30d&service=checkout");
+        assert_eq!(headers["/bucket/spans/raw.json"]["x-amz-tagging"], "retention=30d");
+    }
+
+    #[tokio::test]
+    async fn test_compressed_objects_round_trip() {
+        for (algorithm, level) in [(CompressionAlgorithm::Gzip, 9), (CompressionAlgorithm::Zstd, 11)] {
+            let fake = FakeS3::default();
+            let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+                .with_write_mode(WriteMode::PerBatch)
+                .with_compression(CompressionConfig { algorithm, level });
+
+            writer.write_spans(vec![span_with_id(1), span_with_id(2)]).await.unwrap();
+
+            let key = fake.keys().remove(0);
+            let encoding = fake.headers.lock().unwrap()[&key]["content-encoding"].clone();
+            assert_eq!(encoding, algorithm.to_string().as_str());
+            assert!(!fake.objects.lock().unwrap()[&key].starts_with(b"["), "{} body stored as written", algorithm);
+
+            let spans = writer.read_object(key.trim_start_matches("/bucket/")).await.unwrap();
+            let span_ids: Vec<String> = spans.into_iter().map(|span| span.span_id).collect();
+            assert_eq!(span_ids, vec!["01".repeat(8), "02".repeat(8)], "{} round trip", algorithm);
+
+            // Raw reads pass the stored encoding through
+            let raw = writer.read_raw(key.trim_start_matches("/bucket/")).await.unwrap();
+            assert_eq!(raw.content_encoding.as_deref(), Some(algorithm.to_string().as_str()));
+            assert_eq!(raw.into_bytes().await.unwrap(), fake.objects.lock().unwrap()[&key]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parquet_batch_round_trip() {
+        let fake = FakeS3::default();
+        let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+            .with_format(StorageFormat::Parquet);
+
+        writer.write_spans(vec![span_with_id(1), span_with_id(2)]).await.unwrap();
+
+        let keys = fake.keys();
+        assert_eq!(keys.len(), 1);
+        assert!(keys[0].ends_with(".parquet"), "unexpected key {}", keys[0]);
+        assert_eq!(fake.headers.lock().unwrap()[&keys[0]]["content-type"], PARQUET_CONTENT_TYPE);
+
+        let spans = writer.read_object(keys[0].trim_start_matches("/bucket/")).await.unwrap();
+        let span_ids: Vec<String> = spans.into_iter().map(|span| span.span_id).collect();
+        assert_eq!(span_ids, vec!["01".repeat(8), "02".repeat(8)]);
+    }
+
+    #[tokio::test]
+    async fn test_span_found_regardless_of_id_case() {
+        let fake = FakeS3::default();
+        let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into());
+        let span = span_data(TraceId::from_bytes([0xab; 16]), SpanId::from_bytes([0xcd; 8]), "charge");
+        writer.write_spans(vec![span]).await.unwrap();
+        assert_eq!(fake.keys(), vec![format!("/bucket/spans/{}/{}.json", "ab".repeat(16), "cd".repeat(8))]);
+
+        for (trace_id, span_id) in [("AB", "CD"), ("ab", "cd"), ("Ab", "cD")] {
+            let trace_id = normalize_trace_id(&trace_id.repeat(16)).unwrap();
+            let span_id = normalize_span_id(&span_id.repeat(8)).unwrap();
+            let found = writer.find_span(&trace_id, &span_id, 10).await.unwrap();
+            assert_eq!(found.unwrap().trace_id, "ab".repeat(16));
+        }
+    }
+
+    #[test]
+    fn test_uppercase_ids_normalized_on_read() {
+        let legacy = format!(
+            r#"{{"traceID":"{}","spanID":"00F067AA0BA902B7","parentSpanID":"B7AD","name":"GET","kind":"Server","start_time":1,"end_time":2,"status":"Ok",
+                "links":[{{"traceId":"{}","spanId":"{}"}}]}}"#,
+            "AB".repeat(16), "CD".repeat(16), "EF".repeat(8)
+        );
+        let span = parse_stored_spans(legacy.as_bytes()).unwrap().remove(0);
+        assert_eq!(span.trace_id, "ab".repeat(16));
+        assert_eq!(span.span_id, "00f067aa0ba902b7");
+        assert_eq!(span.parent_span_id.as_deref(), Some("000000000000b7ad"));
+        assert_eq!(span.links[0].trace_id, "cd".repeat(16));
+        assert_eq!(span.links[0].span_id, "ef".repeat(8));
+    }
+
+    #[tokio::test]
+    async fn test_per_trace_writes_one_object_per_trace() {
+        let fake = FakeS3::default();
+        let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+            .with_write_mode(WriteMode::PerTrace);
+        let other_trace = span_data(TraceId::from_bytes([9; 16]), SpanId::from_bytes([3; 8]), "charge");
+
+        writer.write_spans(vec![span_with_id(1), other_trace, span_with_id(2)]).await.unwrap();
+
+        let headers = fake.headers.lock().unwrap();
+        let mut span_counts: Vec<(String, String)> = headers
+            .values()
+            .map(|headers| (
+                headers["x-amz-meta-trace-id"].to_str().unwrap().to_string(),
+                headers["x-amz-meta-span-count"].to_str().unwrap().to_string(),
+            ))
+            .collect();
+        span_counts.sort();
+        assert_eq!(span_counts, vec![
+            ("01".repeat(16), "2".to_string()),
+            ("09".repeat(16), "1".to_string()),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_replay_from_bucket_pages_through_normal_write_path() {
+        let source = FakeS3::default();
+        S3StorageWriter::from_client(source.client(), "bucket".into(), "spans".into())
+            .write_spans((1..=5).map(named_span).collect())
+            .await
+            .unwrap();
+        let destination = FakeS3::default();
+        let writer = S3StorageWriter::from_client(destination.client(), "bucket".into(), "spans".into())
+            .with_write_mode(WriteMode::PerBatch)
+            .with_search_index(true);
+        let writer = Arc::new(writer);
+
+        let reader = S3StorageWriter::from_client(source.client(), "bucket".into(), "spans".into());
+        let summary = SpanReplayer::new(writer.clone()).with_page_size(2).replay_from(&reader).await.unwrap();
+        assert_eq!(summary.replayed, 5);
+        assert_eq!(summary.skipped + summary.failed, 0);
+
+        // One batch object and one index segment per listing page
+        let batches: Vec<String> = destination.keys().into_iter()
+            .filter(|key| !key.contains(INDEX_SEGMENT))
+            .collect();
+        assert_eq!(batches.len(), 3, "{:?}", batches);
+        assert_eq!(index_segments(&destination).len(), 3);
+        let search = SpanSearch { name: Some("op-4".into()), ..SpanSearch::default() };
+        let entries = writer.search_index(&search, 10).await.unwrap().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(batches.contains(&format!("/bucket/{}", entries[0].key)));
+    }
+
+    #[test]
+    fn test_batch_metadata_omits_mixed_trace_ids() {
+        let other_trace = span_data(TraceId::from_bytes([9; 16]), SpanId::from_bytes([2; 8]), "charge");
+
+        let metadata = span_metadata(&[StoredSpan::from(&span_with_id(1)), StoredSpan::from(&other_trace)]);
+        assert_eq!(metadata["span-count"], "2");
+        assert!(!metadata.contains_key("trace-id"));
+    }
+
+    /// Sends one HEAD request through a client built from `settings`,
+    /// returning the client's region and the request's URI
+    async fn request_uri(settings: &S3ClientSettings) -> (Option<String>, String) {
+        use aws_sdk_s3::config::BehaviorVersion;
+        use aws_sdk_s3::primitives::SdkBody;
+        use aws_smithy_runtime::client::http::test_util::infallible_client_fn;
+
+        let uris = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&uris);
+        let builder = S3Builder::new()
+            .behavior_version(BehaviorVersion::latest())
+            .credentials_provider(Credentials::new("test", "test", None, None, "test"))
+            .http_client(infallible_client_fn(move |request| {
+                recorded.lock().unwrap().push(request.uri().to_string());
+                http::Response::builder().status(200).body(SdkBody::empty()).unwrap()
+            }));
+        let config = settings.apply(builder).build();
+        let region = config.region().map(|region| region.to_string());
+
+        S3Client::from_conf(config).head_bucket().bucket("traces").send().await.unwrap();
+        let uri = uris.lock().unwrap()[0].clone();
+        (region, uri)
+    }
+
+    #[tokio::test]
+    async fn test_client_uses_configured_region_and_addressing() {
+        let aws = S3ClientSettings {
+            region: "eu-central-1".into(),
+            endpoint: None,
+            force_path_style: false,
+        };
+        let (region, uri) = request_uri(&aws).await;
+        assert_eq!(region.as_deref(), Some("eu-central-1"));
+        assert!(uri.starts_with("https://traces.s3.eu-central-1.amazonaws.com"), "{}", uri);
+
+        let (region, uri) = request_uri(&S3ClientSettings::default()).await;
+        assert_eq!(region.as_deref(), Some("us-east-1"));
+        assert!(uri.starts_with("http://localhost:4566/traces"), "{}", uri);
+
+        // Path-style against AWS, e.g. for bucket names with dots
+        let (_, uri) = request_uri(&S3ClientSettings { force_path_style: true, ..aws }).await;
+        assert!(uri.starts_with("https://s3.eu-central-1.amazonaws.com/traces"), "{}", uri);
+    }
+
+    #[tokio::test]
+    async fn test_custom_endpoint_keeps_credential_chain() {
+        use aws_sdk_s3::config::SharedCredentialsProvider;
+        use aws_sdk_s3::primitives::SdkBody;
+        use aws_smithy_runtime::client::http::test_util::infallible_client_fn;
+
+        let authorization = Arc::new(Mutex::new(None));
+        let recorded = Arc::clone(&authorization);
+        let chain = Credentials::new("minio-key", "minio-secret", None, None, "chain");
+        let config = aws_config::SdkConfig::builder()
+            .behavior_version(aws_config::BehaviorVersion::latest())
+            .credentials_provider(SharedCredentialsProvider::new(chain))
+            .http_client(infallible_client_fn(move |request| {
+                let header = request.headers().get("authorization").and_then(|value| value.to_str().ok());
+                *recorded.lock().unwrap() = header.map(str::to_string);
+                http::Response::builder().status(200).body(SdkBody::empty()).unwrap()
+            }))
+            .build();
+        let settings = S3ClientSettings { endpoint: Some("http://minio:9000".into()), ..S3ClientSettings::default() };
+
+        let client = S3StorageWriter::client_for(&config, &settings);
+        client.head_bucket().bucket("traces").send().await.unwrap();
+        let authorization = authorization.lock().unwrap().clone().unwrap();
+        assert!(authorization.contains("Credential=minio-key/"), "{}", authorization);
+    }
+
+    /// Span `span_id` of trace `[1; 16]` named `op-<span_id>`
+    fn named_span(span_id: u8) -> SpanData {
+        let mut span = span_with_id(span_id);
+        span.name = format!("op-{}", span_id).into();
+        span
+    }
+
+    fn index_segments(fake: &FakeS3) -> Vec<String> {
+        fake.keys().into_iter().filter(|key| key.contains(SPAN_INDEX_PREFIX)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_search_index_written_and_searched() {
+        for write_mode in [WriteMode::PerSpan, WriteMode::PerBatch] {
+            let fake = FakeS3::default();
+            let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+                .with_write_mode(write_mode)
+                .with_search_index(true);
+            writer.write_spans(vec![named_span(2), named_span(3)]).await.unwrap();
+
+            let segments = index_segments(&fake);
+            assert_eq!(segments.len(), 1, "{:?}", write_mode);
+            assert!(segments[0].starts_with("/bucket/spans/_index/spans/"), "{}", segments[0]);
+            // Index segments are not listed as spans
+            assert!(writer.list_spans(100).await.unwrap().iter().all(|entry| !entry.key.contains(INDEX_SEGMENT)));
+
+            let search = SpanSearch { name: Some("op-3".into()), ..SpanSearch::default() };
+            let entries = writer.search_index(&search, 10).await.unwrap().unwrap();
+            assert_eq!(entries.len(), 1, "{:?}", write_mode);
+            assert_eq!(entries[0].span_id, "03".repeat(8));
+            let spans = writer.read_object(&entries[0].key).await.unwrap();
+            assert!(spans.iter().any(|span| span.name == "op-3"), "{:?}", write_mode);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_index_disabled_by_default() {
+        let fake = FakeS3::default();
+        let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into());
+        writer.write_spans(vec![named_span(2)]).await.unwrap();
+
+        assert!(index_segments(&fake).is_empty());
+        assert!(writer.search_index(&SpanSearch::default(), 10).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_index_replaces_segments() {
+        let fake = FakeS3::default();
+        S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+            .write_spans(vec![named_span(2), named_span(3)])
+            .await
+            .unwrap();
+        let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+            .with_search_index(true);
+        writer.write_spans(vec![named_span(4)]).await.unwrap();
+        let before = index_segments(&fake);
+
+        assert_eq!(writer.rebuild_index().await.unwrap(), 3);
+        let after = index_segments(&fake);
+        assert_eq!(after.len(), 1);
+        assert_ne!(after, before);
+
+        let mut names: Vec<String> = writer.search_index(&SpanSearch::default(), 10).await.unwrap().unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["op-2", "op-3", "op-4"]);
+    }
+
+    /// Stores a segment indexing `span` in the partition of the hour of `written`
+    fn put_old_segment(fake: &FakeS3, span: &SpanData, written: DateTime<Utc>) -> String {
+        let key = format!("spans/{}", span_index_key(written));
+        let entry = SpanIndexEntry::new(&StoredSpan::from(span), "spans/old.json");
+        let data = serde_json::to_vec(&SpanIndexSegment { entries: vec![entry] }).unwrap();
+        fake.objects.lock().unwrap().insert(format!("/bucket/{}", key), data);
+        key
+    }
+
+    #[tokio::test]
+    async fn test_search_index_reads_whole_partition_before_limit() {
+        let fake = FakeS3::default();
+        let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+            .with_search_index(true);
+        // Segment names sort apart from the start times of their spans
+        let partition = span_index_partition(Utc::now() - chrono::TimeDelta::hours(2));
+        let now = SystemTime::now();
+        for (name, span_id, started) in [("0000", 2, now - Duration::from_secs(60)), ("ffff", 3, now)] {
+            let mut span = named_span(span_id);
+            span.start_time = started;
+            let entry = SpanIndexEntry::new(&StoredSpan::from(&span), "spans/old.json");
+            let data = serde_json::to_vec(&SpanIndexSegment { entries: vec![entry] }).unwrap();
+            fake.objects.lock().unwrap().insert(format!("/bucket/spans/{}/{}.json", partition, name), data);
+        }
+        let mut older = named_span(4);
+        older.start_time = now - Duration::from_secs(7200);
+        put_old_segment(&fake, &older, Utc::now() - chrono::TimeDelta::days(2));
+
+        let found = writer.search_index(&SpanSearch::default(), 1).await.unwrap().unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "op-3");
+    }
+
+    #[tokio::test]
+    async fn test_search_index_skips_partitions_before_start() {
+        let fake = FakeS3::default();
+        let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+            .with_search_index(true);
+        writer.write_spans(vec![named_span(2)]).await.unwrap();
+        // A span starting now, indexed in a partition two days old
+        let mut late = named_span(3);
+        late.start_time = SystemTime::now();
+        put_old_segment(&fake, &late, Utc::now() - chrono::TimeDelta::days(2));
+        assert_eq!(writer.search_index(&SpanSearch::default(), 10).await.unwrap().unwrap().len(), 2);
+
+        let recent = SpanSearch {
+            start: Some((Utc::now() - chrono::TimeDelta::minutes(1)).timestamp_nanos_opt().unwrap() as u64),
+            ..SpanSearch::default()
+        };
+        // The old partition is not read even though its entry matches
+        let entries = writer.search_index(&recent, 10).await.unwrap().unwrap();
+        assert!(entries.is_empty(), "{:?}", entries);
+        let since_epoch = SpanSearch { start: Some(0), ..SpanSearch::default() };
+        assert_eq!(writer.search_index(&since_epoch, 10).await.unwrap().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_compact_index_merges_past_partitions() {
+        let fake = FakeS3::default();
+        let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+            .with_search_index(true);
+        writer.write_spans(vec![named_span(2)]).await.unwrap();
+        writer.write_spans(vec![named_span(3)]).await.unwrap();
+        let old = Utc::now() - chrono::TimeDelta::days(2);
+        let old_segments: Vec<String> = (4..7).map(|span_id| put_old_segment(&fake, &named_span(span_id), old)).collect();
+
+        assert_eq!(writer.compact_index().await.unwrap(), 3);
+        let segments = index_segments(&fake);
+        assert_eq!(segments.len(), 3, "{:?}", segments);
+        assert!(old_segments.iter().all(|key| !segments.contains(&format!("/bucket/{}", key))));
+        let partition = format!("/bucket/spans/{}/", span_index_partition(old));
+        assert_eq!(segments.iter().filter(|key| key.starts_with(&partition)).count(), 1);
+
+        let mut names: Vec<String> = writer.search_index(&SpanSearch::default(), 10).await.unwrap().unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["op-2", "op-3", "op-4", "op-5", "op-6"]);
+        // The current partition is still being written and is left alone
+        assert_eq!(writer.compact_index().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_delete_trace_drops_index_entries() {
+        let fake = FakeS3::default();
+        let writer = S3StorageWriter::from_client(fake.client(), "bucket".into(), "spans".into())
+            .with_search_index(true);
+        let other = span_data(TraceId::from_bytes([2; 16]), SpanId::from_bytes([5; 8]), "refund");
+        writer.write_spans(vec![named_span(2), other]).await.unwrap();
+        writer.write_spans(vec![named_span(3)]).await.unwrap();
+
+        assert_eq!(writer.delete_trace(&"01".repeat(16)).await.unwrap(), 2);
+        let entries = writer.search_index(&SpanSearch::default(), 10).await.unwrap().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].trace_id, "02".repeat(16));
+        // The segment left without entries is deleted
+        assert_eq!(index_segments(&fake).len(), 1);
+    }
+}
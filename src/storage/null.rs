@@ -0,0 +1,63 @@
+use async_trait::async_trait;
+use opentelemetry::sdk::export::trace::SpanData;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::error::StorageError;
+use super::{StorageWriter, StoredSpan};
+
+/// Discards everything written to it, counting writes instead.
+/// Used to benchmark ingestion without storage latency or cost.
+#[derive(Debug, Default)]
+pub struct NullStorageWriter {
+    /// Calls to any write method
+    writes: AtomicU64,
+    /// Spans passed to `write_spans` or `write_stored_spans`
+    spans: AtomicU64,
+}
+
+impl NullStorageWriter {
+    /// Creates a writer that has seen no writes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns how many writes were discarded
+    pub fn writes(&self) -> u64 {
+        self.writes.load(Ordering::Relaxed)
+    }
+
+    /// Returns how many spans were discarded
+    pub fn spans(&self) -> u64 {
+        self.spans.load(Ordering::Relaxed)
+    }
+}
+
+#[async_trait]
+impl StorageWriter for NullStorageWriter {
+    async fn write(&self, _key: &str, _data: &[u8]) -> Result<(), StorageError> {
+        self.writes.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn write_batch(&self, _entries: Vec<(&str, &[u8])>) -> Result<(), StorageError> {
+        self.writes.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    async fn write_spans(&self, spans: Vec<SpanData>) -> Result<(), StorageError> {
+        self.writes.fetch_add(1, Ordering::Relaxed);
+        self.spans.fetch_add(spans.len() as u64, Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn write_stored_spans(&self, spans: Vec<StoredSpan>) -> Result<(), StorageError> {
+        self.writes.fetch_add(1, Ordering::Relaxed);
+        self.spans.fetch_add(spans.len() as u64, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
@@ -0,0 +1,128 @@
+use async_trait::async_trait;
+use opentelemetry::sdk::export::trace::SpanData;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::error::StorageError;
+use super::{resource_attribute, StorageWriter, StoredSpan};
+
+/// Routes spans to per-tenant storage backends by a resource attribute.
+/// Spans whose tenant is missing or unknown, and raw object writes,
+/// go to the default backend.
+pub struct TenantRouter {
+    /// Resource attribute holding the tenant id, e.g. `tenant.id`
+    attribute: String,
+    /// Backend for spans without a recognized tenant
+    default: Arc<dyn StorageWriter>,
+    /// Backends keyed by tenant id
+    tenants: HashMap<String, Arc<dyn StorageWriter>>,
+}
+
+impl TenantRouter {
+    /// Creates a router sending every span to `default` until tenants are added
+    pub fn new(attribute: String, default: Arc<dyn StorageWriter>) -> Self {
+        Self {
+            attribute,
+            default,
+            tenants: HashMap::new(),
+        }
+    }
+
+    /// Sends spans of the given tenant to `writer`
+    pub fn with_tenant(mut self, tenant: String, writer: Arc<dyn StorageWriter>) -> Self {
+        self.tenants.insert(tenant, writer);
+        self
+    }
+
+    /// Returns the backend for a span's tenant
+    fn route(&self, span: &SpanData) -> &Arc<dyn StorageWriter> {
+        resource_attribute(span, &self.attribute)
+            .and_then(|tenant| self.tenants.get(&tenant))
+            .unwrap_or(&self.default)
+    }
+}
+
+#[async_trait]
+impl StorageWriter for TenantRouter {
+    async fn write(&self, key: &str, data: &[u8]) -> Result<(), StorageError> {
+        self.default.write(key, data).await
+    }
+
+    async fn write_batch(&self, entries: Vec<(&str, &[u8])>) -> Result<(), StorageError> {
+        self.default.write_batch(entries).await
+    }
+
+    /// Stored spans do not record resource attributes other than the
+    /// service, so they cannot be routed and go to the default backend
+    async fn write_stored_spans(&self, spans: Vec<StoredSpan>) -> Result<(), StorageError> {
+        self.default.write_stored_spans(spans).await
+    }
+
+    async fn flush(&self) -> Result<(), StorageError> {
+        self.default.flush().await?;
+        for writer in self.tenants.values() {
+            writer.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn write_spans(&self, spans: Vec<SpanData>) -> Result<(), StorageError> {
+        // Group by backend, keeping each group in arrival order
+        let mut groups: Vec<(&Arc<dyn StorageWriter>, Vec<SpanData>)> = Vec::new();
+        for span in spans {
+            let writer = self.route(&span);
+            match groups.iter_mut().find(|(group, _)| Arc::ptr_eq(group, writer)) {
+                Some((_, group)) => group.push(span),
+                None => groups.push((writer, vec![span])),
+            }
+        }
+
+        for (writer, spans) in groups {
+            writer.write_spans(spans).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{span_data, MockStorage};
+    use opentelemetry::sdk::Resource;
+    use opentelemetry::trace::{SpanId, TraceId};
+    use opentelemetry::KeyValue;
+    use std::borrow::Cow;
+
+    fn span(name: &'static str, tenant: Option<&'static str>) -> SpanData {
+        let mut span = span_data(TraceId::from_bytes([1; 16]), SpanId::from_bytes([2; 8]), name);
+        if let Some(tenant) = tenant {
+            span.resource = Cow::Owned(Resource::new(vec![KeyValue::new("tenant.id", tenant)]));
+        }
+        span
+    }
+
+    #[tokio::test]
+    async fn test_spans_routed_by_tenant() {
+        let default = Arc::new(MockStorage::new());
+        let acme = Arc::new(MockStorage::new());
+        let globex = Arc::new(MockStorage::new());
+        let router = TenantRouter::new("tenant.id".into(), default.clone())
+            .with_tenant("acme".into(), acme.clone())
+            .with_tenant("globex".into(), globex.clone());
+
+        router
+            .write_spans(vec![
+                span("a1", Some("acme")),
+                span("g1", Some("globex")),
+                span("u1", Some("initech")),
+                span("a2", Some("acme")),
+                span("n1", None),
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(acme.written_names(), ["a1", "a2"]);
+        assert_eq!(globex.written_names(), ["g1"]);
+        assert_eq!(default.written_names(), ["u1", "n1"]);
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::error::ConfigError;
+
+/// Most tags S3 accepts on one object
+pub const MAX_OBJECT_TAGS: usize = 10;
+
+/// Longest tag key S3 accepts, in characters
+pub const MAX_TAG_KEY_LEN: usize = 128;
+
+/// Longest tag value S3 accepts, in characters
+pub const MAX_TAG_VALUE_LEN: usize = 256;
+
+/// Format of `{date}` in tag values
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Punctuation S3 allows in tag keys and values besides letters, digits and spaces
+const ALLOWED_PUNCTUATION: &str = "+-=._:/@";
+
+/// Values substituted into tag values for one object
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TagFields {
+    /// `service.name` shared by every span of the object
+    pub service: Option<String>,
+    /// Earliest span start in the object, rendered as `YYYY-MM-DD`
+    pub date: Option<DateTime<Utc>>,
+}
+
+/// Tags applied to stored span objects. Values may contain `{service}` and
+/// `{date}`, filled in per object; a tag whose placeholder has no value for
+/// an object (e.g. a batch of several services) is left off that object.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjectTags {
+    /// Tags sorted by key
+    tags: Vec<(String, String)>,
+}
+
+impl ObjectTags {
+    /// Parses configured tags, enforcing S3's count, length and character limits
+    pub fn parse(tags: &HashMap<String, String>) -> Result<Self, ConfigError> {
+        if tags.len() > MAX_OBJECT_TAGS {
+            return Err(ConfigError::InvalidValue(format!(
+                "storage.object_tags holds {} tags, at most {} are allowed", tags.len(), MAX_OBJECT_TAGS
+            )));
+        }
+        let mut parsed: Vec<(String, String)> = tags.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        parsed.sort();
+        for (key, value) in &parsed {
+            let key_len = key.chars().count();
+            if key_len == 0 || key_len > MAX_TAG_KEY_LEN {
+                return Err(ConfigError::InvalidValue(format!(
+                    "storage.object_tags.{} key must be 1 to {} characters", key, MAX_TAG_KEY_LEN
+                )));
+            }
+            if value.chars().count() > MAX_TAG_VALUE_LEN {
+                return Err(ConfigError::InvalidValue(format!(
+                    "storage.object_tags.{} value must be at most {} characters", key, MAX_TAG_VALUE_LEN
+                )));
+            }
+            let literal = value.replace("{service}", "").replace("{date}", "");
+            if !key.chars().chain(literal.chars()).all(is_allowed) {
+                return Err(ConfigError::InvalidValue(format!(
+                    "storage.object_tags.{} may only contain letters, digits, spaces and {}",
+                    key, ALLOWED_PUNCTUATION
+                )));
+            }
+        }
+        Ok(Self { tags: parsed })
+    }
+
+    /// Returns whether no tags are configured
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// Renders the tags of one object as an `x-amz-tagging` query string,
+    /// `None` when no tag applies
+    pub fn render(&self, fields: &TagFields) -> Option<String> {
+        let pairs: Vec<String> = self
+            .tags
+            .iter()
+            .filter_map(|(key, value)| {
+                let value = render_value(value, fields)?;
+                Some(format!("{}={}", url_encode(key), url_encode(&value)))
+            })
+            .collect();
+        (!pairs.is_empty()).then(|| pairs.join("&"))
+    }
+}
+
+/// Fills in the placeholders of a tag value; span-derived text is made
+/// S3-safe and the result cut to the value length limit
+fn render_value(template: &str, fields: &TagFields) -> Option<String> {
+    let mut value = template.to_string();
+    if value.contains("{service}") {
+        let service: String = fields
+            .service
+            .as_deref()?
+            .chars()
+            .map(|c| if is_allowed(c) { c } else { '_' })
+            .collect();
+        value = value.replace("{service}", &service);
+    }
+    if value.contains("{date}") {
+        value = value.replace("{date}", &fields.date?.format(DATE_FORMAT).to_string());
+    }
+    Some(value.chars().take(MAX_TAG_VALUE_LEN).collect())
+}
+
+fn is_allowed(c: char) -> bool {
+    c.is_alphanumeric() || c == ' ' || ALLOWED_PUNCTUATION.contains(c)
+}
+
+/// Percent-encodes everything but ASCII letters, digits and `-._~`
+fn url_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => char::from(byte).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_render_fills_placeholders() {
+        let object_tags = ObjectTags::parse(&tags(&[
+            ("team", "payments"),
+            ("service", "{service}"),
+            ("date", "{date}"),
+        ]))
+        .unwrap();
+        let fields = TagFields {
+            service: Some("checkout api".into()),
+            date: DateTime::from_timestamp(1_714_564_800, 0),
+        };
+
+        assert_eq!(
+            object_tags.render(&fields).as_deref(),
+            Some("date=2024-05-01&service=checkout%20api&team=payments")
+        );
+        assert_eq!(object_tags.render(&TagFields::default()).as_deref(), Some("team=payments"));
+    }
+
+    #[test]
+    fn test_rendered_values_made_safe() {
+        let object_tags = ObjectTags::parse(&tags(&[("service", "{service}")])).unwrap();
+        let fields = TagFields { service: Some(format!("a&b{}", "x".repeat(300))), date: None };
+
+        let rendered = object_tags.render(&fields).unwrap();
+        assert!(rendered.starts_with("service=a_bxxx"), "unexpected tagging {}", rendered);
+        assert_eq!(rendered.len(), "service=".len() + MAX_TAG_VALUE_LEN);
+    }
+
+    #[test]
+    fn test_limits_enforced() {
+        let too_many: Vec<(String, String)> = (0..=MAX_OBJECT_TAGS).map(|n| (format!("k{}", n), "v".into())).collect();
+        let too_many: HashMap<String, String> = too_many.into_iter().collect();
+        let long_key = "k".repeat(MAX_TAG_KEY_LEN + 1);
+        let long_value = "v".repeat(MAX_TAG_VALUE_LEN + 1);
+        let cases = [
+            too_many,
+            tags(&[("", "v")]),
+            tags(&[(long_key.as_str(), "v")]),
+            tags(&[("k", long_value.as_str())]),
+            tags(&[("k", "{region}")]),
+        ];
+
+        for case in cases {
+            assert!(ObjectTags::parse(&case).is_err(), "{:?} should be rejected", case);
+        }
+        assert!(ObjectTags::parse(&tags(&[("cost-center", "team:a/b @1+2=3")])).is_ok());
+    }
+}
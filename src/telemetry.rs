@@ -0,0 +1,99 @@
+use opentelemetry::sdk::trace::{self as sdktrace, Tracer};
+use opentelemetry::sdk::Resource;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use tracing::{Level, Subscriber};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::reload;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer, Registry};
+
+use crate::config::{LoggingConfig, SelfTelemetryConfig};
+
+/// Target prefix of the spans exported as self-telemetry
+const SELF_TELEMETRY_TARGET: &str = "storage_engine";
+
+/// Builds the console log filter from a `RUST_LOG`-style `level`, or from
+/// `RUST_LOG` (default `info`) when unset
+pub fn log_filter(level: Option<&str>) -> Result<EnvFilter, String> {
+    match level {
+        Some(level) => EnvFilter::try_new(level).map_err(|e| format!("invalid log level {:?}: {}", level, e)),
+        None => Ok(EnvFilter::from_default_env().add_directive(Level::INFO.into())),
+    }
+}
+
+/// Builds the console logging layer, filtered by `RUST_LOG` (default `info`)
+pub fn fmt_layer<S>() -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    tracing_subscriber::fmt::layer()
+        .with_filter(EnvFilter::from_default_env().add_directive(Level::INFO.into()))
+}
+
+/// Handle replacing the console log filter of the installed subscriber
+#[derive(Clone)]
+pub struct LogFilterHandle(reload::Handle<EnvFilter, Registry>);
+
+impl LogFilterHandle {
+    /// Switches console logging to `level`, or back to `RUST_LOG` when unset
+    pub fn set(&self, level: Option<&str>) -> Result<(), String> {
+        let filter = log_filter(level)?;
+        self.0.reload(filter).map_err(|e| format!("log filter not replaced: {}", e))
+    }
+}
+
+/// Builds the layer exporting the engine's own spans through `tracer`.
+/// Only spans from this crate are exported, so the OTLP exporter's own
+/// gRPC traffic is never traced.
+pub fn otel_layer<S>(tracer: Tracer) -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    tracing_opentelemetry::layer()
+        .with_tracer(tracer)
+        .with_filter(Targets::new().with_target(SELF_TELEMETRY_TARGET, Level::INFO))
+}
+
+/// Installs the global subscriber: console logging, filtered by
+/// `logging.level`, plus, when enabled, OTLP export of the engine's own spans.
+/// Returns a handle for changing the log filter later.
+pub fn init(
+    config: &SelfTelemetryConfig,
+    logging: &LoggingConfig,
+) -> Result<LogFilterHandle, Box<dyn std::error::Error>> {
+    let otel = if config.enabled {
+        Some(otel_layer(otlp_tracer(config)?))
+    } else {
+        None
+    };
+
+    let (filter, handle) = reload::Layer::new(log_filter(logging.level.as_deref())?);
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_filter(filter))
+        .with(otel)
+        .try_init()?;
+    Ok(LogFilterHandle(handle))
+}
+
+/// Flushes pending self-telemetry spans and stops the exporter
+pub async fn shutdown() {
+    let _ = tokio::task::spawn_blocking(opentelemetry::global::shutdown_tracer_provider).await;
+}
+
+/// Builds a batching tracer exporting to the configured OTLP endpoint
+fn otlp_tracer(config: &SelfTelemetryConfig) -> Result<Tracer, opentelemetry::trace::TraceError> {
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(&config.otlp_endpoint),
+        )
+        .with_trace_config(sdktrace::config().with_resource(Resource::new(vec![
+            KeyValue::new("service.name", config.service_name.clone()),
+        ])))
+        .install_batch(opentelemetry::runtime::Tokio)
+}
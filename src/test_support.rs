@@ -0,0 +1,283 @@
+//! Test doubles shared by unit tests across modules
+
+use async_trait::async_trait;
+use opentelemetry::sdk::export::trace::SpanData;
+use opentelemetry::sdk::trace::{EvictedHashMap, EvictedQueue};
+use opentelemetry::sdk::Resource;
+use opentelemetry::trace::{SpanContext, SpanId, SpanKind, Status, TraceFlags, TraceId, TraceState};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::error::StorageError;
+use crate::health::HealthStatus;
+use crate::storage::index::{SpanIndexEntry, SpanSearch};
+use crate::storage::{RawObject, SpanEntry, StorageReader, StorageWriter, StoredSpan, JSON_CONTENT_TYPE, SCHEMA_VERSION};
+
+/// Server span `name` with id `span_id` of trace `trace_id`, running from 1µs
+/// to 2µs after the epoch with status `Ok`, without attributes, events, links
+/// or resource; tests set other fields directly
+pub(crate) fn span_data(trace_id: TraceId, span_id: SpanId, name: &str) -> SpanData {
+    SpanData {
+        span_context: SpanContext::new(trace_id, span_id, TraceFlags::default(), false, TraceState::default()),
+        parent_span_id: SpanId::INVALID,
+        span_kind: SpanKind::Server,
+        name: Cow::Owned(name.to_string()),
+        start_time: UNIX_EPOCH + Duration::from_nanos(1_000),
+        end_time: UNIX_EPOCH + Duration::from_nanos(2_000),
+        attributes: EvictedHashMap::new(128, 0),
+        events: EvictedQueue::new(128),
+        links: EvictedQueue::new(128),
+        status: Status::Ok,
+        resource: Cow::Owned(Resource::empty()),
+        instrumentation_lib: Default::default(),
+    }
+}
+
+/// Stored form of server span `0202…` of trace `0101…` named `checkout`, with
+/// status `Ok` and the given times; tests override other fields
+pub(crate) fn stored_span(start_time: u64, end_time: u64) -> StoredSpan {
+    StoredSpan {
+        schema_version: SCHEMA_VERSION,
+        trace_id: "01".repeat(16),
+        span_id: "02".repeat(8),
+        parent_span_id: None,
+        name: "checkout".into(),
+        kind: "Server".into(),
+        start_time,
+        end_time,
+        duration_ns: end_time.saturating_sub(start_time),
+        status: "Ok".into(),
+        status_message: None,
+        service_name: None,
+        scope_name: None,
+        scope_version: None,
+        attributes: Default::default(),
+        events: Vec::new(),
+        links: Vec::new(),
+    }
+}
+
+/// In-memory storage backend implementing both `StorageWriter` and
+/// `StorageReader`. Reads serve the spans given to `with_spans`, most
+/// recent first; writes are recorded for inspection. Every trait method
+/// counts its calls, and writes can be delayed or made to fail.
+#[derive(Default)]
+pub(crate) struct MockStorage {
+    /// Spans served to readers, most recent first
+    stored: Mutex<Vec<StoredSpan>>,
+    /// Returned by `list_services`
+    services: Vec<String>,
+    /// Spans passed to `write_spans`
+    written: Mutex<Vec<SpanData>>,
+    /// Objects passed to `write` and `write_batch`
+    objects: Mutex<Vec<(String, Vec<u8>)>>,
+    /// Time each write takes before it is recorded
+    write_delay: Option<Duration>,
+    /// Number of upcoming writes that fail
+    failing_writes: AtomicUsize,
+    /// Number of upcoming span reads that fail
+    failing_reads: AtomicUsize,
+    /// Calls per trait method
+    calls: Mutex<HashMap<&'static str, usize>>,
+    /// Whether `search_index` answers from the served spans
+    search_index: bool,
+}
+
+impl MockStorage {
+    /// Creates an empty backend whose writes succeed immediately
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serves the given spans to readers, the first being the most recent
+    pub(crate) fn with_spans(self, spans: Vec<StoredSpan>) -> Self {
+        *self.stored.lock().unwrap() = spans;
+        self
+    }
+
+    /// Serves `count` copies of one span to readers
+    pub(crate) fn with_copies(self, span: StoredSpan, count: usize) -> Self {
+        self.with_spans(vec![span; count])
+    }
+
+    /// Returns the given names from `list_services`
+    pub(crate) fn with_services(mut self, services: &[&str]) -> Self {
+        self.services = services.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Answers `search_index` as if every served span were indexed
+    pub(crate) fn with_search_index(mut self) -> Self {
+        self.search_index = true;
+        self
+    }
+
+    /// Makes every write take `delay` before it is recorded
+    pub(crate) fn with_write_delay(mut self, delay: Duration) -> Self {
+        self.write_delay = Some(delay);
+        self
+    }
+
+    /// Fails the next `count` writes with `StorageError::WriteFailed`
+    pub(crate) fn fail_next_writes(&self, count: usize) {
+        self.failing_writes.store(count, Ordering::SeqCst);
+    }
+
+    /// Fails the next `count` span reads with `StorageError::ReadFailed`
+    pub(crate) fn fail_next_reads(&self, count: usize) {
+        self.failing_reads.store(count, Ordering::SeqCst);
+    }
+
+    /// Spans written so far, in write order
+    pub(crate) fn written(&self) -> Vec<SpanData> {
+        self.written.lock().unwrap().clone()
+    }
+
+    /// Names of the spans written so far, in write order
+    pub(crate) fn written_names(&self) -> Vec<String> {
+        self.written.lock().unwrap().iter().map(|span| span.name.to_string()).collect()
+    }
+
+    /// Raw objects written so far as `(key, data)`, in write order
+    pub(crate) fn objects(&self) -> Vec<(String, Vec<u8>)> {
+        self.objects.lock().unwrap().clone()
+    }
+
+    /// Number of calls made to a trait method, e.g. `"flush"`
+    pub(crate) fn calls(&self, method: &str) -> usize {
+        self.calls.lock().unwrap().get(method).copied().unwrap_or(0)
+    }
+
+    fn record_call(&self, method: &'static str) {
+        *self.calls.lock().unwrap().entry(method).or_default() += 1;
+    }
+
+    /// Applies the write delay, then consumes a pending failure if any
+    async fn before_write(&self) -> Result<(), StorageError> {
+        if let Some(delay) = self.write_delay {
+            tokio::time::sleep(delay).await;
+        }
+        let failing = self.failing_writes
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        if failing {
+            return Err(StorageError::WriteFailed("injected write failure".into()));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StorageWriter for MockStorage {
+    async fn write(&self, key: &str, data: &[u8]) -> Result<(), StorageError> {
+        self.record_call("write");
+        self.before_write().await?;
+        self.objects.lock().unwrap().push((key.to_string(), data.to_vec()));
+        Ok(())
+    }
+
+    async fn write_batch(&self, entries: Vec<(&str, &[u8])>) -> Result<(), StorageError> {
+        self.record_call("write_batch");
+        self.before_write().await?;
+        self.objects.lock().unwrap().extend(
+            entries.into_iter().map(|(key, data)| (key.to_string(), data.to_vec())),
+        );
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<(), StorageError> {
+        self.record_call("flush");
+        Ok(())
+    }
+
+    async fn write_spans(&self, spans: Vec<SpanData>) -> Result<(), StorageError> {
+        self.record_call("write_spans");
+        self.before_write().await?;
+        self.written.lock().unwrap().extend(spans);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StorageReader for MockStorage {
+    /// Keys are indexes into the served spans; modification times are
+    /// fixed, one second apart, most recent first
+    async fn list_spans(&self, limit: usize) -> Result<Vec<SpanEntry>, StorageError> {
+        self.record_call("list_spans");
+        let count = self.stored.lock().unwrap().len();
+        Ok((0..count.min(limit))
+            .map(|i| SpanEntry {
+                key: i.to_string(),
+                last_modified: UNIX_EPOCH + Duration::from_secs(1_000_000 - i as u64),
+            })
+            .collect())
+    }
+
+    async fn read_span(&self, key: &str) -> Result<StoredSpan, StorageError> {
+        self.record_call("read_span");
+        let failing = self.failing_reads
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        if failing {
+            return Err(StorageError::ReadFailed(format!("injected read failure of {}", key)));
+        }
+        key.parse::<usize>()
+            .ok()
+            .and_then(|index| self.stored.lock().unwrap().get(index).cloned())
+            .ok_or_else(|| StorageError::ReadFailed(format!("no object {}", key)))
+    }
+
+    /// Serves the JSON encoding of the span at the index `key`
+    async fn read_raw(&self, key: &str) -> Result<RawObject, StorageError> {
+        self.record_call("read_raw");
+        let span = self.read_span(key).await?;
+        Ok(RawObject::from_bytes(JSON_CONTENT_TYPE, serde_json::to_vec(&span).unwrap()))
+    }
+
+    async fn list_services(&self) -> Result<Vec<String>, StorageError> {
+        self.record_call("list_services");
+        Ok(self.services.clone())
+    }
+
+    /// Entries are keyed like `list_spans`; `None` unless `with_search_index`
+    async fn search_index(
+        &self,
+        search: &SpanSearch,
+        limit: usize,
+    ) -> Result<Option<Vec<SpanIndexEntry>>, StorageError> {
+        self.record_call("search_index");
+        if !self.search_index {
+            return Ok(None);
+        }
+        Ok(Some(self.stored.lock().unwrap()
+            .iter()
+            .enumerate()
+            .map(|(i, span)| SpanIndexEntry::new(span, &i.to_string()).with_attributes(span, &search.attribute_keys()))
+            .filter(|entry| search.matches(entry))
+            .take(limit)
+            .collect()))
+    }
+
+    async fn delete_trace(&self, trace_id: &str) -> Result<usize, StorageError> {
+        self.record_call("delete_trace");
+        let mut stored = self.stored.lock().unwrap();
+        let before = stored.len();
+        stored.retain(|span| span.trace_id != trace_id);
+        Ok(before - stored.len())
+    }
+
+    fn get_health_status(&self) -> HealthStatus {
+        HealthStatus {
+            is_healthy: true,
+            degraded: false,
+            last_write: 0,
+            queue_size: 0,
+            total_processed: 0,
+            consecutive_failed_writes: 0,
+            total_failed_writes: 0,
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use opentelemetry::sdk::export::trace::SpanData;
+use opentelemetry::trace::TraceId;
+
+/// Spans of one trace waiting to be written together
+#[derive(Debug)]
+pub struct BufferedTrace {
+    pub spans: Vec<SpanData>,
+    /// Encoded request bytes attributed to the spans
+    pub byte_size: u64,
+    /// Write-ahead log entries of the requests that carried the spans
+    pub wal_entries: Vec<u64>,
+    /// When the trace last received a span
+    last_span: Instant,
+}
+
+/// Spans held back per trace so that each trace is written as one object.
+/// A trace is complete once `idle_timeout` passes without new spans for
+/// it; spans arriving later start a new buffered trace. Beyond `max_bytes`
+/// of buffered spans, the longest-idle traces are taken early.
+#[derive(Debug)]
+pub struct TraceBuffer {
+    /// How long a trace receives no spans before it is taken
+    idle_timeout: Duration,
+    /// Buffered bytes beyond which traces are taken before going idle
+    max_bytes: u64,
+    /// Encoded bytes attributed to the buffered spans
+    bytes: u64,
+    /// Buffered traces by id
+    traces: HashMap<TraceId, BufferedTrace>,
+}
+
+impl TraceBuffer {
+    /// Creates a buffer taking traces idle for `idle_timeout`, or earlier
+    /// once more than `max_bytes` are buffered
+    pub fn new(idle_timeout: Duration, max_bytes: u64) -> Self {
+        Self {
+            idle_timeout,
+            max_bytes,
+            bytes: 0,
+            traces: HashMap::new(),
+        }
+    }
+
+    /// Returns how long a trace must be idle before it is taken
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+
+    /// Buffers the spans of one request, splitting its encoded `byte_size`
+    /// evenly across them and recording its `wal_entry` with each trace it
+    /// touches. Returns the number of traces the entry was recorded with.
+    pub fn add(&mut self, spans: Vec<SpanData>, byte_size: u64, wal_entry: Option<u64>) -> usize {
+        self.add_at(spans, byte_size, wal_entry, Instant::now())
+    }
+
+    fn add_at(&mut self, spans: Vec<SpanData>, byte_size: u64, wal_entry: Option<u64>, now: Instant) -> usize {
+        let bytes_per_span = byte_size / spans.len().max(1) as u64;
+        let mut traces = 0;
+        for span in spans {
+            let trace = self.traces
+                .entry(span.span_context.trace_id())
+                .or_insert_with(|| BufferedTrace { spans: Vec::new(), byte_size: 0, wal_entries: Vec::new(), last_span: now });
+            trace.spans.push(span);
+            trace.byte_size += bytes_per_span;
+            trace.last_span = now;
+            self.bytes += bytes_per_span;
+            // Spans of one request arrive together, so a trace already
+            // holding the entry has it last
+            if let Some(entry) = wal_entry {
+                if trace.wal_entries.last() != Some(&entry) {
+                    trace.wal_entries.push(entry);
+                    traces += 1;
+                }
+            }
+        }
+        traces
+    }
+
+    /// Removes the traces that have been idle for `idle_timeout`
+    pub fn take_idle(&mut self) -> Vec<BufferedTrace> {
+        self.take_idle_at(Instant::now())
+    }
+
+    fn take_idle_at(&mut self, now: Instant) -> Vec<BufferedTrace> {
+        let idle: Vec<TraceId> = self.traces
+            .iter()
+            .filter(|(_, trace)| now.duration_since(trace.last_span) >= self.idle_timeout)
+            .map(|(trace_id, _)| *trace_id)
+            .collect();
+        idle.into_iter()
+            .filter_map(|trace_id| self.remove(&trace_id))
+            .collect()
+    }
+
+    /// Removes the longest-idle traces, complete or not, until at most
+    /// `max_bytes` remain buffered
+    pub fn take_over_limit(&mut self) -> Vec<BufferedTrace> {
+        if self.bytes <= self.max_bytes {
+            return Vec::new();
+        }
+        let mut oldest: Vec<(Instant, TraceId)> = self.traces
+            .iter()
+            .map(|(trace_id, trace)| (trace.last_span, *trace_id))
+            .collect();
+        oldest.sort_unstable_by_key(|(last_span, _)| *last_span);
+
+        let mut taken = Vec::new();
+        for (_, trace_id) in oldest {
+            if self.bytes <= self.max_bytes {
+                break;
+            }
+            taken.extend(self.remove(&trace_id));
+        }
+        taken
+    }
+
+    /// Removes every buffered trace, complete or not
+    pub fn drain(&mut self) -> Vec<BufferedTrace> {
+        self.bytes = 0;
+        self.traces.drain().map(|(_, trace)| trace).collect()
+    }
+
+    fn remove(&mut self, trace_id: &TraceId) -> Option<BufferedTrace> {
+        let trace = self.traces.remove(trace_id)?;
+        self.bytes -= trace.byte_size;
+        Some(trace)
+    }
+
+    /// Number of traces currently buffered
+    pub fn len(&self) -> usize {
+        self.traces.len()
+    }
+
+    /// Returns whether no traces are buffered
+    pub fn is_empty(&self) -> bool {
+        self.traces.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::span_data;
+    use opentelemetry::sdk::export::trace::SpanData;
+    use opentelemetry::trace::SpanId;
+
+    fn span(trace: u128, span: u64) -> SpanData {
+        span_data(TraceId::from(trace), SpanId::from(span), &format!("span-{}", span))
+    }
+
+    #[test]
+    fn test_trace_taken_once_idle() {
+        let mut buffer = TraceBuffer::new(Duration::from_secs(5), u64::MAX);
+        let start = Instant::now();
+
+        assert_eq!(buffer.add_at(vec![span(1, 1), span(2, 2)], 20, Some(7), start), 2);
+        assert_eq!(buffer.add_at(vec![span(1, 3)], 10, Some(8), start + Duration::from_secs(3)), 1);
+        assert_eq!(buffer.len(), 2);
+
+        // Trace 2 is idle, trace 1 received a span since
+        let idle = buffer.take_idle_at(start + Duration::from_secs(5));
+        assert_eq!(idle.len(), 1);
+        assert_eq!(idle[0].spans[0].span_context.trace_id(), TraceId::from(2));
+        assert_eq!(idle[0].byte_size, 10);
+        assert_eq!(idle[0].wal_entries, [7]);
+
+        let idle = buffer.take_idle_at(start + Duration::from_secs(8));
+        assert_eq!(idle.len(), 1);
+        assert_eq!(idle[0].spans.len(), 2);
+        assert_eq!(idle[0].byte_size, 20);
+        assert_eq!(idle[0].wal_entries, [7, 8]);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_longest_idle_traces_taken_over_limit() {
+        let mut buffer = TraceBuffer::new(Duration::from_secs(5), 25);
+        let start = Instant::now();
+
+        buffer.add_at(vec![span(1, 1)], 10, None, start);
+        buffer.add_at(vec![span(2, 2)], 10, None, start + Duration::from_secs(1));
+        assert!(buffer.take_over_limit().is_empty());
+
+        // Trace 1 received a span last, so trace 2 is taken first
+        buffer.add_at(vec![span(1, 3)], 10, None, start + Duration::from_secs(2));
+        let taken = buffer.take_over_limit();
+        assert_eq!(taken.len(), 1);
+        assert_eq!(taken[0].spans[0].span_context.trace_id(), TraceId::from(2));
+
+        buffer.add_at(vec![span(3, 4)], 30, None, start + Duration::from_secs(3));
+        let taken: Vec<TraceId> = buffer.take_over_limit()
+            .iter()
+            .map(|trace| trace.spans[0].span_context.trace_id())
+            .collect();
+        assert_eq!(taken, [TraceId::from(1), TraceId::from(3)]);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_drain_takes_incomplete_traces() {
+        let mut buffer = TraceBuffer::new(Duration::from_secs(5), u64::MAX);
+        assert_eq!(buffer.add(vec![span(1, 1), span(1, 2), span(2, 3)], 30, None), 0);
+
+        assert!(buffer.take_idle().is_empty());
+        let mut drained: Vec<usize> = buffer.drain().iter().map(|trace| trace.spans.len()).collect();
+        drained.sort();
+        assert_eq!(drained, vec![1, 2]);
+        assert!(buffer.is_empty());
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::config::WalConfig;
+use crate::error::StorageError;
+
+/// Extension of log segment files
+const SEGMENT_EXTENSION: &str = "wal";
+
+/// Record holding one logged request
+const ENTRY_RECORD: u8 = 1;
+
+/// Record marking an entry as stored
+const COMMIT_RECORD: u8 = 2;
+
+/// Bytes before a record's payload: kind, entry id and payload length
+const RECORD_HEADER_LEN: usize = 1 + 8 + 4;
+
+/// Segment currently appended to
+#[derive(Debug)]
+struct ActiveSegment {
+    sequence: u64,
+    file: File,
+    size: u64,
+}
+
+#[derive(Debug, Default)]
+struct WalState {
+    /// Segment new records are appended to, created on first use
+    active: Option<ActiveSegment>,
+    /// Sequence number of the next segment
+    next_sequence: u64,
+    /// Id given to the next entry
+    next_id: u64,
+    /// Uncommitted entry ids per segment, oldest segment first
+    segments: BTreeMap<u64, HashSet<u64>>,
+    /// Segment holding each uncommitted entry
+    entry_segments: HashMap<u64, u64>,
+    /// Entries a previous run left uncommitted, oldest first
+    recovered: Vec<(u64, Vec<u8>)>,
+}
+
+/// Append-only log of received requests, so requests still queued in
+/// memory survive a crash. Each request is appended and synced as an entry
+/// before it is queued, and committed once it is stored; entries left
+/// uncommitted are handed back by `take_uncommitted` after a restart.
+///
+/// The log is split into segments of about `max_segment_bytes`. Commits are
+/// appended to the newest segment, so segments are deleted oldest first,
+/// once they and every older segment hold no uncommitted entries.
+/// Commits are not synced: after a crash a stored request may be replayed.
+#[derive(Debug)]
+pub struct WriteAheadLog {
+    /// Directory holding the segments
+    dir: PathBuf,
+    /// Size at which a new segment is started
+    max_segment_bytes: u64,
+    state: Mutex<WalState>,
+}
+
+impl WriteAheadLog {
+    /// Opens the log directory, creating it if needed and reading back the
+    /// entries previous runs left uncommitted. New records go to a fresh
+    /// segment, so a record torn by a crash is never appended to.
+    pub async fn open(config: &WalConfig) -> Result<Self, StorageError> {
+        let dir = PathBuf::from(&config.dir);
+        fs::create_dir_all(&dir).await.map_err(|e| io_error(&dir, e))?;
+
+        let mut sequences = Vec::new();
+        let mut files = fs::read_dir(&dir).await.map_err(|e| io_error(&dir, e))?;
+        while let Some(file) = files.next_entry().await.map_err(|e| io_error(&dir, e))? {
+            let path = file.path();
+            if path.extension().is_some_and(|ext| ext == SEGMENT_EXTENSION) {
+                if let Some(sequence) = path.file_stem().and_then(|stem| stem.to_str()?.parse().ok()) {
+                    sequences.push(sequence);
+                }
+            }
+        }
+        sequences.sort_unstable();
+
+        let mut entries = BTreeMap::new();
+        let mut committed = HashSet::new();
+        let mut state = WalState::default();
+        for &sequence in &sequences {
+            let path = segment_path(&dir, sequence);
+            let data = fs::read(&path).await.map_err(|e| io_error(&path, e))?;
+            for (kind, id, payload) in parse_records(&data, &path) {
+                match kind {
+                    ENTRY_RECORD => {
+                        entries.insert(id, (sequence, payload.to_vec()));
+                    }
+                    _ => {
+                        committed.insert(id);
+                    }
+                }
+                state.next_id = state.next_id.max(id + 1);
+            }
+            state.segments.insert(sequence, HashSet::new());
+        }
+
+        for (id, (sequence, data)) in entries {
+            if committed.contains(&id) {
+                continue;
+            }
+            state.segments.entry(sequence).or_default().insert(id);
+            state.entry_segments.insert(id, sequence);
+            state.recovered.push((id, data));
+        }
+        state.next_sequence = sequences.last().map_or(0, |last| last + 1);
+
+        let wal = Self {
+            dir,
+            max_segment_bytes: config.max_segment_bytes,
+            state: Mutex::new(state),
+        };
+        wal.remove_committed_segments(&mut *wal.state.lock().await).await?;
+        Ok(wal)
+    }
+
+    /// Appends one encoded request and syncs it to disk, returning the
+    /// entry id to commit once the request is stored
+    pub async fn append(&self, data: &[u8]) -> Result<u64, StorageError> {
+        let mut state = self.state.lock().await;
+        let id = state.next_id;
+        self.write_record(&mut state, ENTRY_RECORD, id, data, true).await?;
+        state.next_id += 1;
+
+        let sequence = state.active.as_ref().map_or(0, |active| active.sequence);
+        state.segments.entry(sequence).or_default().insert(id);
+        state.entry_segments.insert(id, sequence);
+        Ok(id)
+    }
+
+    /// Marks an entry as stored, deleting segments no longer needed
+    pub async fn commit(&self, id: u64) -> Result<(), StorageError> {
+        let mut state = self.state.lock().await;
+        let Some(sequence) = state.entry_segments.remove(&id) else { return Ok(()) };
+        if let Some(pending) = state.segments.get_mut(&sequence) {
+            pending.remove(&id);
+        }
+        self.write_record(&mut state, COMMIT_RECORD, id, &[], false).await?;
+        self.remove_committed_segments(&mut state).await
+    }
+
+    /// Takes the entries previous runs left uncommitted, oldest first.
+    /// They stay in the log until committed.
+    pub async fn take_uncommitted(&self) -> Vec<(u64, Vec<u8>)> {
+        std::mem::take(&mut self.state.lock().await.recovered)
+    }
+
+    /// Number of entries not yet committed
+    pub async fn uncommitted(&self) -> usize {
+        self.state.lock().await.entry_segments.len()
+    }
+
+    /// Appends a record to the active segment, starting a new segment when
+    /// the record would take the active one past `max_segment_bytes`
+    async fn write_record(
+        &self,
+        state: &mut WalState,
+        kind: u8,
+        id: u64,
+        payload: &[u8],
+        sync: bool,
+    ) -> Result<(), StorageError> {
+        let mut record = Vec::with_capacity(RECORD_HEADER_LEN + payload.len());
+        record.push(kind);
+        record.extend_from_slice(&id.to_be_bytes());
+        record.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        record.extend_from_slice(payload);
+
+        let full = state.active.as_ref().is_some_and(|active| {
+            active.size > 0 && active.size + record.len() as u64 > self.max_segment_bytes
+        });
+        if state.active.is_none() || full {
+            let sequence = state.next_sequence;
+            let path = segment_path(&self.dir, sequence);
+            let file = File::create(&path).await.map_err(|e| io_error(&path, e))?;
+            state.next_sequence += 1;
+            state.segments.entry(sequence).or_default();
+            state.active = Some(ActiveSegment { sequence, file, size: 0 });
+        }
+
+        let Some(active) = state.active.as_mut() else { return Ok(()) };
+        let path = segment_path(&self.dir, active.sequence);
+        active.file.write_all(&record).await.map_err(|e| io_error(&path, e))?;
+        if sync {
+            active.file.sync_data().await.map_err(|e| io_error(&path, e))?;
+        }
+        active.size += record.len() as u64;
+        Ok(())
+    }
+
+    /// Deletes the oldest segments while they hold no uncommitted entries,
+    /// stopping at the active segment
+    async fn remove_committed_segments(&self, state: &mut WalState) -> Result<(), StorageError> {
+        let active = state.active.as_ref().map(|active| active.sequence);
+        while let Some((&sequence, pending)) = state.segments.first_key_value() {
+            if !pending.is_empty() || Some(sequence) == active {
+                break;
+            }
+            state.segments.remove(&sequence);
+            let path = segment_path(&self.dir, sequence);
+            match fs::remove_file(&path).await {
+                Ok(()) => info!("Removed committed WAL segment {}", path.display()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(io_error(&path, e)),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Splits a segment into `(kind, id, payload)` records, stopping at a
+/// record torn by a crash
+fn parse_records<'a>(mut data: &'a [u8], path: &Path) -> Vec<(u8, u64, &'a [u8])> {
+    let mut records = Vec::new();
+    while !data.is_empty() {
+        if data.len() < RECORD_HEADER_LEN {
+            warn!("Ignoring torn record at the end of {}", path.display());
+            break;
+        }
+        let kind = data[0];
+        let id = u64::from_be_bytes(data[1..9].try_into().unwrap_or_default());
+        let len = u32::from_be_bytes(data[9..13].try_into().unwrap_or_default()) as usize;
+        let Some(payload) = data.get(RECORD_HEADER_LEN..RECORD_HEADER_LEN + len) else {
+            warn!("Ignoring torn record at the end of {}", path.display());
+            break;
+        };
+        if kind != ENTRY_RECORD && kind != COMMIT_RECORD {
+            warn!("Ignoring the rest of {} after an unknown record", path.display());
+            break;
+        }
+        records.push((kind, id, payload));
+        data = &data[RECORD_HEADER_LEN + len..];
+    }
+    records
+}
+
+fn segment_path(dir: &Path, sequence: u64) -> PathBuf {
+    dir.join(format!("{:020}.{}", sequence, SEGMENT_EXTENSION))
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> StorageError {
+    StorageError::WriteFailed(format!("WAL {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn config(dir: &TempDir, max_segment_bytes: u64) -> WalConfig {
+        WalConfig {
+            enabled: true,
+            dir: dir.path().to_string_lossy().into_owned(),
+            max_segment_bytes,
+        }
+    }
+
+    async fn segment_count(dir: &TempDir) -> usize {
+        let mut count = 0;
+        let mut files = fs::read_dir(dir.path()).await.unwrap();
+        while files.next_entry().await.unwrap().is_some() {
+            count += 1;
+        }
+        count
+    }
+
+    #[tokio::test]
+    async fn test_uncommitted_entries_recovered_in_order() {
+        let dir = TempDir::new().unwrap();
+        {
+            let wal = WriteAheadLog::open(&config(&dir, 1024)).await.unwrap();
+            let first = wal.append(b"first").await.unwrap();
+            wal.append(b"second").await.unwrap();
+            wal.append(b"third").await.unwrap();
+            wal.commit(first).await.unwrap();
+        }
+
+        let wal = WriteAheadLog::open(&config(&dir, 1024)).await.unwrap();
+        let recovered: Vec<Vec<u8>> = wal.take_uncommitted().await.into_iter().map(|(_, data)| data).collect();
+        assert_eq!(recovered, vec![b"second".to_vec(), b"third".to_vec()]);
+        assert_eq!(wal.uncommitted().await, 2);
+
+        // New entries never reuse recovered ids
+        let id = wal.append(b"fourth").await.unwrap();
+        assert_eq!(id, 3);
+    }
+
+    #[tokio::test]
+    async fn test_segments_rotated_and_removed_once_committed() {
+        let dir = TempDir::new().unwrap();
+        let wal = WriteAheadLog::open(&config(&dir, 40)).await.unwrap();
+
+        // Each 20-byte entry record fills half a segment
+        let mut ids = Vec::new();
+        for _ in 0..4 {
+            ids.push(wal.append(b"payload").await.unwrap());
+        }
+        assert!(segment_count(&dir).await >= 2);
+
+        for id in ids {
+            wal.commit(id).await.unwrap();
+        }
+        assert_eq!(wal.uncommitted().await, 0);
+        assert_eq!(segment_count(&dir).await, 1, "only the active segment is kept");
+
+        drop(wal);
+        let wal = WriteAheadLog::open(&config(&dir, 40)).await.unwrap();
+        assert!(wal.take_uncommitted().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_torn_record_ignored() {
+        let dir = TempDir::new().unwrap();
+        {
+            let wal = WriteAheadLog::open(&config(&dir, 1024)).await.unwrap();
+            wal.append(b"complete").await.unwrap();
+        }
+        let path = segment_path(dir.path(), 0);
+        let mut data = fs::read(&path).await.unwrap();
+        data.extend_from_slice(&[ENTRY_RECORD, 0, 0]);
+        fs::write(&path, data).await.unwrap();
+
+        let wal = WriteAheadLog::open(&config(&dir, 1024)).await.unwrap();
+        let recovered = wal.take_uncommitted().await;
+        assert_eq!(recovered, vec![(0, b"complete".to_vec())]);
+    }
+}
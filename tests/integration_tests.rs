@@ -1,16 +1,420 @@
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use opentelemetry::sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+use opentelemetry::sdk::trace::TracerProvider;
+use opentelemetry::trace::TracerProvider as _;
 use storage_engine::*;
-use tokio::sync::mpsc;
+use storage_engine::auth::BearerAuth;
+use storage_engine::config::ServerConfig;
+use storage_engine::health::HealthCheck;
+use storage_engine::proto::opentelemetry::proto::collector::trace::v1::trace_service_client::TraceServiceClient;
+use storage_engine::proto::{
+    ExportTraceServiceRequest, ExportTraceServiceResponse, ResourceSpans, ScopeSpans, Span, TraceService,
+    TraceServiceServer,
+};
+use storage_engine::server::{concurrency_limit_layer, message_size_layer};
+use storage_engine::storage::StorageWriter;
+use storage_engine::telemetry;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::codec::CompressionEncoding;
+use tonic::transport::Server as GrpcServer;
+use tonic::{Request, Response, Status};
+use tracing_subscriber::layer::SubscriberExt;
+
+/// In-memory storage backend recording every span written
+#[derive(Default)]
+struct MemoryStorage {
+    spans: Mutex<Vec<SpanData>>,
+}
+
+#[async_trait]
+impl StorageWriter for MemoryStorage {
+    async fn write(&self, _key: &str, _data: &[u8]) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    async fn write_batch(&self, _entries: Vec<(&str, &[u8])>) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    async fn write_spans(&self, spans: Vec<SpanData>) -> Result<(), StorageError> {
+        self.spans.lock().unwrap().extend(spans);
+        Ok(())
+    }
+}
 
 #[tokio::test]
 async fn test_end_to_end_flow() {
     // Setup test environment
     let (tx, rx) = mpsc::channel(100);
-    let config = ProcessingConfig::default();
-    
-    // Initialize components
-    let engine = EngineCore::new(rx, config).await.unwrap();
-    let server = ListenerServer::new(tx);
-    
-    // Run test scenarios
-    // ...
-} 
\ No newline at end of file
+    let config = ProcessingConfig {
+        batch_size: 1,
+        batch_timeout_ms: 1000,
+        ..ProcessingConfig::default()
+    };
+    let storage = Arc::new(MemoryStorage::default());
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+    // Initialize components sharing the engine's health check
+    let mut engine = EngineCore::with_storage(rx, config, storage.clone())
+        .with_shutdown_signal(shutdown_rx);
+    let health_check = engine.get_health_check();
+    let server = ListenerServer::new(tx.into(), Arc::clone(&health_check));
+    let engine_handle = tokio::spawn(async move { engine.process_messages().await });
+
+    // Export one span through the gRPC handler
+    let response = server
+        .export(Request::new(request_with_span_name("checkout".into())))
+        .await;
+    assert!(response.is_ok());
+
+    // Wait for the engine to write it
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while storage.spans.lock().unwrap().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("span was not written to storage");
+
+    shutdown_tx.send(true).unwrap();
+    engine_handle.await.unwrap();
+
+    let spans = storage.spans.lock().unwrap();
+    assert_eq!(spans.len(), 1);
+    assert_eq!(spans[0].name, "checkout");
+    assert_eq!(spans[0].span_context.trace_id().to_string(), "01".repeat(16));
+    assert_eq!(health_check.get_health_status().total_processed, 1);
+}
+
+/// Span exporter keeping finished self-telemetry spans in memory
+#[derive(Debug, Clone, Default)]
+struct InMemoryExporter {
+    spans: Arc<Mutex<Vec<SpanData>>>,
+}
+
+impl SpanExporter for InMemoryExporter {
+    fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        self.spans.lock().unwrap().extend(batch);
+        Box::pin(std::future::ready(Ok(())))
+    }
+}
+
+#[tokio::test]
+async fn test_self_telemetry_spans() {
+    let exporter = InMemoryExporter::default();
+    let provider = TracerProvider::builder()
+        .with_simple_exporter(exporter.clone())
+        .build();
+    let subscriber = tracing_subscriber::registry()
+        .with(telemetry::otel_layer(provider.tracer("test")));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let (tx, rx) = mpsc::channel(10);
+    let config = ProcessingConfig {
+        batch_size: 1,
+        batch_timeout_ms: 1000,
+        ..ProcessingConfig::default()
+    };
+    let storage = Arc::new(MemoryStorage::default());
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let mut engine = EngineCore::with_storage(rx, config, storage.clone())
+        .with_shutdown_signal(shutdown_rx);
+    let server = ListenerServer::new(tx.into(), engine.get_health_check());
+    let engine_handle = tokio::spawn(async move { engine.process_messages().await });
+
+    server
+        .export(Request::new(request_with_span_name("traced".into())))
+        .await
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while storage.spans.lock().unwrap().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("span was not written to storage");
+
+    shutdown_tx.send(true).unwrap();
+    engine_handle.await.unwrap();
+    provider.force_flush();
+
+    let spans = exporter.spans.lock().unwrap();
+    let find = |name: &str| {
+        spans
+            .iter()
+            .find(|span| span.name == name)
+            .unwrap_or_else(|| panic!("no {} span exported", name))
+    };
+    find("export");
+    let batch = find("process_batch");
+    let write = find("write_spans");
+    assert_eq!(write.parent_span_id, batch.span_context.span_id());
+}
+
+fn server_config(max_decoding_message_size: usize) -> ServerConfig {
+    ServerConfig {
+        host: "127.0.0.1".into(),
+        port: 0,
+        max_connections: 1000,
+        max_decoding_message_size,
+        accept_gzip: true,
+        shutdown_timeout_ms: 30_000,
+        max_spans_per_request: 100_000,
+        max_resource_spans: 10_000,
+        validate_on_ingest: false,
+    }
+}
+
+fn request_with_span_name(name: String) -> ExportTraceServiceRequest {
+    ExportTraceServiceRequest {
+        resource_spans: vec![ResourceSpans {
+            resource: None,
+            scope_spans: vec![ScopeSpans {
+                scope: None,
+                spans: vec![Span {
+                    trace_id: vec![1; 16],
+                    span_id: vec![2; 8],
+                    name,
+                    ..Default::default()
+                }],
+                schema_url: String::new(),
+            }],
+            schema_url: String::new(),
+        }],
+    }
+}
+
+/// Starts a gRPC server on an ephemeral port and returns its URL and the engine-side receiver
+async fn start_grpc_server(
+    config: ServerConfig,
+) -> (String, mpsc::Receiver<storage_engine::core::QueuedMessage>) {
+    let (tx, rx) = mpsc::channel(10);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let listener_server = ListenerServer::new(tx.into(), Arc::new(HealthCheck::new()));
+
+    tokio::spawn(async move {
+        GrpcServer::builder()
+            .layer(concurrency_limit_layer(config.max_connections))
+            .layer(message_size_layer(config.max_decoding_message_size))
+            .add_service(listener_server.into_service(&config, BearerAuth::default()))
+            .serve_with_incoming(TcpListenerStream::new(listener))
+            .await
+            .unwrap();
+    });
+
+    (format!("http://{}", addr), rx)
+}
+
+#[tokio::test]
+async fn test_gzip_compressed_export() {
+    let (url, mut rx) = start_grpc_server(server_config(4 * 1024 * 1024)).await;
+    let mut client = TraceServiceClient::connect(url)
+        .await
+        .unwrap()
+        .send_compressed(CompressionEncoding::Gzip);
+
+    let response = client.export(request_with_span_name("compressed".into())).await;
+    assert!(response.is_ok(), "gzip export failed: {:?}", response.err());
+
+    let received = rx.recv().await.unwrap().request;
+    let span = &received.resource_spans[0].scope_spans[0].spans[0];
+    assert_eq!(span.name, "compressed");
+}
+
+#[tokio::test]
+async fn test_oversized_export_rejected() {
+    let (url, mut rx) = start_grpc_server(server_config(1024)).await;
+    let mut client = TraceServiceClient::connect(url).await.unwrap();
+
+    let status = client
+        .export(request_with_span_name("x".repeat(4096)))
+        .await
+        .unwrap_err();
+
+    assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+    assert!(status.message().contains("1024"));
+    assert!(rx.try_recv().is_err());
+}
+
+/// Trace service that holds each export for a while, tracking how many run at once
+#[derive(Default)]
+struct SlowTraceService {
+    in_flight: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+#[tonic::async_trait]
+impl TraceService for SlowTraceService {
+    async fn export(
+        &self,
+        _request: Request<ExportTraceServiceRequest>,
+    ) -> Result<Response<ExportTraceServiceResponse>, Status> {
+        let running = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(running, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        Ok(Response::new(ExportTraceServiceResponse {}))
+    }
+}
+
+#[tokio::test]
+async fn test_concurrent_exports_limited() {
+    let service = Arc::new(SlowTraceService::default());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let server = TraceServiceServer::from_arc(service.clone());
+    tokio::spawn(async move {
+        GrpcServer::builder()
+            .layer(concurrency_limit_layer(2))
+            .add_service(server)
+            .serve_with_incoming(TcpListenerStream::new(listener))
+            .await
+            .unwrap();
+    });
+
+    // Separate clients, so the limit must hold across connections
+    let mut exports = Vec::new();
+    for i in 0..6 {
+        let url = url.clone();
+        exports.push(tokio::spawn(async move {
+            let mut client = TraceServiceClient::connect(url).await.unwrap();
+            client.export(request_with_span_name(format!("span-{}", i))).await
+        }));
+    }
+    for export in exports {
+        assert!(export.await.unwrap().is_ok());
+    }
+
+    // Excess requests were queued rather than rejected or run all at once
+    assert_eq!(service.peak.load(Ordering::SeqCst), 2);
+}
+
+#[cfg(feature = "bulk-export")]
+#[tokio::test]
+async fn test_streamed_bulk_export_stored() {
+    use storage_engine::proto::storage_engine::bulk::v1::{
+        bulk_trace_service_client::BulkTraceServiceClient, ExportChunk,
+    };
+
+    let (tx, rx) = mpsc::channel(10);
+    let config = ProcessingConfig {
+        batch_size: 1,
+        batch_timeout_ms: 1000,
+        ..ProcessingConfig::default()
+    };
+    let storage = Arc::new(MemoryStorage::default());
+    let mut engine = EngineCore::with_storage(rx, config, storage.clone());
+    let server = ListenerServer::new(tx.into(), engine.get_health_check());
+    tokio::spawn(async move { engine.process_messages().await });
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let service = server.into_bulk_service(&server_config(4 * 1024 * 1024), BearerAuth::default());
+    tokio::spawn(async move {
+        GrpcServer::builder()
+            .add_service(service)
+            .serve_with_incoming(TcpListenerStream::new(listener))
+            .await
+            .unwrap();
+    });
+
+    // Three chunks of one ResourceSpans each, the last carrying two spans
+    let mut chunks: Vec<ExportChunk> = (0..3)
+        .map(|i| ExportChunk {
+            resource_spans: request_with_span_name(format!("chunk-{}", i)).resource_spans,
+        })
+        .collect();
+    let extra = chunks[2].resource_spans[0].scope_spans[0].spans[0].clone();
+    chunks[2].resource_spans[0].scope_spans[0].spans.push(Span { name: "chunk-2b".into(), ..extra });
+
+    let mut client = BulkTraceServiceClient::connect(url).await.unwrap();
+    let summary = client.export_stream(tokio_stream::iter(chunks)).await.unwrap().into_inner();
+    assert_eq!((summary.chunks, summary.resource_spans, summary.spans), (3, 3, 4));
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while storage.spans.lock().unwrap().len() < 4 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("streamed spans were not written to storage");
+
+    let mut names: Vec<String> = storage.spans.lock().unwrap().iter().map(|span| span.name.to_string()).collect();
+    names.sort();
+    assert_eq!(names, ["chunk-0", "chunk-1", "chunk-2", "chunk-2b"]);
+}
+
+#[cfg(feature = "kafka")]
+#[tokio::test]
+async fn test_kafka_message_stored() {
+    use prost::Message;
+    use std::collections::VecDeque;
+    use storage_engine::kafka::{KafkaIngest, KafkaMessage, MessageSource};
+
+    /// Topic partition replaying fixed messages and recording committed offsets
+    struct FakeTopic {
+        messages: VecDeque<KafkaMessage>,
+        committed: Arc<Mutex<Vec<i64>>>,
+    }
+
+    #[async_trait]
+    impl MessageSource for FakeTopic {
+        async fn recv(&mut self) -> Result<KafkaMessage, ProcessingError> {
+            match self.messages.pop_front() {
+                Some(message) => Ok(message),
+                None => futures::future::pending().await,
+            }
+        }
+
+        fn commit(&mut self, message: &KafkaMessage) -> Result<(), ProcessingError> {
+            self.committed.lock().unwrap().push(message.offset);
+            Ok(())
+        }
+    }
+
+    let (tx, rx) = mpsc::channel(10);
+    let config = ProcessingConfig {
+        batch_size: 1,
+        batch_timeout_ms: 1000,
+        ..ProcessingConfig::default()
+    };
+    let storage = Arc::new(MemoryStorage::default());
+    let mut engine = EngineCore::with_storage(rx, config, storage.clone());
+    tokio::spawn(async move { engine.process_messages().await });
+
+    let committed = Arc::new(Mutex::new(Vec::new()));
+    let topic = FakeTopic {
+        messages: VecDeque::from([
+            KafkaMessage { payload: b"not protobuf".to_vec(), partition: 0, offset: 0 },
+            KafkaMessage {
+                payload: request_with_span_name("from-kafka".into()).encode_to_vec(),
+                partition: 0,
+                offset: 1,
+            },
+        ]),
+        committed: Arc::clone(&committed),
+    };
+    tokio::spawn(KafkaIngest::new(topic, tx.into()).run());
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while storage.spans.lock().unwrap().is_empty() || committed.lock().unwrap().len() < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Kafka message was not written to storage");
+
+    assert_eq!(storage.spans.lock().unwrap()[0].name, "from-kafka");
+    // The undecodable message is skipped but committed so it is not read again
+    assert_eq!(*committed.lock().unwrap(), [0, 1]);
+}